@@ -0,0 +1,310 @@
+//! Exact integer liquidity <-> token amount conversions, ported from the Raydium CLMM program's
+//! `liquidity_amounts` and `sqrt_price_math`. Sqrt prices are Q64.64, as in `tick_math`; amounts are raw.
+
+use std::cmp::Ordering;
+
+/// A 512-bit unsigned integer as little-endian u64 limbs, wide enough for `(liquidity << 64) * sqrt price`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct U512([u64; 8]);
+
+impl U512 {
+    const ZERO: U512 = U512([0; 8]);
+
+    fn from_u128(value: u128) -> Self {
+        let mut limbs = [0; 8];
+        limbs[0] = value as u64;
+        limbs[1] = (value >> 64) as u64;
+        U512(limbs)
+    }
+
+    fn to_u128(self) -> Option<u128> {
+        self.0[2..].iter().all(|&limb| limb == 0).then(|| (self.0[1] as u128) << 64 | self.0[0] as u128)
+    }
+
+    /// Schoolbook product; callers keep it below 2^512.
+    fn mul(self, other: U512) -> U512 {
+        let mut product = [0u64; 8];
+        for i in 0..8 {
+            let mut carry = 0u128;
+            for j in 0..8 - i {
+                let sum = product[i + j] as u128 + self.0[i] as u128 * other.0[j] as u128 + carry;
+                product[i + j] = sum as u64;
+                carry = sum >> 64;
+            }
+        }
+        U512(product)
+    }
+
+    fn bit(&self, index: usize) -> bool {
+        self.0[index / 64] >> (index % 64) & 1 == 1
+    }
+
+    fn shl1(self) -> U512 {
+        let mut shifted = [0u64; 8];
+        for (i, limb) in shifted.iter_mut().enumerate() {
+            *limb = self.0[i] << 1 | if i > 0 { self.0[i - 1] >> 63 } else { 0 };
+        }
+        U512(shifted)
+    }
+
+    fn sub(self, other: U512) -> U512 {
+        let mut difference = [0u64; 8];
+        let mut borrow = false;
+        for (i, limb) in difference.iter_mut().enumerate() {
+            let (value, borrow_a) = self.0[i].overflowing_sub(other.0[i]);
+            let (value, borrow_b) = value.overflowing_sub(borrow as u64);
+            *limb = value;
+            borrow = borrow_a || borrow_b;
+        }
+        U512(difference)
+    }
+
+    fn add_one(self) -> U512 {
+        let mut sum = self.0;
+        for limb in sum.iter_mut() {
+            let (value, carry) = limb.overflowing_add(1);
+            *limb = value;
+            if !carry {
+                break;
+            }
+        }
+        U512(sum)
+    }
+
+    /// Bitwise long division; `divisor` must be non-zero.
+    fn div_rem(self, divisor: U512) -> (U512, U512) {
+        let (mut quotient, mut remainder) = (U512::ZERO, U512::ZERO);
+        for index in (0..512).rev() {
+            remainder = remainder.shl1();
+            if self.bit(index) {
+                remainder.0[0] |= 1;
+            }
+            if remainder >= divisor {
+                remainder = remainder.sub(divisor);
+                quotient.0[index / 64] |= 1 << (index % 64);
+            }
+        }
+        (quotient, remainder)
+    }
+
+    /// `self / divisor`, rounded down or up.
+    fn div(self, divisor: U512, round_up: bool) -> U512 {
+        let (quotient, remainder) = self.div_rem(divisor);
+        if round_up && remainder != U512::ZERO { quotient.add_one() } else { quotient }
+    }
+}
+
+impl Ord for U512 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.iter().rev().cmp(other.0.iter().rev())
+    }
+}
+
+impl PartialOrd for U512 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+const Q64: u128 = 1 << 64;
+
+/// `a * b / denominator`, rounded down or up.
+fn mul_div(a: u128, b: u128, denominator: u128, round_up: bool) -> U512 {
+    U512::from_u128(a).mul(U512::from_u128(b)).div(U512::from_u128(denominator), round_up)
+}
+
+/// `a * b / denominator` rounded down, for the program's other products that overflow u128, such as
+/// reward growth (`seconds * emissions_per_second_x64 / liquidity`) and owed amounts (`growth * liquidity / 2^64`).
+pub fn mul_div_floor(a: u128, b: u128, denominator: u128) -> Result<u128, String> {
+    if denominator == 0 {
+        return Err("division by zero".to_string());
+    }
+    to_u128(mul_div(a, b, denominator, false), "product")
+}
+
+/// Orders two sqrt prices. Zero is below every valid sqrt price and would divide by zero.
+fn sorted(sqrt_price_a_x64: u128, sqrt_price_b_x64: u128) -> Result<(u128, u128), String> {
+    let (lower, upper) = (sqrt_price_a_x64.min(sqrt_price_b_x64), sqrt_price_a_x64.max(sqrt_price_b_x64));
+    if lower == 0 {
+        return Err("sqrt price 0 is outside the valid range".to_string());
+    }
+    Ok((lower, upper))
+}
+
+/// Like `sorted`, also rejecting an empty range: no amount provides liquidity over zero width.
+fn sorted_nonempty(sqrt_price_a_x64: u128, sqrt_price_b_x64: u128) -> Result<(u128, u128), String> {
+    let (lower, upper) = sorted(sqrt_price_a_x64, sqrt_price_b_x64)?;
+    if lower == upper {
+        return Err(format!("sqrt price range [{}, {}] is empty", lower, upper));
+    }
+    Ok((lower, upper))
+}
+
+fn to_u128(value: U512, what: &str) -> Result<u128, String> {
+    value.to_u128().ok_or_else(|| format!("{} overflows u128", what))
+}
+
+fn to_u64(value: U512, what: &str) -> Result<u64, String> {
+    to_u128(value, what)?.try_into().map_err(|_| format!("{} overflows u64", what))
+}
+
+/// Liquidity provided by `amount_0` over `[sqrt_price_a, sqrt_price_b]`:
+/// `amount_0 * (sqrt_a * sqrt_b / 2^64) / (sqrt_b - sqrt_a)`, rounded down at each step.
+pub fn liquidity_from_amount_0(sqrt_price_a_x64: u128, sqrt_price_b_x64: u128, amount_0: u64) -> Result<u128, String> {
+    let (lower, upper) = sorted_nonempty(sqrt_price_a_x64, sqrt_price_b_x64)?;
+    let intermediate = to_u128(mul_div(lower, upper, Q64, false), "sqrt price product")?;
+    to_u128(mul_div(amount_0 as u128, intermediate, upper - lower, false), "liquidity")
+}
+
+/// Liquidity provided by `amount_1` over `[sqrt_price_a, sqrt_price_b]`: `amount_1 * 2^64 / (sqrt_b - sqrt_a)`, rounded down.
+pub fn liquidity_from_amount_1(sqrt_price_a_x64: u128, sqrt_price_b_x64: u128, amount_1: u64) -> Result<u128, String> {
+    let (lower, upper) = sorted_nonempty(sqrt_price_a_x64, sqrt_price_b_x64)?;
+    to_u128(mul_div(amount_1 as u128, Q64, upper - lower, false), "liquidity")
+}
+
+/// The largest liquidity `amount_0` and `amount_1` can provide over `[sqrt_price_a, sqrt_price_b]` at the
+/// current price. At or below the range only token 0 counts, above it only token 1, and inside it the
+/// smaller of the two liquidities, so the other token is only partly used.
+pub fn liquidity_from_amounts(
+    sqrt_price_current_x64: u128,
+    sqrt_price_a_x64: u128,
+    sqrt_price_b_x64: u128,
+    amount_0: u64,
+    amount_1: u64,
+) -> Result<u128, String> {
+    let (lower, upper) = sorted_nonempty(sqrt_price_a_x64, sqrt_price_b_x64)?;
+    if sqrt_price_current_x64 <= lower {
+        liquidity_from_amount_0(lower, upper, amount_0)
+    } else if sqrt_price_current_x64 < upper {
+        Ok(liquidity_from_amount_0(sqrt_price_current_x64, upper, amount_0)?
+            .min(liquidity_from_amount_1(lower, sqrt_price_current_x64, amount_1)?))
+    } else {
+        liquidity_from_amount_1(lower, upper, amount_1)
+    }
+}
+
+/// Token 0 held by `liquidity` over `[sqrt_price_a, sqrt_price_b]`:
+/// `(liquidity << 64) * (sqrt_b - sqrt_a) / sqrt_b / sqrt_a`. Deposits round up, withdrawals down.
+pub fn amount_0_delta(sqrt_price_a_x64: u128, sqrt_price_b_x64: u128, liquidity: u128, round_up: bool) -> Result<u64, String> {
+    let (lower, upper) = sorted(sqrt_price_a_x64, sqrt_price_b_x64)?;
+    let amount = U512::from_u128(liquidity)
+        .mul(U512::from_u128(Q64))
+        .mul(U512::from_u128(upper - lower))
+        .div(U512::from_u128(upper), round_up)
+        .div(U512::from_u128(lower), round_up);
+    to_u64(amount, "token 0 amount")
+}
+
+/// Token 1 held by `liquidity` over `[sqrt_price_a, sqrt_price_b]`: `liquidity * (sqrt_b - sqrt_a) / 2^64`.
+/// Deposits round up, withdrawals down.
+pub fn amount_1_delta(sqrt_price_a_x64: u128, sqrt_price_b_x64: u128, liquidity: u128, round_up: bool) -> Result<u64, String> {
+    let (lower, upper) = sorted(sqrt_price_a_x64, sqrt_price_b_x64)?;
+    to_u64(mul_div(liquidity, upper - lower, Q64, round_up), "token 1 amount")
+}
+
+/// Token amounts of a position with `liquidity` over `[sqrt_price_a, sqrt_price_b]` at the current price,
+/// split as the program does: all token 0 below the range, all token 1 at or above its top, both inside.
+pub fn amounts_for_liquidity(
+    sqrt_price_current_x64: u128,
+    sqrt_price_a_x64: u128,
+    sqrt_price_b_x64: u128,
+    liquidity: u128,
+    round_up: bool,
+) -> Result<(u64, u64), String> {
+    let (lower, upper) = sorted(sqrt_price_a_x64, sqrt_price_b_x64)?;
+    if sqrt_price_current_x64 < lower {
+        Ok((amount_0_delta(lower, upper, liquidity, round_up)?, 0))
+    } else if sqrt_price_current_x64 < upper {
+        Ok((
+            amount_0_delta(sqrt_price_current_x64, upper, liquidity, round_up)?,
+            amount_1_delta(lower, sqrt_price_current_x64, liquidity, round_up)?,
+        ))
+    } else {
+        Ok((0, amount_1_delta(lower, upper, liquidity, round_up)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Sqrt prices of 1, 1.21, 100/110, 110/100, 99/110 and 111/100 in Q64.64, rounded down,
+    // the prices of the Uniswap v3 SqrtPriceMath and LiquidityAmounts test cases.
+    const SQRT_1: u128 = Q64;
+    const SQRT_1_21: u128 = 20291418481080506777;
+    const SQRT_100_110: u128 = 17588280367669894507;
+    const SQRT_110_100: u128 = 19347108404436883958;
+    const SQRT_99_110: u128 = 17500118006140547654;
+    const SQRT_111_100: u128 = 19434850842809205733;
+
+    #[test]
+    fn amount_deltas_round_in_the_requested_direction() {
+        let liquidity = 1_000_000_000_000_000_000;
+        assert_eq!(amount_0_delta(SQRT_1, SQRT_1_21, liquidity, true), Ok(90909090909090910));
+        assert_eq!(amount_0_delta(SQRT_1, SQRT_1_21, liquidity, false), Ok(90909090909090909));
+        assert_eq!(amount_1_delta(SQRT_1, SQRT_1_21, liquidity, true), Ok(100000000000000000));
+        assert_eq!(amount_1_delta(SQRT_1, SQRT_1_21, liquidity, false), Ok(99999999999999999));
+        // The order of the bounds does not matter
+        assert_eq!(amount_0_delta(SQRT_1_21, SQRT_1, liquidity, true), Ok(90909090909090910));
+        assert_eq!(amount_1_delta(SQRT_1_21, SQRT_1, liquidity, false), Ok(99999999999999999));
+    }
+
+    #[test]
+    fn amount_deltas_of_an_empty_range_or_no_liquidity_are_zero() {
+        for round_up in [false, true] {
+            assert_eq!(amount_0_delta(SQRT_1, SQRT_1, 1 << 100, round_up), Ok(0));
+            assert_eq!(amount_1_delta(SQRT_1, SQRT_1, 1 << 100, round_up), Ok(0));
+            assert_eq!(amount_0_delta(SQRT_1, SQRT_1_21, 0, round_up), Ok(0));
+            assert_eq!(amount_1_delta(SQRT_1, SQRT_1_21, 0, round_up), Ok(0));
+        }
+        // A single unit of liquidity over one ulp of sqrt price only shows up when rounding up
+        assert_eq!(amount_1_delta(SQRT_1, SQRT_1 + 1, 1, false), Ok(0));
+        assert_eq!(amount_1_delta(SQRT_1, SQRT_1 + 1, 1, true), Ok(1));
+    }
+
+    #[test]
+    fn amount_deltas_that_overflow_u64_are_errors() {
+        assert_eq!(amount_1_delta(SQRT_1, 2 * SQRT_1, u128::MAX, false), Err("token 1 amount overflows u64".to_string()));
+        assert_eq!(amount_0_delta(SQRT_1, 2 * SQRT_1, u128::MAX, false), Err("token 0 amount overflows u64".to_string()));
+        assert_eq!(amount_0_delta(0, SQRT_1, 1, false), Err("sqrt price 0 is outside the valid range".to_string()));
+    }
+
+    #[test]
+    fn liquidity_from_amounts_uses_the_tokens_the_price_allows() {
+        // Inside the range the smaller of the two liquidities wins; below only token 0 counts, above only token 1
+        assert_eq!(liquidity_from_amounts(SQRT_1, SQRT_100_110, SQRT_110_100, 100, 200), Ok(2148));
+        assert_eq!(liquidity_from_amounts(SQRT_99_110, SQRT_100_110, SQRT_110_100, 100, 200), Ok(1048));
+        assert_eq!(liquidity_from_amounts(SQRT_111_100, SQRT_100_110, SQRT_110_100, 100, 200), Ok(2097));
+        // At the lower bound the price counts as below the range, at the upper bound as above it
+        assert_eq!(liquidity_from_amounts(SQRT_100_110, SQRT_100_110, SQRT_110_100, 100, 200), Ok(1048));
+        assert_eq!(liquidity_from_amounts(SQRT_110_100, SQRT_100_110, SQRT_110_100, 100, 200), Ok(2097));
+        assert_eq!(
+            liquidity_from_amounts(SQRT_1, SQRT_1, SQRT_1, 100, 200),
+            Err(format!("sqrt price range [{}, {}] is empty", SQRT_1, SQRT_1))
+        );
+    }
+
+    #[test]
+    fn liquidity_from_amounts_round_trips_within_the_amounts() {
+        for sqrt_price_current_x64 in [SQRT_99_110, SQRT_1, SQRT_111_100] {
+            let (amount_0, amount_1) = (1_000_000_000, 2_000_000_000);
+            let liquidity = liquidity_from_amounts(sqrt_price_current_x64, SQRT_100_110, SQRT_110_100, amount_0, amount_1).unwrap();
+            // Even when rounded up, as for a deposit, the liquidity never needs more than was offered
+            let (needed_0, needed_1) =
+                amounts_for_liquidity(sqrt_price_current_x64, SQRT_100_110, SQRT_110_100, liquidity, true).unwrap();
+            assert!(needed_0 <= amount_0 && needed_1 <= amount_1, "{:?} > {:?}", (needed_0, needed_1), (amount_0, amount_1));
+            let (withdrawn_0, withdrawn_1) =
+                amounts_for_liquidity(sqrt_price_current_x64, SQRT_100_110, SQRT_110_100, liquidity, false).unwrap();
+            assert!(withdrawn_0 <= needed_0 && withdrawn_1 <= needed_1);
+        }
+    }
+
+    #[test]
+    fn mul_div_floor_handles_products_past_u128() {
+        assert_eq!(mul_div_floor(u128::MAX, u128::MAX, u128::MAX), Ok(u128::MAX));
+        assert_eq!(mul_div_floor(u128::MAX, 3, 4), Ok(u128::MAX / 4 * 3 + 2));
+        assert_eq!(mul_div_floor(7, 3, 2), Ok(10));
+        assert_eq!(mul_div_floor(u128::MAX, 2, 1), Err("product overflows u128".to_string()));
+        assert_eq!(mul_div_floor(1, 1, 0), Err("division by zero".to_string()));
+    }
+}
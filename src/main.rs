@@ -1,20 +1,56 @@
+use solana_sdk::account::Account;
 use solana_sdk::pubkey::Pubkey;
-use solana_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_client::client_error::ClientError;
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use std::io::IsTerminal;
 use std::str::FromStr;
+use std::time::Duration;
 use clap::{Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use base64::Engine;
+use anchor_lang::AnchorSerialize;
 
-use anchor_lang::AnchorDeserialize;
 
 // --- Module Imports ---
+mod liquidity_math;
+#[cfg(test)]
+mod mock_fetcher;
 mod onchain_states;
-use onchain_states::{PoolState, TickArrayBitmapExtension, TickArrayState};
+mod pool_cache;
+#[cfg(test)]
+mod rpc_tests;
+#[cfg(test)]
+mod unit_tests;
+mod rpc_util;
+mod tick_math;
+mod units;
+use pool_cache::PoolCache;
+use rpc_util::{is_account_not_found, AccountFetcher, FetchOptions, RpcFetcher};
+use tick_math::{
+    get_sqrt_price_at_tick, get_tick_at_sqrt_price, scale_sqrt_price, MAX_SQRT_PRICE_X64, MAX_TICK, MIN_SQRT_PRICE_X64, MIN_TICK,
+};
+use units::{q64_to_f64, ArrayStartIndex, SqrtPriceX64, Tick};
+use onchain_states::{
+    account_type_name, check_clmm_account, decode_clmm_account, read_mint_decimals, read_token_account_owner,
+    AmmConfig, ClmmAccount, ObservationState, PersonalPositionState, PoolScoped, PoolState, RewardInfo, TickArrayBitmapExtension, TickArrayLayout, TickArrayRef, TickArrayState, TickState,
+    FEE_RATE_DENOMINATOR, OBSERVATION_NUM, PERSONAL_POSITION_POOL_ID_OFFSET, PERSONAL_POSITION_STATE_DISCRIMINATOR, PERSONAL_POSITION_STATE_LEN,
+    POOL_STATE_DISCRIMINATOR, POOL_STATE_LEN, POOL_STATE_TOKEN_MINT_0_OFFSET, POOL_STATE_TOKEN_MINT_1_OFFSET,
+    SPL_TOKEN_2022_PROGRAM_ID, SPL_TOKEN_PROGRAM_ID, SWAP_V2_DISCRIMINATOR, SwapV2Args, TICK_ARRAY_POOL_ID_OFFSET, TICK_ARRAY_STATE_DISCRIMINATOR, TICK_ARRAY_STATE_LEN,
+};
 
 // --- Core Constants ---
 const TICK_ARRAY_SIZE: i32 = 60;
 const Q_RATIO: f64 = 1.0001;
 const RAYDIUM_CLMM_PROGRAM_ID: &str = "CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK";
+const RAYDIUM_CLMM_DEVNET_PROGRAM_ID: &str = "devi51mZmdwUJGU9hjN27vEz64Gps7uUefqxg27EAtH";
+const MAINNET_RPC_URL: &str = "https://api.mainnet-beta.solana.com";
+const DEVNET_RPC_URL: &str = "https://api.devnet.solana.com";
 const TICK_ARRAY_SEED: &[u8] = b"tick_array";
 const TICK_ARRAY_BITMAP_SEED: &[u8] = b"pool_tick_array_bitmap_extension";
+const POSITION_SEED: &[u8] = b"position";
+const SPL_MEMO_PROGRAM_ID: &str = "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr";
+const ASSOCIATED_TOKEN_PROGRAM_ID: &str = "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL";
 
 // --- Data Structures for Clarity ---
 
@@ -35,6 +71,108 @@ enum PriceInput {
     Token0PerToken1Human { price: f64 },
 }
 
+impl PriceInput {
+    /// The same price read with token 0 and token 1 exchanged (`--swap-tokens`): `t1-per-t0` becomes
+    /// `t0-per-t1` and vice versa, for raw and human prices alike.
+    fn flipped(self) -> Self {
+        match self {
+            PriceInput::Token1PerToken0Raw { price } => PriceInput::Token0PerToken1Raw { price },
+            PriceInput::Token0PerToken1Raw { price } => PriceInput::Token1PerToken0Raw { price },
+            PriceInput::Token1PerToken0Human { price } => PriceInput::Token0PerToken1Human { price },
+            PriceInput::Token0PerToken1Human { price } => PriceInput::Token1PerToken0Human { price },
+        }
+    }
+}
+
+/// How a price that falls between two ticks is mapped to a tick index.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum RoundingMode {
+    /// The tick at or below the price (e.g. lower bounds).
+    Floor,
+    /// The tick at or above the price (e.g. upper bounds of a position).
+    Ceil,
+    /// The closest tick.
+    Nearest,
+    /// The closest usable tick: a multiple of the tick spacing (e.g. UI inputs).
+    NearestAligned,
+}
+
+/// Distance in ticks below which a price is treated as lying exactly on a tick,
+/// absorbing floating error from `powi`/`log` (about 1e-10 ticks at the protocol limits).
+const TICK_SNAP_EPSILON: f64 = 1e-6;
+
+/// Largest decimals accepted on the command line (the practical maximum for SPL mints).
+const MAX_TOKEN_DECIMALS: u8 = 18;
+
+/// Tick spacings of Raydium's CLMM fee tiers. Any other spacing on the command line is most likely a typo.
+const KNOWN_TICK_SPACINGS: [u16; 5] = [1, 10, 60, 120, 240];
+
+/// Warns when a `--tick-spacing` that cannot be checked against a pool is not a known one, since a
+/// wrong spacing silently shifts every array start index and PDA.
+fn warn_unknown_tick_spacing(tick_spacing: u16) {
+    if !KNOWN_TICK_SPACINGS.contains(&tick_spacing) {
+        eprintln!(
+            "Warning: --tick-spacing {} is not one of Raydium's tick spacings {:?}; array start indices and PDAs will not match any Raydium pool.",
+            tick_spacing, KNOWN_TICK_SPACINGS
+        );
+    }
+}
+
+/// Value parser for the `--decimals0`/`--decimals1` flags, rejecting values above `MAX_TOKEN_DECIMALS`.
+fn decimals_parser() -> clap::builder::RangedI64ValueParser<u8> {
+    clap::value_parser!(u8).range(..=MAX_TOKEN_DECIMALS as i64)
+}
+
+/// Process exit codes, so scripts can branch on the failure reason. Also listed in `--help`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ExitCode {
+    Success = 0,
+    /// A check found inconsistencies, or the on-chain data cannot answer the request.
+    Failure = 1,
+    /// Invalid input: a bad pubkey, decimals, argument combination or unwritable output path. Clap usage errors also exit 2.
+    InvalidInput = 2,
+    AccountNotFound = 3,
+    /// An account has the wrong owner, discriminator or layout.
+    Deserialize = 4,
+    /// An RPC request failed after all retries.
+    Network = 5,
+}
+
+const EXIT_CODES_HELP: &str = "Exit codes:
+  0  success
+  1  a check found inconsistencies, or the data cannot answer the request
+  2  invalid input (bad pubkey, bad decimals, bad argument combination, unwritable output path)
+  3  account not found
+  4  account could not be deserialized (wrong owner, discriminator or layout)
+  5  network error (RPC request failed after all retries)";
+
+/// Prints `Error: {message}` to stderr and exits with `code`.
+fn fail(code: ExitCode, message: impl std::fmt::Display) -> ! {
+    eprintln!("Error: {}", message);
+    std::process::exit(code as i32);
+}
+
+/// Exits after a failed fetch of `what`: `AccountNotFound` for a missing account, `Network` otherwise.
+fn fail_fetch(what: impl std::fmt::Display, error: &ClientError) -> ! {
+    let code = if is_account_not_found(error) { ExitCode::AccountNotFound } else { ExitCode::Network };
+    fail(code, format!("Failed to fetch {}: {}", what, error))
+}
+
+/// Exits after a failed `getProgramAccounts`, with a hint, since the call is often disabled by the provider.
+fn fail_get_program_accounts(error: &ClientError) -> ! {
+    eprintln!("Error: getProgramAccounts failed: {}", error);
+    eprintln!(
+        "Many RPC providers, including the public Solana endpoints, disable or restrict getProgramAccounts. \
+        Retry with --rpc-url pointing at a provider that supports it (e.g. a dedicated Helius, Triton or QuickNode endpoint)."
+    );
+    std::process::exit(ExitCode::Network as i32);
+}
+
+/// Parses a base58 pubkey argument, exiting with `InvalidInput` when it is malformed.
+fn parse_pubkey(value: &str, what: &str) -> Pubkey {
+    Pubkey::from_str(value).unwrap_or_else(|_| fail(ExitCode::InvalidInput, format!("Invalid {} '{}'.", what, value)))
+}
+
 /// Helper struct for all tick-to-price and price-to-tick conversions.
 struct TickConverter {
     decimals_0: u8,
@@ -44,22 +182,61 @@ struct TickConverter {
 impl TickConverter {
     // --- Core Conversion Logic ---
 
+    /// Returns `10^decimals_0 / 10^decimals_1`, the factor between raw and human token1/token0 prices.
+    /// Computed from the difference so large decimals never overflow an intermediate power.
+    fn decimal_adjustment(&self) -> f64 {
+        10f64.powi(self.decimals_0 as i32 - self.decimals_1 as i32)
+    }
+
     /// Converts a tick index to its raw price (token_1 / token_0).
-    fn tick_to_raw_price(&self, tick: i32) -> f64 {
-        Q_RATIO.powi(tick)
+    fn tick_to_raw_price(&self, tick: Tick) -> f64 {
+        Q_RATIO.powi(tick.0)
     }
 
     /// Converts a raw price (token_1 / token_0) to its corresponding tick index (by rounding down).
-    fn raw_price_to_tick(&self, price: f64) -> i32 {
-        price.log(Q_RATIO).floor() as i32
+    fn raw_price_to_tick(&self, price: f64) -> Tick {
+        self.raw_price_to_tick_rounded(price, RoundingMode::Floor)
+    }
+
+    /// Converts a raw price to a tick index using the given rounding mode, with a tick spacing of 1
+    /// (`NearestAligned` then behaves like `Nearest`).
+    fn raw_price_to_tick_rounded(&self, price: f64, rounding: RoundingMode) -> Tick {
+        self.raw_price_to_tick_spaced(price, rounding, 1)
+    }
+
+    /// Converts a raw price to a tick index using the given rounding mode; `NearestAligned` rounds
+    /// to the closest multiple of `tick_spacing` (halfway rounds away from zero), kept within the
+    /// usable multiples inside `[MIN_TICK, MAX_TICK]`.
+    /// A price within `TICK_SNAP_EPSILON` of an exact tick maps to that tick before rounding, so
+    /// `1.0001^t` converts back to `t` despite `log` landing just below or above it.
+    fn raw_price_to_tick_spaced(&self, price: f64, rounding: RoundingMode, tick_spacing: u16) -> Tick {
+        let mut exact = price.log(Q_RATIO);
+        if (exact - exact.round()).abs() < TICK_SNAP_EPSILON {
+            exact = exact.round();
+        }
+        let tick = match rounding {
+            RoundingMode::Floor => exact.floor() as i32,
+            RoundingMode::Ceil => exact.ceil() as i32,
+            RoundingMode::Nearest => exact.round() as i32,
+            RoundingMode::NearestAligned => {
+                let spacing = tick_spacing as i32;
+                let aligned = (exact / spacing as f64).round() as i32 * spacing;
+                aligned.clamp(-(MAX_TICK / spacing) * spacing, (MAX_TICK / spacing) * spacing)
+            }
+        };
+        Tick(tick)
     }
     
     // --- Flexible Conversion Functions ---
 
     /// Converts a tick index to a price in any of the specified formats.
-    fn tick_to_price(&self, tick: i32, format: PriceInput) -> f64 {
-        let raw_price = self.tick_to_raw_price(tick);
-        let decimal_adjustment = 10f64.powi(self.decimals_0 as i32) / 10f64.powi(self.decimals_1 as i32);
+    fn tick_to_price(&self, tick: Tick, format: PriceInput) -> f64 {
+        self.raw_to_price(self.tick_to_raw_price(tick), format)
+    }
+
+    /// Converts a raw price (token_1 / token_0) to any of the specified formats.
+    fn raw_to_price(&self, raw_price: f64, format: PriceInput) -> f64 {
+        let decimal_adjustment = self.decimal_adjustment();
         
         match format {
             PriceInput::Token1PerToken0Raw { .. } => raw_price,
@@ -69,38 +246,75 @@ impl TickConverter {
         }
     }
 
-    /// Converts a price from any specified format back to a tick index.
-    fn price_to_tick(&self, price_info: PriceInput) -> i32 {
-        let decimal_adjustment = 10f64.powi(self.decimals_0 as i32) / 10f64.powi(self.decimals_1 as i32);
+    /// Converts a price from any specified format back to a tick index (by rounding down).
+    fn price_to_tick(&self, price_info: PriceInput) -> Tick {
+        self.price_to_tick_rounded(price_info, RoundingMode::Floor)
+    }
+
+    /// Converts a price from any specified format back to a tick index using the given rounding mode.
+    /// Rounding applies to the tick, so for the inverted `t0-per-t1` formats `Ceil` still means
+    /// the higher tick (the lower token0/token1 price).
+    fn price_to_tick_rounded(&self, price_info: PriceInput, rounding: RoundingMode) -> Tick {
+        self.raw_price_to_tick_rounded(self.to_raw_price(price_info), rounding)
+    }
+
+    /// Like `price_to_tick_rounded`, with `NearestAligned` rounding to a multiple of `tick_spacing`.
+    fn price_to_tick_spaced(&self, price_info: PriceInput, rounding: RoundingMode, tick_spacing: u16) -> Tick {
+        self.raw_price_to_tick_spaced(self.to_raw_price(price_info), rounding, tick_spacing)
+    }
+
+    /// Converts a price range to ticks, returned in the order of the inputs. With no rounding mode the
+    /// range rounds outward: the bound with the lower tick rounds down and the other up, so a tight
+    /// range never collapses onto a single tick unless both prices sit exactly on it.
+    fn price_range_to_ticks(&self, lower: PriceInput, upper: PriceInput, rounding: Option<RoundingMode>, tick_spacing: u16) -> (Tick, Tick) {
+        let (lower_rounding, upper_rounding) = match rounding {
+            Some(rounding) => (rounding, rounding),
+            // Inverted formats give the lower price the higher tick
+            None if self.to_raw_price(lower) <= self.to_raw_price(upper) => (RoundingMode::Floor, RoundingMode::Ceil),
+            None => (RoundingMode::Ceil, RoundingMode::Floor),
+        };
+        (
+            self.price_to_tick_spaced(lower, lower_rounding, tick_spacing),
+            self.price_to_tick_spaced(upper, upper_rounding, tick_spacing),
+        )
+    }
+
+    /// Converts a price from any specified format to the raw token_1 / token_0 price.
+    fn to_raw_price(&self, price_info: PriceInput) -> f64 {
+        let decimal_adjustment = self.decimal_adjustment();
 
-        let raw_price = match price_info {
+        match price_info {
             PriceInput::Token1PerToken0Raw { price } => price,
             PriceInput::Token0PerToken1Raw { price } => 1.0 / price,
             PriceInput::Token1PerToken0Human { price } => price / decimal_adjustment,
             PriceInput::Token0PerToken1Human { price } => 1.0 / (price * decimal_adjustment),
-        };
-        
-        self.raw_price_to_tick(raw_price)
+        }
     }
 
     /// Prints all price variations for a given tick index.
-    fn print_all_prices(&self, tick: i32) {
+    fn print_all_prices(&self, tick: Tick, prices: PriceDisplay) {
         println!("--- Price Representations for Tick Index {} ---", tick);
+        self.print_price_formats(self.tick_to_raw_price(tick), prices);
 
-        let t1_per_t0_raw_price = self.tick_to_price(tick, PriceInput::Token1PerToken0Raw { price: 0.0 });
-        println!("  - Token1/Token0 (Raw):   {:.12}", t1_per_t0_raw_price);
-        
-        let t0_per_t1_raw_price = self.tick_to_price(tick, PriceInput::Token0PerToken1Raw { price: 0.0 });
-        println!("  - Token0/Token1 (Raw):   {:.12}", t0_per_t1_raw_price);
+        match tick.sqrt_price() {
+            Ok(sqrt_price_x64) => println!("  - SqrtPriceX64:          {}", sqrt_price_x64),
+            Err(e) => println!("  - SqrtPriceX64:          n/a ({})", e),
+        }
+    }
 
-        let t1_per_t0_human_price = self.tick_to_price(tick, PriceInput::Token1PerToken0Human { price: 0.0 });
-        println!("  - Token1/Token0 (Human): {:.12}", t1_per_t0_human_price);
+    /// Prints a raw price (token_1 / token_0) in all four price formats.
+    fn print_price_formats(&self, raw_price: f64, prices: PriceDisplay) {
+        let t1_per_t0_raw_price = self.raw_to_price(raw_price, PriceInput::Token1PerToken0Raw { price: 0.0 });
+        println!("  - Token1/Token0 (Raw):   {}", prices.format(t1_per_t0_raw_price));
         
-        let t0_per_t1_human_price = self.tick_to_price(tick, PriceInput::Token0PerToken1Human { price: 0.0 });
-        println!("  - Token0/Token1 (Human): {:.12}", t0_per_t1_human_price);
+        let t0_per_t1_raw_price = self.raw_to_price(raw_price, PriceInput::Token0PerToken1Raw { price: 0.0 });
+        println!("  - Token0/Token1 (Raw):   {}", prices.format(t0_per_t1_raw_price));
 
-        let sqrt_price_x64 = (self.tick_to_raw_price(tick).sqrt() * (2_u128.pow(64) as f64)) as u128;
-        println!("  - SqrtPriceX64:          {}", sqrt_price_x64);
+        let t1_per_t0_human_price = self.raw_to_price(raw_price, PriceInput::Token1PerToken0Human { price: 0.0 });
+        println!("  - Token1/Token0 (Human): {}", prices.format(t1_per_t0_human_price));
+        
+        let t0_per_t1_human_price = self.raw_to_price(raw_price, PriceInput::Token0PerToken1Human { price: 0.0 });
+        println!("  - Token0/Token1 (Human): {}", prices.format(t0_per_t1_human_price));
     }
 }
 
@@ -116,38 +330,51 @@ impl TickArrayHelper {
     }
 
     /// Gets the start tick index for the array that contains a given tick index.
-    fn get_array_start_index(&self, tick_index: i32) -> i32 {
-        let ticks_in_array = self.tick_indices_per_array();
-        let mut start = tick_index / ticks_in_array;
-        
-        if tick_index < 0 && tick_index % ticks_in_array != 0 {
-            start -= 1;
-        }
-        
-        start * ticks_in_array
+    fn get_array_start_index(&self, tick_index: Tick) -> ArrayStartIndex {
+        tick_index.array_start(self.tick_spacing)
     }
     
     /// Given a start_tick_index, determines the full range of tick *indices* it covers.
-    fn get_array_tick_range(&self, start_index: i32) -> (i32, i32) {
-        let end_index = start_index + self.tick_indices_per_array();
-        (start_index, end_index - 1)
+    fn get_array_tick_range(&self, start_index: ArrayStartIndex) -> (Tick, Tick) {
+        (start_index.first_tick(), start_index.end_tick(self.tick_spacing))
+    }
+
+    /// The last on-grid tick stored in an array: `start + 59 * tick_spacing`. Unlike the end of
+    /// `get_array_tick_range` (`start + 60 * tick_spacing - 1`), this is a tick a position can use,
+    /// so it is the one to convert when displaying an array's price range. In the array holding
+    /// `MAX_TICK` it is the last usable multiple of the spacing instead, as the later slots are past the limit.
+    fn last_valid_tick(&self, start_index: ArrayStartIndex) -> Tick {
+        start_index.last_tick(self.tick_spacing).min(Tick(self.max_usable_tick()))
+    }
+
+    /// The largest multiple of the tick spacing within `MAX_TICK`; its negation is the smallest within `MIN_TICK`.
+    fn max_usable_tick(&self) -> i32 {
+        MAX_TICK / self.tick_spacing as i32 * self.tick_spacing as i32
+    }
+
+    /// Start indices of the first and last arrays holding ticks within `[MIN_TICK, MAX_TICK]`.
+    /// No tick array outside this range can ever be initialized.
+    fn valid_array_start_range(&self) -> (ArrayStartIndex, ArrayStartIndex) {
+        (self.get_array_start_index(Tick(MIN_TICK)), self.get_array_start_index(Tick(MAX_TICK)))
+    }
+
+    /// Given a start_tick_index, returns its first and last usable on-grid ticks, for price display.
+    fn get_array_valid_tick_range(&self, start_index: ArrayStartIndex) -> (Tick, Tick) {
+        (start_index.first_tick().max(Tick(-self.max_usable_tick())), self.last_valid_tick(start_index))
     }
     
-    /// Aligns a tick to be a valid tick according to the pool's tick spacing.
-    fn align_tick_to_spacing(&self, tick: i32) -> i32 {
-        let mut compressed = tick / self.tick_spacing as i32;
-        if tick < 0 && tick % self.tick_spacing as i32 != 0 {
-            compressed -= 1; // Round toward negative infinity for consistency
-        }
-        compressed * self.tick_spacing as i32
+    /// Aligns a tick to be a valid tick according to the pool's tick spacing
+    /// (rounding toward negative infinity).
+    fn align_tick_to_spacing(&self, tick: Tick) -> Tick {
+        tick.align_down(self.tick_spacing)
     }
 
 
     /// Prints detailed information about a specific tick index.
-    fn print_tick_info(&self, tick_index: i32) {
+    fn print_tick_info(&self, tick_index: Tick) {
         let aligned_tick = self.align_tick_to_spacing(tick_index);
         let start_index = self.get_array_start_index(aligned_tick);
-        let offset = (aligned_tick - start_index) / self.tick_spacing as i32;
+        let offset = start_index.slot_of(aligned_tick, self.tick_spacing);
 
         println!("--- Info for Tick Index {} ---", tick_index);
         if tick_index != aligned_tick {
@@ -159,47 +386,218 @@ impl TickArrayHelper {
     }
     
     /// Prints detailed information about a specific tick array.
-    fn print_array_info(&self, start_index: i32) {
+    fn print_array_info(&self, start_index: ArrayStartIndex) {
         let (start, end) = self.get_array_tick_range(start_index);
         
         println!("--- Info for Tick Array starting at {} ---", start_index);
         println!("  - Tick Spacing: {}", self.tick_spacing);
         println!("  - Covers Tick Index Range: [{}, {}]", start, end);
+        let last_valid_tick = self.last_valid_tick(start_index);
+        println!("  - Last Valid Tick: {} (slot {})", last_valid_tick, start_index.slot_of(last_valid_tick, self.tick_spacing));
         println!("  - Contains {} storage 'Slots'. Each slot holds data for one *valid* tick.", TICK_ARRAY_SIZE);
         println!("  - Slot to Tick Index Mapping:");
 
         for slot in 0..TICK_ARRAY_SIZE {
-            let tick_index = start_index + (slot * self.tick_spacing as i32);
-            println!("    - Slot {:2}: Tick {}", slot, tick_index);
+            println!("    - Slot {:2}: Tick {}", slot, start_index.tick_at_slot(slot, self.tick_spacing));
         }
     }
 }
 
 /// --- CLI Argument Parsing ---
 #[derive(Parser)]
-#[clap(author, version, about, long_about = None)]
+#[clap(author, version, about, long_about = None, after_help = EXIT_CODES_HELP)]
 struct Cli {
+    /// Cluster shortcut: selects the default RPC URL and the known CLMM program id.
+    #[clap(long, global = true, value_enum, default_value_t = Cluster::Mainnet)]
+    cluster: Cluster,
+    /// CLMM program id used for all PDA derivations (overrides the --cluster default).
+    #[clap(long, global = true, env = "RAYDIUM_CLMM_PROGRAM_ID")]
+    program_id: Option<String>,
+    /// The RPC URL (defaults to the public endpoint of the selected --cluster when RAYDIUM_RPC_URL is unset).
+    /// Repeat the flag or pass a comma-separated list to spread fetches across several endpoints.
+    #[clap(long, global = true, value_delimiter = ',', env = "RAYDIUM_RPC_URL")]
+    rpc_url: Vec<String>,
+    /// Pin all fetches of a command to the first endpoint that responds, avoiding cross-endpoint slot skew.
+    #[clap(long, global = true)]
+    strict_single_slot: bool,
+    /// Client-side limit on RPC requests per second (a batched request counts as one).
+    #[clap(long, global = true)]
+    max_rps: Option<f64>,
+    /// Maximum number of tick array fetches in flight at once.
+    #[clap(long, global = true, default_value_t = 4)]
+    concurrency: usize,
+    /// Do not show progress bars on stderr.
+    #[clap(long, global = true)]
+    quiet: bool,
+    /// Highlight the current price, initialized ticks and warnings with ANSI colors.
+    /// `auto` colors only when stdout is a terminal and NO_COLOR is unset.
+    #[clap(long, global = true, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+    /// Commitment level used for all RPC fetches.
+    #[clap(long, global = true, value_enum, default_value_t = Commitment::Finalized)]
+    commitment: Commitment,
+    /// Number of times a failed RPC request is retried (rate limits, timeouts, 5xx).
+    #[clap(long, global = true, default_value = "3")]
+    retries: u32,
+    /// Delay before the first retry in milliseconds; doubled on each further retry.
+    #[clap(long, global = true, default_value = "500")]
+    retry_delay_ms: u64,
+    /// Require the RPC to serve account data at or after this slot (`min_context_slot`);
+    /// fetches from a node that is behind fail instead of returning stale state.
+    #[clap(long, global = true)]
+    min_slot: Option<u64>,
+    /// Cache fetched pool accounts in this directory, so offline commands given --pool-id reuse
+    /// the tick spacing and mint decimals across runs instead of fetching the pool each time.
+    #[clap(long, global = true, env = "RAYDIUM_CACHE_DIR")]
+    cache_dir: Option<std::path::PathBuf>,
+    /// Seconds a cached pool account stays valid.
+    #[clap(long, global = true, default_value = "3600")]
+    cache_ttl_secs: u64,
+    /// Ignore cached pool accounts and fetch again (the cache is still refreshed).
+    #[clap(long, global = true)]
+    no_cache: bool,
+    /// Print prices with this many decimal places instead of six significant digits.
+    #[clap(long, global = true)]
+    precision: Option<usize>,
+    /// Print prices in scientific notation.
+    #[clap(long, global = true)]
+    scientific: bool,
     #[clap(subcommand)]
     command: Commands,
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Cluster {
+    Mainnet,
+    Devnet,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Commitment {
+    Processed,
+    Confirmed,
+    Finalized,
+}
+
+impl Commitment {
+    fn config(&self) -> CommitmentConfig {
+        match self {
+            Commitment::Processed => CommitmentConfig::processed(),
+            Commitment::Confirmed => CommitmentConfig::confirmed(),
+            Commitment::Finalized => CommitmentConfig::finalized(),
+        }
+    }
+}
+
+impl Cluster {
+    fn default_rpc_url(&self) -> &'static str {
+        match self {
+            Cluster::Mainnet => MAINNET_RPC_URL,
+            Cluster::Devnet => DEVNET_RPC_URL,
+        }
+    }
+
+    fn default_program_id(&self) -> &'static str {
+        match self {
+            Cluster::Mainnet => RAYDIUM_CLMM_PROGRAM_ID,
+            Cluster::Devnet => RAYDIUM_CLMM_DEVNET_PROGRAM_ID,
+        }
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Convert a tick index to various price formats.
     TickToPrice {
         #[clap(long)]
         tick: i32,
+        #[clap(long, required_unless_present = "pool_id", value_parser = decimals_parser())]
+        decimals0: Option<u8>,
+        #[clap(long, required_unless_present = "pool_id", value_parser = decimals_parser())]
+        decimals1: Option<u8>,
+        /// Fetch the decimals from the pool instead of passing them (uses --rpc-url); explicit flags are cross-checked.
         #[clap(long)]
-        decimals0: u8,
+        pool_id: Option<String>,
+        /// The decimals were given in the opposite token order: swaps them. Output stays in the pool's token order.
         #[clap(long)]
-        decimals1: u8,
+        swap_tokens: bool,
+        /// Print only the price in this format, as a bare number, instead of every representation.
+        #[clap(long, value_enum)]
+        format: Option<ArgPriceFormat>,
     },
     /// Convert a price (in various formats) to a tick index.
     PriceToTick {
+        #[clap(long, required_unless_present = "pool_id", value_parser = decimals_parser())]
+        decimals0: Option<u8>,
+        #[clap(long, required_unless_present = "pool_id", value_parser = decimals_parser())]
+        decimals1: Option<u8>,
+        /// Fetch the decimals from the pool instead of passing them (uses --rpc-url); explicit flags are cross-checked.
+        #[clap(long)]
+        pool_id: Option<String>,
+        /// How to round a price that falls between two ticks. Use `ceil` for position upper bounds.
+        /// A price within 1e-6 ticks of a tick counts as on it, so exact tick prices round to themselves.
+        #[clap(long, alias = "round", value_enum, default_value_t = RoundingMode::Floor)]
+        rounding: RoundingMode,
+        /// Tick spacing for `--rounding nearest-aligned` (read from --pool-id when omitted).
+        #[clap(long)]
+        tick_spacing: Option<u16>,
+        /// Your token 0 and token 1 are the pool's token 1 and token 0: swaps the decimals and flips the price format.
+        #[clap(long)]
+        swap_tokens: bool,
+        #[clap(subcommand)]
+        price: PriceInput,
+    },
+    /// Convert a price to the exact `sqrt_price_x64` of its tick, e.g. for a swap's `sqrt_price_limit_x64`.
+    PriceToSqrtPrice {
         #[clap(long)]
+        price: f64,
+        #[clap(long, value_enum, default_value_t = ArgPriceFormat::T1PerT0Human)]
+        format: ArgPriceFormat,
+        #[clap(long, required_unless_present = "pool_id", value_parser = decimals_parser())]
+        decimals0: Option<u8>,
+        #[clap(long, required_unless_present = "pool_id", value_parser = decimals_parser())]
+        decimals1: Option<u8>,
+        /// Fetch the decimals from the pool instead of passing them (uses --rpc-url); explicit flags are cross-checked.
+        #[clap(long)]
+        pool_id: Option<String>,
+        /// How to round a price that falls between two ticks (`nearest-aligned` acts like `nearest`).
+        #[clap(long, alias = "round", value_enum, default_value_t = RoundingMode::Floor)]
+        rounding: RoundingMode,
+        /// Your token 0 and token 1 are the pool's token 1 and token 0: swaps the decimals and flips the price format.
+        #[clap(long)]
+        swap_tokens: bool,
+    },
+    /// Convert a `sqrt_price_x64` (as stored in `PoolState` or shown by explorers) to prices and its tick.
+    SqrtToPrice {
+        #[clap(long)]
+        sqrt_price_x64: u128,
+        #[clap(long, value_parser = decimals_parser())]
         decimals0: u8,
+        #[clap(long, value_parser = decimals_parser())]
+        decimals1: u8,
+        /// Also report whether the tick is a multiple of this tick spacing.
         #[clap(long)]
+        tick_spacing: Option<u16>,
+        /// The decimals were given in the opposite token order: swaps them. Output stays in the pool's token order.
+        #[clap(long)]
+        swap_tokens: bool,
+    },
+    /// Convert a price to its `sqrt_price_x64` without snapping to a tick, e.g. to initialize a new pool.
+    PriceToSqrt {
+        #[clap(long, value_parser = decimals_parser())]
+        decimals0: u8,
+        #[clap(long, value_parser = decimals_parser())]
         decimals1: u8,
+        /// Your token 0 and token 1 are the pool's token 1 and token 0: swaps the decimals and flips the price format.
+        #[clap(long)]
+        swap_tokens: bool,
         #[clap(subcommand)]
         price: PriceInput,
     },
@@ -214,19 +612,47 @@ enum Commands {
     TickInfo {
         #[clap(long)]
         tick: i32,
+        #[clap(long, required_unless_present = "pool_id")]
+        tick_spacing: Option<u16>,
+        /// Fetch the missing tick spacing and decimals from this pool (uses --rpc-url); explicit flags are cross-checked.
+        #[clap(long)]
+        pool_id: Option<String>,
+    },
+    /// Print only the start index of the array containing a tick, for scripts.
+    TickToArrayStart {
+        #[clap(long, allow_hyphen_values = true)]
+        tick: i32,
         #[clap(long)]
         tick_spacing: u16,
     },
-    /// Convert a tick array to its corresponding price range.
-    ArrayToPriceRange {
+    /// Print only a tick rounded down to the tick spacing, for scripts.
+    AlignTick {
+        #[clap(long, allow_hyphen_values = true)]
+        tick: i32,
         #[clap(long)]
-        start_index: i32,
+        tick_spacing: u16,
+    },
+    /// Convert a percentage price move to a tick count, and the tick arrays it spans.
+    PctToTicks {
+        /// Price move in percent; negative for a downward move (e.g. --pct=-1.5).
+        #[clap(long, allow_hyphen_values = true)]
+        pct: f64,
         #[clap(long)]
         tick_spacing: u16,
+    },
+    /// Convert a tick array to its corresponding price range.
+    ArrayToPriceRange {
         #[clap(long)]
-        decimals0: u8,
+        start_index: i32,
+        #[clap(long, required_unless_present = "pool_id")]
+        tick_spacing: Option<u16>,
+        #[clap(long, required_unless_present = "pool_id", value_parser = decimals_parser())]
+        decimals0: Option<u8>,
+        #[clap(long, required_unless_present = "pool_id", value_parser = decimals_parser())]
+        decimals1: Option<u8>,
+        /// Fetch the missing tick spacing and decimals from this pool (uses --rpc-url); explicit flags are cross-checked.
         #[clap(long)]
-        decimals1: u8,
+        pool_id: Option<String>,
     },
     /// Find all tick arrays that a given price range crosses.
     PriceRangeToArrays {
@@ -234,35 +660,196 @@ enum Commands {
         price_lower: f64,
         #[clap(long)]
         price_upper: f64,
-        #[clap(long)]
-        tick_spacing: u16,
-        #[clap(long)]
-        decimals0: u8,
-        #[clap(long)]
-        decimals1: u8,
+        #[clap(long, required_unless_present = "pool_id")]
+        tick_spacing: Option<u16>,
+        #[clap(long, required_unless_present = "pool_id", value_parser = decimals_parser())]
+        decimals0: Option<u8>,
+        #[clap(long, required_unless_present = "pool_id", value_parser = decimals_parser())]
+        decimals1: Option<u8>,
         #[clap(long, value_enum, default_value_t = ArgPriceFormat::T1PerT0Human)]
         format: ArgPriceFormat,
+        /// Fetch the missing tick spacing and decimals from this pool (uses --rpc-url); explicit flags are cross-checked.
+        #[clap(long)]
+        pool_id: Option<String>,
+        /// Round both prices with this mode. By default the range rounds outward (lower tick down, upper tick up).
+        #[clap(long, alias = "rounding", value_enum)]
+        round: Option<RoundingMode>,
+        /// Your token 0 and token 1 are the pool's token 1 and token 0: swaps the decimals and flips the price format.
+        #[clap(long)]
+        swap_tokens: bool,
     },
     /// Derive the PDA for a tick array from a tick index or price.
     DerivePda {
         #[clap(long)]
         pool_id: String,
+        /// Fetched from the pool when omitted (uses --rpc-url); cross-checked when --rpc-url is given.
         #[clap(long)]
-        tick_spacing: u16,
-        #[clap(long)]
-        decimals0: u8,
+        tick_spacing: Option<u16>,
+        /// Only needed for price input; fetched from the pool when omitted.
+        #[clap(long, value_parser = decimals_parser())]
+        decimals0: Option<u8>,
+        #[clap(long, value_parser = decimals_parser())]
+        decimals1: Option<u8>,
+        /// Tick to derive the array PDA for; repeat for several ticks.
         #[clap(long)]
-        decimals1: u8,
+        tick: Vec<i32>,
+        /// `LOWER,UPPER`: also derive every array covering this tick range.
+        #[clap(long, value_delimiter = ',', allow_hyphen_values = true, value_name = "LOWER,UPPER")]
+        tick_range: Vec<i32>,
+        /// Print the exact PDA seed bytes (hex), including the big-endian start index.
+        #[clap(long, conflicts_with = "json")]
+        debug_seeds: bool,
+        /// Print the arrays as JSON.
         #[clap(long)]
-        tick: Option<i32>,
+        json: bool,
         #[clap(subcommand)]
         price: Option<PriceInput>,
     },
+    /// Derive the PDAs of every tick array covering a tick range.
+    TickRangeToPdas {
+        #[clap(long)]
+        pool_id: String,
+        #[clap(long, allow_hyphen_values = true)]
+        tick_lower: i32,
+        #[clap(long, allow_hyphen_values = true)]
+        tick_upper: i32,
+        #[clap(long)]
+        tick_spacing: u16,
+    },
+    /// Show which bitmap word and bit track a tick array (no RPC needed).
+    BitmapPosition {
+        /// Array start index to look up (mutually exclusive with --tick).
+        #[clap(long, group = "input", allow_hyphen_values = true)]
+        start_index: Option<i32>,
+        /// Any tick; its containing array is looked up (mutually exclusive with --start-index).
+        #[clap(long, group = "input", allow_hyphen_values = true)]
+        tick: Option<i32>,
+        #[clap(long)]
+        tick_spacing: u16,
+    },
+    /// Recover the start index, tick range and price range of a tick array from its PDA.
+    /// Searches every candidate start index offline; with an RPC URL, reads the account instead.
+    ReversePda {
+        #[clap(long)]
+        pool_id: String,
+        /// The tick array PDA to look up.
+        #[clap(long)]
+        pda: String,
+        /// Required for the offline search; with an RPC URL the pool's spacing is used and this is cross-checked.
+        #[clap(long)]
+        tick_spacing: Option<u16>,
+        /// Mint decimals for human prices in the offline search (raw prices without them).
+        #[clap(long, value_parser = decimals_parser(), requires = "decimals1")]
+        decimals0: Option<u8>,
+        #[clap(long, value_parser = decimals_parser(), requires = "decimals0")]
+        decimals1: Option<u8>,
+    },
+    /// Check a tick array PDA derived elsewhere (e.g. by an SDK) against the one derived here. Exits 1 on mismatch.
+    VerifyPda {
+        #[clap(long)]
+        pool_id: String,
+        #[clap(long, allow_hyphen_values = true)]
+        start_index: i32,
+        /// The PDA to check.
+        #[clap(long)]
+        pda: String,
+        /// Also check that --start-index is a multiple of 60 * tick spacing, and on a mismatch
+        /// search for the start index the PDA actually belongs to.
+        #[clap(long)]
+        tick_spacing: Option<u16>,
+    },
+    /// Size a position: liquidity from token amounts, or token amounts from liquidity (exact integer math).
+    #[clap(subcommand)]
+    PositionMath(PositionMathCommands),
+    /// Impermanent loss of a concentrated position between an entry and an exit price, versus holding.
+    Il {
+        #[clap(long)]
+        price_lower: f64,
+        #[clap(long)]
+        price_upper: f64,
+        /// Price when the position is opened.
+        #[clap(long)]
+        entry_price: f64,
+        /// Price to evaluate the position at.
+        #[clap(long)]
+        exit_price: f64,
+        /// Price format of all four prices.
+        #[clap(long, value_enum, default_value_t = ArgPriceFormat::T1PerT0Human)]
+        format: ArgPriceFormat,
+        /// Position value at entry, in human token 1 units. Only scales the amounts.
+        #[clap(long, default_value_t = 1.0)]
+        deposit_value: f64,
+        #[clap(long, required_unless_present = "pool_id", value_parser = decimals_parser())]
+        decimals0: Option<u8>,
+        #[clap(long, required_unless_present = "pool_id", value_parser = decimals_parser())]
+        decimals1: Option<u8>,
+        /// Fetch the decimals from the pool instead of passing them (uses --rpc-url); explicit flags are cross-checked.
+        #[clap(long)]
+        pool_id: Option<String>,
+    },
     /// --- New RPC Commands ---
     #[clap(subcommand)]
     Rpc(RpcCommands),
 }
 
+#[derive(Subcommand)]
+enum PositionMathCommands {
+    /// Liquidity that a deposit of token 0 and token 1 provides over a price range, and the amounts it uses.
+    LiquidityFromAmounts {
+        /// Token 0 amount in human units.
+        #[clap(long)]
+        amount0: f64,
+        /// Token 1 amount in human units.
+        #[clap(long)]
+        amount1: f64,
+        #[clap(long)]
+        price_lower: f64,
+        #[clap(long)]
+        price_upper: f64,
+        /// Current pool price (read from --pool-id when omitted).
+        #[clap(long, required_unless_present = "pool_id")]
+        current_price: Option<f64>,
+        /// Price format of --price-lower, --price-upper and --current-price.
+        #[clap(long, value_enum, default_value_t = ArgPriceFormat::T1PerT0Human)]
+        format: ArgPriceFormat,
+        #[clap(long, required_unless_present = "pool_id", conflicts_with = "pool_id", value_parser = decimals_parser())]
+        decimals0: Option<u8>,
+        #[clap(long, required_unless_present = "pool_id", conflicts_with = "pool_id", value_parser = decimals_parser())]
+        decimals1: Option<u8>,
+        /// Snap the range to multiples of this tick spacing (default 1; read from --pool-id).
+        #[clap(long, conflicts_with = "pool_id")]
+        tick_spacing: Option<u16>,
+        /// Read the current price, decimals and tick spacing from the pool (uses --rpc-url).
+        #[clap(long)]
+        pool_id: Option<String>,
+    },
+    /// Token amounts that a liquidity over a price range takes to deposit and returns on withdrawal.
+    AmountsFromLiquidity {
+        #[clap(long)]
+        liquidity: u128,
+        #[clap(long)]
+        price_lower: f64,
+        #[clap(long)]
+        price_upper: f64,
+        /// Current pool price (read from --pool-id when omitted).
+        #[clap(long, required_unless_present = "pool_id")]
+        current_price: Option<f64>,
+        /// Price format of --price-lower, --price-upper and --current-price.
+        #[clap(long, value_enum, default_value_t = ArgPriceFormat::T1PerT0Human)]
+        format: ArgPriceFormat,
+        #[clap(long, required_unless_present = "pool_id", conflicts_with = "pool_id", value_parser = decimals_parser())]
+        decimals0: Option<u8>,
+        #[clap(long, required_unless_present = "pool_id", conflicts_with = "pool_id", value_parser = decimals_parser())]
+        decimals1: Option<u8>,
+        /// Snap the range to multiples of this tick spacing (default 1; read from --pool-id).
+        #[clap(long, conflicts_with = "pool_id")]
+        tick_spacing: Option<u16>,
+        /// Read the current price, decimals and tick spacing from the pool (uses --rpc-url).
+        #[clap(long)]
+        pool_id: Option<String>,
+    },
+}
+
 #[derive(ValueEnum, Clone, Copy, Debug)]
 enum ArgPriceFormat {
     T1PerT0Raw,
@@ -271,6 +858,28 @@ enum ArgPriceFormat {
     T0PerT1Human,
 }
 
+impl ArgPriceFormat {
+    /// Pairs a price with this format.
+    fn with_price(self, price: f64) -> PriceInput {
+        match self {
+            ArgPriceFormat::T1PerT0Raw => PriceInput::Token1PerToken0Raw { price },
+            ArgPriceFormat::T0PerT1Raw => PriceInput::Token0PerToken1Raw { price },
+            ArgPriceFormat::T1PerT0Human => PriceInput::Token1PerToken0Human { price },
+            ArgPriceFormat::T0PerT1Human => PriceInput::Token0PerToken1Human { price },
+        }
+    }
+
+    /// The same quantity seen with token 0 and token 1 exchanged (`--swap-tokens`).
+    fn flipped(self) -> Self {
+        match self {
+            ArgPriceFormat::T1PerT0Raw => ArgPriceFormat::T0PerT1Raw,
+            ArgPriceFormat::T0PerT1Raw => ArgPriceFormat::T1PerT0Raw,
+            ArgPriceFormat::T1PerT0Human => ArgPriceFormat::T0PerT1Human,
+            ArgPriceFormat::T0PerT1Human => ArgPriceFormat::T1PerT0Human,
+        }
+    }
+}
+
 // --- New CLI Commands for RPC ---
 #[derive(Subcommand)]
 enum RpcCommands {
@@ -278,29 +887,36 @@ enum RpcCommands {
     PoolState {
         #[clap(long)]
         pool_id: String,
-        #[clap(long, default_value = "https://api.mainnet-beta.solana.com")]
-        rpc_url: String,
+        /// Also print the pool's accounts, open time and last-update epoch.
+        #[clap(long)]
+        full: bool,
+        /// Print every field of the decoded account (Debug format) instead of the curated view.
+        #[clap(long, conflicts_with = "full")]
+        raw: bool,
     },
     /// Fetches the Token 0 and Token 1 mint addresses for a pool.
     TokenMints {
         #[clap(long)]
         pool_id: String,
-        #[clap(long, default_value = "https://api.mainnet-beta.solana.com")]
-        rpc_url: String,
     },
     /// Fetches and reads the default bitmap from the pool state.
     DefaultBitmap {
          #[clap(long)]
         pool_id: String,
-        #[clap(long, default_value = "https://api.mainnet-beta.solana.com")]
-        rpc_url: String,
+        /// Print only start indices and tick ranges, skipping the price conversions.
+        #[clap(long)]
+        no_prices: bool,
     },
     /// Fetches and reads the bitmap extension account.
     ExtensionBitmap {
          #[clap(long)]
         pool_id: String,
-        #[clap(long, default_value = "https://api.mainnet-beta.solana.com")]
-        rpc_url: String,
+        /// Print only start indices and tick ranges, skipping the price conversions.
+        #[clap(long)]
+        no_prices: bool,
+        /// Print every field of the decoded extension (Debug format) instead of the array list.
+        #[clap(long, conflicts_with = "no_prices")]
+        raw: bool,
     },
     /// Fetches and parses a specific tick array account.
     TickArray {
@@ -308,8 +924,12 @@ enum RpcCommands {
         pool_id: String,
         #[clap(long)]
         start_index: i32,
-        #[clap(long, default_value = "https://api.mainnet-beta.solana.com")]
-        rpc_url: String,
+        /// Print every field of the decoded array (Debug format) instead of the curated view.
+        #[clap(long)]
+        raw: bool,
+        /// The price format of the per-tick prices.
+        #[clap(long, value_enum, default_value_t = HumanPriceFormat::T0PerT1, conflicts_with = "raw")]
+        format: HumanPriceFormat,
     },
     /// Fetches pool state and all bitmaps to provide a full liquidity analysis.
     FullAnalysis {
@@ -317,8 +937,25 @@ enum RpcCommands {
         pool_id: String,
         #[clap(long, value_enum, default_value_t = HumanPriceFormat::T0PerT1)]
         format: HumanPriceFormat,
-        #[clap(long, default_value = "https://api.mainnet-beta.solana.com")]
-        rpc_url: String,
+        /// Read pool state and extension in one call pinned to the current slot, then fetch
+        /// every initialized array together and report bitmap/array inconsistencies.
+        #[clap(long)]
+        snapshot: bool,
+        /// Also fetch every initialized array (in batches of 100) and show its initialized tick
+        /// count. Rows are printed as each batch arrives.
+        #[clap(long)]
+        with_ticks: bool,
+        /// Like --with-ticks, adding each array's summed liquidity_gross and net liquidity change.
+        #[clap(long)]
+        detailed: bool,
+    },
+    /// Writes the pool state, both bitmaps and every initialized tick array to a JSON file, for offline analysis.
+    Snapshot {
+        #[clap(long)]
+        pool_id: String,
+        /// Path of the JSON file to write.
+        #[clap(long)]
+        out: std::path::PathBuf,
     },
     /// Displays a text-based visualization of the pool's liquidity distribution.
     LiquidityCurve {
@@ -328,46 +965,62 @@ enum RpcCommands {
         format: HumanPriceFormat,
         #[clap(long, default_value = "50")]
         max_width: usize,
-        #[clap(long, default_value = "https://api.mainnet-beta.solana.com")]
-        rpc_url: String,
         /// Show tick array start/end markers (debug mode)
         #[clap(long)]
         show_arrays: bool,
+        /// Scale bars logarithmically so thin and deep liquidity regions are both visible.
+        #[clap(long)]
+        log_scale: bool,
+        /// Write the chart as an SVG file to this path instead of printing it.
+        #[clap(long)]
+        svg: Option<std::path::PathBuf>,
     },
     /// Fetches all *initialized* tick arrays within a given price range and their neighbors.
     InitializedRange {
         #[clap(long)]
         pool_id: String,
-        #[clap(long)]
-        price_lower: f64,
-        #[clap(long)]
-        price_upper: f64,
-        /// The price format for your --price-lower and --price-upper inputs
+        #[clap(long, required_unless_present = "tick_lower", conflicts_with_all = ["tick_lower", "tick_upper"])]
+        price_lower: Option<f64>,
+        #[clap(long, required_unless_present = "tick_upper", conflicts_with_all = ["tick_lower", "tick_upper"])]
+        price_upper: Option<f64>,
+        /// Alternative to --price-lower: the lower tick, used as given with no price conversion.
+        #[clap(long, allow_hyphen_values = true, requires = "tick_upper")]
+        tick_lower: Option<i32>,
+        /// Alternative to --price-upper: the upper tick, used as given with no price conversion.
+        #[clap(long, allow_hyphen_values = true, requires = "tick_lower")]
+        tick_upper: Option<i32>,
+        /// The price format for your --price-lower and --price-upper inputs, and for the prices printed.
         #[clap(long, value_enum)] 
         format: HumanPriceFormat,
-        /// The RPC URL (uses the same default as other commands)
-        #[clap(long, default_value = "https://api.mainnet-beta.solana.com")]
-        rpc_url: String,
+        /// Round both prices with this mode. By default the range rounds outward (lower tick down, upper tick up).
+        #[clap(long, alias = "rounding", value_enum, conflicts_with_all = ["tick_lower", "tick_upper"])]
+        round: Option<RoundingMode>,
     },
     /// Fetches initialized arrays based on a center price and percentage range.
     InitializedRangePercent {
         #[clap(long)]
         pool_id: String,
-        /// The center price for the range.
-        #[clap(long)]
-        price: f64,
+        /// The center price for the range. With --arrays-below/--arrays-above it defaults to the current price.
+        #[clap(long, required_unless_present = "arrays_below")]
+        price: Option<f64>,
         /// The lower-end percentage (e.g., 10 for -10%).
-        #[clap(long)]
-        lower_pct: f64,
+        #[clap(long, required_unless_present = "arrays_below", conflicts_with_all = ["arrays_below", "arrays_above"])]
+        lower_pct: Option<f64>,
         /// The upper-end percentage (e.g., 30 for +30%).
-        #[clap(long)]
-        upper_pct: f64,
+        #[clap(long, required_unless_present = "arrays_below", conflicts_with_all = ["arrays_below", "arrays_above"])]
+        upper_pct: Option<f64>,
+        /// Alternative to the percentages: cover this many tick arrays below the array holding the center price.
+        #[clap(long, requires = "arrays_above")]
+        arrays_below: Option<u32>,
+        /// Alternative to the percentages: cover this many tick arrays above the array holding the center price.
+        #[clap(long, requires = "arrays_below")]
+        arrays_above: Option<u32>,
         /// The price format for your --price input.
         #[clap(long, value_enum)] 
         format: HumanPriceFormat,
-        /// The RPC URL (uses the same default as other commands)
-        #[clap(long, default_value = "https://api.mainnet-beta.solana.com")]
-        rpc_url: String,
+        /// Round both prices with this mode. By default the range rounds outward (lower tick down, upper tick up).
+        #[clap(long, alias = "rounding", value_enum, conflicts_with = "arrays_below")]
+        round: Option<RoundingMode>,
     },
     /// Calculates the required tick arrays for a swap.
     GetSwapArrays {
@@ -379,18 +1032,34 @@ enum RpcCommands {
         /// Price format for the --price argument (e.g., 't0-per-t1').
         #[clap(long, value_enum)]
         format: HumanPriceFormat,
-        /// Max % price move in your favor (for tx latency). e.g., 0.1
+        /// Max % the bought token may get cheaper before the swap lands (better fill; tx latency). e.g., 0.1
+        #[clap(long, required_unless_present = "favorable_ticks", conflicts_with_all = ["favorable_ticks", "impact_ticks"])]
+        favorable_pct: Option<f64>,
+        /// Max % the swap may move the price against you (swap impact). e.g., 0.5
+        #[clap(long, required_unless_present = "impact_ticks", conflicts_with_all = ["favorable_ticks", "impact_ticks"])]
+        impact_pct: Option<f64>,
+        /// Alternative to --favorable-pct: offset in ticks from the start tick on the favorable side.
         #[clap(long)]
-        favorable_pct: f64,
-        /// Max % price move against you (for swap impact). e.g., 0.5
+        favorable_ticks: Option<u32>,
+        /// Alternative to --impact-pct: offset in ticks from the start tick on the impact side.
         #[clap(long)]
-        impact_pct: f64,
+        impact_ticks: Option<u32>,
         /// Optional: The price to start the calculation from.
         /// If not provided, uses the pool's live current price.
         #[clap(long)]
         price: Option<f64>,
-        #[clap(long, default_value = "https://api.mainnet-beta.solana.com")]
-        rpc_url: String,
+        /// Output format. `json` and `ts` print only the ordered array list, for transaction builders.
+        #[clap(long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+        /// Also print the complete ordered `swap_v2` account list, with signer/writable roles.
+        #[clap(long)]
+        full_accounts: bool,
+        /// Fetch the selected arrays and check that each exists on-chain (exit code 1 if any is missing).
+        #[clap(long)]
+        verify: bool,
+        /// Also compute the swap's `sqrt_price_limit_x64` for this slippage %, from the live pool price.
+        #[clap(long)]
+        slippage_pct: Option<f64>,
     },
     /// Calculates the required tick arrays for a swap (blindly, assumes all arrays exist).
     GetSwapArraysBlind {
@@ -402,258 +1071,1751 @@ enum RpcCommands {
         /// Price format for the --price argument (e.g., 't0-per-t1').
         #[clap(long, value_enum)]
         format: HumanPriceFormat,
-        /// Max % price move in your favor (for tx latency). e.g., 0.1
+        /// Max % the bought token may get cheaper before the swap lands (better fill; tx latency). e.g., 0.1
+        #[clap(long, required_unless_present = "favorable_ticks", conflicts_with_all = ["favorable_ticks", "impact_ticks"])]
+        favorable_pct: Option<f64>,
+        /// Max % the swap may move the price against you (swap impact). e.g., 0.5
+        #[clap(long, required_unless_present = "impact_ticks", conflicts_with_all = ["favorable_ticks", "impact_ticks"])]
+        impact_pct: Option<f64>,
+        /// Alternative to --favorable-pct: offset in ticks from the start tick on the favorable side.
         #[clap(long)]
-        favorable_pct: f64,
-        /// Max % price move against you (for swap impact). e.g., 0.5
+        favorable_ticks: Option<u32>,
+        /// Alternative to --impact-pct: offset in ticks from the start tick on the impact side.
         #[clap(long)]
-        impact_pct: f64,
+        impact_ticks: Option<u32>,
         /// Optional: The price to start the calculation from.
         /// If not provided, uses the pool's live current price.
         #[clap(long)]
         price: Option<f64>,
-        #[clap(long, default_value = "https://api.mainnet-beta.solana.com")]
-        rpc_url: String,
+        /// Output format. `json` and `ts` print only the ordered array list, for transaction builders.
+        #[clap(long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
     },
-    /// Fetches and visually inspects a single tick array by start index OR PDA.
-    InspectArray {
+    /// Builds an unsigned swap_v2 instruction (discriminator, Borsh args, account metas) and prints it. Never signs or sends.
+    BuildSwapIx {
         #[clap(long)]
         pool_id: String,
-        /// The start tick index of the array to inspect (mutually exclusive with --pda)
-        #[clap(long, group = "input")]
-        start_index: Option<i32>,
-        /// The PDA of the array to inspect (mutually exclusive with --start-index)
-        #[clap(long, group = "input")]
-        pda: Option<String>,
-        #[clap(long, default_value = "https://api.mainnet-beta.solana.com")]
-        rpc_url: String,
-    }
-}
-
-#[derive(ValueEnum, Clone, Copy, Debug)]
-enum HumanPriceFormat {
-    T0PerT1,
-    T1PerT0,
-}
-
-#[derive(ValueEnum, Clone, Copy, Debug)]
-enum SwapDirection {
-    /// Buying Token 1 by selling Token 0. Price/tick increases.
-    #[clap(name = "buy-t1")]
-    BuyT1,
-    /// Buying Token 0 by selling Token 1. Price/tick decreases.
-    #[clap(name = "buy-t0")]
-    BuyT0,
-}
-
-// --- Liquidity Curve Helper Structs and Functions ---
-
-fn format_liquidity(liquidity: u128) -> String {
-    if liquidity >= 1_000_000_000_000 {
-        format!("{:.2}T", liquidity as f64 / 1_000_000_000_000.0)
-    } else if liquidity >= 1_000_000_000 {
-        format!("{:.2}B", liquidity as f64 / 1_000_000_000.0)
-    } else if liquidity >= 1_000_000 {
-        format!("{:.2}M", liquidity as f64 / 1_000_000.0)
-    } else if liquidity >= 1_000 {
-        format!("{:.2}K", liquidity as f64 / 1_000.0)
-    } else {
-        format!("{}", liquidity)
+        #[clap(long, value_enum)]
+        direction: SwapDirection,
+        /// Amount in raw base units: the exact input, or the exact output with --exact-out.
+        #[clap(long)]
+        amount: u64,
+        /// Raw base units: the minimum output, or the maximum input with --exact-out.
+        #[clap(long)]
+        other_amount_threshold: u64,
+        /// How far the raw price may move against the swap, in percent. Sets sqrt_price_limit_x64 and the tick arrays.
+        #[clap(long)]
+        slippage_pct: f64,
+        /// Max % the price may move in the trader's favor before the swap lands (adds arrays on that side).
+        #[clap(long, default_value_t = 0.0)]
+        favorable_pct: f64,
+        /// Treat --amount as the exact output (is_base_input = false).
+        #[clap(long)]
+        exact_out: bool,
+        /// Wallet that will sign the swap and owns the token accounts.
+        #[clap(long)]
+        payer: String,
+        /// Token account paying the input. Defaults to the payer's associated token account.
+        #[clap(long)]
+        input_token_account: Option<String>,
+        /// Token account receiving the output. Defaults to the payer's associated token account.
+        #[clap(long)]
+        output_token_account: Option<String>,
+        /// Print only the instruction JSON.
+        #[clap(long)]
+        json: bool,
+    },
+    /// Prints a one-line pool summary for logs: tick, price, liquidity, tick spacing and status.
+    Summary {
+        #[clap(long)]
+        pool_id: String,
+        #[clap(long, value_enum, default_value_t = HumanPriceFormat::T0PerT1)]
+        format: HumanPriceFormat,
+    },
+    /// Reports how far a swap can move the price within N tick arrays, or how many arrays an X% move needs.
+    Coverage {
+        #[clap(long)]
+        pool_id: String,
+        #[clap(long, value_enum)]
+        direction: SwapDirection,
+        /// Number of tick arrays passed to the swap, starting with the current one.
+        #[clap(long, required_unless_present = "target_pct", conflicts_with = "target_pct")]
+        num_arrays: Option<u32>,
+        /// Price move to cover in the swap direction, in percent (e.g., 5 for 5%).
+        #[clap(long)]
+        target_pct: Option<f64>,
+        /// Count only initialized arrays, from the bitmaps (uninitialized ones are skipped by the swap).
+        #[clap(long)]
+        initialized_only: bool,
+    },
+    /// Computes a swap's sqrt_price_limit_x64 from a slippage tolerance, with exact integer math on the live price.
+    SwapLimit {
+        #[clap(long)]
+        pool_id: String,
+        #[clap(long, value_enum)]
+        direction: SwapDirection,
+        /// How far the raw price may move against the swap, in percent (e.g., 1 for 1%).
+        #[clap(long)]
+        slippage_pct: f64,
+    },
+    /// Computes the tick and price at the far edge of the Nth tick array a swap would cross from the current tick.
+    ArraysToPrice {
+        #[clap(long)]
+        pool_id: String,
+        #[clap(long, value_enum)]
+        direction: SwapDirection,
+        /// Number of arrays, counting the one holding the current tick as the first.
+        #[clap(long, default_value_t = 3)]
+        num_arrays: u32,
+    },
+    /// Fetches and visually inspects a single tick array by start index OR PDA.
+    InspectArray {
+        #[clap(long)]
+        pool_id: String,
+        /// The start tick index of the array to inspect (mutually exclusive with --pda)
+        #[clap(long, group = "input")]
+        start_index: Option<i32>,
+        /// The PDA of the array to inspect (mutually exclusive with --start-index)
+        #[clap(long, group = "input")]
+        pda: Option<String>,
+        /// Dump the raw account (owner, lamports, hex data with field offsets) instead of decoding it.
+        #[clap(long)]
+        raw: bool,
+        /// Print the exact PDA seed bytes (hex), including the big-endian start index.
+        #[clap(long)]
+        debug_seeds: bool,
+        /// Render the 60 slots as a compact 6x10 grid instead of one block per slot.
+        #[clap(long)]
+        grid: bool,
+        /// Render one row per slot (tick, price, liquidity net and gross), with runs of empty slots collapsed.
+        #[clap(long, conflicts_with_all = ["grid", "raw"])]
+        compact: bool,
+        /// The price format of the per-tick prices, the --compact table and the JSON slots.
+        #[clap(long, value_enum, default_value_t = HumanPriceFormat::T0PerT1)]
+        format: HumanPriceFormat,
+        /// Also print each initialized tick's fee and reward growth outside, and the fee growth inside
+        /// each band between adjacent initialized ticks.
+        #[clap(long, conflicts_with = "raw")]
+        show_fees: bool,
+        /// Output format. `json` prints only the slot table, as an array of all 60 slots.
+        #[clap(long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+    /// Recovers the start index, tick range and price range of a tick array from its PDA.
+    PdaInfo {
+        #[clap(long)]
+        pool_id: String,
+        /// The tick array PDA to look up.
+        #[clap(long)]
+        pda: String,
+    },
+    /// Computes the active liquidity that would be in range at a given price.
+    LiquidityAt {
+        #[clap(long)]
+        pool_id: String,
+        /// The price to query.
+        #[clap(long)]
+        price: f64,
+        /// The price format for your --price input.
+        #[clap(long, value_enum)]
+        format: HumanPriceFormat,
+    },
+    /// Finds the tick arrays (and PDAs) a new position needs for a price range, and whether they must be initialized.
+    PositionArrays {
+        #[clap(long)]
+        pool_id: String,
+        #[clap(long)]
+        price_lower: f64,
+        #[clap(long)]
+        price_upper: f64,
+        /// The price format for your --price-lower/--price-upper inputs.
+        #[clap(long, value_enum)]
+        format: HumanPriceFormat,
+    },
+    /// Previews opening a position: snapped ticks, the accounts `open_position_v2` needs, and the rent for arrays it must create.
+    PositionPreview {
+        #[clap(long)]
+        pool_id: String,
+        #[clap(long)]
+        price_lower: f64,
+        #[clap(long)]
+        price_upper: f64,
+        /// The price format for your --price-lower/--price-upper inputs.
+        #[clap(long, value_enum)]
+        format: HumanPriceFormat,
+    },
+    /// The pool's reward emissions: rate, schedule and budget of each active reward slot.
+    Rewards {
+        #[clap(long)]
+        pool_id: String,
+    },
+    /// The pool's fee counters in human units: LP fees, claimed fees, protocol and fund fees.
+    Fees {
+        #[clap(long)]
+        pool_id: String,
+        /// Sample the pool again after this many seconds and show the fees accrued in between.
+        #[clap(long)]
+        since: Option<u64>,
+    },
+    /// Lifetime swap volume per direction and its average execution price, from the pool's swap counters.
+    SwapStats {
+        #[clap(long)]
+        pool_id: String,
+        /// Sample the pool again after this many seconds and show the flow in between.
+        #[clap(long)]
+        sample_secs: Option<u64>,
+    },
+    /// Reward growth inside a tick range per reward token, and the rewards a liquidity accrues from it.
+    RangeRewards {
+        #[clap(long)]
+        pool_id: String,
+        #[clap(long, allow_hyphen_values = true)]
+        tick_lower: i32,
+        #[clap(long, allow_hyphen_values = true)]
+        tick_upper: i32,
+        /// Position liquidity to scale the growth by.
+        #[clap(long)]
+        liquidity: u128,
+    },
+    /// Checks that the pool's sqrt_price_x64 maps back to its tick_current with exact integer math. Exits 1 on mismatch.
+    VerifyPrice {
+        #[clap(long)]
+        pool_id: String,
+    },
+    /// Finds the nearest initialized tick at or beyond a tick, fetching only the arrays the bitmaps mark.
+    NextTick {
+        #[clap(long)]
+        pool_id: String,
+        /// Tick to search from (inclusive). Defaults to the pool's current tick.
+        #[clap(long, allow_hyphen_values = true)]
+        from_tick: Option<i32>,
+        #[clap(long, value_enum)]
+        direction: TickDirection,
+    },
+    /// Lists the next initialized tick arrays in a direction from the bitmaps alone (no array fetches).
+    NextArray {
+        #[clap(long)]
+        pool_id: String,
+        /// Tick to search from; its array is included if initialized. Defaults to the pool's current tick.
+        #[clap(long, allow_hyphen_values = true)]
+        from_tick: Option<i32>,
+        #[clap(long, value_enum)]
+        direction: TickDirection,
+        /// Number of arrays to list.
+        #[clap(long, default_value_t = 1)]
+        count: usize,
+    },
+    /// Computes the liquidity-weighted average tick/price of the initialized ticks (where LP liquidity is centered).
+    Lwap {
+        #[clap(long)]
+        pool_id: String,
+    },
+    /// Computes a time-weighted average tick/price from the pool's observation account.
+    Twap {
+        #[clap(long)]
+        pool_id: String,
+        /// Length of the averaging window in seconds, ending at the latest observation. The tick cumulative
+        /// at the window start is interpolated between the observations around it.
+        #[clap(long, value_parser = clap::value_parser!(u32).range(1..))]
+        seconds: u32,
+    },
+    /// Computes the token amounts available between the current price and ±pct away (orderbook-style depth).
+    Depth {
+        #[clap(long)]
+        pool_id: String,
+        /// Width of the window on each side of the current price, in percent (e.g., 2 for ±2%).
+        #[clap(long)]
+        pct: f64,
+    },
+    /// Simulates an exact-input swap against the pool's initialized liquidity, including the trade fee.
+    SimulateSwap {
+        #[clap(long)]
+        pool_id: String,
+        #[clap(long, value_enum)]
+        direction: SwapDirection,
+        /// Input amount in human units of the token being sold (token 0 for buy-t1, token 1 for buy-t0).
+        #[clap(long)]
+        amount_in: f64,
+        /// Stop the swap at this human price (in --format), snapped to a tick towards the current price.
+        #[clap(long, conflicts_with = "sqrt_price_limit")]
+        price_limit: Option<f64>,
+        /// Stop the swap at this exact `sqrt_price_limit_x64`, as the swap instruction takes it.
+        #[clap(long)]
+        sqrt_price_limit: Option<u128>,
+        /// Format of --price-limit.
+        #[clap(long, value_enum, default_value_t = HumanPriceFormat::T0PerT1)]
+        format: HumanPriceFormat,
+    },
+    /// Simulates a range of swap sizes and prints amount out, effective price and price impact for each.
+    ImpactTable {
+        #[clap(long)]
+        pool_id: String,
+        #[clap(long, value_enum)]
+        direction: SwapDirection,
+        /// Comma-separated input amounts in human units of the token being sold.
+        #[clap(long, value_delimiter = ',', required = true)]
+        sizes: Vec<f64>,
+    },
+    /// Lists the CLMM pools of a token pair, in either mint order (uses getProgramAccounts).
+    FindPools {
+        #[clap(long)]
+        mint_a: String,
+        #[clap(long)]
+        mint_b: String,
+    },
+    /// Lists the LP positions of a pool with their tick range, liquidity and owner (uses getProgramAccounts).
+    Positions {
+        #[clap(long)]
+        pool_id: String,
+        #[clap(long, value_enum, default_value_t = HumanPriceFormat::T0PerT1)]
+        format: HumanPriceFormat,
+        /// Skip resolving the wallet holding each position NFT (two extra requests per position).
+        #[clap(long)]
+        skip_owners: bool,
+    },
+    /// Cross-checks the pool's bitmaps against the tick array accounts that actually exist. Exits 1 on any inconsistency.
+    VerifyBitmaps {
+        #[clap(long)]
+        pool_id: String,
+    },
+    /// Finds tick arrays holding liquidity without a bitmap bit, and marked arrays with no initialized ticks (uses getProgramAccounts).
+    OrphanArrays {
+        #[clap(long)]
+        pool_id: String,
+        #[clap(long, value_enum, default_value_t = HumanPriceFormat::T0PerT1)]
+        format: HumanPriceFormat,
+    },
+    /// Rebuilds the active liquidity from initialized ticks and compares it with the pool state. Exits 1 on mismatch.
+    VerifyLiquidity {
+        #[clap(long)]
+        pool_id: String,
+        /// Print each array's contribution to localize missing ranges.
+        #[clap(long)]
+        detailed: bool,
     }
 }
 
-/// Prints a text-based visualization of the exact on-chain liquidity ranges.
-fn print_exact_liquidity_ranges(
-    all_ticks: &mut Vec<(i32, i128)>,
-    converter: &TickConverter,
-    price_format: PriceInput,
-    max_width: usize,
-    current_tick: i32,
-    tick_spacing: u16,
-    pool_pubkey: &Pubkey,
-    program_id: &Pubkey,
-    show_arrays: bool,
-) {
-    if all_ticks.is_empty() {
-        println!("No liquidity boundaries found in this pool.");
-        return;
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum HumanPriceFormat {
+    T0PerT1,
+    T1PerT0,
+}
+
+impl RpcCommands {
+    /// True when the command writes machine-readable output (JSON, TypeScript or a log line) to stdout instead of the text report.
+    fn json_output(&self) -> bool {
+        matches!(
+            self,
+            RpcCommands::GetSwapArrays { output: OutputFormat::Json | OutputFormat::Ts, .. }
+                | RpcCommands::GetSwapArraysBlind { output: OutputFormat::Json | OutputFormat::Ts, .. }
+                | RpcCommands::InspectArray { output: OutputFormat::Json, .. }
+                | RpcCommands::Summary { .. }
+                | RpcCommands::BuildSwapIx { json: true, .. }
+        )
     }
+}
 
-    all_ticks.sort_by_key(|(tick, _)| *tick);
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat {
+    Text,
+    Json,
+    /// A TypeScript snippet of `PublicKey` constructors, ready to paste into a transaction builder.
+    Ts,
+}
 
-    if all_ticks.len() == 1 {
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum TickDirection {
+    /// Toward higher ticks (higher raw token1/token0 price).
+    Up,
+    /// Toward lower ticks.
+    Down,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum SwapDirection {
+    /// Buying Token 1 by selling Token 0 (zero-for-one). The swap pushes the tick down.
+    #[clap(name = "buy-t1")]
+    BuyT1,
+    /// Buying Token 0 by selling Token 1 (one-for-zero). The swap pushes the tick up.
+    #[clap(name = "buy-t0")]
+    BuyT0,
+}
+
+impl SwapDirection {
+    /// The way the swap moves the tick: buy-t1 down, buy-t0 up.
+    fn tick_direction(self) -> TickDirection {
+        match self {
+            SwapDirection::BuyT1 => TickDirection::Down,
+            SwapDirection::BuyT0 => TickDirection::Up,
+        }
+    }
+}
+
+/// Warns on stderr, so JSON output stays clean, when the pool's status bits pause swaps or its
+/// `open_time` is still ahead: a swap assembled from this output would fail on-chain.
+fn warn_if_swaps_paused(pool_state: &PoolState) {
+    let status = pool_state.pool_status();
+    if !status.swap_enabled() {
+        eprintln!("WARNING: swaps are DISABLED on this pool (status {:#010b}); a swap through it will fail until they are re-enabled.", status.0);
+    }
+    let now = unix_now();
+    if pool_state.open_time > now {
         eprintln!(
-            "Warning: only one initialized tick boundary found at tick {} (liq_net = {}). \
-    This usually means the opposite boundary array wasn’t fetched due to a wrong PDA.",
-            all_ticks[0].0, all_ticks[0].1
+            "WARNING: the pool has not opened yet: trading starts at {} ({}); a swap through it will fail until then.",
+            format_unix_time(pool_state.open_time),
+            format_relative_time(pool_state.open_time, now)
         );
     }
+}
 
-    // Find max cumulative liquidity for normalization
-    let mut max_liquidity: i128 = 0;
-    let mut temp_liquidity: i128 = 0;
-    for &(_, liq_net) in all_ticks.iter() {
-        temp_liquidity += liq_net;
-        if temp_liquidity > max_liquidity {
-            max_liquidity = temp_liquidity;
-        }
+/// Converts a price range to the tick range of a new position. Both bounds round outward so the
+/// requested prices stay inside, then align to the tick spacing; an upper bound that aligns below its
+/// price or onto the lower bound moves up one spacing. Exits if the range leaves `[MIN_TICK, MAX_TICK]`.
+fn position_tick_range(converter: &TickConverter, helper: &TickArrayHelper, lower: PriceInput, upper: PriceInput) -> (Tick, Tick) {
+    // Inverted formats give the lower price the higher tick
+    let (first, second) = converter.price_range_to_ticks(lower, upper, None, helper.tick_spacing);
+    let (raw_lower, raw_upper) = (first.min(second), first.max(second));
+    let tick_lower = helper.align_tick_to_spacing(raw_lower);
+    let mut tick_upper = helper.align_tick_to_spacing(raw_upper);
+    if tick_upper < raw_upper || tick_upper == tick_lower {
+        tick_upper = tick_upper.offset(helper.tick_spacing as i32);
     }
+    if tick_lower < Tick(MIN_TICK) || tick_upper > Tick(MAX_TICK) {
+        fail(ExitCode::InvalidInput, format!("tick range [{}, {}] is outside the program limits [{}, {}].", tick_lower, tick_upper, MIN_TICK, MAX_TICK));
+    }
+    (tick_lower, tick_upper)
+}
 
-    if max_liquidity <= 0 {
-        println!("No active liquidity found in this pool.");
-        return;
+/// Prints `sqrt_price_x64` next to `tick_current` and the tick derived from it with the exact integer
+/// tick math, and returns whether the two are consistent. Besides an exact match, the program leaves
+/// `tick_current = t - 1` with the price exactly on tick `t` after a downward swap ends on that boundary.
+fn print_price_consistency(pool_state: &PoolState) -> bool {
+    println!("--- Price Consistency (exact integer math) ---");
+    println!("  - sqrt_price_x64:           {}", format_q64(pool_state.sqrt_price_x64));
+    println!("  - tick_current:             {}", pool_state.tick_current);
+    let derived_tick = match get_tick_at_sqrt_price(pool_state.sqrt_price_x64) {
+        Ok(tick) => tick,
+        Err(e) => {
+            println!("  - Status: MISMATCH ({})", e);
+            return false;
+        }
+    };
+    println!("  - Tick from sqrt_price_x64: {}", derived_tick);
+    if let Ok(expected_sqrt_price) = get_sqrt_price_at_tick(pool_state.tick_current) {
+        let offset = pool_state.sqrt_price_x64 as i128 - expected_sqrt_price as i128;
+        println!("  - Sqrt at tick_current:     {} (offset: {:+})", expected_sqrt_price, offset);
     }
 
-    println!("\n--- Exact Liquidity Distribution ---");
-    println!("{:<35} | {:<12} | {}", "Price Range", "Liquidity", "Distribution");
-    println!("{:-<100}", "");
+    let on_upper_boundary = derived_tick == pool_state.tick_current + 1
+        && get_sqrt_price_at_tick(derived_tick) == Ok(pool_state.sqrt_price_x64);
+    if derived_tick == pool_state.tick_current {
+        println!("  - Status: OK");
+        true
+    } else if on_upper_boundary {
+        println!("  - Status: OK (price sits exactly on tick {}, left there by a downward swap)", derived_tick);
+        true
+    } else {
+        println!("  - Status: MISMATCH (tick_current is off by {} ticks)", pool_state.tick_current - derived_tick);
+        false
+    }
+}
 
-    let ticks_per_array = TICK_ARRAY_SIZE * tick_spacing as i32;
-    let mut cumulative_liquidity: i128 = 0;
-    let mut last_tick_processed: Option<i32> = None;
-    let mut current_array_start: Option<i32> = None;
+/// getProgramAccounts filters matching every `TickArrayState` account of `pool_pubkey`.
+fn pool_tick_array_filters(pool_pubkey: &Pubkey) -> Vec<RpcFilterType> {
+    vec![
+        RpcFilterType::DataSize(TICK_ARRAY_STATE_LEN as u64),
+        RpcFilterType::Memcmp(Memcmp::new_base58_encoded(0, &TICK_ARRAY_STATE_DISCRIMINATOR)),
+        RpcFilterType::Memcmp(Memcmp::new_base58_encoded(TICK_ARRAY_POOL_ID_OFFSET, pool_pubkey.as_ref())),
+    ]
+}
 
-    for &(tick, liquidity_net) in all_ticks.iter() {
-        let array_start_index = (tick / ticks_per_array) * ticks_per_array;
+/// How far a swap range extends from the start tick on each side.
+#[derive(Clone, Copy, Debug)]
+enum SwapRange {
+    /// `--favorable-pct` / `--impact-pct`: price moves, converted to ticks through the raw price.
+    Percent { favorable: f64, impact: f64 },
+    /// `--favorable-ticks` / `--impact-ticks`: tick offsets, used as-is.
+    Ticks { favorable: u32, impact: u32 },
+}
 
-        // Detect entering a new array
-        if current_array_start != Some(array_start_index) {
-            // If we were inside a previous array, close it
-            if let Some(prev_start) = current_array_start {
-                if show_arrays {
-                    println!("--- Array End: {:<7} ---", prev_start + ticks_per_array - 1);
-                }
-            }
+impl SwapRange {
+    fn from_args(favorable_pct: Option<f64>, impact_pct: Option<f64>, favorable_ticks: Option<u32>, impact_ticks: Option<u32>) -> Self {
+        match (favorable_pct, impact_pct, favorable_ticks, impact_ticks) {
+            (Some(favorable), Some(impact), None, None) => SwapRange::Percent { favorable, impact },
+            (None, None, Some(favorable), Some(impact)) => SwapRange::Ticks { favorable, impact },
+            _ => unreachable!("Percent and tick flags are exclusive and required in pairs (enforced by clap)"),
+        }
+    }
+}
 
-            // Open new array
-            let (pda, _) = Pubkey::find_program_address(
-                &[
-                    TICK_ARRAY_SEED,
-                    pool_pubkey.as_ref(),
-                    &array_start_index.to_be_bytes(),
-                ],
-                program_id,
-            );
-            if show_arrays {
-                println!(
-                    "\n--- Array Start: {:<7} (PDA: {}) ---",
-                    array_start_index, pda
-                );
+/// Returns `(tick_favorable, tick_impact)` for a swap starting at `tick_start`.
+///
+/// "Favorable" is a move that gives the trader a better fill before the swap lands: the price
+/// of the token being bought falls by up to the favorable amount. The swap itself then moves the
+/// tick the other way, by up to the impact amount (the "impact" side).
+///
+/// - `BuyT1`: token 1 gets cheaper when the raw token1/token0 price rises, so the favorable
+///   tick is above `tick_start` and the impact tick is below it.
+/// - `BuyT0`: token 0 gets cheaper when the raw price falls, so the favorable tick is below
+///   `tick_start` and the impact tick is above it.
+///
+/// Tick offsets skip the float price round-trip. Both ticks are clamped to `[MIN_TICK, MAX_TICK]`.
+/// A percentage of 100 or more on the side where the price falls (impact for `BuyT1`, favorable
+/// for `BuyT0`) would take the price to zero or below, and is an error.
+fn swap_tick_bounds(
+    converter: &TickConverter,
+    direction: SwapDirection,
+    tick_start: i32,
+    range: SwapRange,
+) -> std::result::Result<(i32, i32), String> {
+    match range {
+        SwapRange::Percent { favorable, impact } => {
+            let (flag, falling_pct) = match direction {
+                SwapDirection::BuyT1 => ("--impact-pct", impact),
+                SwapDirection::BuyT0 => ("--favorable-pct", favorable),
+            };
+            if falling_pct >= 100.0 {
+                return Err(format!("{} must be below 100 for {:?}: the price cannot fall by {}%", flag, direction, falling_pct));
             }
-
-            current_array_start = Some(array_start_index);
+            let start_raw_price = converter.tick_to_raw_price(Tick(tick_start));
+            let (favorable_raw_price, impact_raw_price) = match direction {
+                SwapDirection::BuyT1 => (start_raw_price * (1.0 + favorable / 100.0), start_raw_price * (1.0 - impact / 100.0)),
+                SwapDirection::BuyT0 => (start_raw_price * (1.0 - favorable / 100.0), start_raw_price * (1.0 + impact / 100.0)),
+            };
+            let clamp = |tick: i32| tick.clamp(MIN_TICK, MAX_TICK);
+            Ok((clamp(converter.raw_price_to_tick(favorable_raw_price).0), clamp(converter.raw_price_to_tick(impact_raw_price).0)))
+        }
+        SwapRange::Ticks { favorable, impact } => {
+            let offset = |ticks: i64| (tick_start as i64 + ticks).clamp(MIN_TICK as i64, MAX_TICK as i64) as i32;
+            Ok(match direction {
+                SwapDirection::BuyT1 => (offset(favorable as i64), offset(-(impact as i64))),
+                SwapDirection::BuyT0 => (offset(-(favorable as i64)), offset(impact as i64)),
+            })
         }
+    }
+}
 
-        // Print the liquidity range (same as before)
-        if let Some(last_tick) = last_tick_processed {
-            if cumulative_liquidity > 0 {
-                let price_start = converter.tick_to_price(last_tick, price_format);
-                let price_end = converter.tick_to_price(tick - 1, price_format);
-                let normalized =
-                    (cumulative_liquidity as f64 / max_liquidity as f64 * max_width as f64) as usize;
-                let bar = "█".repeat(normalized.max(1));
-
-                let marker = if current_tick >= last_tick && current_tick < tick {
-                    let current_price = converter.tick_to_price(current_tick, price_format);
-                    format!("  [CURRENT PRICE: {:.6}]", current_price)
-                } else {
-                    String::new()
-                };
+/// Prints each side of the swap range as a tick offset and the equivalent raw price move.
+fn print_swap_range(tick_start: i32, tick_favorable: i32, tick_impact: i32) {
+    let price_move_pct = |tick: i32| (Q_RATIO.powi(tick - tick_start) - 1.0).abs() * 100.0;
+    println!("Favorable:     {:+} ticks ({:.4}%)", tick_favorable - tick_start, price_move_pct(tick_favorable));
+    println!("Impact:        {:+} ticks ({:.4}%)", tick_impact - tick_start, price_move_pct(tick_impact));
+}
 
-                let (p_start, p_end) = if price_start < price_end {
-                    (price_start, price_end)
-                } else {
-                    (price_end, price_start)
-                };
+/// Denominator of the price factor passed to `scale_sqrt_price` (slippage resolution: 1e-8 %).
+const SLIPPAGE_DENOMINATOR: u64 = 10_000_000_000;
+
+/// Returns `(sqrt_price_limit_x64, clamped)` for a swap from `sqrt_price_x64` that tolerates the
+/// raw price moving `slippage_pct` against the trader: down for buy-t1 (the tick decreases), up for
+/// buy-t0. The limit is computed with exact integer math and clamped into the open interval
+/// `(MIN_SQRT_PRICE_X64, MAX_SQRT_PRICE_X64)` the program accepts; `clamped` reports that case.
+fn swap_sqrt_price_limit(
+    sqrt_price_x64: u128,
+    direction: SwapDirection,
+    slippage_pct: f64,
+) -> std::result::Result<(u128, bool), String> {
+    let max_pct = match direction {
+        SwapDirection::BuyT1 => 100.0,
+        SwapDirection::BuyT0 => 1_000_000.0,
+    };
+    if slippage_pct.is_nan() || slippage_pct <= 0.0 || slippage_pct >= max_pct {
+        return Err(format!("--slippage-pct must be in (0, {}) for {:?}, got {}", max_pct, direction, slippage_pct));
+    }
+    let slippage = (slippage_pct * (SLIPPAGE_DENOMINATOR as f64 / 100.0)).round() as u64;
+    let (numerator, limit) = match direction {
+        SwapDirection::BuyT1 => (SLIPPAGE_DENOMINATOR - slippage, MIN_SQRT_PRICE_X64 + 1),
+        SwapDirection::BuyT0 => (SLIPPAGE_DENOMINATOR + slippage, MAX_SQRT_PRICE_X64 - 1),
+    };
+    let scaled = scale_sqrt_price(sqrt_price_x64, numerator, SLIPPAGE_DENOMINATOR);
+    Ok(match direction {
+        SwapDirection::BuyT1 if scaled < limit => (limit, true),
+        SwapDirection::BuyT0 if scaled > limit => (limit, true),
+        _ => (scaled, false),
+    })
+}
+
+/// Formats a Q64.64 sqrt price limit as human prices in both formats.
+fn format_swap_limit_price(converter: &TickConverter, sqrt_price_x64: SqrtPriceX64, prices: PriceDisplay) -> String {
+    let t1_per_t0 = sqrt_price_x64.to_raw_price() * converter.decimal_adjustment();
+    format!("{} T1/T0, {} T0/T1", prices.format(t1_per_t0), prices.format(1.0 / t1_per_t0))
+}
+
+/// Warns on stderr that a slippage-derived limit hit the program's sqrt price bound.
+fn warn_swap_limit_clamped(direction: SwapDirection, slippage_pct: f64) {
+    let bound = match direction {
+        SwapDirection::BuyT1 => "MIN_SQRT_PRICE_X64 + 1",
+        SwapDirection::BuyT0 => "MAX_SQRT_PRICE_X64 - 1",
+    };
+    eprintln!(
+        "Warning: {}% slippage goes past the program's sqrt price bounds; the limit is clamped to {} (effectively no limit).",
+        slippage_pct, bound
+    );
+}
+
+// --- Terminal Colors ---
+
+/// ANSI styling for the text reports. A disabled palette returns text unchanged,
+/// so `--color never` output is byte-identical to the uncolored format.
+#[derive(Clone, Copy)]
+struct Palette {
+    enabled: bool,
+}
+
+impl Palette {
+    fn new(choice: ColorChoice) -> Self {
+        let enabled = match choice {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+        };
+        Palette { enabled }
+    }
+
+    fn paint(&self, code: &str, text: &str) -> String {
+        if self.enabled {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// The current price line or marker (bold yellow).
+    fn current(&self, text: &str) -> String {
+        self.paint("1;33", text)
+    }
+
+    /// Initialized ticks (green).
+    fn tick(&self, text: &str) -> String {
+        self.paint("32", text)
+    }
+
+    /// Warnings and failed checks (bold red).
+    fn warning(&self, text: &str) -> String {
+        self.paint("1;31", text)
+    }
+}
+
+// --- Price Formatting ---
+
+/// Significant digits shown by the default price format. Adjacent ticks differ by 0.01%, so six
+/// digits always tell them apart.
+const PRICE_SIGNIFICANT_DIGITS: usize = 6;
+
+/// Prices further from 1 than this are printed in scientific notation by the default format.
+const PRICE_FIXED_RANGE: std::ops::Range<f64> = 1e-12..1e15;
+
+/// How prices are printed in text reports, from `--precision` and `--scientific`.
+///
+/// The default keeps `PRICE_SIGNIFICANT_DIGITS` significant digits, so the number of decimal
+/// places follows the magnitude of the price: a meme-coin price like 0.000000004213 keeps its
+/// digits and a stable pair is not padded to twelve decimals. The pool's decimals shift human
+/// prices by whole powers of ten, which this absorbs.
+#[derive(Clone, Copy)]
+struct PriceDisplay {
+    /// Fixed number of decimal places (or of mantissa digits in scientific mode).
+    precision: Option<usize>,
+    scientific: bool,
+}
+
+impl PriceDisplay {
+    fn format(&self, price: f64) -> String {
+        if !price.is_finite() || price == 0.0 {
+            return format!("{}", price);
+        }
+        // Number of digits before the first significant one (negative for prices below 1)
+        let magnitude = price.abs().log10().floor() as i32;
+        let significant_decimals = (PRICE_SIGNIFICANT_DIGITS as i32 - 1 - magnitude).max(0) as usize;
+        if self.scientific || (self.precision.is_none() && !PRICE_FIXED_RANGE.contains(&price.abs())) {
+            let mantissa_digits = self.precision.unwrap_or(PRICE_SIGNIFICANT_DIGITS - 1);
+            return format!("{:.*e}", mantissa_digits, price);
+        }
+        match self.precision {
+            Some(precision) => group_thousands(&format!("{:.*}", precision, price)),
+            None => {
+                // Large prices are rounded in their integer part too, e.g. 1234567.891 -> 1,234,570
+                let excess_digits = magnitude + 1 - PRICE_SIGNIFICANT_DIGITS as i32;
+                let rounded = if excess_digits > 0 { (price / 10f64.powi(excess_digits)).round() * 10f64.powi(excess_digits) } else { price };
+                group_thousands(&format!("{:.*}", significant_decimals, rounded))
+            }
+        }
+    }
+
+    /// Like `format`, without thousands separators, for output that scripts parse.
+    fn format_plain(&self, price: f64) -> String {
+        self.format(price).replace(',', "")
+    }
+
+    /// Formats a price range as `[low, high]`.
+    fn range(&self, start: f64, end: f64) -> String {
+        format!("[{}, {}]", self.format(start), self.format(end))
+    }
+}
+
+/// Inserts `,` every three digits in the integer part of a formatted decimal number.
+fn group_thousands(number: &str) -> String {
+    let (sign, unsigned) = number.strip_prefix('-').map_or(("", number), |rest| ("-", rest));
+    let (integer, fraction) = unsigned.split_once('.').map_or((unsigned, None), |(i, f)| (i, Some(f)));
+    let mut grouped = String::with_capacity(number.len() + integer.len() / 3);
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    match fraction {
+        Some(fraction) => format!("{}{}.{}", sign, grouped, fraction),
+        None => format!("{}{}", sign, grouped),
+    }
+}
+
+// --- Liquidity Curve Helper Structs and Functions ---
+
+fn format_liquidity(liquidity: u128) -> String {
+    if liquidity >= 1_000_000_000_000 {
+        format!("{:.2}T", liquidity as f64 / 1_000_000_000_000.0)
+    } else if liquidity >= 1_000_000_000 {
+        format!("{:.2}B", liquidity as f64 / 1_000_000_000.0)
+    } else if liquidity >= 1_000_000 {
+        format!("{:.2}M", liquidity as f64 / 1_000_000.0)
+    } else if liquidity >= 1_000 {
+        format!("{:.2}K", liquidity as f64 / 1_000.0)
+    } else {
+        format!("{}", liquidity)
+    }
+}
+
+/// Raw token amounts held per unit of liquidity at `sqrt_price` over `[sqrt_lower, sqrt_upper]`, with
+/// real-valued sqrt prices: all token 0 at or below the range, all token 1 at or above it.
+fn amounts_per_liquidity(sqrt_price: f64, sqrt_lower: f64, sqrt_upper: f64) -> (f64, f64) {
+    let sqrt_price = sqrt_price.clamp(sqrt_lower, sqrt_upper);
+    (1.0 / sqrt_price - 1.0 / sqrt_upper, sqrt_price - sqrt_lower)
+}
+
+/// Converts a human token amount to raw units, rounding to the nearest unit. Exits on a negative
+/// amount or one that does not fit a u64.
+fn human_to_raw_amount(amount: f64, decimals: u8, flag: &str) -> u64 {
+    let raw = (amount * 10f64.powi(decimals as i32)).round();
+    if !(raw >= 0.0 && raw <= u64::MAX as f64) {
+        fail(ExitCode::InvalidInput, format!("{} {} is negative or too large for a token amount.", flag, amount));
+    }
+    raw as u64
+}
+
+/// Formats a Q64.64 value as its raw integer followed by the decoded number, e.g. `6786630301821314530 (0.367909)`.
+fn format_q64(value: u128) -> String {
+    let decoded = q64_to_f64(value);
+    if value == 0 || (1e-4..1e9).contains(&decoded) {
+        format!("{} ({:.6})", value, decoded)
+    } else {
+        format!("{} ({:.6e})", value, decoded)
+    }
+}
+
+/// Formats a raw token amount in human units with every decimal place, e.g. `1.500000000`.
+fn format_token_amount(raw: impl Into<u128>, decimals: u8) -> String {
+    let raw: u128 = raw.into();
+    let digits = format!("{:0>width$}", raw, width = decimals as usize + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - decimals as usize);
+    if fraction.is_empty() { whole.to_string() } else { format!("{}.{}", whole, fraction) }
+}
+
+/// Crosses an initialized tick: moving up adds its `liquidity_net` to the active liquidity,
+/// moving down subtracts it. Valid tick data never overflows an i128, so an overflow means corrupt
+/// or mis-decoded ticks: it is reported on stderr and the result saturates instead of panicking
+/// (debug) or wrapping into a wrong value (release).
+fn cross_liquidity_net(liquidity: i128, liquidity_net: i128, tick: i32, direction: TickDirection) -> i128 {
+    let crossed = match direction {
+        TickDirection::Up => liquidity.checked_add(liquidity_net),
+        TickDirection::Down => liquidity.checked_sub(liquidity_net),
+    };
+    crossed.unwrap_or_else(|| {
+        eprintln!(
+            "Warning: liquidity overflows i128 when crossing tick {} (liquidity_net = {}); the tick data is likely corrupt. Saturating.",
+            tick, liquidity_net
+        );
+        match direction {
+            TickDirection::Up => liquidity.saturating_add(liquidity_net),
+            TickDirection::Down => liquidity.saturating_sub(liquidity_net),
+        }
+    })
+}
+
+/// Returns the active liquidity at `tick`: the sum of `liquidity_net` of every
+/// initialized tick at or below it. `all_ticks` must be sorted by tick.
+fn cumulative_liquidity_at(all_ticks: &[(i32, i128)], tick: i32) -> i128 {
+    all_ticks.iter()
+        .take_while(|(t, _)| *t <= tick)
+        .fold(0, |liquidity, &(t, liquidity_net)| cross_liquidity_net(liquidity, liquidity_net, t, TickDirection::Up))
+}
+
+/// Returns the raw `(amount_0, amount_1)` swapped when moving the price from the current
+/// `sqrt_price` to `target_sqrt_price`, integrating over the segments between initialized ticks.
+/// Between two initialized ticks the active liquidity is constant, so each segment uses
+/// `amount_0 = L * (1/sqrt_lo - 1/sqrt_hi)` and `amount_1 = L * (sqrt_hi - sqrt_lo)`.
+/// Also returns the number of initialized ticks crossed. `all_ticks` must be sorted by tick.
+fn depth_to_sqrt_price(
+    all_ticks: &[(i32, i128)],
+    converter: &TickConverter,
+    tick_current: i32,
+    sqrt_price: f64,
+    liquidity: u128,
+    target_sqrt_price: f64,
+) -> (f64, f64, usize) {
+    let mut liquidity = i128::try_from(liquidity).unwrap_or(i128::MAX);
+    let mut sqrt_price = sqrt_price;
+    let (mut amount_0, mut amount_1) = (0.0, 0.0);
+    let mut ticks_crossed = 0;
+    let mut add_segment = |from: f64, to: f64, liquidity: i128| {
+        let (lo, hi) = if from < to { (from, to) } else { (to, from) };
+        let liquidity = liquidity.max(0) as f64;
+        amount_0 += liquidity * (1.0 / lo - 1.0 / hi);
+        amount_1 += liquidity * (hi - lo);
+    };
+
+    // Moving down crosses ticks at or below the current tick and removes their liquidity_net;
+    // moving up crosses ticks above it and adds theirs.
+    let moving_down = target_sqrt_price < sqrt_price;
+    let crossed: Box<dyn Iterator<Item = &(i32, i128)>> = if moving_down {
+        Box::new(all_ticks.iter().rev().filter(|(tick, _)| *tick <= tick_current))
+    } else {
+        Box::new(all_ticks.iter().filter(|(tick, _)| *tick > tick_current))
+    };
+    for &(tick, liquidity_net) in crossed {
+        let tick_sqrt_price = converter.tick_to_raw_price(Tick(tick)).sqrt();
+        let reached_target = if moving_down { tick_sqrt_price <= target_sqrt_price } else { tick_sqrt_price >= target_sqrt_price };
+        if reached_target {
+            break;
+        }
+        add_segment(sqrt_price, tick_sqrt_price, liquidity);
+        sqrt_price = tick_sqrt_price;
+        liquidity = cross_liquidity_net(liquidity, liquidity_net, tick, if moving_down { TickDirection::Down } else { TickDirection::Up });
+        ticks_crossed += 1;
+    }
+    // The last initialized tick inside the window keeps its liquidity up to the target.
+    add_segment(sqrt_price, target_sqrt_price, liquidity);
+    (amount_0, amount_1, ticks_crossed)
+}
+
+/// Outcome of an exact-input swap walk, in raw token units.
+struct SwapResult {
+    /// Input consumed, including the trade fee.
+    amount_in: f64,
+    amount_out: f64,
+    fee: f64,
+    end_sqrt_price: f64,
+    ticks_crossed: usize,
+    /// False when the walk stopped before the whole input was used.
+    filled: bool,
+    /// True when it stopped at the sqrt price limit rather than running out of initialized liquidity.
+    hit_price_limit: bool,
+}
+
+/// Walks an exact-input swap of `amount_in` raw units from the current `sqrt_price`.
+/// The trade fee (`fee_rate` out of `FEE_RATE_DENOMINATOR`) is taken off the input first,
+/// then the rest is spent segment by segment between initialized ticks, where the liquidity is constant:
+/// - `BuyT1` (token 0 in, price down): `dx = L * (1/sqrt_new - 1/sqrt)`, `dy = L * (sqrt - sqrt_new)`.
+/// - `BuyT0` (token 1 in, price up): `dy = L * (sqrt_new - sqrt)`, `dx = L * (1/sqrt - 1/sqrt_new)`.
+///
+/// The walk stops unfilled when no initialized tick is left in the swap direction, or when the price
+/// reaches `sqrt_price_limit_x64` (compared exactly against each tick's integer sqrt price, so a limit
+/// on a tick crosses it as the program does). `all_ticks` must be sorted by tick.
+#[allow(clippy::too_many_arguments)]
+fn simulate_swap(
+    all_ticks: &[(i32, i128)],
+    converter: &TickConverter,
+    direction: SwapDirection,
+    tick_current: i32,
+    sqrt_price: f64,
+    liquidity: u128,
+    amount_in: f64,
+    fee_rate: u32,
+    sqrt_price_limit_x64: Option<u128>,
+) -> SwapResult {
+    let fee_fraction = fee_rate as f64 / FEE_RATE_DENOMINATOR as f64;
+    let mut remaining = amount_in * (1.0 - fee_fraction);
+    let mut liquidity = i128::try_from(liquidity).unwrap_or(i128::MAX);
+    let mut sqrt_price = sqrt_price;
+    let mut amount_out = 0.0;
+    let mut ticks_crossed = 0;
+
+    let zero_for_one = matches!(direction, SwapDirection::BuyT1);
+    let crossed: Box<dyn Iterator<Item = &(i32, i128)>> = if zero_for_one {
+        Box::new(all_ticks.iter().rev().filter(|(tick, _)| *tick <= tick_current))
+    } else {
+        Box::new(all_ticks.iter().filter(|(tick, _)| *tick > tick_current))
+    };
+    let consumed = |remaining: f64| amount_in - remaining / (1.0 - fee_fraction);
+    for &(tick, liquidity_net) in crossed {
+        // The limit may end the walk before this tick, or exactly on it
+        let tick_sqrt_price_x64 = get_sqrt_price_at_tick(tick).ok();
+        let limit_before_tick = match (sqrt_price_limit_x64, tick_sqrt_price_x64) {
+            (Some(limit), Some(tick_sqrt)) => if zero_for_one { limit > tick_sqrt } else { limit < tick_sqrt },
+            _ => false,
+        };
+        let limit_on_tick = sqrt_price_limit_x64.is_some() && sqrt_price_limit_x64 == tick_sqrt_price_x64;
+        let tick_sqrt_price = if limit_before_tick {
+            q64_to_f64(sqrt_price_limit_x64.unwrap_or_default())
+        } else {
+            converter.tick_to_raw_price(Tick(tick)).sqrt()
+        };
+        let segment_liquidity = liquidity.max(0) as f64;
+        if segment_liquidity > 0.0 {
+            let max_in = if zero_for_one {
+                segment_liquidity * (1.0 / tick_sqrt_price - 1.0 / sqrt_price)
+            } else {
+                segment_liquidity * (tick_sqrt_price - sqrt_price)
+            };
+            if remaining < max_in {
+                // The input runs out inside this segment
+                let end_sqrt_price = if zero_for_one {
+                    1.0 / (1.0 / sqrt_price + remaining / segment_liquidity)
+                } else {
+                    sqrt_price + remaining / segment_liquidity
+                };
+                amount_out += if zero_for_one {
+                    segment_liquidity * (sqrt_price - end_sqrt_price)
+                } else {
+                    segment_liquidity * (1.0 / sqrt_price - 1.0 / end_sqrt_price)
+                };
+                return SwapResult {
+                    amount_in,
+                    amount_out,
+                    fee: amount_in * fee_fraction,
+                    end_sqrt_price,
+                    ticks_crossed,
+                    filled: true,
+                    hit_price_limit: false,
+                };
+            }
+            remaining -= max_in;
+            amount_out += if zero_for_one {
+                segment_liquidity * (sqrt_price - tick_sqrt_price)
+            } else {
+                segment_liquidity * (1.0 / sqrt_price - 1.0 / tick_sqrt_price)
+            };
+        }
+        sqrt_price = tick_sqrt_price;
+        if !limit_before_tick {
+            liquidity = cross_liquidity_net(liquidity, liquidity_net, tick, direction.tick_direction());
+            ticks_crossed += 1;
+        }
+        if limit_before_tick || limit_on_tick {
+            // Partial fill: the rest of the input stays with the trader
+            let consumed = consumed(remaining);
+            return SwapResult {
+                amount_in: consumed,
+                amount_out,
+                fee: consumed * fee_fraction,
+                end_sqrt_price: sqrt_price,
+                ticks_crossed,
+                filled: false,
+                hit_price_limit: true,
+            };
+        }
+    }
+
+    // Out of initialized ticks: only the input spent so far (and its share of the fee) is consumed
+    let consumed = consumed(remaining);
+    SwapResult {
+        amount_in: consumed,
+        amount_out,
+        fee: consumed * fee_fraction,
+        end_sqrt_price: sqrt_price,
+        ticks_crossed,
+        filled: false,
+        hit_price_limit: false,
+    }
+}
+
+/// Returns the human price of the swap's output token in units of its input token at `sqrt_price`,
+/// i.e. how many output tokens one input token buys before fees and impact.
+fn out_per_in_price(converter: &TickConverter, direction: SwapDirection, sqrt_price: f64) -> f64 {
+    let t1_per_t0 = sqrt_price.powi(2) * converter.decimal_adjustment();
+    match direction {
+        SwapDirection::BuyT1 => t1_per_t0,
+        SwapDirection::BuyT0 => 1.0 / t1_per_t0,
+    }
+}
+
+/// Fetches the initialized ticks a swap in `direction` can cross (arrays from the current one
+/// towards the swap side), sorted by tick, together with the pool's trade fee rate.
+async fn fetch_swap_liquidity(
+    rpc_client: &dyn AccountFetcher,
+    pool_pubkey: &Pubkey,
+    program_id: &Pubkey,
+    pool_state: &PoolState,
+    direction: SwapDirection,
+) -> (Vec<(i32, i128)>, u32) {
+    let amm_config = fetch_clmm_account::<AmmConfig>(rpc_client, &pool_state.amm_config, program_id).await;
+    let helper = TickArrayHelper { tick_spacing: pool_state.tick_spacing };
+    let current_start = helper.get_array_start_index(Tick(pool_state.tick_current)).0;
+    let swap_arrays: Vec<i32> = fetch_initialized_arrays(rpc_client, pool_pubkey, program_id, pool_state).await
+        .into_iter()
+        .filter(|&start_index| match direction {
+            SwapDirection::BuyT1 => start_index <= current_start,
+            SwapDirection::BuyT0 => start_index >= current_start,
+        })
+        .collect();
+
+    println!("Fetching {} initialized tick arrays on the swap side...", swap_arrays.len());
+    let mut all_ticks = fetch_initialized_ticks(rpc_client, pool_pubkey, program_id, &swap_arrays).await;
+    all_ticks.sort_by_key(|(tick, _)| *tick);
+    println!("Done.");
+    (all_ticks, amm_config.trade_fee_rate)
+}
+
+/// Returns `liquidity` as a fraction of `max_liquidity`. Log scaling uses
+/// `ln(1 + liquidity) / ln(1 + max)`, which keeps ranges orders of magnitude below the peak visible.
+fn liquidity_fraction(liquidity: i128, max_liquidity: i128, log_scale: bool) -> f64 {
+    if liquidity <= 0 || max_liquidity <= 0 {
+        return 0.0;
+    }
+    if log_scale {
+        (liquidity as f64).ln_1p() / (max_liquidity as f64).ln_1p()
+    } else {
+        liquidity as f64 / max_liquidity as f64
+    }
+}
+
+/// Returns the bar length for `liquidity` relative to `max_liquidity`, at most `max_width`.
+/// Any positive liquidity gets at least one block, unless `max_width` is 0.
+fn liquidity_bar_width(liquidity: i128, max_liquidity: i128, max_width: usize, log_scale: bool) -> usize {
+    if liquidity <= 0 || max_liquidity <= 0 {
+        return 0;
+    }
+    let fraction = liquidity_fraction(liquidity, max_liquidity, log_scale);
+    ((fraction * max_width as f64).round() as usize).clamp(max_width.min(1), max_width)
+}
+
+/// Splits sorted `(tick, liquidity_net)` boundaries into `(tick_lower, tick_upper, liquidity)`
+/// ranges with positive active liquidity.
+fn liquidity_segments(all_ticks: &[(i32, i128)]) -> Vec<(i32, i32, i128)> {
+    let mut segments = Vec::new();
+    let mut cumulative_liquidity: i128 = 0;
+    for window in all_ticks.windows(2) {
+        cumulative_liquidity = cross_liquidity_net(cumulative_liquidity, window[0].1, window[0].0, TickDirection::Up);
+        if cumulative_liquidity > 0 {
+            segments.push((window[0].0, window[1].0, cumulative_liquidity));
+        }
+    }
+    segments
+}
+
+/// Renders the liquidity distribution as a standalone SVG bar chart: one bar per range,
+/// spanning its price range on the x-axis, with height proportional to active liquidity.
+/// The x-axis is laid out by tick, i.e. logarithmic in price, so wide and narrow ranges
+/// both stay readable. Returns `None` if no range has positive liquidity.
+fn render_liquidity_svg(
+    all_ticks: &[(i32, i128)],
+    converter: &TickConverter,
+    price_format: PriceInput,
+    current_tick: i32,
+    log_scale: bool,
+    pool_id: &str,
+    prices: PriceDisplay,
+) -> Option<String> {
+    const WIDTH: f64 = 1000.0;
+    const HEIGHT: f64 = 420.0;
+    const MARGIN_LEFT: f64 = 60.0;
+    const MARGIN_RIGHT: f64 = 20.0;
+    const MARGIN_TOP: f64 = 40.0;
+    const MARGIN_BOTTOM: f64 = 60.0;
+    const AXIS_LABELS: usize = 6;
+
+    let segments = liquidity_segments(all_ticks);
+    let max_liquidity = segments.iter().map(|&(_, _, liquidity)| liquidity).max()?;
+    let tick_min = segments.first()?.0.min(current_tick);
+    let tick_max = segments.last()?.1.max(current_tick);
+
+    // Prices in the chosen format may fall as the tick rises (e.g. t0-per-t1); flip the
+    // axis so price always increases to the right.
+    let increasing = converter.tick_to_price(Tick(tick_max), price_format) >= converter.tick_to_price(Tick(tick_min), price_format);
+    let plot_width = WIDTH - MARGIN_LEFT - MARGIN_RIGHT;
+    let plot_height = HEIGHT - MARGIN_TOP - MARGIN_BOTTOM;
+    let span = (tick_max - tick_min).max(1) as f64;
+    let x_of = |tick: i32| {
+        let fraction = (tick - tick_min) as f64 / span;
+        MARGIN_LEFT + plot_width * if increasing { fraction } else { 1.0 - fraction }
+    };
+    let baseline = MARGIN_TOP + plot_height;
+
+    let mut svg = String::new();
+    svg.push_str(&format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" font-family=\"monospace\" font-size=\"11\">\n",
+        w = WIDTH, h = HEIGHT
+    ));
+    svg.push_str(&format!("<rect width=\"{}\" height=\"{}\" fill=\"white\"/>\n", WIDTH, HEIGHT));
+    svg.push_str(&format!(
+        "<text x=\"{}\" y=\"20\" font-size=\"14\">Liquidity distribution for {}{}</text>\n",
+        MARGIN_LEFT, pool_id, if log_scale { " (log scale)" } else { "" }
+    ));
+
+    // 1. Bars
+    for &(tick_lower, tick_upper, liquidity) in &segments {
+        let (x0, x1) = (x_of(tick_lower), x_of(tick_upper));
+        let bar_height = plot_height * liquidity_fraction(liquidity, max_liquidity, log_scale);
+        let price_lower = converter.tick_to_price(Tick(tick_lower), price_format);
+        let price_upper = converter.tick_to_price(Tick(tick_upper), price_format);
+        let (p_start, p_end) = if price_lower < price_upper { (price_lower, price_upper) } else { (price_upper, price_lower) };
+        svg.push_str(&format!(
+            "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" fill=\"#4a7bd0\" stroke=\"#2f5597\" stroke-width=\"0.5\"><title>[{} - {}] liquidity {}</title></rect>\n",
+            x0.min(x1), baseline - bar_height, (x1 - x0).abs().max(1.0), bar_height,
+            prices.format(p_start), prices.format(p_end), format_liquidity(liquidity as u128)
+        ));
+    }
+
+    // 2. Axis with evenly spaced price labels
+    svg.push_str(&format!(
+        "<line x1=\"{l}\" y1=\"{b}\" x2=\"{r}\" y2=\"{b}\" stroke=\"black\"/>\n",
+        l = MARGIN_LEFT, r = WIDTH - MARGIN_RIGHT, b = baseline
+    ));
+    for i in 0..AXIS_LABELS {
+        let tick = tick_min + ((tick_max - tick_min) as f64 * i as f64 / (AXIS_LABELS - 1) as f64) as i32;
+        let x = x_of(tick);
+        svg.push_str(&format!("<line x1=\"{x:.2}\" y1=\"{b}\" x2=\"{x:.2}\" y2=\"{t}\" stroke=\"black\"/>\n", b = baseline, t = baseline + 5.0));
+        svg.push_str(&format!(
+            "<text x=\"{:.2}\" y=\"{}\" text-anchor=\"middle\">{}</text>\n",
+            x, baseline + 18.0, prices.format(converter.tick_to_price(Tick(tick), price_format))
+        ));
+    }
+
+    // 3. Current price marker
+    let x_current = x_of(current_tick);
+    svg.push_str(&format!(
+        "<line x1=\"{x:.2}\" y1=\"{t}\" x2=\"{x:.2}\" y2=\"{b}\" stroke=\"red\" stroke-width=\"1.5\" stroke-dasharray=\"4 3\"/>\n",
+        x = x_current, t = MARGIN_TOP, b = baseline
+    ));
+    svg.push_str(&format!(
+        "<text x=\"{:.2}\" y=\"{}\" fill=\"red\" text-anchor=\"middle\">current {}</text>\n",
+        x_current, MARGIN_TOP - 4.0, prices.format(converter.tick_to_price(Tick(current_tick), price_format))
+    ));
+    svg.push_str(&format!(
+        "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">Price</text>\n",
+        MARGIN_LEFT + plot_width / 2.0, HEIGHT - 15.0
+    ));
+    svg.push_str("</svg>\n");
+    Some(svg)
+}
+
+/// Points at the array that most likely holds the missing boundary when only one initialized tick was
+/// read. A positive `liquidity_net` opens liquidity upward, so its closing tick lies above, and a
+/// negative one closes liquidity, so the opening tick lies below. The nearest array on that side with
+/// its bitmap bit set was fetched but yielded nothing; with no such array, the bitmap itself lacks the bit.
+fn single_boundary_hint(
+    tick: i32,
+    liquidity_net: i128,
+    tick_spacing: u16,
+    initialized_arrays: &[i32],
+    pool_pubkey: &Pubkey,
+    program_id: &Pubkey,
+) -> String {
+    let own_start = Tick(tick).array_start(tick_spacing);
+    let upward = liquidity_net > 0;
+    let side = if upward { "above" } else { "below" };
+    let candidate = if upward {
+        initialized_arrays.iter().filter(|&&start| start > own_start.0).min()
+    } else {
+        initialized_arrays.iter().filter(|&&start| start < own_start.0).max()
+    };
+    match candidate {
+        Some(&start) => format!(
+            "Hint: the opposite boundary should be {} tick {}. The nearest array on that side with its bitmap bit set \
+    starts at {} (PDA {}) but returned no initialized ticks: check that account and its derivation.",
+            side,
+            tick,
+            start,
+            derive_tick_array_pda(pool_pubkey, start, program_id)
+        ),
+        None => {
+            let neighbor = own_start.shifted(if upward { 1 } else { -1 }, tick_spacing);
+            format!(
+                "Hint: the opposite boundary should be {} tick {}, but no array on that side has its bitmap bit set. \
+    The neighboring array starts at {} (PDA {}); if it exists, the bitmap is missing its bit.",
+                side,
+                tick,
+                neighbor,
+                derive_tick_array_pda(pool_pubkey, neighbor.0, program_id)
+            )
+        }
+    }
+}
+
+/// Prints a text-based visualization of the exact on-chain liquidity ranges.
+#[allow(clippy::too_many_arguments)]
+fn print_exact_liquidity_ranges(
+    all_ticks: &mut [(i32, i128)],
+    converter: &TickConverter,
+    price_format: PriceInput,
+    max_width: usize,
+    current_tick: i32,
+    tick_spacing: u16,
+    initialized_arrays: &[i32],
+    pool_pubkey: &Pubkey,
+    program_id: &Pubkey,
+    show_arrays: bool,
+    log_scale: bool,
+    palette: Palette,
+    prices: PriceDisplay,
+) {
+    if all_ticks.is_empty() {
+        println!("No liquidity boundaries found in this pool.");
+        return;
+    }
+
+    all_ticks.sort_by_key(|(tick, _)| *tick);
+
+    if all_ticks.len() == 1 {
+        let (tick, liquidity_net) = all_ticks[0];
+        eprintln!(
+            "{}",
+            palette.warning(&format!(
+                "Warning: only one initialized tick boundary found at tick {} (liq_net = {}). \
+    This usually means the opposite boundary array wasn’t fetched due to a wrong PDA.\n{}",
+                tick,
+                liquidity_net,
+                single_boundary_hint(tick, liquidity_net, tick_spacing, initialized_arrays, pool_pubkey, program_id)
+            ))
+        );
+    }
+
+    // Normalize against the peak of the running cumulative, not its final value:
+    // the cumulative can return to zero in gaps between separate liquidity regions.
+    // Overflow saturates silently here; the loop below reports it.
+    let max_liquidity = all_ticks.iter()
+        .scan(0i128, |cumulative, &(_, liquidity_net)| {
+            *cumulative = cumulative.saturating_add(liquidity_net);
+            Some(*cumulative)
+        })
+        .max()
+        .unwrap_or(0);
+
+    if max_liquidity <= 0 {
+        println!("No active liquidity found in this pool.");
+        return;
+    }
+
+    println!("\n--- Exact Liquidity Distribution ---");
+    println!("{:<35} | {:<12} | Distribution", "Price Range", "Liquidity");
+    println!("{:-<100}", "");
+
+    let ticks_per_array = TICK_ARRAY_SIZE * tick_spacing as i32;
+    let mut cumulative_liquidity: i128 = 0;
+    let mut last_tick_processed: Option<i32> = None;
+    let mut current_array_start: Option<i32> = None;
+
+    for &(tick, liquidity_net) in all_ticks.iter() {
+        let array_start_index = (tick / ticks_per_array) * ticks_per_array;
+
+        // Detect entering a new array
+        if current_array_start != Some(array_start_index) {
+            // If we were inside a previous array, close it
+            if let Some(prev_start) = current_array_start
+                && show_arrays
+            {
+                println!("--- Array End: {:<7} ---", prev_start + ticks_per_array - 1);
+            }
+
+            // Open new array
+            let (pda, _) = Pubkey::find_program_address(
+                &[
+                    TICK_ARRAY_SEED,
+                    pool_pubkey.as_ref(),
+                    &array_start_index.to_be_bytes(),
+                ],
+                program_id,
+            );
+            if show_arrays {
                 println!(
-                    "[{:<15.6} - {:<15.6}] | {:<12} | {}{}",
-                    p_start,
-                    p_end,
-                    format_liquidity(cumulative_liquidity as u128),
-                    bar,
-                    marker
+                    "\n--- Array Start: {:<7} (PDA: {}) ---",
+                    array_start_index, pda
                 );
             }
+
+            current_array_start = Some(array_start_index);
+        }
+
+        // Print the liquidity range (same as before)
+        if let Some(last_tick) = last_tick_processed
+            && cumulative_liquidity > 0
+        {
+            let price_start = converter.tick_to_price(Tick(last_tick), price_format);
+            let price_end = converter.tick_to_price(Tick(tick - 1), price_format);
+            let bar = "█".repeat(liquidity_bar_width(cumulative_liquidity, max_liquidity, max_width, log_scale));
+
+            let marker = if current_tick >= last_tick && current_tick < tick {
+                let current_price = converter.tick_to_price(Tick(current_tick), price_format);
+                palette.current(&format!("  [CURRENT PRICE: {}]", prices.format(current_price)))
+            } else {
+                String::new()
+            };
+
+            let (p_start, p_end) = if price_start < price_end {
+                (price_start, price_end)
+            } else {
+                (price_end, price_start)
+            };
+            println!(
+                "[{:<15} - {:<15}] | {:<12} | {}{}",
+                prices.format(p_start),
+                prices.format(p_end),
+                format_liquidity(cumulative_liquidity as u128),
+                bar,
+                marker
+            );
         }
 
-        cumulative_liquidity += liquidity_net;
+        cumulative_liquidity = cross_liquidity_net(cumulative_liquidity, liquidity_net, tick, TickDirection::Up);
         last_tick_processed = Some(tick);
     }
 
     // Close final array at the end
-    if let Some(start) = current_array_start {
-        if show_arrays {
-            println!("--- Array End: {:<7} ---", start + ticks_per_array - 1);
-        }
+    if let Some(start) = current_array_start
+        && show_arrays
+    {
+        println!("--- Array End: {:<7} ---", start + ticks_per_array - 1);
+    }
+}
+
+/// Grid columns per row in `print_tick_array_visualization`; 6 rows of 10 cover the 60 slots.
+const GRID_COLUMNS: usize = 10;
+
+/// One character for the size of a liquidity value, from the unit `format_liquidity` would use:
+/// `K`, `M`, `B` or `T`, and `*` below a thousand.
+fn liquidity_magnitude_char(liquidity: u128) -> char {
+    match format_liquidity(liquidity).chars().last() {
+        Some(unit) if unit.is_ascii_alphabetic() => unit,
+        _ => '*',
     }
 }
 
 /// Prints a visual representation of a single TickArrayState, highlighting initialized ticks.
+/// With `grid`, the slots are drawn as a 6x10 grid of liquidity magnitude characters instead.
 fn print_tick_array_visualization(
     tick_array: &TickArrayState,
+    slots: &[ArraySlot],
     tick_spacing: u16,
     pda: &Pubkey,
+    view: ArrayView,
+    palette: Palette,
 ) {
     println!("\n--- Visual Inspection of Tick Array (Start Index: {}) ---", tick_array.start_tick_index);
     println!("PDA Address: {}", pda);
     println!("{} initialized ticks found.", tick_array.initialized_tick_count);
     println!("{:-<80}", "");
 
-    for (slot_index, tick_state) in tick_array.ticks.iter().enumerate() {
-        // Calculate the absolute tick index for this slot
-        let tick_index = tick_array.start_tick_index + (slot_index as i32 * tick_spacing as i32);
-
-        if tick_state.liquidity_gross != 0 {
-            // This is an initialized tick, make it stand out
-            println!("┌─ SLOT {:<2} ──────────────────────────────────────────────────────────────────┐", slot_index);
-            println!("│  Tick Index: {}", tick_index);
-            println!("│  Liquidity Net:   {}", tick_state.liquidity_net);
-            println!("│  Liquidity Gross: {}", tick_state.liquidity_gross);
-            println!("└──────────────────────────────────────────────────────────────────────────┘");
-        } else {
-            // This is an uninitialized tick
-            println!("- Slot {:<2} (Tick {}) is empty.", slot_index, tick_index);
+    match view {
+        ArrayView::Grid => {
+            let header: String = (0..GRID_COLUMNS).map(|column| format!("{:>3}", column)).collect();
+            println!("{:<13}{}", "Slot", header);
+            for row in slots.chunks(GRID_COLUMNS) {
+                let cells: String = row.iter()
+                    .map(|slot| if slot.liquidity_gross != 0 {
+                        palette.tick(&format!("{:>3}", liquidity_magnitude_char(slot.liquidity_gross)))
+                    } else {
+                        format!("{:>3}", '.')
+                    })
+                    .collect();
+                println!("{:<13}{}", format!("+{:<2} {}", row[0].slot, row[0].tick), cells);
+            }
+            println!("{:-<80}", "");
+            println!("Rows start at the slot and tick shown; each column adds one slot ({} ticks).", tick_spacing);
+            println!("Legend: '.' empty, liquidity_gross '*' < 1K, 'K' >= 1K, 'M' >= 1M, 'B' >= 1B, 'T' >= 1T");
+        }
+        ArrayView::Compact { format, prices } => {
+            println!("{:>4} | {:>8} | {:>18} | {:>22} | {:>22}", "Slot", "Tick", format!("Price ({:?})", format), "Liquidity Net", "Liquidity Gross");
+            // Runs of empty slots collapse into one line, printed when the run ends
+            let mut empty_run: Option<(usize, usize)> = None;
+            let print_empty_run = |run: Option<(usize, usize)>| match run {
+                Some((first, last)) if first == last => println!("       slot {} empty", first),
+                Some((first, last)) => println!("       slots {}–{} empty", first, last),
+                None => {}
+            };
+            for slot in slots {
+                if slot.liquidity_gross == 0 {
+                    empty_run = Some((empty_run.map_or(slot.slot, |(first, _)| first), slot.slot));
+                    continue;
+                }
+                print_empty_run(empty_run.take());
+                println!("{}", palette.tick(&format!(
+                    "{:>4} | {:>8} | {:>18} | {:>22} | {:>22}",
+                    slot.slot, slot.tick, prices.format(slot.price), format!("{:+}", slot.liquidity_net), slot.liquidity_gross
+                )));
+            }
+            print_empty_run(empty_run);
+            println!("{:-<80}", "");
+        }
+        ArrayView::Blocks { format, prices } => {
+            for slot in slots {
+                if slot.liquidity_gross != 0 {
+                    // This is an initialized tick, make it stand out
+                    println!("{}", palette.tick(&format!("┌─ SLOT {:<2} ──────────────────────────────────────────────────────────────────┐", slot.slot)));
+                    println!("{}", palette.tick(&format!("│  Tick Index: {}", slot.tick)));
+                    for line in initialized_slot_lines(slot, format, None, prices) {
+                        println!("{}", palette.tick(&format!("│  {}", line)));
+                    }
+                    println!("{}", palette.tick("└──────────────────────────────────────────────────────────────────────────┘"));
+                } else {
+                    // This is an uninitialized tick
+                    println!("- Slot {:<2} (Tick {}) is empty.", slot.slot, slot.tick);
+                }
+            }
+            println!("{:-<80}", "");
         }
     }
-    println!("{:-<80}", "");
+}
+
+/// The lines describing an initialized slot in `rpc tick-array` and `rpc inspect-array`: its price,
+/// the distance from the current tick when given, and its liquidity. Callers add the tick and layout.
+fn initialized_slot_lines(slot: &ArraySlot, format: HumanPriceFormat, tick_current: Option<i32>, prices: PriceDisplay) -> Vec<String> {
+    let mut lines = vec![format!("{:<17}{}", format!("Price ({:?}):", format), prices.format(slot.price))];
+    if let Some(tick_current) = tick_current {
+        let distance = slot.tick - tick_current;
+        lines.push(format!(
+            "{:<17}{:+} ticks ({:+.2} bps) from current tick {}",
+            "Distance:", distance, (Q_RATIO.powi(distance) - 1.0) * 10_000.0, tick_current
+        ));
+    }
+    lines.push(format!("{:<17}{}", "Liquidity Net:", slot.liquidity_net));
+    lines.push(format!("{:<17}{}", "Liquidity Gross:", slot.liquidity_gross));
+    lines
+}
+
+/// How `rpc inspect-array` renders the slots of a tick array.
+#[derive(Clone, Copy)]
+enum ArrayView {
+    /// One block per initialized slot, with its price, and one line per empty slot.
+    Blocks { format: HumanPriceFormat, prices: PriceDisplay },
+    /// A 6x10 grid of liquidity magnitudes.
+    Grid,
+    /// One row per initialized slot, with its price; runs of empty slots on one line.
+    Compact { format: HumanPriceFormat, prices: PriceDisplay },
+}
+
+/// One slot of a tick array, as every `rpc inspect-array` view and its JSON output show it.
+struct ArraySlot {
+    slot: usize,
+    tick: i32,
+    /// Human price at the tick, in the `--format` orientation.
+    price: f64,
+    liquidity_net: i128,
+    liquidity_gross: u128,
+}
+
+/// The 60 slots of a tick array, with the tick index each slot stands for and its price.
+fn tick_array_slots(tick_array: &TickArrayState, tick_spacing: u16, converter: &TickConverter, format: HumanPriceFormat) -> Vec<ArraySlot> {
+    let format = match format {
+        HumanPriceFormat::T0PerT1 => PriceInput::Token0PerToken1Human { price: 0.0 },
+        HumanPriceFormat::T1PerT0 => PriceInput::Token1PerToken0Human { price: 0.0 },
+    };
+    tick_array.ticks.iter().enumerate()
+        .map(|(slot, tick_state)| {
+            // The absolute tick index of the slot, whether or not it is initialized
+            let tick = tick_array.start_tick_index + slot as i32 * tick_spacing as i32;
+            ArraySlot {
+                slot,
+                tick,
+                price: converter.tick_to_price(Tick(tick), format),
+                liquidity_net: tick_state.liquidity_net,
+                liquidity_gross: tick_state.liquidity_gross,
+            }
+        })
+        .collect()
+}
+
+/// Prints the fee and reward growth outside each initialized tick of the array, in human units per
+/// unit of liquidity (rewards stay raw: their mint decimals are not fetched), then the fee growth
+/// inside each band between adjacent initialized ticks, as the program computes it for a position.
+fn print_tick_array_growths(tick_array: &TickArrayState, pool_state: &PoolState) {
+    let initialized: Vec<&TickState> = tick_array.ticks.iter().filter(|tick| tick.liquidity_gross != 0).collect();
+    let rewards: Vec<usize> = (0..pool_state.reward_infos.len())
+        .filter(|&slot| pool_state.reward_infos[slot].token_mint != Pubkey::default())
+        .collect();
+    let scale = [pool_state.mint_decimals_0, pool_state.mint_decimals_1].map(|decimals| 10f64.powi(decimals as i32));
+    let fee_cell = |growth: u128, token: usize| format!("{:.6e}", q64_to_f64(growth) / scale[token]);
+
+    // 1. Growth outside each initialized tick
+    println!("--- Fee and Reward Growth Outside (per unit of liquidity) ---");
+    let reward_headers: String = rewards.iter().map(|slot| format!(" | {:>14}", format!("Reward {} (raw)", slot))).collect();
+    println!("{:>8} | {:>14} | {:>14}{}", "Tick", "Fee 0 (t0)", "Fee 1 (t1)", reward_headers);
+    println!("{:-<1$}", "", 42 + 17 * rewards.len());
+    for tick in &initialized {
+        let reward_cells: String = rewards.iter()
+            .map(|&slot| format!(" | {:>14}", format!("{:.6e}", q64_to_f64(tick.reward_growths_outside_x64[slot]))))
+            .collect();
+        println!(
+            "{:>8} | {:>14} | {:>14}{}",
+            tick.tick, fee_cell(tick.fee_growth_outside_0_x64, 0), fee_cell(tick.fee_growth_outside_1_x64, 1), reward_cells
+        );
+    }
+    if initialized.len() < 2 {
+        println!("\nFewer than two initialized ticks: no band to compute the fee growth inside of.");
+        return;
+    }
+
+    // 2. Growth inside each band, from the pool's global growth and the two boundary ticks
+    println!("\n--- Fee Growth Inside Each Band (per unit of liquidity) ---");
+    println!("{:>19} | {:>14} | {:>14}", "Band", "Fee 0 (t0)", "Fee 1 (t1)");
+    println!("{:-<53}", "");
+    let globals = [pool_state.fee_growth_global_0_x64, pool_state.fee_growth_global_1_x64];
+    let mut best: [Option<(u128, String)>; 2] = [None, None];
+    for pair in initialized.windows(2) {
+        let band = format!("[{}, {}]", pair[0].tick, pair[1].tick);
+        let cells = [0, 1].map(|token| {
+            let outside = |tick: &TickState| if token == 0 { tick.fee_growth_outside_0_x64 } else { tick.fee_growth_outside_1_x64 };
+            let inside = growth_inside(
+                globals[token],
+                pool_state.tick_current,
+                (Tick(pair[0].tick), Some(outside(pair[0]))),
+                (Tick(pair[1].tick), Some(outside(pair[1]))),
+            );
+            if inside > globals[token] {
+                return "wrapped".to_string();
+            }
+            if inside > 0 && best[token].as_ref().is_none_or(|(growth, _)| inside > *growth) {
+                best[token] = Some((inside, band.clone()));
+            }
+            fee_cell(inside, token)
+        });
+        println!("{:>19} | {:>14} | {:>14}", band, cells[0], cells[1]);
+    }
+    for (token, best) in best.iter().enumerate() {
+        if let Some((_, band)) = best {
+            println!("Highest token {} fee growth: {}", token, band);
+        }
+    }
+    println!("\nOutside values count from when each tick was initialized, so a band whose ticks were initialized");
+    println!("later shows less growth. \"wrapped\" marks a band whose growth went below zero for that reason.");
 }
 
 /// --- Main Application Logic ---
-fn main() {
+#[tokio::main]
+async fn main() {
     let cli = Cli::parse();
 
+    let program_id_str = cli.program_id.unwrap_or_else(|| cli.cluster.default_program_id().to_string());
+    let program_id = parse_pubkey(&program_id_str, "program id");
+    // Offline commands with an RPC fallback only use the network when a URL was given explicitly
+    let rpc_url_given = !cli.rpc_url.is_empty();
+    let rpc_url = if cli.rpc_url.is_empty() {
+        vec![cli.cluster.default_rpc_url().to_string()]
+    } else {
+        cli.rpc_url
+    };
+    let fetch_options = FetchOptions {
+        commitment: cli.commitment.config(),
+        retries: cli.retries,
+        retry_delay: Duration::from_millis(cli.retry_delay_ms),
+        strict_single_slot: cli.strict_single_slot,
+        max_rps: cli.max_rps,
+        concurrency: cli.concurrency,
+        // Progress bars go to stderr and are only useful on an interactive terminal
+        show_progress: !cli.quiet && std::io::stderr().is_terminal(),
+        min_context_slot: cli.min_slot,
+    };
+    let palette = Palette::new(cli.color);
+    let prices = PriceDisplay { precision: cli.precision, scientific: cli.scientific };
+    let pool_cache = cli.cache_dir.map(|dir| PoolCache::new(dir, Duration::from_secs(cli.cache_ttl_secs), !cli.no_cache));
+    if fetch_options.max_rps.is_some_and(|rps| rps <= 0.0) {
+        fail(ExitCode::InvalidInput, "--max-rps must be greater than zero.");
+    }
+    if fetch_options.concurrency == 0 {
+        fail(ExitCode::InvalidInput, "--concurrency must be at least 1.");
+    }
+
     match cli.command {
-        Commands::TickToPrice { tick, decimals0, decimals1 } => {
-            let converter = TickConverter { decimals_0: decimals0, decimals_1: decimals1 };
-            converter.print_all_prices(tick);
+        Commands::TickToPrice { tick, decimals0, decimals1, pool_id, swap_tokens, format } => {
+            let given = PoolParams { tick_spacing: None, decimals_0: decimals0, decimals_1: decimals1 }.swapped_if(swap_tokens);
+            let (decimals_0, decimals_1) = resolve_pool_params(pool_id.as_deref(), given, rpc_url, fetch_options, &program_id, pool_cache.as_ref(), format.is_some()).await.decimals();
+            let converter = TickConverter { decimals_0, decimals_1 };
+            if let Some(format) = format {
+                println!("{}", prices.format_plain(converter.raw_to_price(converter.tick_to_raw_price(Tick(tick)), format.with_price(0.0))));
+            } else {
+                if swap_tokens {
+                    print_swap_tokens_note(decimals_0, decimals_1);
+                }
+                converter.print_all_prices(Tick(tick), prices);
+            }
         }
-        Commands::PriceToTick { decimals0, decimals1, price } => {
-            let converter = TickConverter { decimals_0: decimals0, decimals_1: decimals1 };
-            let tick = converter.price_to_tick(price);
+        Commands::PriceToTick { decimals0, decimals1, pool_id, rounding, tick_spacing, swap_tokens, price } => {
+            let given = PoolParams { tick_spacing, decimals_0: decimals0, decimals_1: decimals1 }.swapped_if(swap_tokens);
+            let params = resolve_pool_params(pool_id.as_deref(), given, rpc_url, fetch_options, &program_id, pool_cache.as_ref(), false).await;
+            let (decimals_0, decimals_1) = params.decimals();
+            let price = if swap_tokens { price.flipped() } else { price };
+            if swap_tokens {
+                print_swap_tokens_note(decimals_0, decimals_1);
+            }
+            let converter = TickConverter { decimals_0, decimals_1 };
+            let tick = match rounding {
+                RoundingMode::NearestAligned => converter.price_to_tick_spaced(price, rounding, params.tick_spacing()),
+                _ => converter.price_to_tick_rounded(price, rounding),
+            };
             println!("--- Price to Tick Conversion ---");
             println!("Input Price: {:?}", price);
+            println!("Rounding: {:?}", rounding);
             println!("Resulting Tick Index: {}", tick);
         }
+        Commands::PriceToSqrtPrice { price, format, decimals0, decimals1, pool_id, rounding, swap_tokens } => {
+            let given = PoolParams { tick_spacing: None, decimals_0: decimals0, decimals_1: decimals1 }.swapped_if(swap_tokens);
+            let (decimals_0, decimals_1) = resolve_pool_params(pool_id.as_deref(), given, rpc_url, fetch_options, &program_id, pool_cache.as_ref(), false).await.decimals();
+            let format = if swap_tokens { format.flipped() } else { format };
+            if swap_tokens {
+                print_swap_tokens_note(decimals_0, decimals_1);
+            }
+            let converter = TickConverter { decimals_0, decimals_1 };
+            let price_input = format.with_price(price);
+            let raw_price = converter.to_raw_price(price_input);
+            if !(raw_price.is_finite() && raw_price > 0.0) {
+                fail(ExitCode::InvalidInput, format!("--price {} does not convert to a positive raw price.", price));
+            }
+
+            // 1. Price -> tick with the exact rounding path, then the program's integer tick -> sqrt price
+            let tick = converter.price_to_tick_rounded(price_input, rounding);
+            let sqrt_price_x64 = tick.sqrt_price().unwrap_or_else(|e| fail(ExitCode::InvalidInput, e));
+
+            // 2. The naive value straight from the f64 price, for comparison
+            let naive_sqrt_price_x64 = (raw_price.sqrt() * 2f64.powi(64)) as u128;
+
+            println!("--- Sqrt Price for {:?} ---", price_input);
+            println!("  - Tick:                         {} (rounding: {:?})", tick, rounding);
+            println!("  - Price at That Tick:           {}", prices.format(converter.tick_to_price(tick, price_input)));
+            println!("  - sqrt_price_x64 (exact):       {}", sqrt_price_x64);
+            println!("  - sqrt_price_x64 (naive f64):   {}", naive_sqrt_price_x64);
+            println!("  - Difference (naive - exact):   {}", naive_sqrt_price_x64 as i128 - sqrt_price_x64.0 as i128);
+            match SqrtPriceX64(naive_sqrt_price_x64).tick() {
+                Ok(naive_tick) => println!("  - Tick of the Naive Value:      {}", naive_tick),
+                Err(e) => println!("  - Tick of the Naive Value:      n/a ({})", e),
+            }
+            println!("\nThe exact value is the tick's sqrt price, bit-exact with the program; the naive value is the");
+            println!("input price itself, off the tick grid and subject to f64 rounding.");
+        }
+        Commands::SqrtToPrice { sqrt_price_x64, decimals0, decimals1, tick_spacing, swap_tokens } => {
+            if let Some(tick_spacing) = tick_spacing {
+                warn_unknown_tick_spacing(tick_spacing);
+            }
+            let (decimals_0, decimals_1) = if swap_tokens { (decimals1, decimals0) } else { (decimals0, decimals1) };
+            if swap_tokens {
+                print_swap_tokens_note(decimals_0, decimals_1);
+            }
+            let converter = TickConverter { decimals_0, decimals_1 };
+            let sqrt_price_x64 = SqrtPriceX64(sqrt_price_x64);
+
+            // 1. Exact integer tick, the same one the program stores as `tick_current`
+            let tick = sqrt_price_x64.tick().unwrap_or_else(|e| fail(ExitCode::InvalidInput, e));
+            let on_tick = tick.sqrt_price().is_ok_and(|tick_sqrt_price| tick_sqrt_price == sqrt_price_x64);
+
+            // 2. Prices from the sqrt price itself, not from the tick
+            println!("--- Prices for sqrt_price_x64 {} ---", sqrt_price_x64);
+            converter.print_price_formats(sqrt_price_x64.to_raw_price(), prices);
+            if on_tick {
+                println!("  - Tick:                  {} (exactly on the tick)", tick);
+            } else {
+                println!("  - Tick:                  {} (between ticks {} and {})", tick, tick, tick.offset(1));
+            }
+            if let Some(tick_spacing) = tick_spacing {
+                let aligned = tick.align_down(tick_spacing);
+                if aligned == tick {
+                    println!("  - Aligned to Spacing:    yes (spacing {})", tick_spacing);
+                } else {
+                    println!("  - Aligned to Spacing:    no (spacing {}; usable ticks around it: {} and {})", tick_spacing, aligned, aligned.offset(tick_spacing as i32));
+                }
+            }
+        }
+        Commands::PriceToSqrt { decimals0, decimals1, swap_tokens, price } => {
+            let (decimals_0, decimals_1) = if swap_tokens { (decimals1, decimals0) } else { (decimals0, decimals1) };
+            let price = if swap_tokens { price.flipped() } else { price };
+            if swap_tokens {
+                print_swap_tokens_note(decimals_0, decimals_1);
+            }
+            let converter = TickConverter { decimals_0, decimals_1 };
+            let raw_price = converter.to_raw_price(price);
+            if !(raw_price.is_finite() && raw_price > 0.0) {
+                fail(ExitCode::InvalidInput, format!("{:?} does not convert to a positive raw price.", price));
+            }
+
+            // 1. sqrt(raw_price) in Q64.64, the value the pool's initialize instruction takes
+            let sqrt_price_x64 = SqrtPriceX64((raw_price.sqrt() * 2f64.powi(64)) as u128);
+
+            // 2. The tick the pool would start at, via the program's integer math (also the range check)
+            let tick = sqrt_price_x64.tick().unwrap_or_else(|e| fail(ExitCode::InvalidInput, e));
+
+            println!("--- Sqrt Price for {:?} ---", price);
+            println!("  - sqrt_price_x64:          {}", sqrt_price_x64);
+            println!("  - Tick:                    {}", tick);
+            println!("  - Price Back from Value:   {}", prices.format(converter.raw_to_price(sqrt_price_x64.to_raw_price(), price)));
+        }
         Commands::ArrayInfo { start_index, tick_spacing } => {
+            warn_unknown_tick_spacing(tick_spacing);
+            let helper = TickArrayHelper { tick_spacing };
+            helper.print_array_info(ArrayStartIndex(start_index));
+        }
+        Commands::TickInfo { tick, tick_spacing, pool_id } => {
+            let given = PoolParams { tick_spacing, decimals_0: None, decimals_1: None };
+            let tick_spacing = resolve_pool_params(pool_id.as_deref(), given, rpc_url, fetch_options, &program_id, pool_cache.as_ref(), false).await.tick_spacing();
             let helper = TickArrayHelper { tick_spacing };
-            helper.print_array_info(start_index);
+            helper.print_tick_info(Tick(tick));
         }
-        Commands::TickInfo { tick, tick_spacing } => {
+        Commands::TickToArrayStart { tick, tick_spacing } => {
+            warn_unknown_tick_spacing(tick_spacing);
             let helper = TickArrayHelper { tick_spacing };
-            helper.print_tick_info(tick);
+            println!("{}", helper.get_array_start_index(Tick(tick)));
         }
-        Commands::ArrayToPriceRange { start_index, tick_spacing, decimals0, decimals1 } => {
+        Commands::AlignTick { tick, tick_spacing } => {
+            warn_unknown_tick_spacing(tick_spacing);
             let helper = TickArrayHelper { tick_spacing };
-            let converter = TickConverter { decimals_0: decimals0, decimals_1: decimals1 };
-            let (tick_start, tick_end) = helper.get_array_tick_range(start_index);
+            println!("{}", helper.align_tick_to_spacing(Tick(tick)));
+        }
+        Commands::PctToTicks { pct, tick_spacing } => {
+            if pct.is_nan() || pct <= -100.0 {
+                fail(ExitCode::InvalidInput, "--pct must be greater than -100.");
+            }
+            if tick_spacing == 0 {
+                fail(ExitCode::InvalidInput, "--tick-spacing must be at least 1.");
+            }
+            warn_unknown_tick_spacing(tick_spacing);
+            // 1. ticks = log(1 + pct/100) / log(1.0001); a move and its reverse give opposite tick counts
+            let exact = (1.0 + pct / 100.0).log(Q_RATIO);
+            let rounded = exact.round() as i64;
+            println!("--- Price Move of {:+}% ---", pct);
+            println!("  - Ticks (exact):   {:+.4}", exact);
+            println!("  - Ticks (rounded): {:+} (a {:+.4}% move)", rounded, (Q_RATIO.powf(rounded as f64) - 1.0) * 100.0);
+
+            // 2. Arrays: a move of d ticks touches floor(d/W)+1 to ceil(d/W)+1 arrays of W ticks,
+            //    depending on where it starts
+            let distance = rounded.unsigned_abs();
+            let ticks_per_array = (TICK_ARRAY_SIZE as u64) * tick_spacing as u64;
+            println!("  - Tick Spacing:    {} ({} ticks per array)", tick_spacing, ticks_per_array);
+            println!("  - Spacing Steps:   {}", distance.div_ceil(tick_spacing as u64));
+            println!("  - Array Widths:    {:.4}", distance as f64 / ticks_per_array as f64);
+            let (fewest, most) = (distance / ticks_per_array + 1, distance.div_ceil(ticks_per_array) + 1);
+            if fewest == most {
+                println!("  - Arrays Touched:  {}", fewest);
+            } else {
+                println!("  - Arrays Touched:  {} to {}, depending on the start tick", fewest, most);
+            }
+        }
+        Commands::ArrayToPriceRange { start_index, tick_spacing, decimals0, decimals1, pool_id } => {
+            let given = PoolParams { tick_spacing, decimals_0: decimals0, decimals_1: decimals1 };
+            let params = resolve_pool_params(pool_id.as_deref(), given, rpc_url, fetch_options, &program_id, pool_cache.as_ref(), false).await;
+            let (decimals_0, decimals_1) = params.decimals();
+            let helper = TickArrayHelper { tick_spacing: params.tick_spacing() };
+            let converter = TickConverter { decimals_0, decimals_1 };
+            let (tick_start, tick_end) = helper.get_array_valid_tick_range(ArrayStartIndex(start_index));
             println!("--- Price Range for Tick Array {} ---", start_index);
             println!("\nStart of Range (Tick {}):", tick_start);
-            converter.print_all_prices(tick_start);
+            converter.print_all_prices(tick_start, prices);
             println!("\nEnd of Range (Tick {}):", tick_end);
-            converter.print_all_prices(tick_end);
+            converter.print_all_prices(tick_end, prices);
         }
-        Commands::PriceRangeToArrays { price_lower, price_upper, tick_spacing, decimals0, decimals1, format } => {
-            let converter = TickConverter { decimals_0: decimals0, decimals_1: decimals1 };
+        Commands::PriceRangeToArrays { price_lower, price_upper, tick_spacing, decimals0, decimals1, format, pool_id, round, swap_tokens } => {
+            let given = PoolParams { tick_spacing, decimals_0: decimals0, decimals_1: decimals1 }.swapped_if(swap_tokens);
+            let params = resolve_pool_params(pool_id.as_deref(), given, rpc_url, fetch_options, &program_id, pool_cache.as_ref(), false).await;
+            let (decimals_0, decimals_1) = params.decimals();
+            let format = if swap_tokens { format.flipped() } else { format };
+            if swap_tokens {
+                print_swap_tokens_note(decimals_0, decimals_1);
+            }
+            let tick_spacing = params.tick_spacing();
+            let converter = TickConverter { decimals_0, decimals_1 };
             let helper = TickArrayHelper { tick_spacing };
             
             // Determine which price format to use for the converter
@@ -680,919 +2842,4540 @@ fn main() {
                 ),
             };
 
-            let tick_lower = converter.price_to_tick(price_format_lower);
-            let tick_upper = converter.price_to_tick(price_format_upper);
+            let (tick_lower, tick_upper) = converter.price_range_to_ticks(price_format_lower, price_format_upper, round, tick_spacing);
+
+            let start_array_index = helper.get_array_start_index(tick_lower);
+            let end_array_index = helper.get_array_start_index(tick_upper);
+            
+            println!("--- Arrays Crossed by Price Range {} (Format: {:?}) ---", prices.range(price_lower, price_upper), format);
+            println!("  - Corresponding Tick Range: [{}, {}]", tick_lower, tick_upper);
+            println!("\n{:<15} | {:<25} | Price Range (in specified format)", "Array Start", "Tick Range");
+            println!("{:-<90}", "");
+
+            let step = helper.tick_indices_per_array();
+
+            // Handle both increasing and decreasing tick traversals
+            if start_array_index <= end_array_index {
+                let mut current_array_start = start_array_index;
+                while current_array_start <= end_array_index {
+                    let (tick_start, tick_end) = helper.get_array_tick_range(current_array_start);
+                    let price_start = converter.tick_to_price(tick_start, price_input_template);
+                    let price_end = converter.tick_to_price(helper.last_valid_tick(current_array_start), price_input_template);
+                    
+                    println!("{:<15} | [{:<11}, {:<11}] | {}", current_array_start, tick_start, tick_end, prices.range(price_start, price_end));
+                    current_array_start = current_array_start.shifted(1, tick_spacing);
+                }
+            } else { // Handle case where ticks are decreasing
+                let mut current_array_start = start_array_index;
+                while current_array_start >= end_array_index {
+                    let (tick_start, tick_end) = helper.get_array_tick_range(current_array_start);
+                    let price_start = converter.tick_to_price(tick_start, price_input_template);
+                    let price_end = converter.tick_to_price(helper.last_valid_tick(current_array_start), price_input_template);
+
+                    println!("{:<15} | [{:<11}, {:<11}] | {}", current_array_start, tick_start, tick_end, prices.range(price_start, price_end));
+                    current_array_start = current_array_start.shifted(-1, tick_spacing);
+                }
+            }
+
+            // Summary: array count, covered span, and where the aligned range ends fall within their arrays
+            let array_count = (end_array_index.0 - start_array_index.0).abs() / step + 1;
+            let (first_array, last_array) = (start_array_index.min(end_array_index), start_array_index.max(end_array_index));
+            println!("{:-<90}", "");
+            println!(
+                "Summary: {} array(s) crossed, covering ticks [{}, {}] ({} tick indices).",
+                array_count, first_array, helper.get_array_tick_range(last_array).1, array_count * step
+            );
+            for (label, tick) in [("Lower", tick_lower.min(tick_upper)), ("Upper", tick_lower.max(tick_upper))] {
+                let aligned = helper.align_tick_to_spacing(tick);
+                let array_start = helper.get_array_start_index(aligned);
+                let slot = array_start.slot_of(aligned, tick_spacing);
+                let position = match slot {
+                    0 => "on an array boundary (first slot)".to_string(),
+                    s if s == TICK_ARRAY_SIZE - 1 => "on an array boundary (last slot)".to_string(),
+                    s => format!("mid-array (slot {} of 0..{})", s, TICK_ARRAY_SIZE - 1),
+                };
+                println!("  - {} tick {} aligns to {} in array {}: {}", label, tick, aligned, array_start, position);
+            }
+        }
+        Commands::DerivePda { pool_id, tick_spacing, decimals0, decimals1, tick, tick_range, debug_seeds, json, price } => {
+            if tick.is_empty() && tick_range.is_empty() && price.is_none() {
+                fail(ExitCode::InvalidInput, "You must provide --tick, --tick-range or a price subcommand for derive-pda.");
+            }
+            if !(tick.is_empty() && tick_range.is_empty()) && price.is_some() {
+                fail(ExitCode::InvalidInput, "You must provide either --tick/--tick-range OR a price subcommand, not both.");
+            }
+
+            if !tick_range.is_empty() && tick_range.len() != 2 {
+                fail(ExitCode::InvalidInput, "--tick-range takes exactly two ticks: LOWER,UPPER.");
+            }
+
+            // The pool id is always known here, so fetch it for missing flags, or to cross-check them with --rpc-url
+            let given = PoolParams { tick_spacing, decimals_0: decimals0, decimals_1: decimals1 };
+            let missing = tick_spacing.is_none() || (price.is_some() && (decimals0.is_none() || decimals1.is_none()));
+            let params = if missing || rpc_url_given {
+                resolve_pool_params(Some(&pool_id), given, rpc_url, fetch_options, &program_id, pool_cache.as_ref(), false).await
+            } else {
+                if let Some(tick_spacing) = given.tick_spacing {
+                    warn_unknown_tick_spacing(tick_spacing);
+                }
+                given
+            };
+            let tick_spacing = params.tick_spacing();
+            let helper = TickArrayHelper { tick_spacing };
+            let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+
+            let input_ticks = match price {
+                Some(price) => {
+                    let (decimals_0, decimals_1) = params.decimals();
+                    vec![TickConverter { decimals_0, decimals_1 }.price_to_tick(price)]
+                }
+                None => tick.into_iter().map(Tick).collect(),
+            };
+
+            if let [input_tick] = input_ticks[..]
+                && tick_range.is_empty()
+                && !json
+            {
+                let start_index = helper.get_array_start_index(input_tick);
+                let pda = derive_tick_array_pda(&pool_pubkey, start_index.0, &program_id);
+
+                println!("--- Tick Array PDA Derivation ---");
+                println!("  - Input Tick Index: {}", input_tick);
+                println!("  - Tick Spacing: {}", tick_spacing);
+                println!("  - This tick belongs to the array that *starts* at index: {}", start_index);
+                println!("  - Pool ID: {}", pool_id);
+                println!("  - Derived PDA: {}", pda);
+                if debug_seeds {
+                    println!();
+                    print_tick_array_seeds(&pool_pubkey, start_index.0, &program_id);
+                }
+            } else {
+                // 1. Group the input ticks by array; a --tick-range adds every array between its bounds
+                let mut arrays: std::collections::BTreeMap<i32, Vec<i32>> = std::collections::BTreeMap::new();
+                if let [lower, upper] = tick_range[..] {
+                    let (low, high) = (lower.min(upper), lower.max(upper));
+                    let mut start_index = helper.get_array_start_index(Tick(low));
+                    while start_index <= helper.get_array_start_index(Tick(high)) {
+                        arrays.entry(start_index.0).or_default();
+                        start_index = start_index.shifted(1, tick_spacing);
+                    }
+                    arrays.entry(helper.get_array_start_index(Tick(low)).0).or_default().push(low);
+                    arrays.entry(helper.get_array_start_index(Tick(high)).0).or_default().push(high);
+                }
+                for &input_tick in &input_ticks {
+                    arrays.entry(helper.get_array_start_index(input_tick).0).or_default().push(input_tick.0);
+                }
+
+                // 2. Derive each array's PDA once
+                let result = DerivePdaJson {
+                    pool_id: pool_id.clone(),
+                    tick_spacing,
+                    arrays: arrays
+                        .into_iter()
+                        .map(|(start_index, mut ticks)| {
+                            ticks.sort();
+                            ticks.dedup();
+                            DerivePdaArrayJson {
+                                start_index,
+                                pda: derive_tick_array_pda(&pool_pubkey, start_index, &program_id).to_string(),
+                                ticks,
+                            }
+                        })
+                        .collect(),
+                };
+
+                // 3. Print
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&result).unwrap());
+                } else {
+                    let shared = result.arrays.iter().filter(|array| array.ticks.len() > 1).count();
+                    println!("--- Tick Array PDA Derivation ({} arrays) ---", result.arrays.len());
+                    println!("  - Tick Spacing: {}", tick_spacing);
+                    println!("  - Pool ID: {}", pool_id);
+                    if shared > 0 {
+                        println!("  - Arrays shared by several input ticks are listed once ({} shared).", shared);
+                    }
+                    println!("\n{:<25} | {:<12} | PDA", "Input Ticks", "Array Start");
+                    println!("{:-<90}", "");
+                    for array in &result.arrays {
+                        let ticks = if array.ticks.is_empty() {
+                            "(in range)".to_string()
+                        } else {
+                            array.ticks.iter().map(i32::to_string).collect::<Vec<_>>().join(", ")
+                        };
+                        println!("{:<25} | {:<12} | {}", ticks, array.start_index, array.pda);
+                    }
+                    if debug_seeds {
+                        for array in &result.arrays {
+                            println!();
+                            print_tick_array_seeds(&pool_pubkey, array.start_index, &program_id);
+                        }
+                    }
+                }
+            }
+        }
+        Commands::BitmapPosition { start_index, tick, tick_spacing } => {
+            warn_unknown_tick_spacing(tick_spacing);
+            let helper = TickArrayHelper { tick_spacing };
+            let start_index = match (start_index, tick) {
+                (Some(start_index), _) => start_index,
+                (None, Some(tick)) => {
+                    let start_index = helper.get_array_start_index(Tick(tick));
+                    println!("Tick {} is in the array starting at {}.", tick, start_index);
+                    start_index.0
+                }
+                (None, None) => {
+                    fail(ExitCode::InvalidInput, "You must provide either --start-index or --tick.");
+                }
+            };
+            let ticks_per_array = helper.tick_indices_per_array();
+
+            println!("--- Bitmap Position for Array Start Index {} (tick spacing {}) ---", start_index, tick_spacing);
+            let Some(position) = BitmapPosition::from_start_index(start_index, tick_spacing) else {
+                if start_index % ticks_per_array != 0 {
+                    fail(ExitCode::InvalidInput, format!(
+                        "{} is not a multiple of {} (60 * tick spacing). The containing array starts at {}.",
+                        start_index, ticks_per_array, helper.get_array_start_index(Tick(start_index))
+                    ));
+                }
+                fail(ExitCode::InvalidInput, format!(
+                    "Array offset {} is outside the range covered by the default and extension bitmaps.",
+                    start_index / ticks_per_array
+                ));
+            };
+            println!("  - Array Offset: {} ({} / {})", start_index / ticks_per_array, start_index, ticks_per_array);
+            match position.location {
+                BitmapLocation::Default => println!("  - Bitmap:       default (PoolState::tick_array_bitmap, 16 words)"),
+                BitmapLocation::Positive { chunk } => println!("  - Bitmap:       extension positive_tick_array_bitmap[{}] (8 words)", chunk),
+                BitmapLocation::Negative { chunk } => println!("  - Bitmap:       extension negative_tick_array_bitmap[{}] (8 words)", chunk),
+            }
+            println!("  - Word Index:   {}", position.word);
+            println!("  - Bit Index:    {} (mask {:#018x})", position.bit, 1u64 << position.bit);
+
+            // Decode the bit back the way the bitmap readers do
+            let inverse = position.start_index(tick_spacing);
+            if inverse == start_index {
+                println!("  - Self-check:   bit decodes back to start index {} (OK)", inverse);
+            } else {
+                println!("  - Self-check:   bit decodes back to start index {} (MISMATCH, expected {})", inverse, start_index);
+            }
+        }
+        Commands::TickRangeToPdas { pool_id, tick_lower, tick_upper, tick_spacing } => {
+            // Cross-check the spacing against the pool when --rpc-url is given, as derive-pda does
+            let tick_spacing = if rpc_url_given {
+                let given = PoolParams { tick_spacing: Some(tick_spacing), decimals_0: None, decimals_1: None };
+                resolve_pool_params(Some(&pool_id), given, rpc_url, fetch_options, &program_id, pool_cache.as_ref(), false).await.tick_spacing()
+            } else {
+                warn_unknown_tick_spacing(tick_spacing);
+                tick_spacing
+            };
+            let helper = TickArrayHelper { tick_spacing };
+            let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+
+            // Accept the bounds in either order; off-grid ticks simply fall into their containing array.
+            let (low, high) = if tick_lower <= tick_upper { (tick_lower, tick_upper) } else { (tick_upper, tick_lower) };
+            let first_start = helper.get_array_start_index(Tick(low));
+            let last_start = helper.get_array_start_index(Tick(high));
+
+            println!("--- Tick Array PDAs for Tick Range [{}, {}] ---", low, high);
+            if tick_lower > tick_upper {
+                println!("  - Note: --tick-lower was above --tick-upper; the bounds were swapped.");
+            }
+            println!("  - Tick Spacing: {}", tick_spacing);
+            println!("  - Pool ID: {}", pool_id);
+            println!("\n{:<15} | {:<25} | PDA", "Array Start", "Tick Range");
+            println!("{:-<90}", "");
+
+            let mut count = 0;
+            let mut start_index = first_start;
+            while start_index <= last_start {
+                let (range_start, range_end) = helper.get_array_tick_range(start_index);
+                let pda = derive_tick_array_pda(&pool_pubkey, start_index.0, &program_id);
+                println!("{:<15} | {:<25} | {}", start_index, format!("[{}, {}]", range_start, range_end), pda);
+                count += 1;
+                start_index = start_index.shifted(1, tick_spacing);
+            }
+            println!("\nTotal arrays: {}", count);
+        }
+        Commands::ReversePda { pool_id, pda, tick_spacing, decimals0, decimals1 } => {
+            let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+            let tick_array_pda = parse_pubkey(&pda, "PDA");
+
+            if rpc_url_given {
+                // 1a. Read the start index from the account and cross-check the derivation
+                let rpc_client = RpcFetcher::new(rpc_url, fetch_options);
+                let tick_array = fetch_clmm_account::<TickArrayState>(&rpc_client, &tick_array_pda, &program_id).await;
+                let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+                let helper = TickArrayHelper { tick_spacing: pool_state.tick_spacing };
+                let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+                let start_index = tick_array.start_tick_index;
+
+                println!("--- Reverse PDA Lookup for {} (read from the account) ---", tick_array_pda);
+                print_tick_array_ranges(&helper, &converter, start_index, prices);
+                if let Some(spacing) = tick_spacing
+                    && spacing != pool_state.tick_spacing
+                {
+                    println!("\nWARNING: --tick-spacing {} differs from the pool's tick spacing {}; using the pool's.", spacing, pool_state.tick_spacing);
+                }
+                if tick_array.pool_id != pool_pubkey {
+                    println!("\nWARNING: This tick array belongs to pool {}, not {}.", tick_array.pool_id, pool_pubkey);
+                } else if derive_tick_array_pda(&pool_pubkey, start_index, &program_id) != tick_array_pda {
+                    println!("\nWARNING: {} is not the canonical PDA for start index {} of this pool.", tick_array_pda, start_index);
+                } else {
+                    println!("\nDerivation check: the PDA of start index {} matches.", start_index);
+                }
+            } else {
+                // 1b. Derive every candidate start index until one matches
+                let Some(tick_spacing) = tick_spacing else {
+                    fail(ExitCode::InvalidInput, "--tick-spacing is required without --rpc-url.");
+                };
+                if tick_spacing == 0 {
+                    fail(ExitCode::InvalidInput, "--tick-spacing must be at least 1.");
+                }
+                warn_unknown_tick_spacing(tick_spacing);
+                let helper = TickArrayHelper { tick_spacing };
+                let converter = TickConverter { decimals_0: decimals0.unwrap_or(0), decimals_1: decimals1.unwrap_or(0) };
+                let Some(start_index) = find_tick_array_start_index(&pool_pubkey, &tick_array_pda, &helper, &program_id) else {
+                    let (min_start, max_start) = helper.valid_array_start_range();
+                    fail(ExitCode::Failure, format!(
+                        "{} is not the tick array PDA of any start index in [{}, {}] for pool {} with tick spacing {} (program {}).",
+                        tick_array_pda, min_start, max_start, pool_pubkey, tick_spacing, program_id
+                    ));
+                };
+
+                println!("--- Reverse PDA Lookup for {} (tick spacing {}) ---", tick_array_pda, tick_spacing);
+                print_tick_array_ranges(&helper, &converter, start_index, prices);
+                if decimals0.is_none() {
+                    println!("\nPrices are raw (no decimal adjustment); pass --decimals0 and --decimals1 for human prices.");
+                }
+            }
+        }
+        Commands::VerifyPda { pool_id, start_index, pda, tick_spacing } => {
+            let tick_spacing = match tick_spacing {
+                Some(tick_spacing) if rpc_url_given => {
+                    let given = PoolParams { tick_spacing: Some(tick_spacing), decimals_0: None, decimals_1: None };
+                    Some(resolve_pool_params(Some(&pool_id), given, rpc_url, fetch_options, &program_id, pool_cache.as_ref(), false).await.tick_spacing())
+                }
+                tick_spacing => {
+                    if let Some(tick_spacing) = tick_spacing {
+                        warn_unknown_tick_spacing(tick_spacing);
+                    }
+                    tick_spacing
+                }
+            };
+            let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+            let provided = parse_pubkey(&pda, "PDA");
+            let seeds = tick_array_seeds(&pool_pubkey, start_index);
+            let (expected, bump) = Pubkey::find_program_address(&seeds.each_ref().map(Vec::as_slice), &program_id);
+
+            println!("--- PDA Verification for Start Index {} ---", start_index);
+            println!("  - Pool ID:      {}", pool_pubkey);
+            println!("  - Program ID:   {}", program_id);
+            println!("  - Expected PDA: {} (bump {})", expected, bump);
+            println!("  - Provided PDA: {}", provided);
+            if let Some(tick_spacing) = tick_spacing {
+                let helper = TickArrayHelper { tick_spacing };
+                let aligned = helper.get_array_start_index(Tick(start_index));
+                if aligned.0 != start_index {
+                    println!(
+                        "  - Warning:      {} is not a multiple of {} (60 * tick spacing); the array containing tick {} starts at {}.",
+                        start_index, helper.tick_indices_per_array(), start_index, aligned
+                    );
+                }
+            }
+
+            if provided == expected {
+                println!("  - Result:       MATCH");
+            } else {
+                println!("  - Result:       MISMATCH");
+
+                // Look for the usual causes: a little-endian start index, the other cluster's program, another array
+                println!("\nChecking common causes:");
+                let le_bytes = start_index.to_le_bytes();
+                let (le_pda, _) = Pubkey::find_program_address(&[&seeds[0], &seeds[1], &le_bytes], &program_id);
+                let mut explained = false;
+                if le_pda == provided {
+                    println!("  - The PDA was derived with a little-endian start index; the program encodes it big-endian.");
+                    explained = true;
+                }
+                for other_program in [RAYDIUM_CLMM_PROGRAM_ID, RAYDIUM_CLMM_DEVNET_PROGRAM_ID] {
+                    let other_program = Pubkey::from_str(other_program).expect("known program id is valid");
+                    if other_program != program_id && derive_tick_array_pda(&pool_pubkey, start_index, &other_program) == provided {
+                        println!("  - The PDA was derived with program id {}; pass --program-id or --cluster to match it.", other_program);
+                        explained = true;
+                    }
+                }
+                if let Some(tick_spacing) = tick_spacing {
+                    let helper = TickArrayHelper { tick_spacing };
+                    if let Some(actual_start) = find_tick_array_start_index(&pool_pubkey, &provided, &helper, &program_id) {
+                        println!("  - The PDA belongs to the array starting at {} of this pool, not {}.", actual_start, start_index);
+                        explained = true;
+                    }
+                }
+                if !explained {
+                    println!("  - No known cause matched: the PDA belongs to another pool or program{}.", if tick_spacing.is_none() { ", or to another start index (pass --tick-spacing to search)" } else { "" });
+                }
+                println!();
+                print_tick_array_seeds(&pool_pubkey, start_index, &program_id);
+                std::process::exit(ExitCode::Failure as i32);
+            }
+        }
+        Commands::Il { price_lower, price_upper, entry_price, exit_price, format, deposit_value, decimals0, decimals1, pool_id } => {
+            if !(price_lower > 0.0 && price_upper > 0.0 && price_lower < price_upper) {
+                fail(ExitCode::InvalidInput, "prices must be positive and --price-lower must be below --price-upper.");
+            }
+            for (flag, price) in [("--entry-price", entry_price), ("--exit-price", exit_price), ("--deposit-value", deposit_value)] {
+                if !(price > 0.0 && price.is_finite()) {
+                    fail(ExitCode::InvalidInput, format!("{} must be positive.", flag));
+                }
+            }
+            let given = PoolParams { tick_spacing: None, decimals_0: decimals0, decimals_1: decimals1 };
+            let (decimals_0, decimals_1) = resolve_pool_params(pool_id.as_deref(), given, rpc_url, fetch_options, &program_id, pool_cache.as_ref(), false).await.decimals();
+            let converter = TickConverter { decimals_0, decimals_1 };
+
+            // 1. Raw sqrt prices; inverted formats swap which bound is the lower one
+            let sqrt_raw = |price: f64| converter.to_raw_price(format.with_price(price)).sqrt();
+            let (sqrt_a, sqrt_b) = (sqrt_raw(price_lower), sqrt_raw(price_upper));
+            let (sqrt_lower, sqrt_upper) = (sqrt_a.min(sqrt_b), sqrt_a.max(sqrt_b));
+            let (sqrt_entry, sqrt_exit) = (sqrt_raw(entry_price), sqrt_raw(exit_price));
+            let regime = |sqrt_price: f64| if sqrt_price <= sqrt_lower {
+                "below the range: all token 0"
+            } else if sqrt_price < sqrt_upper {
+                "in range: both tokens"
+            } else {
+                "above the range: all token 1"
+            };
+
+            // 2. Scale the liquidity so the position is worth --deposit-value token 1 at entry.
+            //    Values are in raw token 1 at the given price: amount_0 * price + amount_1.
+            let value_at = |(amount_0, amount_1): (f64, f64), sqrt_price: f64| amount_0 * sqrt_price * sqrt_price + amount_1;
+            let per_liquidity_entry = amounts_per_liquidity(sqrt_entry, sqrt_lower, sqrt_upper);
+            let liquidity = deposit_value * 10f64.powi(decimals_1 as i32) / value_at(per_liquidity_entry, sqrt_entry);
+            let scale = |(amount_0, amount_1): (f64, f64)| (amount_0 * liquidity, amount_1 * liquidity);
+            let entry_amounts = scale(per_liquidity_entry);
+            let exit_amounts = scale(amounts_per_liquidity(sqrt_exit, sqrt_lower, sqrt_upper));
+            let entry_value = value_at(entry_amounts, sqrt_entry);
+            let lp_value = value_at(exit_amounts, sqrt_exit);
+            let hold_value = value_at(entry_amounts, sqrt_exit);
+
+            println!("--- Impermanent Loss ({:?}) ---", format);
+            println!("  - Range:       {}", prices.range(price_lower, price_upper));
+            println!("  - Entry Price: {} ({})", prices.format(entry_price), regime(sqrt_entry));
+            println!("  - Exit Price:  {} ({})", prices.format(exit_price), regime(sqrt_exit));
+            if sqrt_entry <= sqrt_lower || sqrt_entry >= sqrt_upper {
+                println!("    (the entry is outside the range, so the position starts as a single token; holding it is");
+                println!("     the same until the price crosses into the range)");
+            }
+
+            // 3. Amounts and values side by side, in human units
+            let (scale_0, scale_1) = (10f64.powi(decimals_0 as i32), 10f64.powi(decimals_1 as i32));
+            println!("\n{:<14} {:>24} {:>24} {:>24}", "", "Token 0", "Token 1", "Value (token 1)");
+            for (label, (amount_0, amount_1), value) in [
+                ("Entry", entry_amounts, entry_value),
+                ("Exit, LP", exit_amounts, lp_value),
+                ("Exit, hold", entry_amounts, hold_value),
+            ] {
+                println!(
+                    "{:<14} {:>24.*} {:>24.*} {:>24.*}",
+                    label,
+                    decimals_0 as usize, amount_0 / scale_0,
+                    decimals_1 as usize, amount_1 / scale_1,
+                    decimals_1 as usize, value / scale_1
+                );
+            }
+
+            println!("\n  - Impermanent Loss: {:+.4}% (LP value vs holding, at the exit price)", (lp_value / hold_value - 1.0) * 100.0);
+            println!("  - LP vs Entry:      {:+.4}% (in token 1)", (lp_value / entry_value - 1.0) * 100.0);
+            println!("  - Hold vs Entry:    {:+.4}% (in token 1)", (hold_value / entry_value - 1.0) * 100.0);
+            println!("\nFees and rewards earned while in range are not included.");
+        }
+        Commands::PositionMath(command) => {
+            let (PositionMathCommands::LiquidityFromAmounts { price_lower, price_upper, current_price, format, decimals0, decimals1, tick_spacing, pool_id, .. }
+            | PositionMathCommands::AmountsFromLiquidity { price_lower, price_upper, current_price, format, decimals0, decimals1, tick_spacing, pool_id, .. }) = &command;
+            let (format, price_lower, price_upper, current_price) = (*format, *price_lower, *price_upper, *current_price);
+            if !(price_lower > 0.0 && price_upper > 0.0 && price_lower < price_upper) {
+                fail(ExitCode::InvalidInput, "prices must be positive and --price-lower must be below --price-upper.");
+            }
+            if current_price.is_some_and(|price| !(price > 0.0 && price.is_finite())) {
+                fail(ExitCode::InvalidInput, "--current-price must be positive.");
+            }
+
+            // 1. Decimals, tick spacing and current sqrt price from the pool, or from the flags
+            let (converter, tick_spacing, pool_sqrt_price) = match pool_id {
+                Some(pool_id) => {
+                    let pool_pubkey = parse_pubkey(pool_id, "Pool ID");
+                    let rpc_client = RpcFetcher::new(rpc_url, fetch_options);
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+                    println!(
+                        "Pool {}: tick_spacing = {}, decimals0 = {}, decimals1 = {}, tick_current = {}",
+                        pool_pubkey, pool_state.tick_spacing, pool_state.mint_decimals_0, pool_state.mint_decimals_1, pool_state.tick_current
+                    );
+                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+                    (converter, pool_state.tick_spacing, Some(SqrtPriceX64(pool_state.sqrt_price_x64)))
+                }
+                None => {
+                    let decimals = (decimals0.expect("required without --pool-id"), decimals1.expect("required without --pool-id"));
+                    if let Some(tick_spacing) = *tick_spacing {
+                        warn_unknown_tick_spacing(tick_spacing);
+                    }
+                    (TickConverter { decimals_0: decimals.0, decimals_1: decimals.1 }, tick_spacing.unwrap_or(1), None)
+                }
+            };
+            if tick_spacing == 0 {
+                fail(ExitCode::InvalidInput, "--tick-spacing must be at least 1.");
+            }
+            let sqrt_price_current = match current_price {
+                // A user-supplied price is not snapped to a tick, like a pool's own price
+                Some(price) => SqrtPriceX64((converter.to_raw_price(format.with_price(price)).sqrt() * 2f64.powi(64)) as u128),
+                None => pool_sqrt_price.expect("--current-price is required without --pool-id"),
+            };
+            let tick_current = sqrt_price_current.tick().unwrap_or_else(|e| fail(ExitCode::InvalidInput, format!("--current-price: {}", e)));
+
+            // 2. The position's ticks: outward to usable ticks, then their exact sqrt prices
+            let helper = TickArrayHelper { tick_spacing };
+            let (tick_lower, tick_upper) = position_tick_range(&converter, &helper, format.with_price(price_lower), format.with_price(price_upper));
+            let sqrt_price_of = |tick: Tick| tick.sqrt_price().unwrap_or_else(|e| fail(ExitCode::InvalidInput, e));
+            let (sqrt_price_lower, sqrt_price_upper) = (sqrt_price_of(tick_lower), sqrt_price_of(tick_upper));
+
+            let format_input = format.with_price(0.0);
+            let (bound_a, bound_b) = (converter.tick_to_price(tick_lower, format_input), converter.tick_to_price(tick_upper, format_input));
+            let (snapped_lower, snapped_upper) = (bound_a.min(bound_b), bound_a.max(bound_b));
+            let current_price = converter.raw_to_price(sqrt_price_current.to_raw_price(), format_input);
+            println!("--- Position Range ({:?}) ---", format);
+            println!("  - Ticks:         [{}, {}] (tick spacing {})", tick_lower, tick_upper, tick_spacing);
+            println!("  - Prices:        {}", prices.range(snapped_lower, snapped_upper));
+            let on_grid = |requested: f64, snapped: f64| ((snapped - requested) / requested).abs() < 1e-9;
+            if !(on_grid(price_lower, snapped_lower) && on_grid(price_upper, snapped_upper)) {
+                println!("    (requested {}, widened to the nearest usable ticks)", prices.range(price_lower, price_upper));
+            }
+            let position = if sqrt_price_current < sqrt_price_lower {
+                "below the range: only token 0 is deposited"
+            } else if sqrt_price_current < sqrt_price_upper {
+                "in range: both tokens are deposited"
+            } else {
+                "above the range: only token 1 is deposited"
+            };
+            println!("  - Current Price: {} (tick {}), {}", prices.format(current_price), tick_current, position);
+            println!("  - sqrt_price_x64: current {}, lower {}, upper {}", sqrt_price_current, sqrt_price_lower, sqrt_price_upper);
+
+            // 3. The CLMM formulas over the sqrt prices, in integer math as the program computes them
+            let (decimals_0, decimals_1) = (converter.decimals_0, converter.decimals_1);
+            match command {
+                PositionMathCommands::LiquidityFromAmounts { amount0, amount1, .. } => {
+                    let amount_0 = human_to_raw_amount(amount0, decimals_0, "--amount0");
+                    let amount_1 = human_to_raw_amount(amount1, decimals_1, "--amount1");
+                    let liquidity = liquidity_math::liquidity_from_amounts(
+                        sqrt_price_current.0, sqrt_price_lower.0, sqrt_price_upper.0, amount_0, amount_1,
+                    ).unwrap_or_else(|e| fail(ExitCode::InvalidInput, e));
+                    // What open_position charges for that liquidity, rounded up
+                    let (used_0, used_1) = liquidity_math::amounts_for_liquidity(
+                        sqrt_price_current.0, sqrt_price_lower.0, sqrt_price_upper.0, liquidity, true,
+                    ).unwrap_or_else(|e| fail(ExitCode::InvalidInput, e));
+                    println!("\n--- Liquidity from Amounts ---");
+                    println!("  - Liquidity:     {}", liquidity);
+                    println!("  - Token 0 Used:  {} ({} t0), {} left over", used_0, format_token_amount(used_0, decimals_0), format_token_amount(amount_0.saturating_sub(used_0), decimals_0));
+                    println!("  - Token 1 Used:  {} ({} t1), {} left over", used_1, format_token_amount(used_1, decimals_1), format_token_amount(amount_1.saturating_sub(used_1), decimals_1));
+                }
+                PositionMathCommands::AmountsFromLiquidity { liquidity, .. } => {
+                    let (deposit_0, deposit_1) = liquidity_math::amounts_for_liquidity(
+                        sqrt_price_current.0, sqrt_price_lower.0, sqrt_price_upper.0, liquidity, true,
+                    ).unwrap_or_else(|e| fail(ExitCode::InvalidInput, e));
+                    let (withdraw_0, withdraw_1) = liquidity_math::amounts_for_liquidity(
+                        sqrt_price_current.0, sqrt_price_lower.0, sqrt_price_upper.0, liquidity, false,
+                    ).unwrap_or_else(|e| fail(ExitCode::InvalidInput, e));
+                    println!("\n--- Amounts for Liquidity {} ---", liquidity);
+                    println!("  - Deposit (rounded up):");
+                    println!("      Token 0: {} ({} t0)", deposit_0, format_token_amount(deposit_0, decimals_0));
+                    println!("      Token 1: {} ({} t1)", deposit_1, format_token_amount(deposit_1, decimals_1));
+                    println!("  - Withdrawal (rounded down):");
+                    println!("      Token 0: {} ({} t0)", withdraw_0, format_token_amount(withdraw_0, decimals_0));
+                    println!("      Token 1: {} ({} t1)", withdraw_1, format_token_amount(withdraw_1, decimals_1));
+                }
+            }
+        }
+        Commands::Rpc(rpc_command) => {
+            let json_output = rpc_command.json_output();
+            // Keep stdout machine-readable and stderr free of progress bars in JSON mode
+            let rpc_client = RpcFetcher::new(rpc_url, FetchOptions { show_progress: fetch_options.show_progress && !json_output, ..fetch_options });
+            // Set by checks that report inconsistencies, applied after the fetch summary
+            let mut exit_code = ExitCode::Success;
+            match rpc_command {
+                RpcCommands::PoolState { pool_id, full, raw } => {
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+                    if raw {
+                        let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+                        print_raw_decode(&pool_pubkey, &pool_state);
+                    } else {
+                        pool_state_command(&rpc_client, &pool_pubkey, &program_id, full, prices).await;
+                    }
+                },
+                RpcCommands::BuildSwapIx {
+                    pool_id, direction, amount, other_amount_threshold, slippage_pct, favorable_pct, exact_out,
+                    payer, input_token_account, output_token_account, json,
+                } => {
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+                    let payer = parse_pubkey(&payer, "payer");
+                    let input_token_account = input_token_account.map(|key| parse_pubkey(&key, "input token account"));
+                    let output_token_account = output_token_account.map(|key| parse_pubkey(&key, "output token account"));
+
+                    // 1. Fetch Base Data (PoolState + Extension)
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+                    warn_if_swaps_paused(&pool_state);
+                    let (ext_pda, _) = Pubkey::find_program_address(&[TICK_ARRAY_BITMAP_SEED, pool_pubkey.as_ref()], &program_id);
+                    let extension = fetch_pool_account::<TickArrayBitmapExtension>(&rpc_client, &ext_pda, &pool_pubkey, &program_id).await;
+
+                    // 2. Instruction args: the slippage sets the price limit
+                    let (sqrt_price_limit_x64, clamped) = swap_sqrt_price_limit(pool_state.sqrt_price_x64, direction, slippage_pct)
+                        .unwrap_or_else(|e| fail(ExitCode::InvalidInput, &e));
+                    if clamped {
+                        warn_swap_limit_clamped(direction, slippage_pct);
+                    }
+                    let args = SwapV2Args { amount, other_amount_threshold, sqrt_price_limit_x64, is_base_input: !exact_out };
+                    let mut data = SWAP_V2_DISCRIMINATOR.to_vec();
+                    data.extend(args.try_to_vec().expect("Failed to serialize swap_v2 args"));
+
+                    // 3. Tick arrays from the live tick out to the slippage limit, as in get-swap-arrays
+                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+                    let range = SwapRange::Percent { favorable: favorable_pct, impact: slippage_pct };
+                    let (tick_favorable, tick_impact) = swap_tick_bounds(&converter, direction, pool_state.tick_current, range)
+                        .unwrap_or_else(|e| fail(ExitCode::InvalidInput, e));
+                    let tick_range = (tick_favorable.min(tick_impact), tick_favorable.max(tick_impact));
+                    let (ordered, has_surrounding) = select_swap_arrays(&pool_state, &extension, direction, tick_range);
+                    if !has_surrounding {
+                        eprintln!("Warning: No initialized surrounding array found for the impact direction.");
+                    }
+                    let result = swap_arrays_result(&pool_pubkey, &pool_state, &program_id, direction, pool_state.tick_current, tick_range, &ordered);
+
+                    // 4. Caller accounts: token accounts default to the payer's ATAs, under each mint's token program
+                    let (input_mint, output_mint) = match direction {
+                        SwapDirection::BuyT1 => (pool_state.token_mint_0, pool_state.token_mint_1),
+                        SwapDirection::BuyT0 => (pool_state.token_mint_1, pool_state.token_mint_0),
+                    };
+                    let (input_token_account, output_token_account) = match (input_token_account, output_token_account) {
+                        (Some(input), Some(output)) => (input, output),
+                        (input, output) => {
+                            let (_, mints) = rpc_client.get_multiple_accounts_at(&[input_mint, output_mint], None).await
+                                .unwrap_or_else(|e| fail_fetch("the pool mints", &e));
+                            let ata = |mint: &Pubkey, account: &Option<Account>| match account {
+                                Some(account) => associated_token_address(&payer, mint, &account.owner),
+                                None => fail(ExitCode::AccountNotFound, format!("Mint {} not found", mint)),
+                            };
+                            (
+                                input.unwrap_or_else(|| ata(&input_mint, &mints[0])),
+                                output.unwrap_or_else(|| ata(&output_mint, &mints[1])),
+                            )
+                        }
+                    };
+                    let mut accounts = swap_v2_accounts(&result, &pool_state, direction);
+                    for account in accounts.iter_mut() {
+                        match account.name.as_str() {
+                            "payer" => account.pubkey = Some(payer.to_string()),
+                            "input_token_account" => account.pubkey = Some(input_token_account.to_string()),
+                            "output_token_account" => account.pubkey = Some(output_token_account.to_string()),
+                            _ => {}
+                        }
+                    }
+
+                    // 5. Assemble (unsigned; nothing is sent)
+                    let instruction = InstructionJson {
+                        program_id: program_id.to_string(),
+                        keys: accounts.iter()
+                            .map(|account| AccountMetaJson {
+                                pubkey: account.pubkey.clone().expect("All swap_v2 accounts are resolved"),
+                                is_signer: account.signer,
+                                is_writable: account.writable,
+                            })
+                            .collect(),
+                        data: base64::engine::general_purpose::STANDARD.encode(&data),
+                        data_base58: solana_sdk::bs58::encode(&data).into_string(),
+                    };
+                    let instruction_json = serde_json::to_string_pretty(&instruction).expect("Failed to serialize JSON");
+                    if json {
+                        println!("{}", instruction_json);
+                    } else {
+                        println!("--- swap_v2 Instruction for {} ---", pool_id);
+                        println!("Direction:              {:?}", direction);
+                        println!("amount:                 {} ({})", amount, if exact_out { "exact output" } else { "exact input" });
+                        println!("other_amount_threshold: {} ({})", other_amount_threshold, if exact_out { "maximum input" } else { "minimum output" });
+                        println!("sqrt_price_limit_x64:   {} ({}% slippage)", sqrt_price_limit_x64, slippage_pct);
+                        println!("  = {}", format_swap_limit_price(&converter, SqrtPriceX64(sqrt_price_limit_x64), prices));
+                        println!("is_base_input:          {}", args.is_base_input);
+                        print_swap_v2_accounts(&accounts);
+                        println!("\nData ({} bytes):", data.len());
+                        println!("  base58: {}", instruction.data_base58);
+                        println!("  base64: {}", instruction.data);
+                        println!("\n--- Instruction JSON ---");
+                        println!("{}", instruction_json);
+                        println!("\nThe instruction is unsigned and was not sent.");
+                    }
+                },
+                RpcCommands::Summary { pool_id, format } => {
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+
+                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+                    let price_template = match format {
+                        HumanPriceFormat::T0PerT1 => PriceInput::Token0PerToken1Human { price: 0.0 },
+                        HumanPriceFormat::T1PerT0 => PriceInput::Token1PerToken0Human { price: 0.0 },
+                    };
+                    // "ok", or the paused operations, e.g. "no-swaps,no-fee-collection"
+                    let paused: Vec<String> = pool_state.pool_status().operations().iter()
+                        .filter(|(_, enabled)| !enabled)
+                        .map(|(operation, _)| format!("no-{}", operation.replace(' ', "-")))
+                        .collect();
+                    let status = if paused.is_empty() { "ok".to_string() } else { paused.join(",") };
+
+                    println!(
+                        "{} tick={} price={} liq={} spacing={} status={}",
+                        pool_id,
+                        pool_state.tick_current,
+                        prices.format(converter.tick_to_price(Tick(pool_state.tick_current), price_template)),
+                        format_liquidity(pool_state.liquidity),
+                        pool_state.tick_spacing,
+                        status
+                    );
+                },
+                RpcCommands::PositionArrays { pool_id, price_lower, price_upper, format } => {
+                    if price_lower <= 0.0 || price_upper <= 0.0 || price_lower >= price_upper {
+                        fail(ExitCode::InvalidInput, "prices must be positive and --price-lower must be below --price-upper.");
+                    }
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+
+                    // 1. Fetch the pool and the arrays that are already initialized
+                    println!("Fetching pool info and bitmaps...");
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+                    let initialized_arrays = fetch_initialized_arrays(&rpc_client, &pool_pubkey, &program_id, &pool_state).await;
+                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+                    let helper = TickArrayHelper { tick_spacing: pool_state.tick_spacing };
+
+                    // 2. Convert the prices to ticks aligned to the spacing. The range is widened outward
+                    //    so both requested prices stay inside it; inverted formats swap which price is the lower tick.
+                    let (price_template, format_label) = match format {
+                        HumanPriceFormat::T0PerT1 => (PriceInput::Token0PerToken1Human { price: 0.0 }, "T0/T1"),
+                        HumanPriceFormat::T1PerT0 => (PriceInput::Token1PerToken0Human { price: 0.0 }, "T1/T0"),
+                    };
+                    let price_input = |price: f64| match format {
+                        HumanPriceFormat::T0PerT1 => PriceInput::Token0PerToken1Human { price },
+                        HumanPriceFormat::T1PerT0 => PriceInput::Token1PerToken0Human { price },
+                    };
+                    let (tick_lower, tick_upper) = position_tick_range(&converter, &helper, price_input(price_lower), price_input(price_upper));
+                    let bound_price = |tick: Tick| converter.tick_to_price(tick, price_template);
+                    let (p_start, p_end) = {
+                        let (a, b) = (bound_price(tick_lower), bound_price(tick_upper));
+                        if a < b { (a, b) } else { (b, a) }
+                    };
+
+                    println!("\n--- Tick Arrays for a New Position in {} ---", pool_id);
+                    println!("  - Requested Price Range: {} ({})", prices.range(price_lower, price_upper), format_label);
+                    println!("  - Position Tick Range:   [{}, {}] (tick spacing {})", tick_lower, tick_upper, pool_state.tick_spacing);
+                    println!("  - Aligned Price Range:   {} ({})", prices.range(p_start, p_end), format_label);
+
+                    // 3. One array per bound (possibly the same one)
+                    let (ext_pda, _) = Pubkey::find_program_address(&[TICK_ARRAY_BITMAP_SEED, pool_pubkey.as_ref()], &program_id);
+                    let mut needs_extension = false;
+                    for (label, tick) in [("Lower", tick_lower), ("Upper", tick_upper)] {
+                        let start_index = helper.get_array_start_index(tick);
+                        let exists = initialized_arrays.contains(&start_index.0);
+                        let location = BitmapPosition::from_start_index(start_index.0, pool_state.tick_spacing).map(|position| position.location);
+                        needs_extension |= !matches!(location, Some(BitmapLocation::Default));
+                        println!("\n{} Tick Array (tick {}):", label, tick);
+                        println!("  - Start Index: {}", start_index);
+                        println!("  - PDA:         {}", derive_tick_array_pda(&pool_pubkey, start_index.0, &program_id));
+                        if exists {
+                            println!("  - Status:      exists (bit set in the bitmap)");
+                        } else {
+                            println!("  - Status:      NEEDS INIT (open_position will create it and pay its rent)");
+                        }
+                    }
+                    if helper.get_array_start_index(tick_lower) == helper.get_array_start_index(tick_upper) {
+                        println!("\nBoth bounds fall in the same tick array; pass the same PDA for both.");
+                    }
+                    if needs_extension {
+                        println!("\nNote: an array lies outside the default bitmap. Pass the bitmap extension {} as a remaining account.", ext_pda);
+                    }
+                },
+                RpcCommands::PositionPreview { pool_id, price_lower, price_upper, format } => {
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+
+                    // 1. Fetch the pool and the arrays its bitmaps mark as initialized
+                    println!("Fetching pool info and bitmaps...");
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+                    let initialized_arrays = fetch_initialized_arrays(&rpc_client, &pool_pubkey, &program_id, &pool_state).await;
+                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+                    let helper = TickArrayHelper { tick_spacing: pool_state.tick_spacing };
+
+                    // 2. Snap the range outward to usable ticks, as position-arrays does
+                    let (price_template, format_label) = match format {
+                        HumanPriceFormat::T0PerT1 => (PriceInput::Token0PerToken1Human { price: 0.0 }, "T0/T1"),
+                        HumanPriceFormat::T1PerT0 => (PriceInput::Token1PerToken0Human { price: 0.0 }, "T1/T0"),
+                    };
+                    let price_input = |price: f64| match format {
+                        HumanPriceFormat::T0PerT1 => PriceInput::Token0PerToken1Human { price },
+                        HumanPriceFormat::T1PerT0 => PriceInput::Token1PerToken0Human { price },
+                    };
+                    let (tick_lower, tick_upper) = position_tick_range(&converter, &helper, price_input(price_lower), price_input(price_upper));
+                    let (p_start, p_end) = {
+                        let (a, b) = (converter.tick_to_price(tick_lower, price_template), converter.tick_to_price(tick_upper, price_template));
+                        if a < b { (a, b) } else { (b, a) }
+                    };
+                    let start_lower = helper.get_array_start_index(tick_lower);
+                    let start_upper = helper.get_array_start_index(tick_upper);
+
+                    println!("\n--- Open Position Preview for {} ---", pool_id);
+                    if !pool_state.pool_status().open_position_enabled() {
+                        println!("{}", palette.warning("  - WARNING: deposits are DISABLED on this pool; open_position_v2 will fail until they are re-enabled."));
+                    }
+                    println!("  - Requested Price Range: {} ({})", prices.range(price_lower, price_upper), format_label);
+                    println!("  - Aligned Price Range:   {} ({})", prices.range(p_start, p_end), format_label);
+                    let on_grid = |requested: f64, snapped: f64| ((snapped - requested) / requested).abs() < 1e-9;
+                    if on_grid(price_lower, p_start) && on_grid(price_upper, p_end) {
+                        println!("    (the requested prices are already on usable ticks)");
+                    } else {
+                        println!("    (widened to the nearest usable ticks, multiples of tick spacing {})", pool_state.tick_spacing);
+                    }
+
+                    println!("\nInstruction Arguments:");
+                    println!("  - tick_lower_index:             {}", tick_lower);
+                    println!("  - tick_upper_index:             {}", tick_upper);
+                    println!("  - tick_array_lower_start_index: {}", start_lower);
+                    println!("  - tick_array_upper_start_index: {}", start_upper);
+
+                    // 3. Derive the PDAs and fetch them in one call: an array can exist with its bit
+                    //    cleared (no initialized ticks left), so the fetch decides what must be created
+                    let (protocol_position, _) = Pubkey::find_program_address(
+                        &[POSITION_SEED, pool_pubkey.as_ref(), &tick_lower.0.to_be_bytes(), &tick_upper.0.to_be_bytes()],
+                        &program_id,
+                    );
+                    let pda_lower = derive_tick_array_pda(&pool_pubkey, start_lower.0, &program_id);
+                    let pda_upper = derive_tick_array_pda(&pool_pubkey, start_upper.0, &program_id);
+                    let (_, accounts) = rpc_client.get_multiple_accounts_at(&[protocol_position, pda_lower, pda_upper], None).await
+                        .unwrap_or_else(|e| fail_fetch("position accounts", &e));
+                    let status = |account: &Option<Account>| match account {
+                        None => "NEEDS INIT".to_string(),
+                        Some(account) if account.owner != program_id => format!("exists, but owned by {}", account.owner),
+                        Some(_) => "exists".to_string(),
+                    };
+
+                    println!("\nAccounts:");
+                    println!("  - pool_state:        {}", pool_pubkey);
+                    println!("  - protocol_position: {} ({})", protocol_position, status(&accounts[0]));
+                    let mut arrays_to_create = Vec::new();
+                    let mut needs_extension = false;
+                    for (label, start_index, pda, account) in [("tick_array_lower:", start_lower, pda_lower, &accounts[1]), ("tick_array_upper:", start_upper, pda_upper, &accounts[2])] {
+                        let in_bitmap = initialized_arrays.contains(&start_index.0);
+                        let location = BitmapPosition::from_start_index(start_index.0, pool_state.tick_spacing).map(|position| position.location);
+                        needs_extension |= !matches!(location, Some(BitmapLocation::Default));
+                        let bitmap = if in_bitmap { "bit set" } else { "bit clear" };
+                        println!("  - {:<18} {} (start {}; {}, {})", label, pda, start_index, status(account), bitmap);
+                        if in_bitmap && account.is_none() {
+                            println!("    Warning: the bitmap marks this array, but the account was not found.");
+                        }
+                        if account.is_none() && !arrays_to_create.contains(&start_index) {
+                            arrays_to_create.push(start_index);
+                        }
+                    }
+                    if start_lower == start_upper {
+                        println!("    (both bounds fall in the same tick array; pass the same PDA for both)");
+                    }
+                    println!("  - token_vault_0:     {}", pool_state.token_vault_0);
+                    println!("  - token_vault_1:     {}", pool_state.token_vault_1);
+                    println!("  - vault_0_mint:      {}", pool_state.token_mint_0);
+                    println!("  - vault_1_mint:      {}", pool_state.token_mint_1);
+                    println!("  - personal_position: derived from the new position NFT mint, seeds [\"position\", nft_mint]");
+                    if needs_extension {
+                        let (ext_pda, _) = Pubkey::find_program_address(&[TICK_ARRAY_BITMAP_SEED, pool_pubkey.as_ref()], &program_id);
+                        println!("  - remaining account: {} (bitmap extension; an array lies outside the default bitmap)", ext_pda);
+                    }
+
+                    // 4. Rent for the arrays open_position_v2 would create, at the default (mainnet) rate
+                    println!("\nRent for New Tick Arrays:");
+                    if arrays_to_create.is_empty() {
+                        println!("  - None: both tick arrays already exist.");
+                    } else {
+                        let rent_per_array = solana_sdk::rent::Rent::default().minimum_balance(TICK_ARRAY_STATE_LEN);
+                        let total = rent_per_array * arrays_to_create.len() as u64;
+                        println!("  - Per Array: {} lamports ({} bytes, rent-exempt minimum)", rent_per_array, TICK_ARRAY_STATE_LEN);
+                        println!(
+                            "  - Total:     {} lamports ({} SOL) for {} array(s), paid by the position owner",
+                            total,
+                            solana_sdk::native_token::lamports_to_sol(total),
+                            arrays_to_create.len()
+                        );
+                    }
+                },
+                RpcCommands::Rewards { pool_id } => {
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+                    println!("--- Reward Emissions for {} ---", pool_id);
+
+                    // 1. Slots never set up (reward_state 0) are summarized together
+                    let (active, inactive): (Vec<_>, Vec<_>) = pool_state.reward_infos.iter()
+                        .enumerate()
+                        .partition(|(_, reward)| reward.reward_state != 0);
+                    if !inactive.is_empty() {
+                        let slots: Vec<String> = inactive.iter().map(|(slot, _)| slot.to_string()).collect();
+                        println!("Uninitialized slots: {}", slots.join(", "));
+                    }
+                    if active.is_empty() {
+                        println!("No rewards are set up on this pool.");
+                    } else {
+                        // 2. The reward mints' decimals, in one batch. Without them amounts stay raw
+                        let mints: Vec<Pubkey> = active.iter().map(|(_, reward)| reward.token_mint).collect();
+                        let decimals: Vec<Option<u8>> = match rpc_client.get_multiple_accounts_at(&mints, None).await {
+                            Ok((_, accounts)) => accounts.iter()
+                                .map(|account| account.as_ref().and_then(|account| read_mint_decimals(&account.owner, &account.data).ok()))
+                                .collect(),
+                            Err(e) => {
+                                eprintln!("Warning: Failed to fetch the reward mints, amounts are shown raw: {}", e);
+                                vec![None; mints.len()]
+                            }
+                        };
+
+                        // 3. One block per active slot. emissions_per_second_x64 is Q64.64 raw tokens per second
+                        let now = unix_now();
+                        for ((slot, reward), decimals) in active.iter().zip(decimals) {
+                            let amount = |raw: u128| decimals.map_or(format!("{} raw", raw), |decimals| format_token_amount(raw, decimals));
+                            let emitted_over = |seconds: u64| liquidity_math::mul_div_floor(seconds as u128, reward.emissions_per_second_x64, 1u128 << 64)
+                                .map_or_else(|e| format!("n/a ({})", e), amount);
+                            let per_second = q64_to_f64(reward.emissions_per_second_x64) / 10f64.powi(decimals.unwrap_or(0) as i32);
+
+                            println!("\nReward {} ({})", slot, reward_state_name(reward.reward_state));
+                            println!("  - Mint:             {}{}", reward.token_mint, decimals.map_or(" (decimals unavailable)".to_string(), |decimals| format!(" ({} decimals)", decimals)));
+                            println!("  - Vault:            {}", reward.token_vault);
+                            println!("  - Authority:        {}", reward.authority);
+                            println!(
+                                "  - Emission Rate:    {:.*}{} per second, {} per day",
+                                decimals.unwrap_or(0) as usize,
+                                per_second,
+                                if decimals.is_none() { " raw" } else { "" },
+                                emitted_over(86_400)
+                            );
+                            println!("  - Emissions X64:    {} raw per second", format_q64(reward.emissions_per_second_x64));
+                            println!("  - Growth Global:    {}", format_q64(reward.reward_growth_global_x64));
+                            println!("  - Open Time:        {} ({})", format_unix_time(reward.open_time), format_relative_time(reward.open_time, now));
+                            println!("  - End Time:         {} ({})", format_unix_time(reward.end_time), format_relative_time(reward.end_time, now));
+                            println!("  - Last Update:      {} ({})", format_unix_time(reward.last_update_time), format_relative_time(reward.last_update_time, now));
+                            let remaining = if now < reward.open_time {
+                                format!("not started, runs {} once open", format_duration(reward.end_time.saturating_sub(reward.open_time)))
+                            } else if now < reward.end_time {
+                                format_duration(reward.end_time - now)
+                            } else {
+                                "ended".to_string()
+                            };
+                            println!("  - Time Remaining:   {}", remaining);
+                            let claimed_share = if reward.reward_total_emissioned == 0 {
+                                String::new()
+                            } else {
+                                format!(" ({:.2}% claimed)", reward.reward_claimed as f64 / reward.reward_total_emissioned as f64 * 100.0)
+                            };
+                            println!(
+                                "  - Emissioned:       {} emissioned, {} claimed{}",
+                                amount(reward.reward_total_emissioned as u128),
+                                amount(reward.reward_claimed as u128),
+                                claimed_share
+                            );
+                            // Counted from now, or from open_time if the reward has not started yet
+                            println!("  - Remaining Budget: {}", emitted_over(reward.end_time.saturating_sub(now.max(reward.open_time))));
+                        }
+                        println!("\nEmissioned totals are as of the last update ({} ago at most); rewards accrue only to in-range liquidity.",
+                            format_duration(active.iter().map(|(_, reward)| now.saturating_sub(reward.last_update_time)).max().unwrap_or(0)));
+                    }
+                },
+                RpcCommands::Fees { pool_id, since } => {
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+                    let (slot, pool_state) = fetch_pool_state_with_slot(&rpc_client, &pool_pubkey, &program_id).await;
+                    // The configured split is optional context; the counters alone are still worth showing
+                    let amm_config = match rpc_client.get_account(&pool_state.amm_config).await {
+                        Ok(account) => match decode_clmm_account::<AmmConfig>(&pool_state.amm_config, &account.owner, &account.data, &program_id) {
+                            Ok(amm_config) => Some(amm_config),
+                            Err(e) => {
+                                eprintln!("Warning: {}", e);
+                                None
+                            }
+                        },
+                        Err(e) => {
+                            eprintln!("Warning: Failed to fetch AmmConfig {}: {}", pool_state.amm_config, e);
+                            None
+                        }
+                    };
+                    let decimals = [pool_state.mint_decimals_0, pool_state.mint_decimals_1];
+
+                    // 1. The raw counters, and the LP fees still owed to positions
+                    println!("--- Fee Accounting for {} (slot {}) ---", pool_id, slot);
+                    println!("Token 0: {} ({} decimals)", pool_state.token_mint_0, decimals[0]);
+                    println!("Token 1: {} ({} decimals)", pool_state.token_mint_1, decimals[1]);
+                    let counters = pool_fee_counters(&pool_state);
+                    let unclaimed = [0, 1].map(|token| counters[0].1[token].saturating_sub(counters[1].1[token]));
+                    println!("\n{:<28} | {:>26} | {:>26}", "Counter", "Token 0", "Token 1");
+                    println!("{:-<86}", "");
+                    for (label, values) in counters.iter().chain([("LP fees unclaimed", unclaimed)].iter()) {
+                        println!("{:<28} | {:>26} | {:>26}", label, format_token_amount(values[0], decimals[0]), format_token_amount(values[1], decimals[1]));
+                    }
+                    let claimed_share = |token: usize| match counters[0].1[token] {
+                        0 => "n/a".to_string(),
+                        total => format!("{:.2}%", counters[1].1[token] as f64 / total as f64 * 100.0),
+                    };
+                    println!("\nClaimed share of LP fees: token 0 {}, token 1 {}", claimed_share(0), claimed_share(1));
+                    println!("Fee growth per unit of liquidity (raw, Q64.64):");
+                    println!("  - Token 0: {}", format_q64(pool_state.fee_growth_global_0_x64));
+                    println!("  - Token 1: {}", format_q64(pool_state.fee_growth_global_1_x64));
+
+                    // 2. The program takes the protocol and fund shares off each swap fee before crediting
+                    //    the rest to LPs, so total_fees_token_* is already LP-only
+                    if let Some(amm_config) = &amm_config {
+                        let rate = |value: u32| value as f64 / FEE_RATE_DENOMINATOR as f64 * 100.0;
+                        println!("\nConfigured split (AmmConfig {}):", pool_state.amm_config);
+                        println!("  - Trade Fee Rate: {:.4}% of the swap input", rate(amm_config.trade_fee_rate));
+                        println!("  - Protocol Share: {:.2}% of the trade fee", rate(amm_config.protocol_fee_rate));
+                        println!("  - Fund Share:     {:.2}% of the trade fee", rate(amm_config.fund_fee_rate));
+                        println!("  - LP Share:       {:.2}% of the trade fee", 100.0 - rate(amm_config.protocol_fee_rate) - rate(amm_config.fund_fee_rate));
+                    }
+                    println!("\nLP fees are credited after the protocol and fund shares are taken. The protocol and fund");
+                    println!("counters drop to zero when collected, so they only hold what is still uncollected.");
+
+                    // 3. Two-sample mode: the difference between the counters over the interval
+                    if let Some(seconds) = since {
+                        println!("\nSampling again in {}s...", seconds);
+                        tokio::time::sleep(std::time::Duration::from_secs(seconds)).await;
+                        let (later_slot, later_state) = fetch_pool_state_with_slot(&rpc_client, &pool_pubkey, &program_id).await;
+                        let later_counters = pool_fee_counters(&later_state);
+
+                        println!("\n--- Accrual over {}s (slots {} -> {}) ---", seconds, slot, later_slot);
+                        println!("{:<28} | {:>26} | {:>26}", "Counter", "Token 0", "Token 1");
+                        println!("{:-<86}", "");
+                        let delta = |row: usize, token: usize| later_counters[row].1[token] as i128 - counters[row].1[token] as i128;
+                        let format_delta = |value: i128, token: usize| {
+                            let amount = format_token_amount(value.unsigned_abs(), decimals[token]);
+                            if value < 0 { format!("-{} (collected)", amount) } else { format!("+{}", amount) }
+                        };
+                        for (row, (label, _)) in counters.iter().enumerate() {
+                            println!("{:<28} | {:>26} | {:>26}", label, format_delta(delta(row, 0), 0), format_delta(delta(row, 1), 1));
+                        }
+
+                        // Observed protocol share of the trade fee: protocol / (LP + protocol + fund).
+                        // Rounding in the program floors each swap's protocol cut, so the share runs slightly low
+                        if let Some(amm_config) = &amm_config {
+                            let configured = amm_config.protocol_fee_rate as f64 / FEE_RATE_DENOMINATOR as f64;
+                            println!("\nProtocol share of the trade fee (configured {:.2}%):", configured * 100.0);
+                            for token in 0..2 {
+                                let (lp, protocol, fund) = (delta(0, token), delta(2, token), delta(3, token));
+                                let verdict = if protocol < 0 || fund < 0 {
+                                    "n/a (fees were collected during the interval)".to_string()
+                                } else if lp + protocol + fund == 0 {
+                                    "n/a (no fees in this token during the interval)".to_string()
+                                } else {
+                                    let observed = protocol as f64 / (lp + protocol + fund) as f64;
+                                    let matches = (observed - configured).abs() <= configured * 0.01 + 1e-6;
+                                    format!("{:.2}% observed, {}", observed * 100.0, if matches { "matches" } else { "MISMATCH" })
+                                };
+                                println!("  - Token {}: {}", token, verdict);
+                            }
+                        }
+                    }
+                },
+                RpcCommands::SwapStats { pool_id, sample_secs } => {
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+                    let (slot, pool_state) = fetch_pool_state_with_slot(&rpc_client, &pool_pubkey, &program_id).await;
+                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+                    let decimals = [pool_state.mint_decimals_0, pool_state.mint_decimals_1];
+                    let flows = swap_flows(&pool_state);
+
+                    // 1. Lifetime volume per direction. Inputs include the trade fee
+                    println!("--- Lifetime Swap Flow for {} (slot {}) ---", pool_id, slot);
+                    println!("Token 0: {} ({} decimals)", pool_state.token_mint_0, decimals[0]);
+                    println!("Token 1: {} ({} decimals)", pool_state.token_mint_1, decimals[1]);
+                    print_swap_flows(&flows, &converter, prices);
+                    println!("\nThese are lifetime on-chain counters since the pool opened, not 24h figures.");
+
+                    // 2. Two-sample mode: the counters' growth over the interval
+                    if let Some(seconds) = sample_secs {
+                        println!("\nSampling again in {}s...", seconds);
+                        tokio::time::sleep(std::time::Duration::from_secs(seconds)).await;
+                        let (later_slot, later_state) = fetch_pool_state_with_slot(&rpc_client, &pool_pubkey, &program_id).await;
+                        let later_flows = swap_flows(&later_state);
+
+                        println!("\n--- Flow over {}s (slots {} -> {}) ---", seconds, slot, later_slot);
+                        // The counters only grow; a smaller second reading means they wrapped or were reset
+                        let interval = flows.iter().zip(&later_flows)
+                            .map(|(&(label, input, output), &(_, later_input, later_output))| {
+                                Some((label, later_input.checked_sub(input)?, later_output.checked_sub(output)?))
+                            })
+                            .collect::<Option<Vec<SwapFlow>>>();
+                        if let Some([token_0_sold, token_1_sold]) = interval.as_deref() {
+                            let interval = [*token_0_sold, *token_1_sold];
+                            print_swap_flows(&interval, &converter, prices);
+
+                            // Flow rate per side, and the net token flow into the pool
+                            let per_minute = |raw: u128, token: usize| raw as f64 / 10f64.powi(decimals[token] as i32) / seconds.max(1) as f64 * 60.0;
+                            println!("\nRate: {:.6} token 0 sold/min, {:.6} token 1 sold/min", per_minute(interval[0].1, 0), per_minute(interval[1].1, 1));
+                            let net = |token: usize| {
+                                let (received, paid) = if token == 0 { (interval[0].1, interval[1].2) } else { (interval[1].1, interval[0].2) };
+                                let amount = format_token_amount(received.abs_diff(paid), decimals[token]);
+                                if received >= paid { format!("+{}", amount) } else { format!("-{}", amount) }
+                            };
+                            println!("Net into the pool: token 0 {}, token 1 {}", net(0), net(1));
+                            let direction = match interval[0].1.cmp(&interval[1].2) {
+                                std::cmp::Ordering::Greater => "net selling of token 0 (price of token 0 pushed down)",
+                                std::cmp::Ordering::Less => "net buying of token 0 (price of token 0 pushed up)",
+                                std::cmp::Ordering::Equal => "no net direction",
+                            };
+                            println!("Direction: {}", direction);
+                        } else {
+                            println!("n/a: a counter decreased between the two readings (wrapped or reset).");
+                        }
+                    }
+                },
+                RpcCommands::RangeRewards { pool_id, tick_lower, tick_upper, liquidity } => {
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+                    let (tick_lower, tick_upper) = (Tick(tick_lower), Tick(tick_upper));
+                    if tick_lower >= tick_upper || tick_lower < Tick(MIN_TICK) || tick_upper > Tick(MAX_TICK) {
+                        fail(ExitCode::InvalidInput, format!("the tick range must satisfy {} <= --tick-lower < --tick-upper <= {}.", MIN_TICK, MAX_TICK));
+                    }
+                    for (flag, tick) in [("--tick-lower", tick_lower), ("--tick-upper", tick_upper)] {
+                        if tick.align_down(pool_state.tick_spacing) != tick {
+                            fail(ExitCode::InvalidInput, format!("{} {} is not a multiple of the pool's tick spacing {}.", flag, tick, pool_state.tick_spacing));
+                        }
+                    }
+
+                    println!("--- Reward Growth Inside [{}, {}] for {} ---", tick_lower, tick_upper, pool_id);
+                    let position = if pool_state.tick_current < tick_lower.0 {
+                        "below the range"
+                    } else if pool_state.tick_current < tick_upper.0 {
+                        "in range"
+                    } else {
+                        "above the range"
+                    };
+                    println!("  - Current Tick: {} ({})", pool_state.tick_current, position);
+                    println!("  - Liquidity:    {}", liquidity);
+
+                    // 1. Only initialized slots accrue; the program skips the others
+                    let rewards: Vec<(usize, &RewardInfo)> = pool_state.reward_infos.iter().enumerate()
+                        .filter(|(_, reward)| reward.token_mint != Pubkey::default())
+                        .collect();
+                    if rewards.is_empty() {
+                        println!("\nNo reward slots are initialized on this pool.");
+                    } else {
+                        // 2. Both boundary arrays and the reward mints in one call
+                        let (start_lower, start_upper) = (tick_lower.array_start(pool_state.tick_spacing), tick_upper.array_start(pool_state.tick_spacing));
+                        let mut pubkeys = vec![
+                            derive_tick_array_pda(&pool_pubkey, start_lower.0, &program_id),
+                            derive_tick_array_pda(&pool_pubkey, start_upper.0, &program_id),
+                        ];
+                        pubkeys.extend(rewards.iter().map(|(_, reward)| reward.token_mint));
+                        let (_, accounts) = rpc_client.get_multiple_accounts_at(&pubkeys, None).await
+                            .unwrap_or_else(|e| fail_fetch("tick arrays and reward mints", &e));
+                        let decode_array = |index: usize| accounts[index].as_ref().map(|account| {
+                            decode_clmm_account::<TickArrayState>(&pubkeys[index], &account.owner, &account.data, &program_id)
+                                .unwrap_or_else(|e| fail(ExitCode::Deserialize, format!("Failed to decode tick array {}: {}", pubkeys[index], e)))
+                        });
+                        let (array_lower, array_upper) = (decode_array(0), decode_array(1));
+                        // A missing array or a slot with no liquidity_gross is an uninitialized tick
+                        let lower = array_lower.as_ref()
+                            .map(|array| &array.ticks[start_lower.slot_of(tick_lower, pool_state.tick_spacing) as usize])
+                            .filter(|tick_state| tick_state.liquidity_gross != 0);
+                        let upper = array_upper.as_ref()
+                            .map(|array| &array.ticks[start_upper.slot_of(tick_upper, pool_state.tick_spacing) as usize])
+                            .filter(|tick_state| tick_state.liquidity_gross != 0);
+                        for (label, tick, state) in [("Lower", tick_lower, lower), ("Upper", tick_upper, upper)] {
+                            if state.is_none() {
+                                println!("  - {} tick {} is not initialized: no growth outside it is recorded.", label, tick);
+                            }
+                        }
+
+                        // 3. Per slot: bring the global growth up to now, then take the part inside the range
+                        let now = unix_now();
+                        for (position, (slot, reward)) in rewards.iter().enumerate() {
+                            let decimals = accounts[2 + position].as_ref()
+                                .and_then(|account| read_mint_decimals(&account.owner, &account.data).ok());
+                            let global = reward_growth_global_at(reward, pool_state.liquidity, now);
+                            let inside = reward_growth_inside(global, *slot, pool_state.tick_current, (tick_lower, lower), (tick_upper, upper));
+                            let accrued = liquidity_math::mul_div_floor(inside, liquidity, 1u128 << 64);
+
+                            println!("\nReward {}: {} ({})", slot, reward.token_mint, reward_state_name(reward.reward_state));
+                            println!(
+                                "  - Growth Global:  {} (stored {}, updated {}s ago)",
+                                format_q64(global),
+                                reward.reward_growth_global_x64,
+                                now.saturating_sub(reward.last_update_time)
+                            );
+                            let outside = |state: Option<&TickState>| state.map_or("-".to_string(), |tick| tick.reward_growths_outside_x64[*slot].to_string());
+                            println!("  - Growth Outside: lower {}, upper {}", outside(lower), outside(upper));
+                            println!("  - Growth Inside:  {}", inside);
+                            // The value wraps when the boundary ticks were initialized at different times; only
+                            // the difference between two readings is meaningful then
+                            match (accrued, decimals) {
+                                _ if inside > global => println!("  - Accrued:        n/a (growth inside wrapped below zero; compare two readings instead)"),
+                                (Err(e), _) => println!("  - Accrued:        n/a ({})", e),
+                                (Ok(accrued), Some(decimals)) => println!("  - Accrued:        {} ({} tokens, {} decimals)", accrued, format_token_amount(accrued, decimals), decimals),
+                                (Ok(accrued), None) => println!("  - Accrued:        {} raw (mint decimals unavailable)", accrued),
+                            }
+                        }
+                        println!("\nGrowth inside counts from when the boundary ticks were initialized. For an existing position,");
+                        println!("subtract its reward_growth_inside_last_x64 from Growth Inside to get the rewards it has pending.");
+                    }
+                },
+                RpcCommands::VerifyPrice { pool_id } => {
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+
+                    println!("Pool: {}", pool_id);
+                    if !print_price_consistency(&pool_state) {
+                        exit_code = ExitCode::Failure;
+                    }
+                },
+                RpcCommands::TokenMints { pool_id } => {
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+
+                    let (decimals_0, decimals_1) = fetch_mint_decimals(&rpc_client, &pool_state).await;
+
+                    println!("--- Token Mints for Pool {} ---", pool_id);
+                    println!("  Token 0 (t0): {} (decimals: {})", pool_state.token_mint_0, decimals_0);
+                    println!("  Token 1 (t1): {} (decimals: {})", pool_state.token_mint_1, decimals_1);
+                },
+                RpcCommands::DefaultBitmap { pool_id, no_prices } => {
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+                    
+                    println!("--- Initialized Tick Arrays (Default Bitmap) ---");
+                    let helper = TickArrayHelper { tick_spacing: pool_state.tick_spacing };
+                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+                    let initialized = read_default_bitmap(&pool_state.tick_array_bitmap, pool_state.tick_spacing);
+                    
+                    println!("Found {} initialized arrays:", initialized.len());
+                    print_bitmap_arrays(&initialized, &helper, &converter, (!no_prices).then_some(prices));
+                },
+                RpcCommands::GetSwapArraysBlind { pool_id, direction, format, favorable_pct, impact_pct, favorable_ticks, impact_ticks, price, output } => {
+                    let text = output == OutputFormat::Text;
+                    if text {
+                        println!("--- Blind Swap Array Calculation for {} ---", pool_id);
+                        println!("    (Assumes all arrays in range are initialized)");
+                    }
+
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+
+                    // 1. Fetch ONLY PoolState (Needed for tick_spacing, decimals, current_tick)
+                    if text { println!("Fetching pool info..."); }
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+                    if text { println!("Done."); }
+
+                    // 2. Setup Helpers
+                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+                    let helper = TickArrayHelper { tick_spacing: pool_state.tick_spacing };
+
+                    // 3. Determine Start Tick (Same as GetSwapArrays)
+                    let tick_start = match price {
+                        Some(p) => {
+                            let price_input = match format {
+                                HumanPriceFormat::T0PerT1 => PriceInput::Token0PerToken1Human { price: p },
+                                HumanPriceFormat::T1PerT0 => PriceInput::Token1PerToken0Human { price: p },
+                            };
+                            if text { println!("Using --price {} as start", prices.format(p)); }
+                            converter.price_to_tick(price_input).0
+                        },
+                        None => {
+                            if text { println!("No --price provided. Using live pool tick: {}", pool_state.tick_current); }
+                            pool_state.tick_current
+                        }
+                    };
+                    
+                    if text {
+                        println!("Start Tick:    {}", tick_start);
+                        println!("Direction:     {:?}", direction);
+                    }
+                    
+                    // 4. Calculate Tick Range based on Direction (Same as GetSwapArrays),
+                    //    clamped to the valid ticks so every array below can exist
+                    let (tick_favorable, tick_impact) = swap_tick_bounds(&converter, direction, tick_start, SwapRange::from_args(favorable_pct, impact_pct, favorable_ticks, impact_ticks))
+                        .unwrap_or_else(|e| fail(ExitCode::InvalidInput, e));
+                    let (min_tick, max_tick) = (tick_favorable.min(tick_impact).max(MIN_TICK), tick_favorable.max(tick_impact).min(MAX_TICK));
+                    
+                    if text {
+                        print_swap_range(tick_start, tick_favorable, tick_impact);
+                        println!("Calculated Tick Range:  [{}, {}]", min_tick, max_tick);
+                    }
+
+                    // 5. Calculate the arrays BLINDLY: core, favorable and surrounding, in swap order
+                    let (favorable_arrays, core_arrays, surrounding_array) =
+                        blind_swap_arrays(&helper, direction, tick_start, (tick_favorable, tick_impact));
+
+                    // 6. Print Final List (Same as GetSwapArrays, uses the blind lists)
+                    if output != OutputFormat::Text {
+                        let mut ordered: Vec<(&str, i32)> = favorable_arrays.iter().map(|&start_index| ("FAVORABLE", start_index)).collect();
+                        ordered.extend(core_arrays.iter().map(|&start_index| ("CORE", start_index)));
+                        ordered.extend(surrounding_array.map(|(start_index, label)| (label, start_index)));
+                        if surrounding_array.is_none() {
+                            eprintln!("Warning: No surrounding array: the range already reaches the tick limit in the impact direction.");
+                        }
+                        let result = swap_arrays_result(&pool_pubkey, &pool_state, &program_id, direction, tick_start, (min_tick, max_tick), &ordered);
+                        print_swap_arrays_machine(&result, output);
+                    } else {
+                        let total_arrays = core_arrays.len() + favorable_arrays.len() + if surrounding_array.is_some() { 1 } else { 0 };
+                        println!("\n{:=<80}", "");
+                        println!("--- REQUIRED SWAP ARRAYS (BLIND): {} ---", total_arrays);
+
+                        for start_index in &favorable_arrays {
+                            print_swap_array_info("FAVORABLE", *start_index, &pool_pubkey, &program_id);
+                        }
+                        if !favorable_arrays.is_empty() {
+                            println!("\n{:-<80}", "");
+                        }
+                        for start_index in &core_arrays {
+                            print_swap_array_info("CORE", *start_index, &pool_pubkey, &program_id);
+                        }
+
+                        if let Some((start_index, label)) = surrounding_array {
+                            if !core_arrays.is_empty() || !favorable_arrays.is_empty() {
+                                println!("\n{:-<80}", "");
+                            }
+                            print_swap_array_info(label, start_index, &pool_pubkey, &program_id);
+                        } else {
+                            println!("\n[INFO] No surrounding array: the range already reaches the last array before tick {}.", if let SwapDirection::BuyT1 = direction { MIN_TICK } else { MAX_TICK });
+                        }
+                        println!("{:=<80}", "");
+                    }
+                },
+                RpcCommands::GetSwapArrays { pool_id, direction, format, favorable_pct, impact_pct, favorable_ticks, impact_ticks, price, output, full_accounts, verify, slippage_pct } => {
+                    let text = output == OutputFormat::Text;
+                    if text { println!("--- Swap Array Calculation for {} ---", pool_id); }
+
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+
+                    // 1. Fetch Base Data (PoolState + Extension)
+                    if text { println!("Fetching pool info and bitmaps..."); }
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+                    warn_if_swaps_paused(&pool_state);
+
+                    let (ext_pda, _) = Pubkey::find_program_address(&[TICK_ARRAY_BITMAP_SEED, pool_pubkey.as_ref()], &program_id);
+                    let extension = fetch_pool_account::<TickArrayBitmapExtension>(&rpc_client, &ext_pda, &pool_pubkey, &program_id).await;
+                    if text { println!("Done."); }
+
+                    // 2. Setup Converter
+                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+
+                    // 3. Determine Start Tick
+                    let tick_start = match price {
+                        Some(p) => {
+                            let price_input = match format {
+                                HumanPriceFormat::T0PerT1 => PriceInput::Token0PerToken1Human { price: p },
+                                HumanPriceFormat::T1PerT0 => PriceInput::Token1PerToken0Human { price: p },
+                            };
+                            if text { println!("Using --price {} as start", prices.format(p)); }
+                            converter.price_to_tick(price_input).0
+                        },
+                        None => {
+                            if text { println!("No --price provided. Using live pool tick: {}", pool_state.tick_current); }
+                            pool_state.tick_current
+                        }
+                    };
+                    
+                    if text {
+                        println!("Start Tick:    {}", tick_start);
+                        println!("Direction:     {:?}", direction);
+                    }
+                    
+                    // 4. Calculate Tick Range based on Direction (using RAW PRICE)
+                    //    (Based on on-chain facts: buy-t1 = tick decreases, buy-t0 = tick increases.
+                    //    The favorable side is the opposite one: a better fill for the trader.)
+                    let (tick_favorable, tick_impact) = swap_tick_bounds(&converter, direction, tick_start, SwapRange::from_args(favorable_pct, impact_pct, favorable_ticks, impact_ticks))
+                        .unwrap_or_else(|e| fail(ExitCode::InvalidInput, e));
+
+                    let (min_tick, max_tick) = (tick_favorable.min(tick_impact), tick_favorable.max(tick_impact));
+                    
+                    if text {
+                        print_swap_range(tick_start, tick_favorable, tick_impact);
+                        println!("Calculated Tick Range:  [{}, {}]", min_tick, max_tick);
+                    }
+
+                    // 5. Select the initialized arrays along the swap path, in swap order
+                    let (ordered, has_surrounding) = select_swap_arrays(&pool_state, &extension, direction, (min_tick, max_tick));
+                    let mut result = swap_arrays_result(&pool_pubkey, &pool_state, &program_id, direction, tick_start, (min_tick, max_tick), &ordered);
+                    if verify && verify_swap_arrays(&rpc_client, &mut result, &pool_pubkey, &program_id).await > 0 {
+                        exit_code = ExitCode::Failure;
+                    }
+                    if full_accounts {
+                        result.accounts = Some(swap_v2_accounts(&result, &pool_state, direction));
+                    }
+                    let swap_limit = slippage_pct.map(|slippage_pct| {
+                        let (limit, clamped) = swap_sqrt_price_limit(pool_state.sqrt_price_x64, direction, slippage_pct)
+                            .unwrap_or_else(|e| fail(ExitCode::InvalidInput, &e));
+                        if clamped {
+                            warn_swap_limit_clamped(direction, slippage_pct);
+                        }
+                        result.sqrt_price_limit_x64 = Some(limit.to_string());
+                        (slippage_pct, limit)
+                    });
+                    if output != OutputFormat::Text {
+                        if !has_surrounding {
+                            eprintln!("Warning: No initialized surrounding array found for the impact direction.");
+                        }
+                        print_swap_arrays_machine(&result, output);
+                    } else {
+                        let total_arrays = ordered.len();
+                        println!("\n{:=<80}", "");
+                        println!("--- REQUIRED SWAP ARRAYS: {} ---", total_arrays);
+
+                        for array in &result.arrays {
+                            print_swap_array_info(&array.label, array.start_index, &pool_pubkey, &program_id);
+                            match (array.exists, array.initialized_tick_count) {
+                                (Some(true), Some(count)) => println!("                  On-chain: exists, {} initialized ticks", count),
+                                (Some(false), _) => println!("                  On-chain: MISSING"),
+                                _ => {}
+                            }
+                        }
+
+                        if !has_surrounding {
+                            println!("\n[WARNING] No initialized surrounding array found for the impact direction.");
+                        }
+                        if let Some((slippage_pct, limit)) = swap_limit {
+                            println!("\nsqrt_price_limit_x64 ({}% slippage from the live price): {}", slippage_pct, limit);
+                            println!("  = {}", format_swap_limit_price(&converter, SqrtPriceX64(limit), prices));
+                        }
+                        println!("{:=<80}", "");
+
+                        if let Some(accounts) = &result.accounts {
+                            print_swap_v2_accounts(accounts);
+                        }
+                    }
+                },
+                RpcCommands::Coverage { pool_id, direction, num_arrays, target_pct, initialized_only } => {
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+                    let helper = TickArrayHelper { tick_spacing: pool_state.tick_spacing };
+                    let tick_current = pool_state.tick_current;
+
+                    // 1. The arrays along the swap path: contiguous from tick_spacing alone, or initialized from the bitmaps
+                    let initialized = if initialized_only {
+                        let (ext_pda, _) = Pubkey::find_program_address(&[TICK_ARRAY_BITMAP_SEED, pool_pubkey.as_ref()], &program_id);
+                        let extension = fetch_pool_account::<TickArrayBitmapExtension>(&rpc_client, &ext_pda, &pool_pubkey, &program_id).await;
+                        let mut initialized = read_default_bitmap(&pool_state.tick_array_bitmap, pool_state.tick_spacing);
+                        initialized.append(&mut read_extension_bitmap(&extension, pool_state.tick_spacing));
+                        initialized.sort();
+                        Some(initialized)
+                    } else {
+                        None
+                    };
+                    let path = swap_path_arrays(&helper, direction, tick_current, initialized.as_deref());
+
+                    println!("--- Swap Coverage for {} ---", pool_id);
+                    println!("Direction:      {:?} (tick {})", direction, match direction {
+                        SwapDirection::BuyT1 => "decreases",
+                        SwapDirection::BuyT0 => "increases",
+                    });
+                    println!("Current Tick:   {} (array {})", tick_current, helper.get_array_start_index(Tick(tick_current)));
+                    println!("Tick Spacing:   {} ({} ticks per array)", pool_state.tick_spacing, helper.tick_indices_per_array());
+                    println!("Counting:       {}", if initialized_only { "initialized arrays only (bitmaps)" } else { "every array (contiguous)" });
+                    println!("Arrays on path: {}", path.len());
+                    println!();
+
+                    // 2. Answer the question asked
+                    match (num_arrays, target_pct) {
+                        (Some(num_arrays), _) => {
+                            if num_arrays == 0 {
+                                fail(ExitCode::InvalidInput, "--num-arrays must be at least 1");
+                            }
+                            match path.get(num_arrays as usize - 1).or(path.last()) {
+                                Some(&last) => {
+                                    let used = (num_arrays as usize).min(path.len());
+                                    if used < num_arrays as usize {
+                                        println!("Only {} arrays exist in the swap direction; coverage stops at the last one.", used);
+                                    }
+                                    println!("Arrays:         {} ({} .. {})", used, path[0], last);
+                                    print_coverage_edge(&converter, tick_current, swap_path_edge(&helper, direction, last), prices);
+                                }
+                                None => println!("No initialized arrays in the swap direction: the swap cannot move the price."),
+                            }
+                        }
+                        (None, Some(target_pct)) => {
+                            if target_pct.is_nan() || target_pct <= 0.0 || (matches!(direction, SwapDirection::BuyT1) && target_pct >= 100.0) {
+                                fail(ExitCode::InvalidInput, format!("--target-pct must be above 0 (and below 100 for buy-t1), got {}", target_pct));
+                            }
+                            let (_, target_tick) = swap_tick_bounds(&converter, direction, tick_current, SwapRange::Percent { favorable: 0.0, impact: target_pct })
+                                .unwrap_or_else(|e| fail(ExitCode::InvalidInput, e));
+                            println!("Target:         {}{}% (tick {})", match direction { SwapDirection::BuyT1 => "-", SwapDirection::BuyT0 => "+" }, target_pct, target_tick);
+                            let reaches = |start: &i32| match direction {
+                                SwapDirection::BuyT1 => swap_path_edge(&helper, direction, *start) <= target_tick,
+                                SwapDirection::BuyT0 => swap_path_edge(&helper, direction, *start) >= target_tick,
+                            };
+                            match path.iter().position(reaches) {
+                                Some(index) => {
+                                    println!("Arrays Needed:  {} ({} .. {})", index + 1, path[0], path[index]);
+                                    print_coverage_edge(&converter, tick_current, swap_path_edge(&helper, direction, path[index]), prices);
+                                }
+                                None => {
+                                    println!("Arrays Needed:  not reachable; all {} arrays on the path fall short", path.len());
+                                    if let Some(&last) = path.last() {
+                                        print_coverage_edge(&converter, tick_current, swap_path_edge(&helper, direction, last), prices);
+                                    }
+                                }
+                            }
+                        }
+                        (None, None) => unreachable!("--num-arrays or --target-pct is required (enforced by clap)"),
+                    }
+                    if initialized_only {
+                        println!("\nUninitialized arrays hold no liquidity and are skipped by the swap, so they are not counted.");
+                    }
+                },
+                RpcCommands::SwapLimit { pool_id, direction, slippage_pct } => {
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+
+                    let (limit, clamped) = swap_sqrt_price_limit(pool_state.sqrt_price_x64, direction, slippage_pct)
+                        .unwrap_or_else(|e| fail(ExitCode::InvalidInput, &e));
+                    if clamped {
+                        warn_swap_limit_clamped(direction, slippage_pct);
+                    }
+
+                    println!("--- Swap Price Limit for {} ---", pool_id);
+                    println!("Direction:            {:?} (price {})", direction, match direction {
+                        SwapDirection::BuyT1 => "decreases",
+                        SwapDirection::BuyT0 => "increases",
+                    });
+                    println!("Slippage:             {}%", slippage_pct);
+                    println!("Current sqrt_price:   {}", pool_state.sqrt_price_x64);
+                    println!("  = {}", format_swap_limit_price(&converter, SqrtPriceX64(pool_state.sqrt_price_x64), prices));
+                    println!("sqrt_price_limit_x64: {}{}", limit, if clamped { " (clamped)" } else { "" });
+                    println!("  = {}", format_swap_limit_price(&converter, SqrtPriceX64(limit), prices));
+                    if let Ok(tick) = SqrtPriceX64(limit).tick() {
+                        println!("Limit Tick:           {} (current: {})", tick, pool_state.tick_current);
+                    }
+                },
+                RpcCommands::ArraysToPrice { pool_id, direction, num_arrays } => {
+                    if num_arrays == 0 {
+                        fail(ExitCode::InvalidInput, "--num-arrays must be at least 1");
+                    }
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+                    let helper = TickArrayHelper { tick_spacing: pool_state.tick_spacing };
+                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+                    let width = helper.tick_indices_per_array();
+                    let current_array = helper.get_array_start_index(Tick(pool_state.tick_current));
+                    let current_raw_price = SqrtPriceX64(pool_state.sqrt_price_x64).to_raw_price();
+
+                    // 1. Arrays from the current one to the last array inside the tick bounds, inclusive
+                    let (first_array, last_array) = helper.valid_array_start_range();
+                    let available = match direction {
+                        SwapDirection::BuyT0 => (last_array.0 - current_array.0) / width + 1,
+                        SwapDirection::BuyT1 => (current_array.0 - first_array.0) / width + 1,
+                    } as u32;
+                    let crossed = num_arrays.min(available);
+
+                    println!("--- Price After {} Tick Arrays for {} ---", num_arrays, pool_id);
+                    println!("Direction:     {:?} (tick moves {})", direction, match direction {
+                        SwapDirection::BuyT1 => "down",
+                        SwapDirection::BuyT0 => "up",
+                    });
+                    println!("Current Tick:  {} (array {})", pool_state.tick_current, current_array);
+                    println!("Array Width:   {} ticks (60 x tick spacing {})", width, pool_state.tick_spacing);
+
+                    // 2. The far edge of array k: the next array's start going up, its own start going down,
+                    //    clamped to the tick bounds at the last array
+                    println!(
+                        "\n{:>3} | {:>11} | {:>9} | {:>9} | {:>11} | {:>14} | {:>14}",
+                        "#", "Start Index", "Edge Tick", "Ticks", "Price Move", "T1/T0", "T0/T1"
+                    );
+                    println!("{:-<94}", "");
+                    for k in 1..=crossed as i32 {
+                        let (start, edge) = match direction {
+                            SwapDirection::BuyT0 => {
+                                let start = current_array.shifted(k - 1, pool_state.tick_spacing);
+                                (start, (start.0 + width).min(MAX_TICK))
+                            }
+                            SwapDirection::BuyT1 => {
+                                let start = current_array.shifted(1 - k, pool_state.tick_spacing);
+                                (start, start.0.max(MIN_TICK))
+                            }
+                        };
+                        let price_move = converter.tick_to_raw_price(Tick(edge)) / current_raw_price - 1.0;
+                        println!(
+                            "{:>3} | {:>11} | {:>9} | {:>+9} | {:>+10.4}% | {:>14} | {:>14}",
+                            k,
+                            start,
+                            edge,
+                            edge - pool_state.tick_current,
+                            price_move * 100.0,
+                            prices.format(converter.tick_to_price(Tick(edge), PriceInput::Token1PerToken0Human { price: 0.0 })),
+                            prices.format(converter.tick_to_price(Tick(edge), PriceInput::Token0PerToken1Human { price: 0.0 })),
+                        );
+                    }
+                    if crossed < num_arrays {
+                        println!(
+                            "\nOnly {} arrays, counting the current one, fit before {} {}; the last edge is clamped to it.",
+                            available,
+                            match direction { SwapDirection::BuyT0 => "MAX_TICK", SwapDirection::BuyT1 => "MIN_TICK" },
+                            match direction { SwapDirection::BuyT0 => MAX_TICK, SwapDirection::BuyT1 => MIN_TICK },
+                        );
+                    }
+                    println!("\nArrays are counted geometrically. A swap only loads initialized arrays and skips empty ones;");
+                    println!("use `rpc get-swap-arrays` or `rpc next-array` for the arrays it actually needs.");
+                },
+                RpcCommands::ExtensionBitmap { pool_id, no_prices, raw } => {
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+                    
+                    // We need to fetch the main pool state to get decimals and tick_spacing
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+                    
+                    let (pda, _) = Pubkey::find_program_address(&[TICK_ARRAY_BITMAP_SEED, pool_pubkey.as_ref()], &program_id);
+                    let extension = fetch_pool_account::<TickArrayBitmapExtension>(&rpc_client, &pda, &pool_pubkey, &program_id).await;
+
+                    if raw {
+                        print_raw_decode(&pda, &extension);
+                    } else {
+                        println!("--- Initialized Tick Arrays (Extension Bitmap) ---");
+                        let helper = TickArrayHelper { tick_spacing: pool_state.tick_spacing };
+                        let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+                        let mut initialized = read_extension_bitmap(&extension, pool_state.tick_spacing);
+                        initialized.sort(); // Sort for readability
+
+                        println!("Found {} initialized arrays in extension:", initialized.len());
+                        print_bitmap_arrays(&initialized, &helper, &converter, (!no_prices).then_some(prices));
+                    }
+                },
+                RpcCommands::TickArray { pool_id, start_index, raw, format } => {
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+                    if raw {
+                        let pda = derive_tick_array_pda(&pool_pubkey, start_index, &program_id);
+                        let tick_array = fetch_clmm_account::<TickArrayState>(&rpc_client, &pda, &program_id).await;
+                        print_raw_decode(&pda, &tick_array);
+                    } else {
+                        tick_array_command(&rpc_client, &pool_pubkey, &program_id, start_index, format, prices).await;
+                    }
+                },
+                RpcCommands::InitializedRangePercent { pool_id, price, lower_pct, upper_pct, arrays_below, arrays_above, format, round } => {
+                    // Calculate the price range from percentages (array counts need the pool's tick spacing first)
+                    let percent_range = match (price, lower_pct, upper_pct) {
+                        (Some(price), Some(lower_pct), Some(upper_pct)) => {
+                            let price_lower = price * (1.0 - (lower_pct / 100.0));
+                            let price_upper = price * (1.0 + (upper_pct / 100.0));
+
+                            println!("--- Initialized Array Percent Range Analysis for {} ---", pool_id);
+                            println!("Base Price:   {}", prices.format(price));
+                            println!("Range:        -{:.2}% to +{:.2}%", lower_pct, upper_pct);
+                            println!("Calculated Price Range: {}", prices.range(price_lower, price_upper));
+                            Some((price_lower, price_upper))
+                        }
+                        _ => {
+                            println!("--- Initialized Array Neighborhood Analysis for {} ---", pool_id);
+                            None
+                        }
+                    };
+                    
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+
+                    // 1. Fetch Base Data (PoolState + Extension)
+                    println!("Fetching pool info and bitmaps...");
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+
+                    let (ext_pda, _) = Pubkey::find_program_address(&[TICK_ARRAY_BITMAP_SEED, pool_pubkey.as_ref()], &program_id);
+                    let extension = fetch_pool_account::<TickArrayBitmapExtension>(&rpc_client, &ext_pda, &pool_pubkey, &program_id).await;
+                    println!("Done.");
+
+                    // 2. Setup Helpers
+                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+                    let helper = TickArrayHelper { tick_spacing: pool_state.tick_spacing };
+                    let price_input = |price: f64| match format {
+                        HumanPriceFormat::T0PerT1 => PriceInput::Token0PerToken1Human { price },
+                        HumanPriceFormat::T1PerT0 => PriceInput::Token1PerToken0Human { price },
+                    };
+                    let price_template = price_input(0.0); // Template for printing
+                    
+                    // 3. Convert the range to ticks: a price range rounds to ticks, an array neighborhood
+                    //    spans whole arrays around the one holding the center price
+                    let (min_tick, max_tick) = match (percent_range, arrays_below, arrays_above) {
+                        (Some((price_lower, price_upper)), _, _) => {
+                            let (tick_lower, tick_upper) = converter.price_range_to_ticks(price_input(price_lower), price_input(price_upper), round, pool_state.tick_spacing);
+                    
+                            // Ensure min_tick is always the smaller number, max_tick is larger
+                            let (min_tick, max_tick) = if tick_lower > tick_upper {
+                                (tick_upper, tick_lower)
+                            } else {
+                                (tick_lower, tick_upper)
+                            };
+
+                            println!("Input Price Range {} maps to Tick Range [{}, {}]", prices.range(price_lower, price_upper), min_tick, max_tick);
+                            (min_tick, max_tick)
+                        }
+                        (None, Some(arrays_below), Some(arrays_above)) => {
+                            let anchor_tick = match price {
+                                Some(price) => {
+                                    println!("Center Price: {} (--price)", prices.format(price));
+                                    converter.price_to_tick(price_input(price))
+                                }
+                                None => {
+                                    println!("Center Price: {} (current tick {})", prices.format(converter.tick_to_price(Tick(pool_state.tick_current), price_template)), pool_state.tick_current);
+                                    Tick(pool_state.tick_current)
+                                }
+                            };
+                            let anchor = helper.get_array_start_index(anchor_tick);
+                            let (first_valid, last_valid) = helper.valid_array_start_range();
+                            if anchor < first_valid || anchor > last_valid {
+                                fail(ExitCode::InvalidInput, format!("--price maps to tick {}, outside [{}, {}]", anchor_tick, MIN_TICK, MAX_TICK));
+                            }
+
+                            // Counts past the protocol limits are clamped to the first and last valid arrays
+                            let span = helper.tick_indices_per_array() as i64;
+                            let below = (arrays_below as i64).min((anchor.0 - first_valid.0) as i64 / span);
+                            let above = (arrays_above as i64).min((last_valid.0 - anchor.0) as i64 / span);
+                            let first = anchor.shifted(-(below as i32), pool_state.tick_spacing);
+                            let last = anchor.shifted(above as i32, pool_state.tick_spacing);
+                            let (min_tick, max_tick) = (helper.get_array_tick_range(first).0, helper.get_array_tick_range(last).1);
+
+                            println!("Anchor Array: {} (tick {})", anchor, anchor_tick);
+                            println!("Neighborhood: {} arrays below, {} above -> arrays {} to {}", below, above, first, last);
+                            if below < arrays_below as i64 || above < arrays_above as i64 {
+                                println!("(clamped to the valid tick range [{}, {}])", MIN_TICK, MAX_TICK);
+                            }
+                            println!(
+                                "Tick Range [{}, {}] is Price Range {}",
+                                min_tick,
+                                max_tick,
+                                prices.range(converter.tick_to_price(first.first_tick(), price_template), converter.tick_to_price(helper.last_valid_tick(last), price_template))
+                            );
+                            (min_tick, max_tick)
+                        }
+                        _ => fail(ExitCode::InvalidInput, "give either --lower-pct and --upper-pct, or --arrays-below and --arrays-above"),
+                    };
+
+                    // 4. Get ALL initialized arrays and SORT them
+                    let mut all_initialized_arrays = read_default_bitmap(&pool_state.tick_array_bitmap, pool_state.tick_spacing);
+                    all_initialized_arrays.append(&mut read_extension_bitmap(&extension, pool_state.tick_spacing));
+                    all_initialized_arrays.sort();
+
+                    // 5. Filter and Find Arrays
+                    let arrays_in_range: Vec<i32> = all_initialized_arrays.iter()
+                        .filter(|&&start_index| {
+                            let (_tick_start, tick_end) = helper.get_array_tick_range(ArrayStartIndex(start_index));
+                            let array_start = start_index;
+                            let array_end = tick_end; 
+                            (array_start <= max_tick.0) && (array_end >= min_tick)
+                        })
+                        .cloned()
+                        .collect();
+
+                    let lower_surrounding: Option<i32> = all_initialized_arrays.iter()
+                        .rfind(|&&start_index| {
+                            let (_tick_start, tick_end) = helper.get_array_tick_range(ArrayStartIndex(start_index));
+                            tick_end < min_tick 
+                        })
+                        .cloned();
+
+                    let upper_surrounding: Option<i32> = all_initialized_arrays.iter()
+                        .find(|&&start_index| {
+                            start_index > max_tick.0 
+                        })
+                        .cloned();
+
+                    // 6. Fetch all arrays concurrently, then print them in sorted order
+                    let mut to_fetch: Vec<i32> = lower_surrounding.into_iter().collect();
+                    to_fetch.extend(&arrays_in_range);
+                    to_fetch.extend(upper_surrounding);
+                    let mut fetched = fetch_array_details(&rpc_client, &pool_pubkey, &program_id, &to_fetch).await.into_iter();
+
+                    if lower_surrounding.is_some() {
+                        let array = fetched.next().expect("Fetched array missing");
+                        println!("\n{:-<80}", "");
+                        println!("--- (Lower Surrounding Initialized Array) ---");
+                        print_array_details(&array, &program_id, &converter, &helper, price_template, palette, prices);
+                    } else {
+                        println!("\n{:-<80}", "");
+                        println!("--- (No initialized array found below price range) ---");
+                    }
+
+                    println!("\n{:=<80}", "");
+                    println!("--- ARRAYS INITIALIZED WITHIN PRICE RANGE ({}) ---", arrays_in_range.len());
+                    if arrays_in_range.is_empty() {
+                        println!("--- (No initialized arrays found within price range) ---");
+                    } else {
+                        for array in fetched.by_ref().take(arrays_in_range.len()) {
+                            print_array_details(&array, &program_id, &converter, &helper, price_template, palette, prices);
+                        }
+                    }
+                    println!("{:=<80}", "");
+
+
+                    if let Some(array) = fetched.next() {
+                        println!("\n{:-<80}", "");
+                        println!("--- (Upper Surrounding Initialized Array) ---");
+                        print_array_details(&array, &program_id, &converter, &helper, price_template, palette, prices);
+                    } else {
+                        println!("\n{:-<80}", "");
+                        println!("--- (No initialized array found above price range) ---");
+                    }
+                },
+                RpcCommands::InitializedRange { pool_id, price_lower, price_upper, tick_lower, tick_upper, format, round } => {
+                    println!("--- Initialized Array Range Analysis for {} ---", pool_id);
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+
+                    // 1. Fetch Base Data (PoolState + Extension)
+                    println!("Fetching pool info and bitmaps...");
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+
+                    let (ext_pda, _) = Pubkey::find_program_address(&[TICK_ARRAY_BITMAP_SEED, pool_pubkey.as_ref()], &program_id);
+                    let extension = fetch_pool_account::<TickArrayBitmapExtension>(&rpc_client, &ext_pda, &pool_pubkey, &program_id).await;
+                    println!("Done.");
+
+                    // 2. Setup Helpers
+                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+                    let helper = TickArrayHelper { tick_spacing: pool_state.tick_spacing };
+                    
+                    // 3. Convert Price Range to Tick Range (explicit ticks skip the conversion)
+                    let price_template = match format {
+                        HumanPriceFormat::T0PerT1 => PriceInput::Token0PerToken1Human { price: 0.0 },
+                        HumanPriceFormat::T1PerT0 => PriceInput::Token1PerToken0Human { price: 0.0 },
+                    };
+                    let (tick_lower, tick_upper) = match (tick_lower, tick_upper, price_lower, price_upper) {
+                        (Some(tick_lower), Some(tick_upper), _, _) => {
+                            for (flag, tick) in [("--tick-lower", tick_lower), ("--tick-upper", tick_upper)] {
+                                if !(MIN_TICK..=MAX_TICK).contains(&tick) {
+                                    fail(ExitCode::InvalidInput, format!("{} {} is outside [{}, {}]", flag, tick, MIN_TICK, MAX_TICK));
+                                }
+                            }
+                            (Tick(tick_lower), Tick(tick_upper))
+                        }
+                        (None, None, Some(price_lower), Some(price_upper)) => {
+                            let (price_format_lower, price_format_upper) = match format {
+                                HumanPriceFormat::T0PerT1 => (
+                                    PriceInput::Token0PerToken1Human { price: price_lower },
+                                    PriceInput::Token0PerToken1Human { price: price_upper },
+                                ),
+                                HumanPriceFormat::T1PerT0 => (
+                                    PriceInput::Token1PerToken0Human { price: price_lower },
+                                    PriceInput::Token1PerToken0Human { price: price_upper },
+                                ),
+                            };
+                            converter.price_range_to_ticks(price_format_lower, price_format_upper, round, pool_state.tick_spacing)
+                        }
+                        _ => fail(ExitCode::InvalidInput, "give either --price-lower and --price-upper, or --tick-lower and --tick-upper"),
+                    };
+
+                    // Ensure min_tick is always the smaller number, max_tick is larger
+                    let (min_tick, max_tick) = if tick_lower > tick_upper {
+                        (tick_upper, tick_lower)
+                    } else {
+                        (tick_lower, tick_upper)
+                    };
+
+                    if let (Some(price_lower), Some(price_upper)) = (price_lower, price_upper) {
+                        println!("Input Price Range {} maps to Tick Range [{}, {}]", prices.range(price_lower, price_upper), min_tick, max_tick);
+                    } else {
+                        // Echo the prices of the given ticks, so a mistyped tick shows up as an unexpected price
+                        println!(
+                            "Input Tick Range [{}, {}] is Price Range {}",
+                            min_tick,
+                            max_tick,
+                            prices.range(converter.tick_to_price(min_tick, price_template), converter.tick_to_price(max_tick, price_template))
+                        );
+                    }
+
+                    // 4. Get ALL initialized arrays and SORT them
+                    let mut all_initialized_arrays = read_default_bitmap(&pool_state.tick_array_bitmap, pool_state.tick_spacing);
+                    all_initialized_arrays.append(&mut read_extension_bitmap(&extension, pool_state.tick_spacing));
+                    all_initialized_arrays.sort();
+
+                    // 5. Filter and Find Arrays
+                    let arrays_in_range: Vec<i32> = all_initialized_arrays.iter()
+                        .filter(|&&start_index| {
+                            let (_tick_start, tick_end) = helper.get_array_tick_range(ArrayStartIndex(start_index));
+                            // An array overlaps the range if:
+                            // (array_start <= max_tick) AND (array_end >= min_tick)
+                            let array_start = start_index;
+                            let array_end = tick_end; // tick_end from helper is inclusive
+                            (array_start <= max_tick.0) && (array_end >= min_tick)
+                        })
+                        .cloned()
+                        .collect();
+
+                    let lower_surrounding: Option<i32> = all_initialized_arrays.iter()
+                        .rfind(|&&start_index| {
+                            let (_tick_start, tick_end) = helper.get_array_tick_range(ArrayStartIndex(start_index));
+                            tick_end < min_tick // Find arrays that *end* before our range starts
+                        }) // Get the one closest (last) to the range
+                        .cloned();
+
+                    let upper_surrounding: Option<i32> = all_initialized_arrays.iter()
+                        .find(|&&start_index| {
+                            start_index > max_tick.0 // Find arrays that *start* after our range ends
+                        }) // Get the one closest (first) to the range
+                        .cloned();
+
+                    // 6. Fetch all arrays concurrently, then print them in sorted order
+                    let mut to_fetch: Vec<i32> = lower_surrounding.into_iter().collect();
+                    to_fetch.extend(&arrays_in_range);
+                    to_fetch.extend(upper_surrounding);
+                    let mut fetched = fetch_array_details(&rpc_client, &pool_pubkey, &program_id, &to_fetch).await.into_iter();
+
+                    if lower_surrounding.is_some() {
+                        let array = fetched.next().expect("Fetched array missing");
+                        println!("\n{:-<80}", "");
+                        println!("--- (Lower Surrounding Initialized Array) ---");
+                        print_array_details(&array, &program_id, &converter, &helper, price_template, palette, prices);
+                    } else {
+                        println!("\n{:-<80}", "");
+                        println!("--- (No initialized array found below price range) ---");
+                    }
+
+                    println!("\n{:=<80}", "");
+                    println!("--- ARRAYS INITIALIZED WITHIN PRICE RANGE ({}) ---", arrays_in_range.len());
+                    if arrays_in_range.is_empty() {
+                        println!("--- (No initialized arrays found within price range) ---");
+                    } else {
+                        for array in fetched.by_ref().take(arrays_in_range.len()) {
+                            print_array_details(&array, &program_id, &converter, &helper, price_template, palette, prices);
+                        }
+                    }
+                    println!("{:=<80}", "");
+
+
+                    if let Some(array) = fetched.next() {
+                        println!("\n{:-<80}", "");
+                        println!("--- (Upper Surrounding Initialized Array) ---");
+                        print_array_details(&array, &program_id, &converter, &helper, price_template, palette, prices);
+                    } else {
+                        println!("\n{:-<80}", "");
+                        println!("--- (No initialized array found above price range) ---");
+                    }
+                },
+                RpcCommands::LiquidityCurve { pool_id, format, max_width, show_arrays, log_scale, svg } => {
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+
+                    println!("Fetching pool info and bitmaps...");
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+                    
+                    // Get all initialized array start indices from bitmaps
+                    let all_initialized_arrays = fetch_initialized_arrays(&rpc_client, &pool_pubkey, &program_id, &pool_state).await;
+
+                    println!(
+                        "Found {} initialized tick arrays. Fetching each account... (this will be slow)",
+                        all_initialized_arrays.len()
+                    );
+
+                    // Fetch each tick array individually and extract ticks
+                    let mut all_ticks = fetch_initialized_ticks(&rpc_client, &pool_pubkey, &program_id, &all_initialized_arrays).await;
+                    
+                    println!("Done fetching and parsing.");
+                    
+                    let converter = TickConverter {
+                        decimals_0: pool_state.mint_decimals_0,
+                        decimals_1: pool_state.mint_decimals_1,
+                    };
+
+                    let price_format_template = match format {
+                        HumanPriceFormat::T0PerT1 => PriceInput::Token0PerToken1Human { price: 0.0 },
+                        HumanPriceFormat::T1PerT0 => PriceInput::Token1PerToken0Human { price: 0.0 },
+                    };
+
+                    if let Some(svg_path) = svg {
+                        all_ticks.sort_by_key(|(tick, _)| *tick);
+                        let Some(chart) = render_liquidity_svg(&all_ticks, &converter, price_format_template, pool_state.tick_current, log_scale, &pool_id, prices) else {
+                            println!("No active liquidity found in this pool.");
+                            return;
+                        };
+                        std::fs::write(&svg_path, chart)
+                            .unwrap_or_else(|e| fail(ExitCode::InvalidInput, format!("cannot write {}: {}", svg_path.display(), e)));
+                        println!("Wrote liquidity chart to {}", svg_path.display());
+                        return;
+                    }
+
+                    print_exact_liquidity_ranges(
+                        &mut all_ticks,
+                        &converter,
+                        price_format_template,
+                        max_width,
+                        pool_state.tick_current,
+                        pool_state.tick_spacing,
+                        &all_initialized_arrays,
+                        &pool_pubkey,
+                        &program_id,
+                        show_arrays,
+                        log_scale,
+                        palette,
+                        prices,
+                    );
+
+                },
+                RpcCommands::InspectArray { pool_id, start_index, pda, raw, debug_seeds, grid, compact, format, show_fees, output } => {
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+                    let text = match output {
+                        OutputFormat::Text => true,
+                        OutputFormat::Json if raw || grid || compact || show_fees || debug_seeds => fail(
+                            ExitCode::InvalidInput,
+                            "--output json prints only the slot table; drop --raw, --grid, --compact, --show-fees and --debug-seeds.",
+                        ),
+                        OutputFormat::Json => false,
+                        OutputFormat::Ts => fail(ExitCode::InvalidInput, "--output ts is only available for the swap-arrays commands."),
+                    };
+
+                    // Determine the PDA from the provided input (either start_index or pda)
+                    let tick_array_pda = if let Some(start_idx) = start_index {
+                        if text { println!("Deriving PDA from start index {}...", start_idx); }
+                        if debug_seeds {
+                            print_tick_array_seeds(&pool_pubkey, start_idx, &program_id);
+                        }
+                        derive_tick_array_pda(&pool_pubkey, start_idx, &program_id)
+                    } else if let Some(pda_str) = pda {
+                        if text { println!("Using provided PDA {}...", &pda_str); }
+                        parse_pubkey(&pda_str, "PDA")
+                    } else {
+                        // This case is prevented by clap's `group` attribute, but we handle it anyway
+                        fail(ExitCode::InvalidInput, "You must provide either --start-index or --pda.");
+                    };
+
+                    if text { println!("Fetching account data for PDA: {}", tick_array_pda); }
+                    if raw {
+                        let account = rpc_client.get_account(&tick_array_pda).await
+                            .unwrap_or_else(|e| fail_fetch(format!("tick array {}", tick_array_pda), &e));
+                        print_raw_tick_array(&account, &tick_array_pda, &program_id);
+                        if debug_seeds && start_index.is_none() {
+                            println!("\n--debug-seeds with --pda needs the decoded start index; rerun without --raw.");
+                        }
+                        return;
+                    }
+                    // A --pda from another pool would be shown with this pool's tick spacing
+                    let tick_array = fetch_pool_account::<TickArrayState>(&rpc_client, &tick_array_pda, &pool_pubkey, &program_id).await;
+                    
+                    // We still need tick_spacing from the main pool state for correct visualization
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+                    let slots = tick_array_slots(&tick_array, pool_state.tick_spacing, &converter, format);
+                    if !text {
+                        let slots: Vec<ArraySlotJson> = slots.iter().map(ArraySlotJson::new).collect();
+                        println!("{}", serde_json::to_string_pretty(&slots).expect("Failed to serialize JSON"));
+                        return;
+                    }
+                    
+                    println!("Done.");
+
+                    // Call the visualization function, now passing the PDA to be printed
+                    let view = if grid {
+                        ArrayView::Grid
+                    } else if compact {
+                        ArrayView::Compact { format, prices }
+                    } else {
+                        ArrayView::Blocks { format, prices }
+                    };
+                    print_tick_array_visualization(&tick_array, &slots, pool_state.tick_spacing, &tick_array_pda, view, palette);
+                    let current_epoch = rpc_client.get_epoch().await
+                        .inspect_err(|e| eprintln!("Warning: Failed to fetch the current epoch: {}", e))
+                        .ok();
+                    println!("Last updated in {}.", format_epoch(tick_array.recent_epoch, current_epoch));
+                    println!();
+                    print_tick_array_validation(&validate_tick_array(&tick_array, pool_state.tick_spacing), palette);
+                    if show_fees {
+                        println!();
+                        print_tick_array_growths(&tick_array, &pool_state);
+                    }
+                    if debug_seeds && start_index.is_none() {
+                        println!();
+                        // The seeds come from the decoded start index; re-deriving them checks the given PDA
+                        print_tick_array_seeds(&pool_pubkey, tick_array.start_tick_index, &program_id);
+                        if derive_tick_array_pda(&pool_pubkey, tick_array.start_tick_index, &program_id) != tick_array_pda {
+                            println!("  - WARNING: these seeds do not derive the given PDA; it belongs to another pool or program.");
+                        }
+                    }
+                },
+                RpcCommands::PdaInfo { pool_id, pda } => {
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+                    let tick_array_pda = parse_pubkey(&pda, "PDA");
+
+                    // 1. The start index is not recoverable from the PDA itself, so read it from the account
+                    println!("Fetching tick array and pool state...");
+                    let tick_array = fetch_clmm_account::<TickArrayState>(&rpc_client, &tick_array_pda, &program_id).await;
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+                    println!("Done.");
+
+                    let helper = TickArrayHelper { tick_spacing: pool_state.tick_spacing };
+                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+                    let start_index = tick_array.start_tick_index;
+
+                    println!("\n--- PDA Info for {} ---", tick_array_pda);
+                    print_tick_array_ranges(&helper, &converter, start_index, prices);
+                    println!("  - Initialized Ticks: {}/{}", tick_array.initialized_tick_count, TICK_ARRAY_SIZE);
+                    println!("  - Array Pool ID:     {}", tick_array.pool_id);
+
+                    // 2. Cross-check the account against the pool it was requested for
+                    if tick_array.pool_id != pool_pubkey {
+                        println!("\nWARNING: This tick array belongs to pool {}, not {}.", tick_array.pool_id, pool_pubkey);
+                    } else if derive_tick_array_pda(&pool_pubkey, start_index, &program_id) != tick_array_pda {
+                        println!("\nWARNING: {} is not the canonical PDA for start index {} of this pool.", tick_array_pda, start_index);
+                    }
+                },
+                RpcCommands::LiquidityAt { pool_id, price, format } => {
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+
+                    println!("Fetching pool info and bitmaps...");
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+                    let all_initialized_arrays = fetch_initialized_arrays(&rpc_client, &pool_pubkey, &program_id, &pool_state).await;
+
+                    println!("Fetching {} initialized tick arrays...", all_initialized_arrays.len());
+                    let mut all_ticks = fetch_initialized_ticks(&rpc_client, &pool_pubkey, &program_id, &all_initialized_arrays).await;
+                    all_ticks.sort_by_key(|(tick, _)| *tick);
+                    println!("Done.");
+
+                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+                    let price_input = match format {
+                        HumanPriceFormat::T0PerT1 => PriceInput::Token0PerToken1Human { price },
+                        HumanPriceFormat::T1PerT0 => PriceInput::Token1PerToken0Human { price },
+                    };
+                    let target_tick = converter.price_to_tick(price_input);
+                    let liquidity = cumulative_liquidity_at(&all_ticks, target_tick.0);
+
+                    println!("\n--- Active Liquidity at Price {} ({:?}) ---", prices.format(price), format);
+                    println!("  - Target Tick:       {}", target_tick);
+                    println!("  - Active Liquidity:  {} ({})", liquidity, format_liquidity(liquidity.max(0) as u128));
+                    println!("  - Current Tick:      {}", pool_state.tick_current);
+                    println!("  - Current Liquidity: {} ({})", pool_state.liquidity, format_liquidity(pool_state.liquidity));
+                },
+                RpcCommands::NextTick { pool_id, from_tick, direction } => {
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+
+                    println!("Fetching pool info and bitmaps...");
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+                    let from_tick = from_tick.unwrap_or(pool_state.tick_current);
+                    if !(MIN_TICK..=MAX_TICK).contains(&from_tick) {
+                        fail(ExitCode::InvalidInput, format!("--from-tick {} is outside [{}, {}]", from_tick, MIN_TICK, MAX_TICK));
+                    }
+                    let all_initialized_arrays = fetch_initialized_arrays(&rpc_client, &pool_pubkey, &program_id, &pool_state).await;
+
+                    // 1. Candidate arrays from the bitmaps, nearest first: the array holding from_tick, then beyond it
+                    let helper = TickArrayHelper { tick_spacing: pool_state.tick_spacing };
+                    let from_array = helper.get_array_start_index(Tick(from_tick));
+                    let candidates = walk_initialized_arrays(&all_initialized_arrays, from_array.0, direction);
+
+                    // 2. Fetch one array at a time and scan its slots, like the program's next_initialized_tick;
+                    //    the bitmap bounds the search, so it ends when the candidates run out
+                    let mut fetched = 0;
+                    let mut found = None;
+                    for &start_index in &candidates {
+                        fetched += 1;
+                        let ticks = fetch_initialized_tick_states(&rpc_client, &pool_pubkey, &program_id, &[start_index]).await;
+                        found = match direction {
+                            TickDirection::Down => ticks.into_iter().filter(|&(tick, _, _)| tick <= from_tick).max_by_key(|&(tick, _, _)| tick),
+                            TickDirection::Up => ticks.into_iter().filter(|&(tick, _, _)| tick >= from_tick).min_by_key(|&(tick, _, _)| tick),
+                        };
+                        if found.is_some() {
+                            break;
+                        }
+                    }
+                    println!("Done.");
+
+                    println!("\n--- Next Initialized Tick {:?} from {} ---", direction, from_tick);
+                    println!("  - Candidate arrays (bitmaps): {}, fetched: {}", candidates.len(), fetched);
+                    match found {
+                        Some((tick, liquidity_net, liquidity_gross)) => {
+                            let distance = tick - pool_state.tick_current;
+                            println!("  - Tick:            {} (array {})", tick, helper.get_array_start_index(Tick(tick)));
+                            println!("  - Liquidity Net:   {}", liquidity_net);
+                            println!("  - Liquidity Gross: {}", liquidity_gross);
+                            println!(
+                                "  - Distance from current tick {}: {:+} ticks ({:+.2} bps)",
+                                pool_state.tick_current, distance, (Q_RATIO.powi(distance) - 1.0) * 10_000.0
+                            );
+                            let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+                            converter.print_all_prices(Tick(tick), prices);
+                        }
+                        None => println!("  - No initialized tick at or {} tick {}.", match direction {
+                            TickDirection::Down => "below",
+                            TickDirection::Up => "above",
+                        }, from_tick),
+                    }
+                },
+                RpcCommands::NextArray { pool_id, from_tick, direction, count } => {
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+                    let from_tick = from_tick.unwrap_or(pool_state.tick_current);
+                    if !(MIN_TICK..=MAX_TICK).contains(&from_tick) {
+                        fail(ExitCode::InvalidInput, format!("--from-tick {} is outside [{}, {}]", from_tick, MIN_TICK, MAX_TICK));
+                    }
+                    let all_initialized_arrays = fetch_initialized_arrays(&rpc_client, &pool_pubkey, &program_id, &pool_state).await;
+
+                    let helper = TickArrayHelper { tick_spacing: pool_state.tick_spacing };
+                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+                    let from_array = helper.get_array_start_index(Tick(from_tick));
+                    let arrays = walk_initialized_arrays(&all_initialized_arrays, from_array.0, direction);
+
+                    println!("--- Next Initialized Arrays {:?} from Tick {} (array {}) ---", direction, from_tick, from_array);
+                    for (i, &start_index) in arrays.iter().take(count).enumerate() {
+                        let bitmap = match BitmapPosition::from_start_index(start_index, pool_state.tick_spacing) {
+                            Some(BitmapPosition { location: BitmapLocation::Default, word, bit }) => format!("default bitmap word {} bit {}", word, bit),
+                            Some(BitmapPosition { location: BitmapLocation::Positive { chunk }, word, bit }) => format!("extension positive[{}] word {} bit {}", chunk, word, bit),
+                            Some(BitmapPosition { location: BitmapLocation::Negative { chunk }, word, bit }) => format!("extension negative[{}] word {} bit {}", chunk, word, bit),
+                            None => "outside the bitmaps".to_string(),
+                        };
+                        let (tick_lower, tick_upper) = helper.get_array_valid_tick_range(ArrayStartIndex(start_index));
+                        println!("  {}. Start Index: {} ({})", i + 1, start_index, bitmap);
+                        println!("     PDA:   {}", derive_tick_array_pda(&pool_pubkey, start_index, &program_id));
+                        println!("     Ticks: [{}, {}]", tick_lower, tick_upper);
+                        println!(
+                            "     T0/T1: {}",
+                            prices.range(
+                                converter.tick_to_price(tick_lower, PriceInput::Token0PerToken1Human { price: 0.0 }),
+                                converter.tick_to_price(tick_upper, PriceInput::Token0PerToken1Human { price: 0.0 }),
+                            )
+                        );
+                        println!(
+                            "     T1/T0: {}",
+                            prices.range(
+                                converter.tick_to_price(tick_lower, PriceInput::Token1PerToken0Human { price: 0.0 }),
+                                converter.tick_to_price(tick_upper, PriceInput::Token1PerToken0Human { price: 0.0 }),
+                            )
+                        );
+                    }
+                    if arrays.len() < count {
+                        println!("Only {} initialized arrays at or {} tick {}.", arrays.len(), match direction {
+                            TickDirection::Down => "below",
+                            TickDirection::Up => "above",
+                        }, from_tick);
+                    }
+                },
+                RpcCommands::Lwap { pool_id } => {
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+
+                    println!("Fetching pool info and bitmaps...");
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+                    let all_initialized_arrays = fetch_initialized_arrays(&rpc_client, &pool_pubkey, &program_id, &pool_state).await;
+
+                    println!("Fetching {} initialized tick arrays...", all_initialized_arrays.len());
+                    let all_ticks = fetch_initialized_tick_states(&rpc_client, &pool_pubkey, &program_id, &all_initialized_arrays).await;
+                    println!("Done.");
+
+                    // 1. Each position adds its liquidity to the gross of both of its ticks, so weighting
+                    //    ticks by liquidity_gross centers every position at its midpoint tick
+                    let total_gross: f64 = all_ticks.iter().map(|&(_, _, gross)| gross as f64).sum();
+                    if all_ticks.is_empty() || total_gross == 0.0 {
+                        fail(ExitCode::Failure, "The pool has no initialized ticks; there is no liquidity to average.");
+                    }
+                    let lwap_tick = all_ticks.iter().map(|&(tick, _, gross)| tick as f64 * gross as f64).sum::<f64>() / total_gross;
+                    let gross_above: f64 = all_ticks.iter()
+                        .filter(|&&(tick, _, _)| tick > pool_state.tick_current)
+                        .map(|&(_, _, gross)| gross as f64)
+                        .sum();
+
+                    // 2. Prices at the fractional LWAP tick
+                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+                    let t1_per_t0 = Q_RATIO.powf(lwap_tick) * converter.decimal_adjustment();
+                    let distance = lwap_tick - pool_state.tick_current as f64;
+
+                    println!("\n--- Liquidity-Weighted Average Price for {} ---", pool_id);
+                    println!("  - Initialized Ticks: {} in {} arrays", all_ticks.len(), all_initialized_arrays.len());
+                    println!("  - LWAP Tick:         {:.2}", lwap_tick);
+                    println!("  - LWAP Price:        {} T0/T1, {} T1/T0", prices.format(1.0 / t1_per_t0), prices.format(t1_per_t0));
+                    println!("  - Current Tick:      {}", pool_state.tick_current);
+                    println!("  - Distance:          {:+.2} ticks ({:+.4}% raw price)", distance, (Q_RATIO.powf(distance) - 1.0) * 100.0);
+                    println!(
+                        "  - Gross Liquidity:   {:.2}% above the current tick, {:.2}% at or below",
+                        gross_above / total_gross * 100.0,
+                        100.0 - gross_above / total_gross * 100.0
+                    );
+                    let side = if distance > 0.0 { "above" } else if distance < 0.0 { "below" } else { "at" };
+                    println!("LP liquidity is centered {} the current price (in raw token1/token0 terms).", side);
+                },
+                RpcCommands::Twap { pool_id, seconds } => {
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+
+                    println!("Fetching pool state and observation account...");
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+
+                    let observation_state = fetch_clmm_account::<ObservationState>(&rpc_client, &pool_state.observation_key, &program_id).await;
+                    println!("Done.");
+
+                    println!("\n--- TWAP for {} ---", pool_id);
+                    println!("  - Observation Account: {}", pool_state.observation_key);
+                    println!("  - Latest Observation Index: {}", observation_state.observation_index);
+
+                    let Some((avg_tick, window)) = compute_twap_tick(&observation_state, seconds) else {
+                        fail(ExitCode::Failure, "Not enough initialized observations to compute a TWAP.");
+                    };
+
+                    if window < seconds {
+                        println!("  - Note: Only {}s of history is available; requested window was {}s.", window, seconds);
+                    }
+                    println!("  - Window: {}s", window);
+                    println!("  - Average Tick: {}", avg_tick);
+                    println!("  - Spot Tick:    {}", pool_state.tick_current);
+
+                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+                    println!();
+                    converter.print_all_prices(Tick(avg_tick), prices);
+                },
+                RpcCommands::Depth { pool_id, pct } => {
+                    if !(pct > 0.0 && pct < 100.0) {
+                        fail(ExitCode::InvalidInput, "--pct must be between 0 and 100.");
+                    }
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+
+                    // 1. Fetch the pool and the initialized arrays overlapping the ±pct window
+                    println!("Fetching pool info and bitmaps...");
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+                    let helper = TickArrayHelper { tick_spacing: pool_state.tick_spacing };
+
+                    let sqrt_price = q64_to_f64(pool_state.sqrt_price_x64);
+                    let sqrt_price_lower = sqrt_price * (1.0 - pct / 100.0).sqrt();
+                    let sqrt_price_upper = sqrt_price * (1.0 + pct / 100.0).sqrt();
+                    let tick_lower = converter.raw_price_to_tick(sqrt_price_lower.powi(2));
+                    let tick_upper = converter.raw_price_to_tick(sqrt_price_upper.powi(2));
+
+                    let window_start = helper.get_array_start_index(tick_lower);
+                    let window_end = helper.get_array_start_index(tick_upper);
+                    let window_arrays: Vec<i32> = fetch_initialized_arrays(&rpc_client, &pool_pubkey, &program_id, &pool_state).await
+                        .into_iter()
+                        .filter(|&start_index| (window_start..=window_end).contains(&ArrayStartIndex(start_index)))
+                        .collect();
+
+                    println!("Fetching {} initialized tick arrays in the window...", window_arrays.len());
+                    let mut all_ticks = fetch_initialized_ticks(&rpc_client, &pool_pubkey, &program_id, &window_arrays).await;
+                    all_ticks.sort_by_key(|(tick, _)| *tick);
+                    println!("Done.");
+
+                    // 2. Walk the liquidity in both directions from the current price
+                    let walk = |target_sqrt_price| depth_to_sqrt_price(
+                        &all_ticks, &converter, pool_state.tick_current, sqrt_price, pool_state.liquidity, target_sqrt_price,
+                    );
+                    let (down_0, down_1, down_crossed) = walk(sqrt_price_lower);
+                    let (up_0, up_1, up_crossed) = walk(sqrt_price_upper);
+                    let scale_0 = 10f64.powi(pool_state.mint_decimals_0 as i32);
+                    let scale_1 = 10f64.powi(pool_state.mint_decimals_1 as i32);
+
+                    println!("\n--- Depth within ±{}% for {} ---", pct, pool_id);
+                    println!("  - Current Tick:      {}", pool_state.tick_current);
+                    println!("  - Current Price:     {} (T1/T0)", prices.format(converter.tick_to_price(Tick(pool_state.tick_current), PriceInput::Token1PerToken0Human { price: 0.0 })));
+                    println!("  - Current Liquidity: {} ({})", pool_state.liquidity, format_liquidity(pool_state.liquidity));
+
+                    println!("\nPrice down {}% to tick {} (buy-t1: sell token0 for token1):", pct, tick_lower);
+                    println!("  - Token 1 available: {:.6} t1", down_1 / scale_1);
+                    println!("  - Token 0 needed:    ~{:.6} t0", down_0 / scale_0);
+                    println!("  - Initialized ticks crossed: {}", down_crossed);
+
+                    println!("\nPrice up {}% to tick {} (buy-t0: sell token1 for token0):", pct, tick_upper);
+                    println!("  - Token 0 available: {:.6} t0", up_0 / scale_0);
+                    println!("  - Token 1 needed:    ~{:.6} t1", up_1 / scale_1);
+                    println!("  - Initialized ticks crossed: {}", up_crossed);
+
+                    println!("\nAmounts exclude swap fees; \"needed\" is the input before fees.");
+                },
+                RpcCommands::SimulateSwap { pool_id, direction, amount_in, price_limit, sqrt_price_limit, format } => {
+                    if amount_in.is_nan() || amount_in <= 0.0 {
+                        fail(ExitCode::InvalidInput, "--amount-in must be positive.");
+                    }
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+
+                    // 1. Fetch the pool, its fee tier and the arrays on the swap side
+                    println!("Fetching pool info, fee config and bitmaps...");
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+
+                    // 2. Resolve the limit to an exact sqrt price: a human price goes through its tick, rounded
+                    //    towards the current price so the walk never passes the given price
+                    let sqrt_price_limit_x64 = match (price_limit, sqrt_price_limit) {
+                        (Some(price), _) => {
+                            let price_input = match format {
+                                HumanPriceFormat::T0PerT1 => PriceInput::Token0PerToken1Human { price },
+                                HumanPriceFormat::T1PerT0 => PriceInput::Token1PerToken0Human { price },
+                            };
+                            let raw_price = converter.to_raw_price(price_input);
+                            if !(raw_price.is_finite() && raw_price > 0.0) {
+                                fail(ExitCode::InvalidInput, format!("--price-limit {} does not convert to a positive raw price.", price));
+                            }
+                            let rounding = match direction {
+                                SwapDirection::BuyT1 => RoundingMode::Ceil,
+                                SwapDirection::BuyT0 => RoundingMode::Floor,
+                            };
+                            let tick = converter.price_to_tick_rounded(price_input, rounding);
+                            Some(tick.sqrt_price().unwrap_or_else(|e| fail(ExitCode::InvalidInput, format!("--price-limit: {}", e))).0)
+                        }
+                        (None, limit) => limit,
+                    };
+                    if let Some(limit) = sqrt_price_limit_x64 {
+                        // The program rejects a limit at or behind the current price, or at the sqrt price bounds
+                        let (on_swap_side, side) = match direction {
+                            SwapDirection::BuyT1 => (limit > MIN_SQRT_PRICE_X64 && limit < pool_state.sqrt_price_x64, "below"),
+                            SwapDirection::BuyT0 => (limit < MAX_SQRT_PRICE_X64 && limit > pool_state.sqrt_price_x64, "above"),
+                        };
+                        if !on_swap_side {
+                            fail(ExitCode::InvalidInput, format!(
+                                "the price limit (sqrt_price_x64 {}) must be {} the current sqrt price {} for {:?} and strictly inside ({}, {}).",
+                                limit, side, pool_state.sqrt_price_x64, direction, MIN_SQRT_PRICE_X64, MAX_SQRT_PRICE_X64
+                            ));
+                        }
+                    }
+                    let (all_ticks, fee_rate) = fetch_swap_liquidity(&rpc_client, &pool_pubkey, &program_id, &pool_state, direction).await;
+
+                    // 3. Walk the swap
+                    let (in_label, out_label, in_decimals, out_decimals) = match direction {
+                        SwapDirection::BuyT1 => ("t0", "t1", pool_state.mint_decimals_0, pool_state.mint_decimals_1),
+                        SwapDirection::BuyT0 => ("t1", "t0", pool_state.mint_decimals_1, pool_state.mint_decimals_0),
+                    };
+                    let in_scale = 10f64.powi(in_decimals as i32);
+                    let out_scale = 10f64.powi(out_decimals as i32);
+                    let sqrt_price = q64_to_f64(pool_state.sqrt_price_x64);
+                    let result = simulate_swap(
+                        &all_ticks, &converter, direction, pool_state.tick_current, sqrt_price, pool_state.liquidity, amount_in * in_scale, fee_rate,
+                        sqrt_price_limit_x64,
+                    );
+
+                    let spot_price = out_per_in_price(&converter, direction, sqrt_price);
+                    let end_price = out_per_in_price(&converter, direction, result.end_sqrt_price);
+                    let amount_in_human = result.amount_in / in_scale;
+                    let amount_out_human = result.amount_out / out_scale;
+                    let effective_price = if amount_in_human > 0.0 { amount_out_human / amount_in_human } else { 0.0 };
+
+                    println!("\n--- Swap Simulation ({:?}) for {} ---", direction, pool_id);
+                    println!("  - Fee Rate:          {:.4}%", fee_rate as f64 / FEE_RATE_DENOMINATOR as f64 * 100.0);
+                    println!("  - Amount In:         {:.6} {}", amount_in_human, in_label);
+                    println!("  - Fee:               {:.6} {}", result.fee / in_scale, in_label);
+                    println!("  - Amount Out:        {:.6} {}", amount_out_human, out_label);
+                    println!("  - Spot Price:        {} {}/{}", prices.format(spot_price), out_label, in_label);
+                    println!("  - Effective Price:   {} {}/{}", prices.format(effective_price), out_label, in_label);
+                    println!("  - Price Impact:      {:.4}% (incl. fee)", (1.0 - effective_price / spot_price) * 100.0);
+                    println!("  - Start Tick:        {}", pool_state.tick_current);
+                    println!("  - End Tick:          {}", converter.raw_price_to_tick(result.end_sqrt_price.powi(2)));
+                    println!("  - End Price:         {} {}/{}", prices.format(end_price), out_label, in_label);
+                    println!("  - Initialized Ticks Crossed: {}", result.ticks_crossed);
+                    if let Some(limit) = sqrt_price_limit_x64 {
+                        println!("  - Price Limit:       {} ({})", limit, format_swap_limit_price(&converter, SqrtPriceX64(limit), prices));
+                    }
+                    if result.hit_price_limit {
+                        println!(
+                            "\nPrice limit reached: partial fill, {:.6} of {} {} consumed (incl. fee); the rest is not swapped.",
+                            amount_in_human, amount_in, in_label
+                        );
+                    } else if !result.filled {
+                        println!(
+                            "\nInsufficient liquidity: only {:.6} of {} {} could be swapped before the initialized ticks ran out.",
+                            amount_in_human, amount_in, in_label
+                        );
+                    }
+                },
+                RpcCommands::ImpactTable { pool_id, direction, sizes } => {
+                    if sizes.iter().any(|size| size.is_nan() || *size <= 0.0) {
+                        fail(ExitCode::InvalidInput, "all --sizes must be positive.");
+                    }
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+
+                    // 1. Fetch the pool and the swap-side liquidity once for all sizes
+                    println!("Fetching pool info, fee config and bitmaps...");
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+                    let (all_ticks, fee_rate) = fetch_swap_liquidity(&rpc_client, &pool_pubkey, &program_id, &pool_state, direction).await;
+
+                    let (in_label, out_label, in_decimals, out_decimals) = match direction {
+                        SwapDirection::BuyT1 => ("t0", "t1", pool_state.mint_decimals_0, pool_state.mint_decimals_1),
+                        SwapDirection::BuyT0 => ("t1", "t0", pool_state.mint_decimals_1, pool_state.mint_decimals_0),
+                    };
+                    let in_scale = 10f64.powi(in_decimals as i32);
+                    let out_scale = 10f64.powi(out_decimals as i32);
+                    let sqrt_price = q64_to_f64(pool_state.sqrt_price_x64);
+                    let spot_price = out_per_in_price(&converter, direction, sqrt_price);
+
+                    println!("\n--- Price Impact ({:?}) for {} ---", direction, pool_id);
+                    println!("Spot Price: {} {}/{} | Fee Rate: {:.4}%", prices.format(spot_price), out_label, in_label, fee_rate as f64 / FEE_RATE_DENOMINATOR as f64 * 100.0);
+                    println!(
+                        "\n{:<20} | {:<20} | {:<20} | {:<12} | {:<8} | Status",
+                        format!("Amount In ({})", in_label), format!("Amount Out ({})", out_label), format!("Eff. Price ({}/{})", out_label, in_label), "Impact", "Crossed"
+                    );
+                    println!("{:-<110}", "");
+
+                    // 2. Walk each size over the same ticks
+                    for amount_in in sizes {
+                        let result = simulate_swap(
+                            &all_ticks, &converter, direction, pool_state.tick_current, sqrt_price, pool_state.liquidity, amount_in * in_scale, fee_rate, None,
+                        );
+                        let amount_in_human = result.amount_in / in_scale;
+                        let amount_out_human = result.amount_out / out_scale;
+                        let effective_price = if amount_in_human > 0.0 { amount_out_human / amount_in_human } else { 0.0 };
+                        let status = if result.filled {
+                            "ok".to_string()
+                        } else {
+                            format!("insufficient liquidity (max ~{:.6} {})", amount_in_human, in_label)
+                        };
+                        println!(
+                            "{:<20} | {:<20.6} | {:<20} | {:<12} | {:<8} | {}",
+                            amount_in, amount_out_human, prices.format(effective_price),
+                            format!("{:.4}%", (1.0 - effective_price / spot_price) * 100.0), result.ticks_crossed, status
+                        );
+                    }
+                    println!("\nImpact compares the effective price with the spot price and includes the trade fee.");
+                },
+                RpcCommands::FindPools { mint_a, mint_b } => {
+                    let mint_a = parse_pubkey(&mint_a, "mint A");
+                    let mint_b = parse_pubkey(&mint_b, "mint B");
+                    if mint_a == mint_b {
+                        fail(ExitCode::InvalidInput, "--mint-a and --mint-b must differ.");
+                    }
+
+                    // 1. One getProgramAccounts per mint order; the pool may store either mint as token 0
+                    println!("Searching CLMM pools for the pair (getProgramAccounts)...");
+                    let mut pools: Vec<(Pubkey, PoolState)> = Vec::new();
+                    for (mint_0, mint_1) in [(mint_a, mint_b), (mint_b, mint_a)] {
+                        let filters = vec![
+                            RpcFilterType::DataSize(POOL_STATE_LEN as u64),
+                            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(0, &POOL_STATE_DISCRIMINATOR)),
+                            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(POOL_STATE_TOKEN_MINT_0_OFFSET, mint_0.as_ref())),
+                            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(POOL_STATE_TOKEN_MINT_1_OFFSET, mint_1.as_ref())),
+                        ];
+                        let accounts = rpc_client.get_program_accounts(&program_id, filters).await
+                            .unwrap_or_else(|e| fail_get_program_accounts(&e));
+                        for (pubkey, account) in accounts {
+                            match decode_clmm_account::<PoolState>(&pubkey, &account.owner, &account.data, &program_id) {
+                                Ok(pool_state) => pools.push((pubkey, pool_state)),
+                                Err(e) => eprintln!("Warning: Skipping pool: {}", e),
+                            }
+                        }
+                    }
+                    pools.sort_by_key(|(_, pool_state)| std::cmp::Reverse(pool_state.liquidity));
+
+                    // 2. Fee tiers: the distinct AmmConfig accounts, in one batch
+                    let mut config_keys: Vec<Pubkey> = pools.iter().map(|(_, pool_state)| pool_state.amm_config).collect();
+                    config_keys.sort();
+                    config_keys.dedup();
+                    let config_accounts = match rpc_client.get_multiple_accounts_at(&config_keys, None).await {
+                        Ok((_, accounts)) => accounts,
+                        Err(e) => {
+                            eprintln!("Warning: Failed to fetch the AmmConfig accounts, fee rates omitted: {}", e);
+                            vec![None; config_keys.len()]
+                        }
+                    };
+                    let fee_rates: std::collections::HashMap<Pubkey, u32> = config_keys.iter().zip(config_accounts)
+                        .filter_map(|(key, account)| {
+                            let account = account?;
+                            decode_clmm_account::<AmmConfig>(key, &account.owner, &account.data, &program_id).ok()
+                                .map(|config| (*key, config.trade_fee_rate))
+                        })
+                        .collect();
+
+                    // 3. One row per pool, deepest first. Prices are quoted as B per A whichever mint is token 0,
+                    //    so pools of both orders compare directly.
+                    println!("\n--- CLMM Pools for A = {}, B = {} ---", mint_a, mint_b);
+                    if pools.is_empty() {
+                        println!("No pools found.");
+                    } else {
+                        println!("{:<44} | {:>7} | {:>8} | {:<2} | {:>16} | {:>10} | Status", "Pool ID", "Spacing", "Fee", "T0", "Price (B per A)", "Liquidity");
+                        println!("{:-<115}", "");
+                        for (pubkey, pool_state) in &pools {
+                            let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+                            let a_is_token_0 = pool_state.token_mint_0 == mint_a;
+                            let price_template = if a_is_token_0 {
+                                PriceInput::Token1PerToken0Human { price: 0.0 }
+                            } else {
+                                PriceInput::Token0PerToken1Human { price: 0.0 }
+                            };
+                            let price = converter.raw_to_price(SqrtPriceX64(pool_state.sqrt_price_x64).to_raw_price(), price_template);
+                            let status = pool_state.pool_status();
+                            let fee = fee_rates.get(&pool_state.amm_config)
+                                .map_or("n/a".to_string(), |&rate| format!("{:.4}%", rate as f64 / FEE_RATE_DENOMINATOR as f64 * 100.0));
+                            println!(
+                                "{:<44} | {:>7} | {:>8} | {:<2} | {:>16} | {:>10} | {}",
+                                pubkey,
+                                pool_state.tick_spacing,
+                                fee,
+                                if a_is_token_0 { "A" } else { "B" },
+                                prices.format(price),
+                                format_liquidity(pool_state.liquidity),
+                                if status.0 == 0 { "ok".to_string() } else { format!("paused ({:#010b})", status.0) }
+                            );
+                        }
+                        println!("\nFound {} pool(s).", pools.len());
+                    }
+                },
+                RpcCommands::Positions { pool_id, format, skip_owners } => {
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+
+                    // 1. Enumerate every PersonalPositionState of the pool
+                    println!("Fetching pool state and positions (getProgramAccounts)...");
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+                    let filters = vec![
+                        RpcFilterType::DataSize(PERSONAL_POSITION_STATE_LEN as u64),
+                        RpcFilterType::Memcmp(Memcmp::new_base58_encoded(0, &PERSONAL_POSITION_STATE_DISCRIMINATOR)),
+                        RpcFilterType::Memcmp(Memcmp::new_base58_encoded(PERSONAL_POSITION_POOL_ID_OFFSET, pool_pubkey.as_ref())),
+                    ];
+                    let accounts = rpc_client.get_program_accounts(&program_id, filters).await
+                        .unwrap_or_else(|e| fail_get_program_accounts(&e));
+                    let mut positions: Vec<(Pubkey, PersonalPositionState)> = accounts.iter()
+                        .filter_map(|(pubkey, account)| {
+                            match decode_clmm_account::<PersonalPositionState>(pubkey, &account.owner, &account.data, &program_id) {
+                                Ok(position) => Some((*pubkey, position)),
+                                Err(e) => {
+                                    eprintln!("Warning: Skipping position: {}", e);
+                                    None
+                                }
+                            }
+                        })
+                        .collect();
+                    positions.sort_by_key(|(_, position)| (position.tick_lower_index, position.tick_upper_index));
+
+                    // 2. Resolve the wallet holding each position NFT
+                    let owners: Vec<Option<Pubkey>> = if skip_owners {
+                        vec![None; positions.len()]
+                    } else {
+                        println!("Resolving {} position NFT holders...", positions.len());
+                        let mints: Vec<Pubkey> = positions.iter().map(|(_, position)| position.nft_mint).collect();
+                        let holder_accounts = rpc_client.get_largest_token_accounts_concurrent(&mints).await;
+                        let holder_keys: Vec<Pubkey> = holder_accounts.iter().flatten().flatten().copied().collect();
+                        let holder_data = rpc_client.get_multiple_accounts_data(&holder_keys).await.unwrap_or_default();
+                        let mut holder_data = holder_data.into_iter();
+                        holder_accounts.iter()
+                            .map(|holder| match holder {
+                                Ok(Some(_)) => holder_data.next().flatten().and_then(|data| read_token_account_owner(&data)),
+                                _ => None,
+                            })
+                            .collect()
+                    };
+                    println!("Done.");
+
+                    // 3. Print one row per position
+                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+                    let (price_template, format_label) = match format {
+                        HumanPriceFormat::T0PerT1 => (PriceInput::Token0PerToken1Human { price: 0.0 }, "T0/T1"),
+                        HumanPriceFormat::T1PerT0 => (PriceInput::Token1PerToken0Human { price: 0.0 }, "T1/T0"),
+                    };
+
+                    println!("\n--- Positions for {} ---", pool_id);
+                    println!(
+                        "{:<22} | {:<35} | {:<12} | {:<8} | {:<44} | NFT Mint",
+                        "Tick Range", format!("Price Range ({})", format_label), "Liquidity", "In Range", "Owner"
+                    );
+                    println!("{:-<180}", "");
+                    let mut in_range_count = 0;
+                    let mut in_range_liquidity: u128 = 0;
+                    for ((_, position), owner) in positions.iter().zip(&owners) {
+                        let price_lower = converter.tick_to_price(Tick(position.tick_lower_index), price_template);
+                        let price_upper = converter.tick_to_price(Tick(position.tick_upper_index), price_template);
+                        let (p_start, p_end) = if price_lower < price_upper { (price_lower, price_upper) } else { (price_upper, price_lower) };
+                        let in_range = position.tick_lower_index <= pool_state.tick_current && pool_state.tick_current < position.tick_upper_index;
+                        if in_range && position.liquidity > 0 {
+                            in_range_count += 1;
+                            in_range_liquidity += position.liquidity;
+                        }
+                        let owner = match owner {
+                            Some(owner) => owner.to_string(),
+                            None if skip_owners => "-".to_string(),
+                            None => "(unknown)".to_string(),
+                        };
+                        println!(
+                            "{:<22} | {:<35} | {:<12} | {:<8} | {:<44} | {}",
+                            format!("[{}, {}]", position.tick_lower_index, position.tick_upper_index),
+                            prices.range(p_start, p_end),
+                            format_liquidity(position.liquidity),
+                            if in_range { "yes" } else { "no" },
+                            owner,
+                            position.nft_mint
+                        );
+                    }
+
+                    println!("\nTotal positions: {}", positions.len());
+                    println!("In range at tick {}: {} positions, liquidity {} (pool reports {})",
+                        pool_state.tick_current, in_range_count, in_range_liquidity, pool_state.liquidity);
+                },
+                RpcCommands::VerifyBitmaps { pool_id } => {
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+
+                    // 1. Every array the bitmaps claim is initialized
+                    println!("Fetching pool info and bitmaps...");
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+                    let bitmap_arrays = fetch_initialized_arrays(&rpc_client, &pool_pubkey, &program_id, &pool_state).await;
+                    let pdas: Vec<Pubkey> = bitmap_arrays.iter()
+                        .map(|&start_index| derive_tick_array_pda(&pool_pubkey, start_index, &program_id))
+                        .collect();
+
+                    // 2. Fetch them in batches and compare each account with its derived start index
+                    println!("Fetching {} tick arrays marked in the bitmaps...", bitmap_arrays.len());
+                    let (_, accounts) = rpc_client.get_multiple_accounts_at(&pdas, None).await
+                        .unwrap_or_else(|e| fail_fetch("tick arrays", &e));
+                    let mut missing = Vec::new();
+                    let mut mismatched = Vec::new();
+                    for ((&start_index, pda), account) in bitmap_arrays.iter().zip(&pdas).zip(&accounts) {
+                        let Some(account) = account else {
+                            missing.push((start_index, *pda));
+                            continue;
+                        };
+                        match TickArrayRef::new(pda, &account.owner, &account.data, &program_id) {
+                            Ok(tick_array) if tick_array.start_tick_index() != start_index => mismatched.push((
+                                start_index,
+                                *pda,
+                                format!("account holds start_tick_index {}", tick_array.start_tick_index()),
+                            )),
+                            Ok(tick_array) if tick_array.pool_id() != pool_pubkey => mismatched.push((
+                                start_index,
+                                *pda,
+                                format!("account belongs to pool {}", tick_array.pool_id()),
+                            )),
+                            Ok(_) => {}
+                            Err(e) => mismatched.push((start_index, *pda, e)),
+                        }
+                    }
+
+                    // 3. Sweep all tick arrays of the pool and find those with no bit set
+                    println!("Sweeping all tick array accounts of the pool (getProgramAccounts)...");
+                    let unmarked = match rpc_client.get_program_accounts(&program_id, pool_tick_array_filters(&pool_pubkey)).await {
+                        Ok(swept) => {
+                            let mut unmarked: Vec<(i32, Pubkey)> = swept.iter()
+                                .filter_map(|(pubkey, account)| {
+                                    let tick_array = TickArrayRef::new(pubkey, &account.owner, &account.data, &program_id).ok()?;
+                                    let start_index = tick_array.start_tick_index();
+                                    (!bitmap_arrays.contains(&start_index)).then_some((start_index, *pubkey))
+                                })
+                                .collect();
+                            unmarked.sort();
+                            println!("Found {} tick array accounts.", swept.len());
+                            Some(unmarked)
+                        }
+                        Err(e) => {
+                            eprintln!("Warning: getProgramAccounts failed, skipping the unmarked-array check: {}", e);
+                            eprintln!("Many RPC providers disable getProgramAccounts; use --rpc-url with one that supports it.");
+                            None
+                        }
+                    };
+
+                    // 4. Report
+                    println!("\n--- Bitmap Verification for {} ---", pool_id);
+                    println!("Arrays marked in bitmaps: {}", bitmap_arrays.len());
+
+                    println!("\n1. Bit set but account missing: {}", missing.len());
+                    for (start_index, pda) in &missing {
+                        println!("  {:<15} {}", start_index, pda);
+                    }
+                    println!("\n2. Account exists but disagrees with its bit: {}", mismatched.len());
+                    for (start_index, pda, reason) in &mismatched {
+                        println!("  {:<15} {} ({})", start_index, pda, reason);
+                    }
+                    match &unmarked {
+                        Some(unmarked) => {
+                            println!("\n3. Account exists but no bit set: {}", unmarked.len());
+                            for (start_index, pubkey) in unmarked {
+                                println!("  {:<15} {}", start_index, pubkey);
+                            }
+                        }
+                        None => println!("\n3. Account exists but no bit set: (skipped, getProgramAccounts unavailable)"),
+                    }
+
+                    let inconsistencies = missing.len() + mismatched.len() + unmarked.as_ref().map_or(0, Vec::len);
+                    if inconsistencies == 0 {
+                        println!("\nOK: bitmaps and tick array accounts are consistent.");
+                    } else {
+                        println!("\nFAILED: {} inconsistencies found.", inconsistencies);
+                        exit_code = ExitCode::Failure;
+                    }
+                },
+                RpcCommands::OrphanArrays { pool_id, format } => {
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+
+                    // 1. The arrays the bitmaps mark as initialized
+                    println!("Fetching pool info and bitmaps...");
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+                    let bitmap_arrays = fetch_initialized_arrays(&rpc_client, &pool_pubkey, &program_id, &pool_state).await;
+
+                    // 2. Every tick array account of the pool
+                    println!("Sweeping all tick array accounts of the pool (getProgramAccounts)...");
+                    let swept = match rpc_client.get_program_accounts(&program_id, pool_tick_array_filters(&pool_pubkey)).await {
+                        Ok(swept) => swept,
+                        Err(e) => {
+                            eprintln!("Error: getProgramAccounts failed: {}", e);
+                            eprintln!(
+                                "Many RPC providers, including the public Solana endpoints, disable or restrict getProgramAccounts. \
+                                Retry with --rpc-url pointing at a provider that supports it (e.g. a dedicated Helius, Triton or QuickNode endpoint)."
+                            );
+                            std::process::exit(ExitCode::Network as i32);
+                        }
+                    };
+                    println!("Found {} tick array accounts, {} marked in the bitmaps.", swept.len(), bitmap_arrays.len());
+
+                    // 3. Classify: liquidity without a bit, or a bit without liquidity.
+                    //    Entries are (start_index, pda, header count, slots with liquidity_gross > 0)
+                    let mut unmarked_with_liquidity = Vec::new();
+                    let mut marked_but_empty = Vec::new();
+                    for (pubkey, account) in &swept {
+                        let tick_array = match TickArrayRef::new(pubkey, &account.owner, &account.data, &program_id) {
+                            Ok(tick_array) => tick_array,
+                            Err(e) => {
+                                eprintln!("Warning: skipping {}: {}", pubkey, e);
+                                continue;
+                            }
+                        };
+                        let start_index = tick_array.start_tick_index();
+                        let live_ticks = tick_array.initialized_ticks().count();
+                        let entry = (start_index, *pubkey, tick_array.initialized_tick_count(), live_ticks);
+                        let marked = bitmap_arrays.contains(&start_index);
+                        if !marked && live_ticks > 0 {
+                            unmarked_with_liquidity.push(entry);
+                        } else if marked && live_ticks == 0 {
+                            marked_but_empty.push(entry);
+                        }
+                    }
+                    unmarked_with_liquidity.sort();
+                    marked_but_empty.sort();
+
+                    // 4. Report
+                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+                    let helper = TickArrayHelper { tick_spacing: pool_state.tick_spacing };
+                    let (price_template, format_label) = match format {
+                        HumanPriceFormat::T0PerT1 => (PriceInput::Token0PerToken1Human { price: 0.0 }, "T0/T1"),
+                        HumanPriceFormat::T1PerT0 => (PriceInput::Token1PerToken0Human { price: 0.0 }, "T1/T0"),
+                    };
+                    let print_section = |title: &str, entries: &[(i32, Pubkey, u8, usize)]| {
+                        println!("\n{}: {}", title, entries.len());
+                        if entries.is_empty() {
+                            return;
+                        }
+                        println!(
+                            "{:<15} | {:<44} | {:<40} | Init. Ticks (header / live)",
+                            "Start Index", "PDA", format!("Price Range ({})", format_label)
+                        );
+                        println!("{:-<140}", "");
+                        for (start_index, pda, header_count, live_ticks) in entries {
+                            let (tick_start, tick_end) = helper.get_array_valid_tick_range(ArrayStartIndex(*start_index));
+                            let price_start = converter.tick_to_price(tick_start, price_template);
+                            let price_end = converter.tick_to_price(tick_end, price_template);
+                            let (p_start, p_end) = if price_start < price_end { (price_start, price_end) } else { (price_end, price_start) };
+                            println!(
+                                "{:<15} | {:<44} | {:<40} | {} / {}",
+                                start_index, pda.to_string(), prices.range(p_start, p_end), header_count, live_ticks
+                            );
+                        }
+                    };
+
+                    println!("\n--- Orphan Tick Arrays for {} ---", pool_id);
+                    print_section("Arrays with liquidity but no bitmap bit", &unmarked_with_liquidity);
+                    print_section("Arrays with a bitmap bit but no initialized ticks", &marked_but_empty);
+                    println!(
+                        "\nBoth states can be transient on-chain. Persistent entries point to a decoding bug in this tool or a pool anomaly; \
+                        `rpc verify-bitmaps` covers bits whose account is missing entirely."
+                    );
+                },
+                RpcCommands::VerifyLiquidity { pool_id, detailed } => {
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
 
-            let start_array_index = helper.get_array_start_index(tick_lower);
-            let end_array_index = helper.get_array_start_index(tick_upper);
-            
-            println!("--- Arrays Crossed by Price Range [{:.6}, {:.6}] (Format: {:?}) ---", price_lower, price_upper, format);
-            println!("  - Corresponding Tick Range: [{}, {}]", tick_lower, tick_upper);
-            println!("\n{:<15} | {:<25} | {}", "Array Start", "Tick Range", "Price Range (in specified format)");
-            println!("{:-<90}", "");
+                    println!("Fetching pool info and bitmaps...");
+                    let pool_state = fetch_clmm_account::<PoolState>(&rpc_client, &pool_pubkey, &program_id).await;
+                    let all_initialized_arrays = fetch_initialized_arrays(&rpc_client, &pool_pubkey, &program_id, &pool_state).await;
 
-            let step = helper.tick_indices_per_array();
+                    println!("Fetching {} initialized tick arrays...", all_initialized_arrays.len());
+                    let mut all_ticks = fetch_initialized_ticks(&rpc_client, &pool_pubkey, &program_id, &all_initialized_arrays).await;
+                    all_ticks.sort_by_key(|(tick, _)| *tick);
+                    println!("Done.");
 
-            // Handle both increasing and decreasing tick traversals
-            if start_array_index <= end_array_index {
-                let mut current_array_start = start_array_index;
-                while current_array_start <= end_array_index {
-                    let (tick_start, tick_end) = helper.get_array_tick_range(current_array_start);
-                    let price_start = converter.tick_to_price(tick_start, price_input_template);
-                    let price_end = converter.tick_to_price(tick_end, price_input_template);
-                    
-                    println!("{:<15} | [{:<11}, {:<11}] | [{:.8}, {:.8}]", current_array_start, tick_start, tick_end, price_start, price_end);
-                    current_array_start += step;
-                }
-            } else { // Handle case where ticks are decreasing
-                let mut current_array_start = start_array_index;
-                while current_array_start >= end_array_index {
-                    let (tick_start, tick_end) = helper.get_array_tick_range(current_array_start);
-                    let price_start = converter.tick_to_price(tick_start, price_input_template);
-                    let price_end = converter.tick_to_price(tick_end, price_input_template);
+                    // Active liquidity is the sum of liquidity_net at or below the current tick;
+                    // across all ticks the nets must cancel out, since every position adds and removes the same amount.
+                    let reconstructed = cumulative_liquidity_at(&all_ticks, pool_state.tick_current);
+                    let net_total = all_ticks.iter()
+                        .fold(0, |total, &(tick, liquidity_net)| cross_liquidity_net(total, liquidity_net, tick, TickDirection::Up));
+                    let difference = reconstructed.saturating_sub(i128::try_from(pool_state.liquidity).unwrap_or(i128::MAX));
+
+                    if detailed {
+                        let helper = TickArrayHelper { tick_spacing: pool_state.tick_spacing };
+                        println!("\n{:<15} | {:<6} | {:<40} | Running Total", "Array Start", "Ticks", "Net Contribution (ticks <= current)");
+                        println!("{:-<100}", "");
+                        let mut running_total: i128 = 0;
+                        for &start_index in &all_initialized_arrays {
+                            let (tick_start, tick_end) = helper.get_array_tick_range(ArrayStartIndex(start_index));
+                            let array_ticks: Vec<&(i32, i128)> = all_ticks.iter()
+                                .filter(|(tick, _)| (tick_start..=tick_end).contains(&Tick(*tick)))
+                                .collect();
+                            let contribution = array_ticks.iter()
+                                .filter(|(tick, _)| *tick <= pool_state.tick_current)
+                                .fold(0, |total, &&(tick, liquidity_net)| cross_liquidity_net(total, liquidity_net, tick, TickDirection::Up));
+                            // An overflow of the total was already reported by `cumulative_liquidity_at`
+                            running_total = running_total.saturating_add(contribution);
+                            let marker = if (tick_start..=tick_end).contains(&Tick(pool_state.tick_current)) { "  <- current tick" } else { "" };
+                            println!("{:<15} | {:<6} | {:<40} | {}{}", start_index, array_ticks.len(), contribution, running_total, marker);
+                        }
+                    }
 
-                    println!("{:<15} | [{:<11}, {:<11}] | [{:.8}, {:.8}]", current_array_start, tick_start, tick_end, price_start, price_end);
-                    current_array_start -= step;
-                }
+                    println!("\n--- Liquidity Verification for {} ---", pool_id);
+                    println!("  - Current Tick:            {}", pool_state.tick_current);
+                    println!("  - Initialized Ticks:       {}", all_ticks.len());
+                    let reconstructed_label = if reconstructed < 0 { "negative".to_string() } else { format_liquidity(reconstructed as u128) };
+                    println!("  - Reconstructed Liquidity: {} ({})", reconstructed, reconstructed_label);
+                    println!("  - PoolState.liquidity:     {} ({})", pool_state.liquidity, format_liquidity(pool_state.liquidity));
+                    println!("  - Difference:              {}", difference);
+                    println!("  - Sum of all liquidity_net: {} (expected 0)", net_total);
+
+                    if difference == 0 && net_total == 0 {
+                        println!("\nOK: reconstructed liquidity matches the pool state.");
+                    } else {
+                        println!("\nFAILED: the ticks do not add up. Arrays are likely missing from the bitmap readers or failed to fetch.");
+                        if !detailed {
+                            println!("Rerun with --detailed to see where the running total diverges.");
+                        }
+                        exit_code = ExitCode::Failure;
+                    }
+                },
+                RpcCommands::FullAnalysis { pool_id, format, snapshot, with_ticks, detailed } => {
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+                    let columns = if detailed {
+                        ArrayColumns::Detailed
+                    } else if with_ticks {
+                        ArrayColumns::Ticks
+                    } else {
+                        ArrayColumns::None
+                    };
+                    full_analysis_command(&rpc_client, &pool_pubkey, &program_id, format, snapshot, columns, palette, prices).await;
+                },
+                RpcCommands::Snapshot { pool_id, out } => {
+                    let pool_pubkey = parse_pubkey(&pool_id, "Pool ID");
+                    let snapshot = snapshot_command(&rpc_client, &pool_pubkey, &program_id).await;
+                    let json = serde_json::to_string_pretty(&snapshot).expect("Failed to serialize JSON");
+                    if let Err(e) = std::fs::write(&out, json + "\n") {
+                        fail(ExitCode::InvalidInput, format!("cannot write {}: {}", out.display(), e));
+                    }
+                    println!(
+                        "Wrote pool {} at slot {} ({} tick arrays) to {}",
+                        pool_id, snapshot.slot, snapshot.tick_arrays.len(), out.display()
+                    );
+                    if !snapshot.unreadable_arrays.is_empty() {
+                        eprintln!(
+                            "Warning: {} arrays marked in the bitmaps could not be read and are listed under unreadable_arrays.",
+                            snapshot.unreadable_arrays.len()
+                        );
+                    }
+                },
             }
-        }
-        Commands::DerivePda { pool_id, tick_spacing, decimals0, decimals1, tick, price } => {
-            if tick.is_none() && price.is_none() {
-                eprintln!("Error: You must provide either --tick or a price subcommand for derive-pda.");
-                return;
+            if json_output {
+                rpc_client.print_fetch_failures();
+            } else {
+                rpc_client.print_fetch_summary();
             }
-             if tick.is_some() && price.is_some() {
-                eprintln!("Error: You must provide either --tick OR a price subcommand, not both.");
-                return;
+            if exit_code != ExitCode::Success {
+                std::process::exit(exit_code as i32);
             }
+        }
+    }
+}
 
-            let helper = TickArrayHelper { tick_spacing };
-            let converter = TickConverter { decimals_0: decimals0, decimals_1: decimals1 };
+/// Derives the tick array PDA for a pool and array start index.
+fn derive_tick_array_pda(pool_pubkey: &Pubkey, start_index: i32, program_id: &Pubkey) -> Pubkey {
+    let seeds = tick_array_seeds(pool_pubkey, start_index);
+    Pubkey::find_program_address(&seeds.each_ref().map(Vec::as_slice), program_id).0
+}
 
-            let input_tick = if let Some(t) = tick {
-                t
-            } else { // price must be Some
-                converter.price_to_tick(price.unwrap())
-            };
+/// Finds the start index whose tick array PDA is `pda` by deriving every candidate: each multiple of
+/// `60 * tick_spacing` from the array holding MIN_TICK to the one holding MAX_TICK, nearest to tick 0
+/// first since most arrays sit there. Returns `None` if no candidate matches.
+fn find_tick_array_start_index(pool_pubkey: &Pubkey, pda: &Pubkey, helper: &TickArrayHelper, program_id: &Pubkey) -> Option<i32> {
+    let span = helper.tick_indices_per_array();
+    let (min_start, max_start) = helper.valid_array_start_range();
+    let (min_offset, max_offset) = (min_start.0 / span, max_start.0 / span);
+    (0..=max_offset.max(-min_offset))
+        .flat_map(|offset| [offset, -offset])
+        .filter(|offset| (min_offset..=max_offset).contains(offset))
+        .map(|offset| offset * span)
+        .find(|&start_index| derive_tick_array_pda(pool_pubkey, start_index, program_id) == *pda)
+}
 
-            let start_index = helper.get_array_start_index(input_tick);
+/// Prints the start index, full tick range and price range in both formats of a tick array, plus the
+/// valid ticks of an edge array.
+fn print_tick_array_ranges(helper: &TickArrayHelper, converter: &TickConverter, start_index: i32, prices: PriceDisplay) {
+    let (tick_start, tick_end) = helper.get_array_tick_range(ArrayStartIndex(start_index));
+    let (first_valid, last_valid) = helper.get_array_valid_tick_range(ArrayStartIndex(start_index));
+    let [(t1_low, t1_high), (t0_low, t0_high)] = array_price_ranges(helper, converter, start_index);
+    println!("  - Start Index:       {}", start_index);
+    println!("  - Tick Range:        [{}, {}]", tick_start, tick_end);
+    let start = ArrayStartIndex(start_index);
+    if (first_valid, last_valid) != (start.first_tick(), start.last_tick(helper.tick_spacing)) {
+        println!("  - Valid Ticks:       [{}, {}]", first_valid, last_valid);
+    }
+    println!("  - Price Range T1/T0: {}", prices.range(t1_low, t1_high));
+    println!("  - Price Range T0/T1: {}", prices.range(t0_low, t0_high));
+}
 
-            let pool_pubkey = Pubkey::from_str(&pool_id).expect("Invalid Pool ID");
-            let program_id = Pubkey::from_str(RAYDIUM_CLMM_PROGRAM_ID).unwrap();
-            
-            let (pda, _bump) = Pubkey::find_program_address(
-                &[
-                    TICK_ARRAY_SEED,
-                    pool_pubkey.as_ref(),
-                    &start_index.to_be_bytes(),
-                ],
-                &program_id,
-            );
+/// The human price range covered by the valid ticks of an array, as `[(low, high) in T1/T0, (low, high) in T0/T1]`.
+/// Edge arrays are clamped to the usable ticks, so no bound is the price of a tick that cannot exist.
+fn array_price_ranges(helper: &TickArrayHelper, converter: &TickConverter, start_index: i32) -> [(f64, f64); 2] {
+    let (first_valid, last_valid) = helper.get_array_valid_tick_range(ArrayStartIndex(start_index));
+    let t1_per_t0 = |tick| converter.tick_to_price(tick, PriceInput::Token1PerToken0Human { price: 0.0 });
+    let t0_per_t1 = |tick| converter.tick_to_price(tick, PriceInput::Token0PerToken1Human { price: 0.0 });
+    [(t1_per_t0(first_valid), t1_per_t0(last_valid)), (t0_per_t1(last_valid), t0_per_t1(first_valid))]
+}
 
-            println!("--- Tick Array PDA Derivation ---");
-            println!("  - Input Tick Index: {}", input_tick);
-            println!("  - Tick Spacing: {}", tick_spacing);
-            println!("  - This tick belongs to the array that *starts* at index: {}", start_index);
-            println!("  - Pool ID: {}", pool_id);
-            println!("  - Derived PDA: {}", pda);
-        }
-        Commands::Rpc(rpc_command) => {
-            match rpc_command {
-                RpcCommands::PoolState { pool_id, rpc_url } => {
-                    let rpc_client = RpcClient::new(rpc_url);
-                    let pool_pubkey = Pubkey::from_str(&pool_id).expect("Invalid Pool ID");
-                    let account_data = rpc_client.get_account_data(&pool_pubkey).expect("Failed to fetch pool state");
-                    
-                    let pool_state = PoolState::deserialize(&mut &account_data[8..]).expect("Failed to parse pool state");
+/// The seeds of a tick array PDA, in order: `"tick_array"`, the pool pubkey bytes, and the start
+/// index as a big-endian i32 (the program's encoding).
+fn tick_array_seeds(pool_pubkey: &Pubkey, start_index: i32) -> [Vec<u8>; 3] {
+    [TICK_ARRAY_SEED.to_vec(), pool_pubkey.to_bytes().to_vec(), start_index.to_be_bytes().to_vec()]
+}
 
-                    println!("--- Pool State for {} ---", pool_id);
-                    println!("  - Liquidity: {}", pool_state.liquidity);
-                    println!("  - Tick Spacing: {}", pool_state.tick_spacing);
-                    
-                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
-                    converter.print_all_prices(pool_state.tick_current);
-                },
-                RpcCommands::TokenMints { pool_id, rpc_url } => {
-                    let rpc_client = RpcClient::new(rpc_url);
-                    let pool_pubkey = Pubkey::from_str(&pool_id).expect("Invalid Pool ID");
-                    let account_data = rpc_client.get_account_data(&pool_pubkey).expect("Failed to fetch pool state");
-                    
-                    let pool_state = PoolState::deserialize(&mut &account_data[8..]).expect("Failed to parse pool state");
+/// Prints the exact seed bytes of a tick array PDA, to reconcile with a PDA derived elsewhere.
+/// Also shows the PDA a little-endian start index would give, a common mismatch with other SDKs.
+fn print_tick_array_seeds(pool_pubkey: &Pubkey, start_index: i32, program_id: &Pubkey) {
+    let to_hex = |bytes: &[u8]| bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+    let seeds = tick_array_seeds(pool_pubkey, start_index);
+    let (pda, bump) = Pubkey::find_program_address(&seeds.each_ref().map(Vec::as_slice), program_id);
+    let le_bytes = start_index.to_le_bytes();
+    let (le_pda, _) = Pubkey::find_program_address(&[&seeds[0], &seeds[1], &le_bytes], program_id);
+
+    println!("--- PDA Seeds (hex) ---");
+    println!("  - seed[0] \"{}\" ({} bytes): {}", String::from_utf8_lossy(&seeds[0]), seeds[0].len(), to_hex(&seeds[0]));
+    println!("  - seed[1] pool id ({} bytes): {}", seeds[1].len(), to_hex(&seeds[1]));
+    println!("  - seed[2] start index {} as big-endian i32 ({} bytes): {}", start_index, seeds[2].len(), to_hex(&seeds[2]));
+    println!("  - Program ID: {}", program_id);
+    println!("  - Bump: {}", bump);
+    println!("  - PDA: {}", pda);
+    println!("  - With a little-endian start index ({}), the PDA would be {} (wrong; not used by the program)", to_hex(&le_bytes), le_pda);
+}
 
-                    println!("--- Token Mints for Pool {} ---", pool_id);
-                    println!("  Token 0 (t0): {}", pool_state.token_mint_0);
-                    println!("  Token 1 (t1): {}", pool_state.token_mint_1);
-                },
-                RpcCommands::DefaultBitmap { pool_id, rpc_url } => {
-                    let rpc_client = RpcClient::new(rpc_url);
-                    let pool_pubkey = Pubkey::from_str(&pool_id).expect("Invalid Pool ID");
-                    let account_data = rpc_client.get_account_data(&pool_pubkey).expect("Failed to fetch pool state");
-                    let pool_state = PoolState::deserialize(&mut &account_data[8..]).expect("Failed to parse pool state");
-                    
-                    println!("--- Initialized Tick Arrays (Default Bitmap) ---");
-                    let helper = TickArrayHelper { tick_spacing: pool_state.tick_spacing };
-                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
-                    let initialized = read_default_bitmap(&pool_state.tick_array_bitmap, pool_state.tick_spacing);
-                    
-                    println!("Found {} initialized arrays:", initialized.len());
-                    for start_index in initialized {
-                        println!("  - Start Index: {}", start_index);
-                        let (tick_start, tick_end) = helper.get_array_tick_range(start_index);
-                        
-                        // T0 per T1
-                        let p_start_t0_t1 = converter.tick_to_price(tick_start, PriceInput::Token0PerToken1Human{price: 0.0});
-                        let p_end_t0_t1 = converter.tick_to_price(tick_end, PriceInput::Token0PerToken1Human{price: 0.0});
-                        println!("      T0/T1 (Token0/Token1) Price Range: [{:.6}, {:.6}]", p_start_t0_t1, p_end_t0_t1);
-                        
-                        // T1 per T0
-                        let p_start_t1_t0 = converter.tick_to_price(tick_start, PriceInput::Token1PerToken0Human{price: 0.0});
-                        let p_end_t1_t0 = converter.tick_to_price(tick_end, PriceInput::Token1PerToken0Human{price: 0.0});
-                        println!("      T1/T0 (Token1/Token0) Price Range: [{:.6}, {:.6}]", p_start_t1_t0, p_end_t1_t0);
-                    }
-                },
-                RpcCommands::GetSwapArraysBlind { pool_id, direction, format, favorable_pct, impact_pct, price, rpc_url } => {
-                    println!("--- Blind Swap Array Calculation for {} ---", pool_id);
-                    println!("    (Assumes all arrays in range are initialized)");
-                    
-                    let rpc_client = RpcClient::new(rpc_url);
-                    let pool_pubkey = Pubkey::from_str(&pool_id).expect("Invalid Pool ID");
-                    let program_id = Pubkey::from_str(RAYDIUM_CLMM_PROGRAM_ID).unwrap();
+// --- New Bitmap Reader Functions ---
 
-                    // 1. Fetch ONLY PoolState (Needed for tick_spacing, decimals, current_tick)
-                    println!("Fetching pool info...");
-                    let pool_state_data = rpc_client.get_account_data(&pool_pubkey).expect("Failed to fetch pool state");
-                    let pool_state = PoolState::deserialize(&mut &pool_state_data[8..]).expect("Failed to parse pool state");
-                    println!("Done.");
+/// Reads the default 1024-bit bitmap from the PoolState.
+fn read_default_bitmap(bitmap: &[u64; 16], tick_spacing: u16) -> Vec<i32> {
+    read_default_bitmap_positions(bitmap).iter().map(|position| position.start_index(tick_spacing)).collect()
+}
 
-                    // 2. Setup Helpers
-                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
-                    let helper = TickArrayHelper { tick_spacing: pool_state.tick_spacing };
+/// The set bits of the default bitmap, tagged with where they were read.
+fn read_default_bitmap_positions(bitmap: &[u64; 16]) -> Vec<BitmapPosition> {
+    let mut initialized = Vec::new();
 
-                    // 3. Determine Start Tick (Same as GetSwapArrays)
-                    let tick_start = match price {
-                        Some(p) => {
-                            let price_input = match format {
-                                HumanPriceFormat::T0PerT1 => PriceInput::Token0PerToken1Human { price: p },
-                                HumanPriceFormat::T1PerT0 => PriceInput::Token1PerToken0Human { price: p },
-                            };
-                            println!("Using --price {:.8} as start", p);
-                            converter.price_to_tick(price_input)
-                        },
-                        None => {
-                            println!("No --price provided. Using live pool tick: {}", pool_state.tick_current);
-                            pool_state.tick_current
-                        }
-                    };
-                    
-                    println!("Start Tick:    {}", tick_start);
-                    println!("Direction:     {:?}", direction);
-                    
-                    // 4. Calculate Tick Range based on Direction (Same as GetSwapArrays)
-                    let start_raw_price = converter.tick_to_raw_price(tick_start);
-                    let (tick_favorable, tick_impact) = match direction {
-                        SwapDirection::BuyT1 => { // Tick DECREASES
-                            let favorable_raw_price = start_raw_price * (1.0 + (favorable_pct / 100.0));
-                            let impact_raw_price = start_raw_price * (1.0 - (impact_pct / 100.0));
-                            (converter.raw_price_to_tick(favorable_raw_price), converter.raw_price_to_tick(impact_raw_price))
-                        },
-                        SwapDirection::BuyT0 => { // Tick INCREASES
-                            let favorable_raw_price = start_raw_price * (1.0 - (favorable_pct / 100.0));
-                            let impact_raw_price = start_raw_price * (1.0 + (impact_pct / 100.0));
-                            (converter.raw_price_to_tick(favorable_raw_price), converter.raw_price_to_tick(impact_raw_price))
-                        }
-                    };
-                    let (min_tick, max_tick) = (tick_favorable.min(tick_impact), tick_favorable.max(tick_impact));
-                    
-                    println!("Favorable Pct: {:.4}%", favorable_pct);
-                    println!("Impact Pct:    {:.4}%", impact_pct);
-                    println!("Calculated Tick Range:  [{}, {}]", min_tick, max_tick);
+    for (word_idx, &word) in bitmap.iter().enumerate() {
+        if word == 0 { continue; }
+        for bit_idx in 0..64 {
+            if (word & (1u64 << bit_idx)) != 0 {
+                initialized.push(BitmapPosition::in_default_bitmap(word_idx, bit_idx));
+            }
+        }
+    }
+    initialized
+}
 
-                    // 5. Calculate Potential Arrays BLINDLY
-                    let start_array_min = helper.get_array_start_index(min_tick);
-                    let start_array_max = helper.get_array_start_index(max_tick);
-                    let step = helper.tick_indices_per_array();
+/// Prints the arrays of a bitmap listing: each start index with its price range in both formats,
+/// or with only its on-grid tick range when `prices` is `None` (`--no-prices`).
+fn print_bitmap_arrays(initialized: &[i32], helper: &TickArrayHelper, converter: &TickConverter, prices: Option<PriceDisplay>) {
+    for &start_index in initialized {
+        let (tick_start, tick_end) = helper.get_array_valid_tick_range(ArrayStartIndex(start_index));
+        let Some(prices) = prices else {
+            println!("  - Start Index: {} (ticks [{}, {}])", start_index, tick_start, tick_end);
+            continue;
+        };
+        println!("  - Start Index: {}", start_index);
 
-                    let mut potential_arrays = Vec::new();
-                    let mut current_array_start = start_array_min;
-                    while current_array_start <= start_array_max {
-                        potential_arrays.push(current_array_start);
-                        current_array_start += step;
-                    }
-                    
-                    // 6. Define Core and Favorable Tick Ranges (Same as GetSwapArrays)
-                    let (core_min_tick, core_max_tick) = (tick_start.min(tick_impact), tick_start.max(tick_impact));
-                    let (favorable_min_tick, favorable_max_tick) = (tick_start.min(tick_favorable), tick_start.max(tick_favorable));
+        // T0 per T1
+        let p_start_t0_t1 = converter.tick_to_price(tick_start, PriceInput::Token0PerToken1Human{price: 0.0});
+        let p_end_t0_t1 = converter.tick_to_price(tick_end, PriceInput::Token0PerToken1Human{price: 0.0});
+        println!("      T0/T1 (Token0/Token1) Price Range: {}", prices.range(p_start_t0_t1, p_end_t0_t1));
 
-                    // 7. Split Potential Arrays into Core and Favorable (BLIND version)
-                    let mut core_arrays: Vec<i32> = potential_arrays.iter()
-                        .filter(|&&start_index| {
-                            let (_tick_start, tick_end) = helper.get_array_tick_range(start_index);
-                            (start_index <= core_max_tick) && (tick_end >= core_min_tick)
-                        })
-                        .cloned()
-                        .collect();
+        // T1 per T0
+        let p_start_t1_t0 = converter.tick_to_price(tick_start, PriceInput::Token1PerToken0Human{price: 0.0});
+        let p_end_t1_t0 = converter.tick_to_price(tick_end, PriceInput::Token1PerToken0Human{price: 0.0});
+        println!("      T1/T0 (Token1/Token0) Price Range: {}", prices.range(p_start_t1_t0, p_end_t1_t0));
+    }
+}
 
-                    let mut favorable_arrays: Vec<i32> = potential_arrays.iter()
-                        .filter(|&&start_index| {
-                            let (_tick_start, tick_end) = helper.get_array_tick_range(start_index);
-                            let in_favorable_range = (start_index <= favorable_max_tick) && (tick_end >= favorable_min_tick);
-                            let in_core_range = (start_index <= core_max_tick) && (tick_end >= core_min_tick);
-                            in_favorable_range && !in_core_range
-                        })
-                        .cloned()
-                        .collect();
+/// Walks the initialized arrays (sorted ascending, as read from the bitmaps) in `direction`,
+/// starting at the array that starts at `from_start_index` (inclusive): nearest first.
+/// This is the single "next initialized array" search; the default bitmap and the extension
+/// are one sorted list here, so the walk crosses the seam between them transparently.
+fn walk_initialized_arrays(initialized: &[i32], from_start_index: i32, direction: TickDirection) -> Vec<i32> {
+    match direction {
+        TickDirection::Down => initialized.iter().rev().filter(|&&s| s <= from_start_index).cloned().collect(),
+        TickDirection::Up => initialized.iter().filter(|&&s| s >= from_start_index).cloned().collect(),
+    }
+}
 
-                    // 8. Calculate the ONE surrounding array in the direction of IMPACT (BLIND version)
-                    let surrounding_array: Option<(i32, &str)> = match direction { // <-- Assign directly from match
-                        SwapDirection::BuyT1 => { // Impact is DOWN (tick decreases)
-                            let surrounding_start_index = start_array_min - step;
-                            Some((surrounding_start_index, "SURROUNDING_DN")) // <-- Return Some(...)
-                        },
-                        SwapDirection::BuyT0 => { // Impact is UP (tick increases)
-                            let surrounding_start_index = start_array_max + step;
-                            Some((surrounding_start_index, "SURROUNDING_UP")) // <-- Return Some(...)
-                        },
-                    };
+/// `rpc pool-state`: prints the decoded pool, with every remaining field under `--full`. Returns the pool state.
+async fn pool_state_command(rpc_client: &dyn AccountFetcher, pool_pubkey: &Pubkey, program_id: &Pubkey, full: bool, prices: PriceDisplay) -> PoolState {
+    let pool_state = fetch_clmm_account::<PoolState>(rpc_client, pool_pubkey, program_id).await;
+    let now = unix_now();
+
+    println!("--- Pool State for {} ---", pool_pubkey);
+    println!("  - Liquidity: {}", pool_state.liquidity);
+    println!("  - Tick Spacing: {}", pool_state.tick_spacing);
+    println!("  - Pool status: {}", pool_state.pool_status());
+    if pool_state.status != 0 {
+        println!("  - WARNING: Some operations are paused on this pool. Check the status before routing through it.");
+    }
+    if pool_state.open_time > now {
+        println!("  - WARNING: The pool opens {}; swaps fail until then.", format_relative_time(pool_state.open_time, now));
+    }
+    if full {
+        println!("  - AMM Config:      {}", pool_state.amm_config);
+        println!("  - Token Mint 0:    {} ({} decimals)", pool_state.token_mint_0, pool_state.mint_decimals_0);
+        println!("  - Token Mint 1:    {} ({} decimals)", pool_state.token_mint_1, pool_state.mint_decimals_1);
+        println!("  - Token Vault 0:   {}", pool_state.token_vault_0);
+        println!("  - Token Vault 1:   {}", pool_state.token_vault_1);
+        println!("  - Observation:     {}", pool_state.observation_key);
+        println!("  - Tick Current:    {}", pool_state.tick_current);
+        println!("  - Sqrt Price X64:  {}", format_q64(pool_state.sqrt_price_x64));
+        println!("  - Fee Growth 0:    {}", format_q64(pool_state.fee_growth_global_0_x64));
+        println!("  - Fee Growth 1:    {}", format_q64(pool_state.fee_growth_global_1_x64));
+        let age = if pool_state.open_time > now {
+            format!("not open yet, opens {}", format_relative_time(pool_state.open_time, now))
+        } else {
+            format!("open for {}", format_duration(now - pool_state.open_time))
+        };
+        println!("  - Open Time:       {} ({})", format_unix_time(pool_state.open_time), age);
+        let current_epoch = rpc_client.get_epoch().await
+            .inspect_err(|e| eprintln!("Warning: Failed to fetch the current epoch: {}", e))
+            .ok();
+        println!("  - Recent Epoch:    {}", format_epoch(pool_state.recent_epoch, current_epoch));
+    }
+    
+    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+    converter.print_all_prices(Tick(pool_state.tick_current), prices);
+    print_price_consistency(&pool_state);
+    pool_state
+}
 
-                    // 9. Print Final List (Same as GetSwapArrays, uses the blind lists)
-                    let total_arrays = core_arrays.len() + favorable_arrays.len() + if surrounding_array.is_some() { 1 } else { 0 };
-                    println!("\n{:=<80}", "");
-                    println!("--- REQUIRED SWAP ARRAYS (BLIND): {} ---", total_arrays);
+/// `rpc tick-array`: prints the price range and initialized ticks of the array at `start_index`. Returns the array.
+async fn tick_array_command(
+    rpc_client: &dyn AccountFetcher,
+    pool_pubkey: &Pubkey,
+    program_id: &Pubkey,
+    start_index: i32,
+    format: HumanPriceFormat,
+    prices: PriceDisplay,
+) -> TickArrayState {
+    // First, fetch pool state to get decimals and tick_spacing
+    let pool_state = fetch_clmm_account::<PoolState>(rpc_client, pool_pubkey, program_id).await;
+    
+    let helper = TickArrayHelper { tick_spacing: pool_state.tick_spacing };
+    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+    
+    // Now, fetch the tick array
+    let (pda, _) = Pubkey::find_program_address(&[TICK_ARRAY_SEED, pool_pubkey.as_ref(), &start_index.to_be_bytes()], program_id);
+    let tick_array = fetch_pool_account::<TickArrayState>(rpc_client, &pda, pool_pubkey, program_id).await;
+    
+    println!("--- Tick Array Details (Start Index: {}) ---", tick_array.start_tick_index);
+    
+    // Print Price Range for the entire array
+    let (tick_start, tick_end) = helper.get_array_valid_tick_range(ArrayStartIndex(tick_array.start_tick_index));
+    println!("\nPrice Range for this Array (Tick {} to {}):", tick_start, tick_end);
+
+    // T0 per T1
+    let p_start_t0_t1 = converter.tick_to_price(tick_start, PriceInput::Token0PerToken1Human{price: 0.0});
+    let p_end_t0_t1 = converter.tick_to_price(tick_end, PriceInput::Token0PerToken1Human{price: 0.0});
+    println!("  - T0/T1 (Token0/Token1): {}", prices.range(p_start_t0_t1, p_end_t0_t1));
+
+    // T1 per T0
+    let p_start_t1_t0 = converter.tick_to_price(tick_start, PriceInput::Token1PerToken0Human{price: 0.0});
+    let p_end_t1_t0 = converter.tick_to_price(tick_end, PriceInput::Token1PerToken0Human{price: 0.0});
+    println!("  - T1/T0 (Token1/Token0): {}", prices.range(p_start_t1_t0, p_end_t1_t0));
+    
+    println!("\n  - Initialized Ticks: {}", tick_array.initialized_tick_count);
+    for slot in tick_array_slots(&tick_array, pool_state.tick_spacing, &converter, format) {
+        if slot.liquidity_gross != 0 {
+            println!("    - Tick {}:", slot.tick);
+            for line in initialized_slot_lines(&slot, format, Some(pool_state.tick_current), prices) {
+                println!("        {}", line);
+            }
+        }
+    }
+    tick_array
+}
 
-                    match direction {
-                        SwapDirection::BuyT1 => { // Tick DECREASES, print descending
-                            favorable_arrays.sort_by(|a, b| b.cmp(a));
-                            core_arrays.sort_by(|a, b| b.cmp(a));
+/// Reads the pool state and its bitmap extension in one `getMultipleAccounts` call pinned to the
+/// current slot, so both come from the same slot. Returns that slot with the decoded accounts.
+async fn fetch_pool_and_extension_at_slot(
+    rpc_client: &dyn AccountFetcher,
+    pool_pubkey: &Pubkey,
+    program_id: &Pubkey,
+) -> (u64, PoolState, TickArrayBitmapExtension) {
+    let (ext_pda, _) = Pubkey::find_program_address(&[TICK_ARRAY_BITMAP_SEED, pool_pubkey.as_ref()], program_id);
+    let slot = rpc_client.get_slot().await.unwrap_or_else(|e| fail_fetch("current slot", &e));
+    let (context_slot, mut accounts) = rpc_client
+        .get_multiple_accounts_at(&[*pool_pubkey, ext_pda], Some(slot))
+        .await
+        .unwrap_or_else(|e| fail_fetch("pool state and bitmap extension", &e));
+    let ext_account = accounts.pop().flatten()
+        .unwrap_or_else(|| fail(ExitCode::AccountNotFound, format!("Bitmap extension {} not found.", ext_pda)));
+    let pool_account = accounts.pop().flatten()
+        .unwrap_or_else(|| fail(ExitCode::AccountNotFound, format!("Pool state {} not found.", pool_pubkey)));
+    (
+        context_slot,
+        decode_clmm_account_or_exit::<PoolState>(&pool_account, pool_pubkey, program_id),
+        decode_pool_account_or_exit::<TickArrayBitmapExtension>(&ext_account, &ext_pda, pool_pubkey, program_id),
+    )
+}
 
-                            for start_index in &favorable_arrays {
-                                print_swap_array_info("FAVORABLE", *start_index, &pool_pubkey, &program_id);
-                            }
-                            if !favorable_arrays.is_empty() {
-                                println!("\n{:-<80}", "");
-                            }
-                            for start_index in &core_arrays {
-                                print_swap_array_info("CORE", *start_index, &pool_pubkey, &program_id);
-                            }
-                        },
-                        SwapDirection::BuyT0 => { // Tick INCREASES, print ascending
-                            // Arrays are already sorted ascending from the while loop
-                            for start_index in &favorable_arrays {
-                                print_swap_array_info("FAVORABLE", *start_index, &pool_pubkey, &program_id);
-                            }
-                            if !favorable_arrays.is_empty() {
-                                println!("\n{:-<80}", "");
-                            }
-                            for start_index in &core_arrays {
-                                print_swap_array_info("CORE", *start_index, &pool_pubkey, &program_id);
-                            }
-                        },
-                    }
+/// `rpc snapshot`: reads the pool and its bitmap extension at one slot, then every array the bitmaps
+/// mark (no older than that slot), and collects them in one archivable document.
+async fn snapshot_command(rpc_client: &dyn AccountFetcher, pool_pubkey: &Pubkey, program_id: &Pubkey) -> PoolSnapshotJson {
+    // 1. Pool state and extension from the same slot
+    println!("Fetching pool info and bitmaps...");
+    let (slot, pool_state, extension) = fetch_pool_and_extension_at_slot(rpc_client, pool_pubkey, program_id).await;
+
+    // 2. Every array the bitmaps point at, in one batch
+    let mut initialized_arrays = read_default_bitmap(&pool_state.tick_array_bitmap, pool_state.tick_spacing);
+    initialized_arrays.append(&mut read_extension_bitmap(&extension, pool_state.tick_spacing));
+    initialized_arrays.sort();
+    println!("Fetching {} initialized tick arrays...", initialized_arrays.len());
+    let pdas: Vec<Pubkey> = initialized_arrays.iter()
+        .map(|&start_index| derive_tick_array_pda(pool_pubkey, start_index, program_id))
+        .collect();
+    let (arrays_slot, accounts) = rpc_client
+        .get_multiple_accounts_at(&pdas, Some(slot))
+        .await
+        .unwrap_or_else(|e| fail_fetch("tick arrays", &e));
+    println!("Done.");
+
+    // 3. Keep each array's initialized ticks; arrays that cannot be read are listed, not dropped silently
+    let mut tick_arrays = Vec::new();
+    let mut unreadable_arrays = Vec::new();
+    for ((&start_index, pda), account) in initialized_arrays.iter().zip(&pdas).zip(accounts) {
+        let decoded = match account {
+            None => Err("account not found".to_string()),
+            Some(account) => decode_clmm_account::<TickArrayState>(pda, &account.owner, &account.data, program_id)
+                .and_then(|tick_array| check_pool_id(pda, &tick_array, pool_pubkey).map(|()| tick_array)),
+        };
+        match decoded {
+            Ok(tick_array) => tick_arrays.push(TickArraySnapshotJson::new(pda, &tick_array)),
+            Err(error) => unreadable_arrays.push(UnreadableArrayJson { start_index, pda: pda.to_string(), error }),
+        }
+    }
 
-                    if let Some((start_index, label)) = surrounding_array {
-                        if !core_arrays.is_empty() || !favorable_arrays.is_empty() {
-                            println!("\n{:-<80}", "");
-                        }
-                        print_swap_array_info(label, start_index, &pool_pubkey, &program_id);
-                    } else {
-                        // This case is less likely in blind mode but kept for consistency
-                        println!("\n[INFO] Surrounding array calculation resulted in an edge case (e.g., beyond max/min tick limits).");
-                    }
-                    println!("{:=<80}", "");
+    let captured_at = unix_now();
+    PoolSnapshotJson {
+        pool_id: pool_pubkey.to_string(),
+        program_id: program_id.to_string(),
+        slot,
+        arrays_slot,
+        captured_at: format_unix_time(captured_at),
+        captured_at_unix: captured_at,
+        pool: PoolStateJson::new(&pool_state),
+        extension_bitmap: ExtensionBitmapJson {
+            pubkey: Pubkey::find_program_address(&[TICK_ARRAY_BITMAP_SEED, pool_pubkey.as_ref()], program_id).0.to_string(),
+            positive: extension.positive_tick_array_bitmap.iter().map(|chunk| u64_strings(chunk)).collect(),
+            negative: extension.negative_tick_array_bitmap.iter().map(|chunk| u64_strings(chunk)).collect(),
+        },
+        initialized_arrays,
+        tick_arrays,
+        unreadable_arrays,
+    }
+}
 
+/// `rpc full-analysis`: prints every initialized array around the current tick. Returns the start
+/// indices of the arrays marked in the bitmaps and, with `--snapshot`, those that were not usable.
+#[allow(clippy::too_many_arguments)]
+async fn full_analysis_command(
+    rpc_client: &dyn AccountFetcher,
+    pool_pubkey: &Pubkey,
+    program_id: &Pubkey,
+    format: HumanPriceFormat,
+    snapshot: bool,
+    columns: ArrayColumns,
+    palette: Palette,
+    prices: PriceDisplay,
+) -> (Vec<i32>, Vec<(i32, String)>) {
+    // 1. Fetch both Pool State and Extension Bitmap
+    println!("Fetching on-chain data...");
+    let (ext_pda, _) = Pubkey::find_program_address(&[TICK_ARRAY_BITMAP_SEED, pool_pubkey.as_ref()], program_id);
+    let mut snapshot_slot = None;
+    let (pool_state, extension) = if snapshot {
+        let (context_slot, pool_state, extension) = fetch_pool_and_extension_at_slot(rpc_client, pool_pubkey, program_id).await;
+        snapshot_slot = Some(context_slot);
+        (pool_state, extension)
+    } else {
+        (
+            fetch_clmm_account::<PoolState>(rpc_client, pool_pubkey, program_id).await,
+            fetch_pool_account::<TickArrayBitmapExtension>(rpc_client, &ext_pda, pool_pubkey, program_id).await,
+        )
+    };
+    println!("Done.");
+
+    // 2. Setup helpers
+    let helper = TickArrayHelper { tick_spacing: pool_state.tick_spacing };
+    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+    
+    // 3. Combine and sort all initialized arrays, keeping the bitmap each was read from
+    let mut positions = read_default_bitmap_positions(&pool_state.tick_array_bitmap);
+    positions.append(&mut read_extension_bitmap_positions(&extension));
+    let mut sourced_arrays: Vec<(i32, BitmapLocation)> = positions.iter()
+        .map(|position| (position.start_index(pool_state.tick_spacing), position.location))
+        .collect();
+    sourced_arrays.sort_by_key(|&(start_index, _)| start_index);
+    let sources: std::collections::HashMap<i32, BitmapLocation> = sourced_arrays.iter().copied().collect();
+    let all_initialized_arrays: Vec<i32> = sourced_arrays.iter().map(|&(start_index, _)| start_index).collect();
+
+    // 3b. In snapshot mode, fetch every array the bitmaps point at in one batch,
+    // no older than the slot the bitmaps were read at.
+    let mut inconsistent_arrays = Vec::new();
+    let mut arrays_slot = None;
+    // Array columns from the snapshot fetch, reused by --with-ticks and --detailed
+    let mut snapshot_cells = std::collections::HashMap::new();
+    if let Some(slot) = snapshot_slot {
+        let pdas: Vec<Pubkey> = all_initialized_arrays.iter().map(|start_index| {
+            Pubkey::find_program_address(&[TICK_ARRAY_SEED, pool_pubkey.as_ref(), &start_index.to_be_bytes()], program_id).0
+        }).collect();
+        let (context_slot, accounts) = rpc_client
+            .get_multiple_accounts_at(&pdas, Some(slot))
+            .await
+            .unwrap_or_else(|e| fail_fetch("tick arrays", &e));
+        arrays_slot = Some(context_slot);
+        for ((&start_index, pda), account) in all_initialized_arrays.iter().zip(&pdas).zip(accounts) {
+            if columns != ArrayColumns::None {
+                snapshot_cells.insert(start_index, columns.cells(pda, account.as_ref(), program_id));
+            }
+            match account {
+                None => inconsistent_arrays.push((start_index, "account not found".to_string())),
+                Some(account) => match decode_clmm_account::<TickArrayState>(pda, &account.owner, &account.data, program_id) {
+                    Ok(tick_array) if tick_array.initialized_tick_count == 0 => {
+                        inconsistent_arrays.push((start_index, "no initialized ticks".to_string()))
+                    }
+                    Ok(_) => {}
+                    Err(e) => inconsistent_arrays.push((start_index, e)),
                 },
-                RpcCommands::GetSwapArrays { pool_id, direction, format, favorable_pct, impact_pct, price, rpc_url } => {
-                    println!("--- Swap Array Calculation for {} ---", pool_id);
-                    
-                    let rpc_client = RpcClient::new(rpc_url);
-                    let pool_pubkey = Pubkey::from_str(&pool_id).expect("Invalid Pool ID");
-                    let program_id = Pubkey::from_str(RAYDIUM_CLMM_PROGRAM_ID).unwrap();
+            }
+        }
+    }
 
-                    // 1. Fetch Base Data (PoolState + Extension)
-                    println!("Fetching pool info and bitmaps...");
-                    let pool_state_data = rpc_client.get_account_data(&pool_pubkey).expect("Failed to fetch pool state");
-                    let pool_state = PoolState::deserialize(&mut &pool_state_data[8..]).expect("Failed to parse pool state");
+    // 4. Find the current array
+    // let current_array_start_index = helper.get_array_start_index(pool_state.tick_current);
 
-                    let (ext_pda, _) = Pubkey::find_program_address(&[TICK_ARRAY_BITMAP_SEED, pool_pubkey.as_ref()], &program_id);
-                    let ext_data = rpc_client.get_account_data(&ext_pda).expect("Failed to fetch bitmap extension");
-                    let extension = TickArrayBitmapExtension::deserialize(&mut &ext_data[8..]).expect("Failed to parse bitmap extension");
-                    println!("Done.");
+    // 5. Determine user's desired price format
+    let (price_template, format_label) = match format {
+        HumanPriceFormat::T0PerT1 => (PriceInput::Token0PerToken1Human{price: 0.0}, "T0/T1 (Token0/Token1)"),
+        HumanPriceFormat::T1PerT0 => (PriceInput::Token1PerToken0Human{price: 0.0}, "T1/T0 (Token1/Token0)"),
+    };
 
-                    // 2. Setup Helpers
-                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
-                    let helper = TickArrayHelper { tick_spacing: pool_state.tick_spacing };
+    println!("\n--- Full Liquidity Analysis for {} ---", pool_pubkey);
+    println!("Current Tick: {}", pool_state.tick_current);
+    let status = pool_state.pool_status();
+    if status.0 == 0 {
+        println!("Pool Status: {}", status);
+    } else {
+        println!("{}", palette.warning(&format!("Pool Status: {}", status)));
+    }
 
-                    // 3. Determine Start Tick
-                    let tick_start = match price {
-                        Some(p) => {
-                            let price_input = match format {
-                                HumanPriceFormat::T0PerT1 => PriceInput::Token0PerToken1Human { price: p },
-                                HumanPriceFormat::T1PerT0 => PriceInput::Token1PerToken0Human { price: p },
-                            };
-                            println!("Using --price {:.8} as start", p);
-                            converter.price_to_tick(price_input)
-                        },
-                        None => {
-                            println!("No --price provided. Using live pool tick: {}", pool_state.tick_current);
-                            pool_state.tick_current
-                        }
-                    };
-                    
-                    println!("Start Tick:    {}", tick_start);
-                    println!("Direction:     {:?}", direction);
-                    
-                    // 4. Calculate Tick Range based on Direction (using RAW PRICE)
-                    //    (Based on on-chain facts: buy-t1 = tick decreases, buy-t0 = tick increases)
-                    let start_raw_price = converter.tick_to_raw_price(tick_start);
-
-                    let (tick_favorable, tick_impact) = match direction {
-                        SwapDirection::BuyT1 => { // Tick DECREASES (Raw Price DECREASES)
-                            // Favorable move = Tick INCREASES. To make tick INCREASE, `raw_price` must INCREASE.
-                            let favorable_raw_price = start_raw_price * (1.0 + (favorable_pct / 100.0));
-                            // Impact move = Tick DECREASES. To make tick DECREASE, `raw_price` must DECREASE.
-                            let impact_raw_price = start_raw_price * (1.0 - (impact_pct / 100.0));
-                            
-                            (converter.raw_price_to_tick(favorable_raw_price), converter.raw_price_to_tick(impact_raw_price))
-                        },
-                        SwapDirection::BuyT0 => { // Tick INCREASES (Raw Price INCREASES)
-                            // Favorable move = Tick DECREASES. To make tick DECREASE, `raw_price` must DECREASE.
-                            let favorable_raw_price = start_raw_price * (1.0 - (favorable_pct / 100.0));
-                            // Impact move = Tick INCREASES. To make tick INCREASE, `raw_price` must INCREASE.
-                            let impact_raw_price = start_raw_price * (1.0 + (impact_pct / 100.0));
-                            
-                            (converter.raw_price_to_tick(favorable_raw_price), converter.raw_price_to_tick(impact_raw_price))
-                        }
-                    };
+    // The bitmap an array was read from and how far it is from the current price: whole arrays
+    // away, and the price move to its nearest edge (zero for the current array)
+    let current_array = helper.get_array_start_index(Tick(pool_state.tick_current));
+    let current_price = converter.tick_to_price(Tick(pool_state.tick_current), price_template);
+    let location_cells = |start_index: i32| {
+        let arrays_away = (start_index - current_array.0) / helper.tick_indices_per_array();
+        let edge = match arrays_away.signum() {
+            1 => start_index,
+            -1 => start_index + helper.tick_indices_per_array(),
+            _ => pool_state.tick_current,
+        };
+        let price_move = (converter.tick_to_price(Tick(edge), price_template) / current_price - 1.0) * 100.0;
+        // Far arrays move the price by many orders of magnitude
+        let price_move = if price_move.abs() < 1e4 { format!("{:+.2}%", price_move) } else { format!("{:+.2e}%", price_move) };
+        let source = sources.get(&start_index).map(|location| location.to_string()).unwrap_or_default();
+        let arrays_away = if arrays_away == 0 { "0".to_string() } else { format!("{:+}", arrays_away) };
+        format!("{:>7} | {:>6} | {:>10} | ", source, arrays_away, price_move)
+    };
+
+    // With --with-ticks or --detailed, extra columns sit between the distance and the prices
+    let columns_blank = format!("{:>7} | {:>6} | {:>10} | {}", "", "", "", columns.blank());
+    let header = format!(
+        "{:<15} | {:>7} | {:>6} | {:>10} | {}Price / Price Range",
+        "Array Start/Tick", "Source", "Arrays", "Move", columns.header()
+    );
+    let rule = "-".repeat(header.len() + 10);
+    println!("\n{}", header);
+    println!("{}", rule);
+
+    let mut current_tick_printed = false;
+    let print_current_tick = || {
+        println!("{}", rule);
+        println!("{}", palette.current(&format!(
+            "{:<15} | {}Price: {}               <-- YOU ARE HERE",
+            format!("Tick {}", pool_state.tick_current),
+            columns_blank,
+            prices.format(current_price)
+        )));
+        println!("{}", rule);
+    };
+
+    // 6. Print the arrays in batches; with --with-ticks or --detailed each batch is fetched right
+    // before its rows are printed, so output starts after the first round-trip.
+    for batch in all_initialized_arrays.chunks(100) {
+        let array_cells: Vec<String> = if columns == ArrayColumns::None {
+            Vec::new()
+        } else if snapshot_slot.is_some() {
+            batch.iter().map(|start_index| snapshot_cells.remove(start_index).unwrap_or_default()).collect()
+        } else {
+            let pdas: Vec<Pubkey> = batch.iter()
+                .map(|&start_index| derive_tick_array_pda(pool_pubkey, start_index, program_id))
+                .collect();
+            match rpc_client.get_multiple_accounts_at(&pdas, None).await {
+                Ok((_, accounts)) => pdas.iter().zip(accounts)
+                    .map(|(pda, account)| columns.cells(pda, account.as_ref(), program_id))
+                    .collect(),
+                // Keep printing the table; the failure shows up in the fetch summary
+                Err(_) => vec![columns.fetch_failed(); batch.len()],
+            }
+        };
+
+        for (i, &start_index) in batch.iter().enumerate() {
+            // Check if the current tick's position is BEFORE the next array to be printed.
+            if !current_tick_printed && start_index > pool_state.tick_current {
+                print_current_tick();
+                current_tick_printed = true;
+            }
+
+            // Print the array's info
+            let (tick_start, tick_end) = helper.get_array_valid_tick_range(ArrayStartIndex(start_index));
+            let price_start = converter.tick_to_price(tick_start, price_template);
+            let price_end = converter.tick_to_price(tick_end, price_template);
+            let cells = array_cells.get(i).map(String::as_str).unwrap_or_default();
+
+            println!(
+                "{:<15} | {}{}{}",
+                start_index,
+                location_cells(start_index),
+                cells,
+                prices.range(price_start, price_end),
+            );
+        }
+    }
+
+    // This handles the case where the current tick is after the last initialized array in the list.
+    if !current_tick_printed {
+        print_current_tick();
+    }
+    println!("\nPrice format is: {}", format_label);
+
+    if let (Some(slot), Some(arrays_slot)) = (snapshot_slot, arrays_slot) {
+        println!("\nSnapshot slot: {} (pool state + extension)", slot);
+        println!("Tick arrays read at slot: {} ({} accounts, min context slot {})", arrays_slot, all_initialized_arrays.len(), slot);
+        if inconsistent_arrays.is_empty() {
+            println!("All arrays marked in the bitmaps are present and initialized.");
+        } else {
+            println!("{}", palette.warning("Arrays marked in the bitmaps but not usable at the array slot:"));
+            for (start_index, reason) in &inconsistent_arrays {
+                println!("{}", palette.warning(&format!("  {:<15} {}", start_index, reason)));
+            }
+        }
+    }
+    (all_initialized_arrays, inconsistent_arrays)
+}
+
+/// Fetches the bitmap extension and returns every initialized array start index
+/// from both the default bitmap and the extension, sorted ascending.
+async fn fetch_initialized_arrays(
+    rpc_client: &dyn AccountFetcher,
+    pool_pubkey: &Pubkey,
+    program_id: &Pubkey,
+    pool_state: &PoolState,
+) -> Vec<i32> {
+    let (ext_pda, _) = Pubkey::find_program_address(&[TICK_ARRAY_BITMAP_SEED, pool_pubkey.as_ref()], program_id);
+    let extension = fetch_pool_account::<TickArrayBitmapExtension>(rpc_client, &ext_pda, pool_pubkey, program_id).await;
+
+    let mut all_initialized_arrays = read_default_bitmap(&pool_state.tick_array_bitmap, pool_state.tick_spacing);
+    all_initialized_arrays.append(&mut read_extension_bitmap(&extension, pool_state.tick_spacing));
+    all_initialized_arrays.sort();
+    all_initialized_arrays
+}
+
+/// Fetches each tick array and returns `(tick, liquidity_net)` for every initialized tick.
+/// See `fetch_initialized_tick_states`.
+async fn fetch_initialized_ticks(
+    rpc_client: &dyn AccountFetcher,
+    pool_pubkey: &Pubkey,
+    program_id: &Pubkey,
+    start_indices: &[i32],
+) -> Vec<(i32, i128)> {
+    fetch_initialized_tick_states(rpc_client, pool_pubkey, program_id, start_indices).await
+        .into_iter()
+        .map(|(tick, liquidity_net, _)| (tick, liquidity_net))
+        .collect()
+}
+
+/// Fetches each tick array and returns `(tick, liquidity_net, liquidity_gross)` for every initialized tick.
+/// Arrays are fetched concurrently (see `--concurrency`); the result keeps the order of `start_indices`.
+/// Arrays that fail to fetch are recorded by the fetcher and reported at the end of the command.
+async fn fetch_initialized_tick_states(
+    rpc_client: &dyn AccountFetcher,
+    pool_pubkey: &Pubkey,
+    program_id: &Pubkey,
+    start_indices: &[i32],
+) -> Vec<(i32, i128, u128)> {
+    let pdas: Vec<Pubkey> = start_indices.iter()
+        .map(|&start_index| derive_tick_array_pda(pool_pubkey, start_index, program_id))
+        .collect();
+    let accounts = rpc_client.get_accounts_concurrent(&pdas).await;
+
+    let mut all_ticks = Vec::new();
+    for ((start_index, pda), account) in start_indices.iter().zip(&pdas).zip(accounts) {
+        let Ok(account) = account else {
+            continue;
+        };
+        // Zero-copy: only the tick index and liquidity of initialized ticks are read
+        match TickArrayRef::new(pda, &account.owner, &account.data, program_id) {
+            Ok(tick_array) if tick_array.pool_id() != *pool_pubkey || tick_array.start_tick_index() != *start_index => {
+                eprintln!(
+                    "Warning: Skipping tick array at start index {}: {} holds start index {} of pool {}",
+                    start_index, pda, tick_array.start_tick_index(), tick_array.pool_id()
+                );
+            }
+            Ok(tick_array) if tick_array.initialized_tick_count() == 0 => {}
+            Ok(tick_array) => all_ticks.extend(tick_array.initialized_ticks()),
+            Err(e) => eprintln!("Warning: Skipping tick array at start index {}: {}", start_index, e),
+        }
+    }
+    all_ticks
+}
 
-                    let (min_tick, max_tick) = (tick_favorable.min(tick_impact), tick_favorable.max(tick_impact));
-                    
-                    println!("Favorable Pct: {:.4}%", favorable_pct);
-                    println!("Impact Pct:    {:.4}%", impact_pct);
-                    println!("Calculated Tick Range:  [{}, {}]", min_tick, max_tick);
+/// Fetches a CLMM account and decodes it as `T`, exiting if it is not one.
+async fn fetch_clmm_account<T: ClmmAccount>(rpc_client: &dyn AccountFetcher, pubkey: &Pubkey, program_id: &Pubkey) -> T {
+    let account = rpc_client.get_account(pubkey).await
+        .unwrap_or_else(|e| fail_fetch(format!("{} {}", T::NAME, pubkey), &e));
+    decode_clmm_account_or_exit(&account, pubkey, program_id)
+}
 
-                    // 5. Get ALL initialized arrays and SORT them
-                    let mut all_initialized_arrays = read_default_bitmap(&pool_state.tick_array_bitmap, pool_state.tick_spacing);
-                    all_initialized_arrays.append(&mut read_extension_bitmap(&extension, pool_state.tick_spacing));
-                    all_initialized_arrays.sort();
+/// Fetches a tick array or bitmap extension like `fetch_clmm_account`, also exiting when it belongs
+/// to a pool other than `pool_pubkey`.
+async fn fetch_pool_account<T: PoolScoped>(rpc_client: &dyn AccountFetcher, pubkey: &Pubkey, pool_pubkey: &Pubkey, program_id: &Pubkey) -> T {
+    let account = fetch_clmm_account::<T>(rpc_client, pubkey, program_id).await;
+    check_pool_id(pubkey, &account, pool_pubkey).unwrap_or_else(|e| fail(ExitCode::InvalidInput, e));
+    account
+}
 
-                    // 6. Filter and Find Arrays
-                    let mut arrays_in_range: Vec<i32> = all_initialized_arrays.iter()
-                        .filter(|&&start_index| {
-                            let (_tick_start, tick_end) = helper.get_array_tick_range(start_index);
-                            let array_start = start_index;
-                            let array_end = tick_end; 
-                            (array_start <= max_tick) && (array_end >= min_tick)
-                        })
-                        .cloned()
-                        .collect();
+/// `decode_clmm_account_or_exit` with the pool check of `fetch_pool_account`.
+fn decode_pool_account_or_exit<T: PoolScoped>(account: &Account, pubkey: &Pubkey, pool_pubkey: &Pubkey, program_id: &Pubkey) -> T {
+    let account = decode_clmm_account_or_exit::<T>(account, pubkey, program_id);
+    check_pool_id(pubkey, &account, pool_pubkey).unwrap_or_else(|e| fail(ExitCode::InvalidInput, e));
+    account
+}
 
-                    // 7. Find the ONE surrounding array in the direction of IMPACT
-                    let mut surrounding_array: Option<(i32, &str)> = None;
-                    match direction {
-                        SwapDirection::BuyT1 => { // Impact is DOWN (tick decreases)
-                            if let Some(&start_index) = all_initialized_arrays.iter().filter(|&&s| helper.get_array_tick_range(s).1 < min_tick).last() {
-                                surrounding_array = Some((start_index, "SURROUNDING_DN"));
-                            }
-                        },
-                        SwapDirection::BuyT0 => { // Impact is UP (tick increases)
-                            if let Some(&start_index) = all_initialized_arrays.iter().find(|&&s| s > max_tick) {
-                                surrounding_array = Some((start_index, "SURROUNDING_UP"));
-                            }
-                        },
-                    }
+/// Errors when an account records a pool other than `pool_pubkey`. Its ticks would otherwise be
+/// shown with that pool's decimals and tick spacing, giving plausible but meaningless prices.
+fn check_pool_id<T: PoolScoped>(pubkey: &Pubkey, account: &T, pool_pubkey: &Pubkey) -> std::result::Result<(), String> {
+    if account.pool_id() == pool_pubkey {
+        return Ok(());
+    }
+    Err(format!("{} {} belongs to pool {}, not {}.", T::NAME, pubkey, account.pool_id(), pool_pubkey))
+}
 
-                    // 8. Print Final List in correct swap order
-                    let total_arrays = arrays_in_range.len() + if surrounding_array.is_some() { 1 } else { 0 };
-                    println!("\n{:=<80}", "");
-                    println!("--- REQUIRED SWAP ARRAYS: {} ---", total_arrays);
+/// Prints every field of a decoded account in `{:#?}` form, for `--raw` on `rpc pool-state`,
+/// `rpc tick-array` and `rpc extension-bitmap`. Field names and order follow `onchain_states.rs`,
+/// so the output can be compared field by field with the program's account structs.
+fn print_raw_decode<T: ClmmAccount + std::fmt::Debug>(pubkey: &Pubkey, account: &T) {
+    println!("--- {} {} (raw decode) ---", T::NAME, pubkey);
+    println!("{:#?}", account);
+}
 
-                    match direction {
-                        SwapDirection::BuyT1 => { // Tick DECREASES, so print in REVERSE (descending)
-                            arrays_in_range.sort_by(|a, b| b.cmp(a)); // Sort descending
-                            for start_index in &arrays_in_range {
-                                print_swap_array_info("IN-RANGE", *start_index, &pool_pubkey, &program_id);
-                            }
-                        },
-                        SwapDirection::BuyT0 => { // Tick INCREASES, so print in ORDER (ascending)
-                            // .sort() was already called, so it's ascending
-                            for start_index in &arrays_in_range {
-                                print_swap_array_info("IN-RANGE", *start_index, &pool_pubkey, &program_id);
-                            }
-                        },
-                    }
+/// Verifies owner and discriminator of an already-fetched account and decodes it as `T`.
+/// Prints what the account actually is and exits on mismatch.
+fn decode_clmm_account_or_exit<T: ClmmAccount>(account: &Account, pubkey: &Pubkey, program_id: &Pubkey) -> T {
+    decode_clmm_account(pubkey, &account.owner, &account.data, program_id)
+        .unwrap_or_else(|e| fail(ExitCode::Deserialize, e))
+}
 
-                    if let Some((start_index, label)) = surrounding_array {
-                        print_swap_array_info(label, start_index, &pool_pubkey, &program_id);
-                    } else {
-                        println!("\n[WARNING] No initialized surrounding array found for the impact direction.");
-                    }
-                    println!("{:=<80}", "");
+/// Fetches and decodes a pool state together with the slot it was read at.
+async fn fetch_pool_state_with_slot(rpc_client: &dyn AccountFetcher, pool_pubkey: &Pubkey, program_id: &Pubkey) -> (u64, PoolState) {
+    let (slot, accounts) = rpc_client.get_multiple_accounts_at(&[*pool_pubkey], None).await
+        .unwrap_or_else(|e| fail_fetch(format!("PoolState {}", pool_pubkey), &e));
+    let account = accounts.into_iter().next().flatten()
+        .unwrap_or_else(|| fail(ExitCode::AccountNotFound, format!("Pool state {} not found.", pool_pubkey)));
+    (slot, decode_clmm_account_or_exit(&account, pool_pubkey, program_id))
+}
 
-                },
-                RpcCommands::ExtensionBitmap { pool_id, rpc_url } => {
-                    let rpc_client = RpcClient::new(rpc_url);
-                    let pool_pubkey = Pubkey::from_str(&pool_id).expect("Invalid Pool ID");
-                    let program_id = Pubkey::from_str(RAYDIUM_CLMM_PROGRAM_ID).unwrap();
-                    
-                    // We need to fetch the main pool state to get decimals and tick_spacing
-                    let pool_state_data = rpc_client.get_account_data(&pool_pubkey).expect("Failed to fetch pool state");
-                    let pool_state = PoolState::deserialize(&mut &pool_state_data[8..]).expect("Failed to parse pool state");
-                    
-                    let (pda, _) = Pubkey::find_program_address(&[TICK_ARRAY_BITMAP_SEED, pool_pubkey.as_ref()], &program_id);
-                    let account_data = rpc_client.get_account_data(&pda).expect("Failed to fetch bitmap extension");
-                    let extension = TickArrayBitmapExtension::deserialize(&mut &account_data[8..]).expect("Failed to parse bitmap extension");
+/// One swap direction's counters: `(label, total input, total output)` in raw units.
+type SwapFlow = (&'static str, u128, u128);
 
-                    println!("--- Initialized Tick Arrays (Extension Bitmap) ---");
-                    let helper = TickArrayHelper { tick_spacing: pool_state.tick_spacing };
-                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
-                    let mut initialized = read_extension_bitmap(&extension, pool_state.tick_spacing);
-                    initialized.sort(); // Sort for readability
-                    
-                    println!("Found {} initialized arrays in extension:", initialized.len());
-                    for start_index in initialized {
-                        println!("  - Start Index: {}", start_index);
-                        let (tick_start, tick_end) = helper.get_array_tick_range(start_index);
-                        
-                        // T0 per T1
-                        let p_start_t0_t1 = converter.tick_to_price(tick_start, PriceInput::Token0PerToken1Human{price: 0.0});
-                        let p_end_t0_t1 = converter.tick_to_price(tick_end, PriceInput::Token0PerToken1Human{price: 0.0});
-                        println!("      T0/T1 (Token0/Token1) Price Range: [{:.6}, {:.6}]", p_start_t0_t1, p_end_t0_t1);
-                        
-                        // T1 per T0
-                        let p_start_t1_t0 = converter.tick_to_price(tick_start, PriceInput::Token1PerToken0Human{price: 0.0});
-                        let p_end_t1_t0 = converter.tick_to_price(tick_end, PriceInput::Token1PerToken0Human{price: 0.0});
-                        println!("      T1/T0 (Token1/Token0) Price Range: [{:.6}, {:.6}]", p_start_t1_t0, p_end_t1_t0);
-                    }
-                },
-                RpcCommands::TickArray { pool_id, start_index, rpc_url } => {
-                    let rpc_client = RpcClient::new(rpc_url);
-                    let pool_pubkey = Pubkey::from_str(&pool_id).expect("Invalid Pool ID");
-                    let program_id = Pubkey::from_str(RAYDIUM_CLMM_PROGRAM_ID).unwrap();
-
-                    // First, fetch pool state to get decimals and tick_spacing
-                    let pool_account_data = rpc_client.get_account_data(&pool_pubkey).expect("Failed to fetch pool state");
-                    let pool_state = PoolState::deserialize(&mut &pool_account_data[8..]).expect("Failed to parse pool state");
-                    
-                    let helper = TickArrayHelper { tick_spacing: pool_state.tick_spacing };
-                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
-                    
-                    // Now, fetch the tick array
-                    let (pda, _) = Pubkey::find_program_address(&[TICK_ARRAY_SEED, pool_pubkey.as_ref(), &start_index.to_be_bytes()], &program_id);
-                    let account_data = rpc_client.get_account_data(&pda).expect("Failed to fetch tick array");
-                    let tick_array = TickArrayState::deserialize(&mut &account_data[8..]).expect("Failed to parse tick array");
-                    
-                    println!("--- Tick Array Details (Start Index: {}) ---", tick_array.start_tick_index);
-                    
-                    // Print Price Range for the entire array
-                    let (tick_start, tick_end) = helper.get_array_tick_range(tick_array.start_tick_index);
-                    println!("\nPrice Range for this Array (Tick {} to {}):", tick_start, tick_end);
-
-                    // T0 per T1
-                    let p_start_t0_t1 = converter.tick_to_price(tick_start, PriceInput::Token0PerToken1Human{price: 0.0});
-                    let p_end_t0_t1 = converter.tick_to_price(tick_end, PriceInput::Token0PerToken1Human{price: 0.0});
-                    println!("  - T0/T1 (Token0/Token1): [{:.6}, {:.6}]", p_start_t0_t1, p_end_t0_t1);
-
-                    // T1 per T0
-                    let p_start_t1_t0 = converter.tick_to_price(tick_start, PriceInput::Token1PerToken0Human{price: 0.0});
-                    let p_end_t1_t0 = converter.tick_to_price(tick_end, PriceInput::Token1PerToken0Human{price: 0.0});
-                    println!("  - T1/T0 (Token1/Token0): [{:.6}, {:.6}]", p_start_t1_t0, p_end_t1_t0);
-                    
-                    println!("\n  - Initialized Ticks: {}", tick_array.initialized_tick_count);
-                    for tick_state in tick_array.ticks.iter() {
-                        if tick_state.liquidity_gross != 0 {
-                            println!("    - Tick {}:", tick_state.tick);
-                            println!("        LiquidityNet:  {}", tick_state.liquidity_net);
-                            println!("        LiquidityGross: {}", tick_state.liquidity_gross);
-                        }
-                    }
-                },
-                RpcCommands::InitializedRangePercent { pool_id, price, lower_pct, upper_pct, format, rpc_url } => {
-                    // Calculate the price range from percentages
-                    let price_lower = price * (1.0 - (lower_pct / 100.0));
-                    let price_upper = price * (1.0 + (upper_pct / 100.0));
-
-                    println!("--- Initialized Array Percent Range Analysis for {} ---", pool_id);
-                    println!("Base Price:   {:.8}", price);
-                    println!("Range:        -{:.2}% to +{:.2}%", lower_pct, upper_pct);
-                    println!("Calculated Price Range: [{:.8}, {:.8}]", price_lower, price_upper);
-                    
-                    let rpc_client = RpcClient::new(rpc_url);
-                    let pool_pubkey = Pubkey::from_str(&pool_id).expect("Invalid Pool ID");
-                    let program_id = Pubkey::from_str(RAYDIUM_CLMM_PROGRAM_ID).unwrap();
+/// The pool's lifetime swap counters per direction: token 0 sold for token 1, then token 1 sold for token 0.
+fn swap_flows(pool_state: &PoolState) -> [SwapFlow; 2] {
+    [
+        ("Token 0 -> Token 1", pool_state.swap_in_amount_token_0, pool_state.swap_out_amount_token_1),
+        ("Token 1 -> Token 0", pool_state.swap_in_amount_token_1, pool_state.swap_out_amount_token_0),
+    ]
+}
 
-                    // 1. Fetch Base Data (PoolState + Extension)
-                    println!("Fetching pool info and bitmaps...");
-                    let pool_state_data = rpc_client.get_account_data(&pool_pubkey).expect("Failed to fetch pool state");
-                    let pool_state = PoolState::deserialize(&mut &pool_state_data[8..]).expect("Failed to parse pool state");
+/// Prints the volume of each swap direction in human units and its volume-weighted average price in
+/// both orientations: token 1 per token 0 is `out / in` selling token 0 and `in / out` selling token 1.
+fn print_swap_flows(flows: &[SwapFlow; 2], converter: &TickConverter, prices: PriceDisplay) {
+    println!("\n{:<20} | {:>28} | {:>28} | {:>14} | {:>14}", "Direction", "Input", "Output", "VWAP T1/T0", "VWAP T0/T1");
+    println!("{:-<116}", "");
+    for (index, &(label, input, output)) in flows.iter().enumerate() {
+        let (decimals_in, decimals_out) = if index == 0 {
+            (converter.decimals_0, converter.decimals_1)
+        } else {
+            (converter.decimals_1, converter.decimals_0)
+        };
+        // Zero on either side (a new pool, or no swaps in this direction) has no price
+        let (vwap_t1_per_t0, vwap_t0_per_t1) = if input == 0 || output == 0 {
+            ("n/a".to_string(), "n/a".to_string())
+        } else {
+            let raw = if index == 0 { output as f64 / input as f64 } else { input as f64 / output as f64 };
+            let human = raw * converter.decimal_adjustment();
+            (prices.format(human), prices.format(1.0 / human))
+        };
+        println!(
+            "{:<20} | {:>28} | {:>28} | {:>14} | {:>14}",
+            label,
+            format_token_amount(input, decimals_in),
+            format_token_amount(output, decimals_out),
+            vwap_t1_per_t0,
+            vwap_t0_per_t1
+        );
+    }
+}
 
-                    let (ext_pda, _) = Pubkey::find_program_address(&[TICK_ARRAY_BITMAP_SEED, pool_pubkey.as_ref()], &program_id);
-                    let ext_data = rpc_client.get_account_data(&ext_pda).expect("Failed to fetch bitmap extension");
-                    let extension = TickArrayBitmapExtension::deserialize(&mut &ext_data[8..]).expect("Failed to parse bitmap extension");
-                    println!("Done.");
+/// The pool's fee counters as `(label, [token 0, token 1])`, in raw units. Rows are in a fixed
+/// order: LP fees, LP fees claimed, protocol fees, fund fees.
+fn pool_fee_counters(pool_state: &PoolState) -> [(&'static str, [u64; 2]); 4] {
+    [
+        ("LP fees (total)", [pool_state.total_fees_token_0, pool_state.total_fees_token_1]),
+        ("LP fees claimed", [pool_state.total_fees_claimed_token_0, pool_state.total_fees_claimed_token_1]),
+        ("Protocol fees (uncollected)", [pool_state.protocol_fees_token_0, pool_state.protocol_fees_token_1]),
+        ("Fund fees (uncollected)", [pool_state.fund_fees_token_0, pool_state.fund_fees_token_1]),
+    ]
+}
 
-                    // 2. Setup Helpers
-                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
-                    let helper = TickArrayHelper { tick_spacing: pool_state.tick_spacing };
-                    
-                    // 3. Convert Price Range to Tick Range
-                    let (price_format_lower, price_format_upper, price_template) = match format {
-                        HumanPriceFormat::T0PerT1 => (
-                            PriceInput::Token0PerToken1Human { price: price_lower },
-                            PriceInput::Token0PerToken1Human { price: price_upper },
-                            PriceInput::Token0PerToken1Human { price: 0.0 }, // Template for printing
-                        ),
-                        HumanPriceFormat::T1PerT0 => (
-                            PriceInput::Token1PerToken0Human { price: price_lower },
-                            PriceInput::Token1PerToken0Human { price: price_upper },
-                            PriceInput::Token1PerToken0Human { price: 0.0 }, // Template for printing
-                        ),
-                    };
+/// Fetches both token mints of a pool in one `get_multiple_accounts` call and reads their decimals.
+async fn fetch_mint_decimals(rpc_client: &dyn AccountFetcher, pool_state: &PoolState) -> (u8, u8) {
+    let mints = [pool_state.token_mint_0, pool_state.token_mint_1];
+    let (_, accounts) = rpc_client.get_multiple_accounts_at(&mints, None).await
+        .unwrap_or_else(|e| fail_fetch("token mints", &e));
+
+    // The owner tells SPL Token and Token-2022 mints apart
+    let read = |index: usize| {
+        let account = accounts[index].as_ref()
+            .unwrap_or_else(|| fail(ExitCode::AccountNotFound, format!("Token mint {} not found.", mints[index])));
+        read_mint_decimals(&account.owner, &account.data)
+            .unwrap_or_else(|e| fail(ExitCode::Deserialize, format!("Failed to parse token mint {}: {}", mints[index], e)))
+    };
+    (read(0), read(1))
+}
 
-                    let tick_lower = converter.price_to_tick(price_format_lower);
-                    let tick_upper = converter.price_to_tick(price_format_upper);
-                    
-                    // Ensure min_tick is always the smaller number, max_tick is larger
-                    let (min_tick, max_tick) = if tick_lower > tick_upper {
-                        (tick_upper, tick_lower)
-                    } else {
-                        (tick_lower, tick_upper)
-                    };
+/// Seconds since the Unix epoch, by the local clock.
+fn unix_now() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
 
-                    println!("Input Price Range [{:.8}, {:.8}] maps to Tick Range [{}, {}]", price_lower, price_upper, min_tick, max_tick);
+/// A Unix timestamp as ISO-8601 UTC, e.g. `2025-01-31T12:00:00Z`. The date uses the days-to-civil
+/// conversion of the proleptic Gregorian calendar, which avoids a date library for one format.
+fn format_unix_time(timestamp: u64) -> String {
+    let (days, seconds) = ((timestamp / 86_400) as i64, timestamp % 86_400);
+    let shifted = days + 719_468; // days since 0000-03-01
+    let era = shifted.div_euclid(146_097);
+    let day_of_era = shifted - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153; // March = 0
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, seconds / 3_600, seconds / 60 % 60, seconds % 60)
+}
 
-                    // 4. Get ALL initialized arrays and SORT them
-                    let mut all_initialized_arrays = read_default_bitmap(&pool_state.tick_array_bitmap, pool_state.tick_spacing);
-                    all_initialized_arrays.append(&mut read_extension_bitmap(&extension, pool_state.tick_spacing));
-                    all_initialized_arrays.sort();
+/// A span of seconds in its two largest units, e.g. `3d 4h` or `12m 5s`.
+fn format_duration(seconds: u64) -> String {
+    let (days, hours, minutes) = (seconds / 86_400, seconds / 3_600 % 24, seconds / 60 % 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, seconds % 60)
+    } else {
+        format!("{}s", seconds)
+    }
+}
 
-                    // 5. Filter and Find Arrays
-                    let arrays_in_range: Vec<i32> = all_initialized_arrays.iter()
-                        .filter(|&&start_index| {
-                            let (_tick_start, tick_end) = helper.get_array_tick_range(start_index);
-                            let array_start = start_index;
-                            let array_end = tick_end; 
-                            (array_start <= max_tick) && (array_end >= min_tick)
-                        })
-                        .cloned()
-                        .collect();
+/// `timestamp` relative to `now`: `in 3d 4h` or `3d 4h ago`.
+fn format_relative_time(timestamp: u64, now: u64) -> String {
+    if timestamp > now {
+        format!("in {}", format_duration(timestamp - now))
+    } else {
+        format!("{} ago", format_duration(now - timestamp))
+    }
+}
 
-                    let lower_surrounding: Option<i32> = all_initialized_arrays.iter()
-                        .filter(|&&start_index| {
-                            let (_tick_start, tick_end) = helper.get_array_tick_range(start_index);
-                            tick_end < min_tick 
-                        })
-                        .last() 
-                        .cloned();
+/// A `recent_epoch` field: a Solana epoch number (about two days each), not a timestamp, so it is
+/// shown against the current epoch when known.
+fn format_epoch(recent_epoch: u64, current_epoch: Option<u64>) -> String {
+    match current_epoch {
+        Some(current) if current > recent_epoch => format!("epoch {} ({} epochs ago)", recent_epoch, current - recent_epoch),
+        Some(current) if current == recent_epoch => format!("epoch {} (the current epoch)", recent_epoch),
+        _ => format!("epoch {}", recent_epoch),
+    }
+}
 
-                    let upper_surrounding: Option<i32> = all_initialized_arrays.iter()
-                        .filter(|&&start_index| {
-                            start_index > max_tick 
-                        })
-                        .next() 
-                        .cloned();
+/// Name of a `RewardInfo::reward_state` value.
+fn reward_state_name(state: u8) -> &'static str {
+    match state {
+        0 => "uninitialized",
+        1 => "initialized",
+        2 => "opening",
+        3 => "ended",
+        _ => "unknown",
+    }
+}
 
-                    // 6. Fetch and Print Details
-                    if let Some(start_index) = lower_surrounding {
-                        println!("\n{:-<80}", "");
-                        println!("--- (Lower Surrounding Initialized Array) ---");
-                        fetch_and_print_array_details(&rpc_client, &pool_pubkey, &program_id, start_index, &converter, &helper, price_template);
-                    } else {
-                        println!("\n{:-<80}", "");
-                        println!("--- (No initialized array found below price range) ---");
-                    }
+/// `reward_growth_global_x64` brought forward to `now`, as the program's `update_reward_infos` does on
+/// the next instruction: the emissions since `last_update_time`, capped at `end_time`, spread over the
+/// pool's active liquidity. Unchanged before `open_time` or while no liquidity is in range.
+fn reward_growth_global_at(reward: &RewardInfo, pool_liquidity: u128, now: u64) -> u128 {
+    let latest = now.min(reward.end_time);
+    if now <= reward.open_time || pool_liquidity == 0 || latest <= reward.last_update_time {
+        return reward.reward_growth_global_x64;
+    }
+    let seconds = (latest - reward.last_update_time) as u128;
+    liquidity_math::mul_div_floor(seconds, reward.emissions_per_second_x64, pool_liquidity)
+        .map_or(reward.reward_growth_global_x64, |delta| reward.reward_growth_global_x64.wrapping_add(delta))
+}
 
-                    println!("\n{:=<80}", "");
-                    println!("--- ARRAYS INITIALIZED WITHIN PRICE RANGE ({}) ---", arrays_in_range.len());
-                    if arrays_in_range.is_empty() {
-                        println!("--- (No initialized arrays found within price range) ---");
-                    } else {
-                        for start_index in arrays_in_range {
-                            fetch_and_print_array_details(&rpc_client, &pool_pubkey, &program_id, start_index, &converter, &helper, price_template);
-                        }
-                    }
-                    println!("{:=<80}", "");
+/// Reward growth inside `[tick_lower, tick_upper]` for one reward slot, as the program's
+/// `get_reward_growths_inside`. A boundary tick that is not initialized (`None`) has no growth outside.
+fn reward_growth_inside(
+    global: u128,
+    slot: usize,
+    tick_current: i32,
+    (tick_lower, lower): (Tick, Option<&TickState>),
+    (tick_upper, upper): (Tick, Option<&TickState>),
+) -> u128 {
+    let outside = |tick: Option<&TickState>| tick.map(|tick| tick.reward_growths_outside_x64[slot]);
+    growth_inside(global, tick_current, (tick_lower, outside(lower)), (tick_upper, outside(upper)))
+}
 
+/// Growth inside `[tick_lower, tick_upper]` from the global growth and each boundary's growth outside,
+/// with the program's wrapping arithmetic (`get_fee_growth_inside`, `get_reward_growths_inside`).
+/// The current tick decides which side of a boundary its stored value covers; `None` counts as zero.
+fn growth_inside(global: u128, tick_current: i32, (tick_lower, lower): (Tick, Option<u128>), (tick_upper, upper): (Tick, Option<u128>)) -> u128 {
+    let below = match lower {
+        None => 0,
+        Some(outside) if tick_current < tick_lower.0 => global.wrapping_sub(outside),
+        Some(outside) => outside,
+    };
+    let above = match upper {
+        None => 0,
+        Some(outside) if tick_current < tick_upper.0 => outside,
+        Some(outside) => global.wrapping_sub(outside),
+    };
+    global.wrapping_sub(below).wrapping_sub(above)
+}
 
-                    if let Some(start_index) = upper_surrounding {
-                        println!("\n{:-<80}", "");
-                        println!("--- (Upper Surrounding Initialized Array) ---");
-                        fetch_and_print_array_details(&rpc_client, &pool_pubkey, &program_id, start_index, &converter, &helper, price_template);
-                    } else {
-                        println!("\n{:-<80}", "");
-                        println!("--- (No initialized array found above price range) ---");
-                    }
-                },
-                RpcCommands::InitializedRange { pool_id, price_lower, price_upper, format, rpc_url } => {
-                    println!("--- Initialized Array Range Analysis for {} ---", pool_id);
-                    let rpc_client = RpcClient::new(rpc_url);
-                    let pool_pubkey = Pubkey::from_str(&pool_id).expect("Invalid Pool ID");
-                    let program_id = Pubkey::from_str(RAYDIUM_CLMM_PROGRAM_ID).unwrap();
+/// States how `--swap-tokens` was applied, since token labels in the output follow the pool's order.
+fn print_swap_tokens_note(decimals_0: u8, decimals_1: u8) {
+    println!("Note: --swap-tokens: token 0 has {} decimals and token 1 has {}; labels below follow the pool's token order.", decimals_0, decimals_1);
+}
 
-                    // 1. Fetch Base Data (PoolState + Extension)
-                    println!("Fetching pool info and bitmaps...");
-                    let pool_state_data = rpc_client.get_account_data(&pool_pubkey).expect("Failed to fetch pool state");
-                    let pool_state = PoolState::deserialize(&mut &pool_state_data[8..]).expect("Failed to parse pool state");
+/// Tick spacing and mint decimals, which offline commands take as flags or from `--pool-id`.
+struct PoolParams {
+    tick_spacing: Option<u16>,
+    decimals_0: Option<u8>,
+    decimals_1: Option<u8>,
+}
 
-                    let (ext_pda, _) = Pubkey::find_program_address(&[TICK_ARRAY_BITMAP_SEED, pool_pubkey.as_ref()], &program_id);
-                    let ext_data = rpc_client.get_account_data(&ext_pda).expect("Failed to fetch bitmap extension");
-                    let extension = TickArrayBitmapExtension::deserialize(&mut &ext_data[8..]).expect("Failed to parse bitmap extension");
-                    println!("Done.");
+impl PoolParams {
+    /// Under `--swap-tokens` the decimals flags name the tokens in the opposite order; puts them in the pool's.
+    fn swapped_if(self, swap_tokens: bool) -> Self {
+        if swap_tokens {
+            PoolParams { decimals_0: self.decimals_1, decimals_1: self.decimals_0, ..self }
+        } else {
+            self
+        }
+    }
 
-                    // 2. Setup Helpers
-                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
-                    let helper = TickArrayHelper { tick_spacing: pool_state.tick_spacing };
-                    
-                    // 3. Convert Price Range to Tick Range
-                    let (price_format_lower, price_format_upper, price_template) = match format {
-                        HumanPriceFormat::T0PerT1 => (
-                            PriceInput::Token0PerToken1Human { price: price_lower },
-                            PriceInput::Token0PerToken1Human { price: price_upper },
-                            PriceInput::Token0PerToken1Human { price: 0.0 }, // Template for printing
-                        ),
-                        HumanPriceFormat::T1PerT0 => (
-                            PriceInput::Token1PerToken0Human { price: price_lower },
-                            PriceInput::Token1PerToken0Human { price: price_upper },
-                            PriceInput::Token1PerToken0Human { price: 0.0 }, // Template for printing
-                        ),
-                    };
+    fn tick_spacing(&self) -> u16 {
+        self.tick_spacing
+            .unwrap_or_else(|| fail(ExitCode::InvalidInput, "--tick-spacing is required when the pool cannot be fetched."))
+    }
 
-                    let tick_lower = converter.price_to_tick(price_format_lower);
-                    let tick_upper = converter.price_to_tick(price_format_upper);
-                    
-                    // Ensure min_tick is always the smaller number, max_tick is larger
-                    let (min_tick, max_tick) = if tick_lower > tick_upper {
-                        (tick_upper, tick_lower)
-                    } else {
-                        (tick_lower, tick_upper)
-                    };
+    fn decimals(&self) -> (u8, u8) {
+        match (self.decimals_0, self.decimals_1) {
+            (Some(decimals_0), Some(decimals_1)) => (decimals_0, decimals_1),
+            _ => fail(ExitCode::InvalidInput, "--decimals0 and --decimals1 are required when the pool cannot be fetched."),
+        }
+    }
+}
+
+/// Fills in the pool parameters of an offline command from the pool's `PoolState`, fetched once.
+/// Without a pool id the flags are returned as given. Flags that disagree with the pool are warned
+/// about and the pool's values used. If the RPC is unreachable, the flags are kept so pure-offline
+/// usage still works; a missing account or a non-pool account still exits. `bare_output` moves the
+/// resolved values line to stderr, for commands whose stdout is a single value.
+async fn resolve_pool_params(
+    pool_id: Option<&str>,
+    given: PoolParams,
+    rpc_url: Vec<String>,
+    fetch_options: FetchOptions,
+    program_id: &Pubkey,
+    cache: Option<&PoolCache>,
+    bare_output: bool,
+) -> PoolParams {
+    let Some(pool_id) = pool_id else {
+        if let Some(tick_spacing) = given.tick_spacing {
+            warn_unknown_tick_spacing(tick_spacing);
+        }
+        return given;
+    };
+    let pool_pubkey = parse_pubkey(pool_id, "Pool ID");
+    let (pool_state, cached_age) = match cache.and_then(|cache| cache.load(&pool_pubkey)) {
+        Some((account, age)) => (decode_clmm_account_or_exit::<PoolState>(&account, &pool_pubkey, program_id), Some(age)),
+        None => {
+            let rpc_client = RpcFetcher::new(rpc_url, fetch_options);
+            let account = match rpc_client.get_account(&pool_pubkey).await {
+                Ok(account) => account,
+                Err(e) if is_account_not_found(&e) => fail_fetch(format!("PoolState {}", pool_pubkey), &e),
+                Err(e) => {
+                    eprintln!("Warning: could not fetch pool {} ({}); using the values passed on the command line.", pool_pubkey, e);
+                    if let Some(tick_spacing) = given.tick_spacing {
+                        warn_unknown_tick_spacing(tick_spacing);
+                    }
+                    return given;
+                }
+            };
+            let pool_state = decode_clmm_account_or_exit::<PoolState>(&account, &pool_pubkey, program_id);
+            if let Some(cache) = cache {
+                cache.store(&pool_pubkey, &account, &pool_state);
+            }
+            (pool_state, None)
+        }
+    };
+
+    let resolved = format!(
+        "Resolved from pool {}: tick_spacing = {}, decimals0 = {}, decimals1 = {}{}",
+        pool_pubkey,
+        pool_state.tick_spacing,
+        pool_state.mint_decimals_0,
+        pool_state.mint_decimals_1,
+        cached_age.map_or(String::new(), |age| format!(" (cached {}s ago)", age.as_secs()))
+    );
+    if bare_output {
+        eprintln!("{}", resolved);
+    } else {
+        println!("{}", resolved);
+    }
+    fn check<T: PartialEq + std::fmt::Display>(flag: &str, given: Option<T>, on_chain: &T) {
+        if let Some(given) = given
+            && given != *on_chain
+        {
+            eprintln!("Warning: {} {} differs from the pool's {}; using the pool's value.", flag, given, on_chain);
+        }
+    }
+    if let Some(tick_spacing) = given.tick_spacing
+        && tick_spacing != pool_state.tick_spacing
+    {
+        eprintln!(
+            "WARNING: --tick-spacing {} does NOT match the pool's tick_spacing {}. Array start indices and PDAs computed with {} would be wrong; using the pool's value.",
+            tick_spacing, pool_state.tick_spacing, tick_spacing
+        );
+    }
+    check("--decimals0", given.decimals_0, &pool_state.mint_decimals_0);
+    check("--decimals1", given.decimals_1, &pool_state.mint_decimals_1);
+    if pool_state.mint_decimals_0 > MAX_TOKEN_DECIMALS || pool_state.mint_decimals_1 > MAX_TOKEN_DECIMALS {
+        eprintln!("Warning: a mint has more than {} decimals; human prices may lose precision.", MAX_TOKEN_DECIMALS);
+    }
+    PoolParams {
+        tick_spacing: Some(pool_state.tick_spacing),
+        decimals_0: Some(pool_state.mint_decimals_0),
+        decimals_1: Some(pool_state.mint_decimals_1),
+    }
+}
 
-                    println!("Input Price Range [{:.6}, {:.6}] maps to Tick Range [{}, {}]", price_lower, price_upper, min_tick, max_tick);
+/// Computes the time-weighted average tick over (at most) the last `seconds` of the
+/// observation ring buffer. The tick cumulative at the window start is interpolated between
+/// the two observations around it, since the tick is constant between observations.
+/// Returns the average tick and the window actually covered.
+fn compute_twap_tick(observation_state: &ObservationState, seconds: u32) -> Option<(i32, u32)> {
+    let latest_index = observation_state.observation_index as usize % OBSERVATION_NUM;
+    let latest = observation_state.observations[latest_index];
+    if latest.block_timestamp == 0 || seconds == 0 {
+        return None;
+    }
+    let target_timestamp = latest.block_timestamp.saturating_sub(seconds);
+
+    // Walk backwards from the latest entry, wrapping around the ring buffer, until we
+    // find the newest observation at or before the start of the window.
+    let (mut oldest, mut newer) = (None, latest);
+    for step in 1..OBSERVATION_NUM {
+        let index = (latest_index + OBSERVATION_NUM - step) % OBSERVATION_NUM;
+        let observation = observation_state.observations[index];
+        if observation.block_timestamp == 0 || observation.block_timestamp >= newer.block_timestamp {
+            break; // Uninitialized slot or wrapped onto newer data
+        }
+        oldest = Some(observation);
+        if observation.block_timestamp <= target_timestamp {
+            break;
+        }
+        newer = observation;
+    }
 
-                    // 4. Get ALL initialized arrays and SORT them
-                    let mut all_initialized_arrays = read_default_bitmap(&pool_state.tick_array_bitmap, pool_state.tick_spacing);
-                    all_initialized_arrays.append(&mut read_extension_bitmap(&extension, pool_state.tick_spacing));
-                    all_initialized_arrays.sort();
+    let oldest = oldest?;
+    let (start_timestamp, start_cumulative) = if oldest.block_timestamp < target_timestamp {
+        // The window starts inside (oldest, newer]: interpolate the cumulative there
+        let elapsed = (target_timestamp - oldest.block_timestamp) as i128;
+        let span = (newer.block_timestamp - oldest.block_timestamp) as i128;
+        let delta = (newer.tick_cumulative - oldest.tick_cumulative) as i128;
+        (target_timestamp, oldest.tick_cumulative + (delta * elapsed).div_euclid(span) as i64)
+    } else {
+        (oldest.block_timestamp, oldest.tick_cumulative)
+    };
+    let window = latest.block_timestamp - start_timestamp;
+    let tick_delta = latest.tick_cumulative - start_cumulative;
+    let avg_tick = tick_delta.div_euclid(window as i64) as i32;
+    Some((avg_tick, window))
+}
 
-                    // 5. Filter and Find Arrays
-                    let arrays_in_range: Vec<i32> = all_initialized_arrays.iter()
-                        .filter(|&&start_index| {
-                            let (_tick_start, tick_end) = helper.get_array_tick_range(start_index);
-                            // An array overlaps the range if:
-                            // (array_start <= max_tick) AND (array_end >= min_tick)
-                            let array_start = start_index;
-                            let array_end = tick_end; // tick_end from helper is inclusive
-                            (array_start <= max_tick) && (array_end >= min_tick)
-                        })
-                        .cloned()
-                        .collect();
+/// One tick array in the `--output json` result of the swap-arrays commands.
+#[derive(Serialize)]
+struct SwapArrayJson {
+    label: String,
+    start_index: i32,
+    pda: String,
+    /// Always true: the program updates tick arrays it crosses.
+    writable: bool,
+    /// With `--verify`: whether the account was found on-chain.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exists: Option<bool>,
+    /// With `--verify`: the array's `initialized_tick_count`, when it exists.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    initialized_tick_count: Option<u8>,
+}
 
-                    let lower_surrounding: Option<i32> = all_initialized_arrays.iter()
-                        .filter(|&&start_index| {
-                            let (_tick_start, tick_end) = helper.get_array_tick_range(start_index);
-                            tick_end < min_tick // Find arrays that *end* before our range starts
-                        })
-                        .last() // Get the one closest (last) to the range
-                        .cloned();
+/// Result of `derive-pda --json` and of its multi-tick table.
+#[derive(Serialize)]
+struct DerivePdaJson {
+    pool_id: String,
+    tick_spacing: u16,
+    /// Each array once, sorted by start index.
+    arrays: Vec<DerivePdaArrayJson>,
+}
 
-                    let upper_surrounding: Option<i32> = all_initialized_arrays.iter()
-                        .filter(|&&start_index| {
-                            start_index > max_tick // Find arrays that *start* after our range ends
-                        })
-                        .next() // Get the one closest (first) to the range
-                        .cloned();
+/// One tick array of `DerivePdaJson`.
+#[derive(Serialize)]
+struct DerivePdaArrayJson {
+    start_index: i32,
+    pda: String,
+    /// The input ticks (including `--tick-range` bounds) in this array; empty for arrays only inside the range.
+    ticks: Vec<i32>,
+}
 
-                    // 6. Fetch and Print Details using the new helper function
+/// Result of the swap-arrays commands in `--output json` mode.
+#[derive(Serialize)]
+struct SwapArraysJson {
+    pool_id: String,
+    bitmap_extension: String,
+    observation_key: String,
+    direction: String,
+    start_tick: i32,
+    tick_range: [i32; 2],
+    /// Arrays in swap traversal order, ready to pass as `remaining_accounts`.
+    arrays: Vec<SwapArrayJson>,
+    /// The complete `swap_v2` account list, with `--full-accounts`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    accounts: Option<Vec<SwapAccountJson>>,
+    /// The `sqrt_price_limit_x64` for `--slippage-pct`, as a decimal string (u128 exceeds JSON-safe integers).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sqrt_price_limit_x64: Option<String>,
+}
 
-                    if let Some(start_index) = lower_surrounding {
-                        println!("\n{:-<80}", "");
-                        println!("--- (Lower Surrounding Initialized Array) ---");
-                        fetch_and_print_array_details(&rpc_client, &pool_pubkey, &program_id, start_index, &converter, &helper, price_template);
-                    } else {
-                        println!("\n{:-<80}", "");
-                        println!("--- (No initialized array found below price range) ---");
-                    }
+/// One account of a `swap_v2` instruction, in instruction order.
+#[derive(Serialize)]
+struct SwapAccountJson {
+    name: String,
+    /// `None` for the accounts the caller supplies: the payer and its token accounts.
+    pubkey: Option<String>,
+    signer: bool,
+    writable: bool,
+}
 
-                    println!("\n{:=<80}", "");
-                    println!("--- ARRAYS INITIALIZED WITHIN PRICE RANGE ({}) ---", arrays_in_range.len());
-                    if arrays_in_range.is_empty() {
-                        println!("--- (No initialized arrays found within price range) ---");
-                    } else {
-                        for start_index in arrays_in_range {
-                            fetch_and_print_array_details(&rpc_client, &pool_pubkey, &program_id, start_index, &converter, &helper, price_template);
-                        }
-                    }
-                    println!("{:=<80}", "");
+/// Result of `rpc snapshot`. Integers that can exceed 2^53 (every u64, u128 and i128 field) are
+/// decimal strings, so JavaScript readers do not lose precision; pubkeys are base58.
+#[derive(Serialize)]
+struct PoolSnapshotJson {
+    pool_id: String,
+    program_id: String,
+    /// Slot of the pool state and bitmap extension read.
+    slot: u64,
+    /// Slot of the tick array read: never older than `slot`, but possibly later.
+    arrays_slot: u64,
+    /// When the snapshot was taken by this machine's clock, ISO-8601 UTC.
+    captured_at: String,
+    captured_at_unix: u64,
+    pool: PoolStateJson,
+    extension_bitmap: ExtensionBitmapJson,
+    /// Start indices of the arrays marked in either bitmap, sorted.
+    initialized_arrays: Vec<i32>,
+    tick_arrays: Vec<TickArraySnapshotJson>,
+    /// Arrays marked in the bitmaps whose account is missing or does not decode.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    unreadable_arrays: Vec<UnreadableArrayJson>,
+}
+
+/// The decoded `PoolState` fields of a snapshot, without padding.
+#[derive(Serialize)]
+struct PoolStateJson {
+    amm_config: String,
+    owner: String,
+    token_mint_0: String,
+    token_mint_1: String,
+    token_vault_0: String,
+    token_vault_1: String,
+    observation_key: String,
+    mint_decimals_0: u8,
+    mint_decimals_1: u8,
+    tick_spacing: u16,
+    liquidity: String,
+    sqrt_price_x64: String,
+    tick_current: i32,
+    fee_growth_global_0_x64: String,
+    fee_growth_global_1_x64: String,
+    protocol_fees_token_0: String,
+    protocol_fees_token_1: String,
+    swap_in_amount_token_0: String,
+    swap_out_amount_token_1: String,
+    swap_in_amount_token_1: String,
+    swap_out_amount_token_0: String,
+    status: u8,
+    reward_infos: Vec<RewardInfoJson>,
+    /// The default bitmap's 16 words, lowest array offset first.
+    tick_array_bitmap: Vec<String>,
+    total_fees_token_0: String,
+    total_fees_claimed_token_0: String,
+    total_fees_token_1: String,
+    total_fees_claimed_token_1: String,
+    fund_fees_token_0: String,
+    fund_fees_token_1: String,
+    open_time: String,
+    recent_epoch: String,
+}
+
+impl PoolStateJson {
+    fn new(pool_state: &PoolState) -> Self {
+        PoolStateJson {
+            amm_config: pool_state.amm_config.to_string(),
+            owner: pool_state.owner.to_string(),
+            token_mint_0: pool_state.token_mint_0.to_string(),
+            token_mint_1: pool_state.token_mint_1.to_string(),
+            token_vault_0: pool_state.token_vault_0.to_string(),
+            token_vault_1: pool_state.token_vault_1.to_string(),
+            observation_key: pool_state.observation_key.to_string(),
+            mint_decimals_0: pool_state.mint_decimals_0,
+            mint_decimals_1: pool_state.mint_decimals_1,
+            tick_spacing: pool_state.tick_spacing,
+            liquidity: pool_state.liquidity.to_string(),
+            sqrt_price_x64: pool_state.sqrt_price_x64.to_string(),
+            tick_current: pool_state.tick_current,
+            fee_growth_global_0_x64: pool_state.fee_growth_global_0_x64.to_string(),
+            fee_growth_global_1_x64: pool_state.fee_growth_global_1_x64.to_string(),
+            protocol_fees_token_0: pool_state.protocol_fees_token_0.to_string(),
+            protocol_fees_token_1: pool_state.protocol_fees_token_1.to_string(),
+            swap_in_amount_token_0: pool_state.swap_in_amount_token_0.to_string(),
+            swap_out_amount_token_1: pool_state.swap_out_amount_token_1.to_string(),
+            swap_in_amount_token_1: pool_state.swap_in_amount_token_1.to_string(),
+            swap_out_amount_token_0: pool_state.swap_out_amount_token_0.to_string(),
+            status: pool_state.status,
+            reward_infos: pool_state.reward_infos.iter().map(RewardInfoJson::new).collect(),
+            tick_array_bitmap: u64_strings(&pool_state.tick_array_bitmap),
+            total_fees_token_0: pool_state.total_fees_token_0.to_string(),
+            total_fees_claimed_token_0: pool_state.total_fees_claimed_token_0.to_string(),
+            total_fees_token_1: pool_state.total_fees_token_1.to_string(),
+            total_fees_claimed_token_1: pool_state.total_fees_claimed_token_1.to_string(),
+            fund_fees_token_0: pool_state.fund_fees_token_0.to_string(),
+            fund_fees_token_1: pool_state.fund_fees_token_1.to_string(),
+            open_time: pool_state.open_time.to_string(),
+            recent_epoch: pool_state.recent_epoch.to_string(),
+        }
+    }
+}
+
+/// One `RewardInfo` slot of `PoolStateJson`.
+#[derive(Serialize)]
+struct RewardInfoJson {
+    reward_state: u8,
+    open_time: String,
+    end_time: String,
+    last_update_time: String,
+    emissions_per_second_x64: String,
+    reward_total_emissioned: String,
+    reward_claimed: String,
+    token_mint: String,
+    token_vault: String,
+    authority: String,
+    reward_growth_global_x64: String,
+}
 
+impl RewardInfoJson {
+    fn new(reward_info: &RewardInfo) -> Self {
+        RewardInfoJson {
+            reward_state: reward_info.reward_state,
+            open_time: reward_info.open_time.to_string(),
+            end_time: reward_info.end_time.to_string(),
+            last_update_time: reward_info.last_update_time.to_string(),
+            emissions_per_second_x64: reward_info.emissions_per_second_x64.to_string(),
+            reward_total_emissioned: reward_info.reward_total_emissioned.to_string(),
+            reward_claimed: reward_info.reward_claimed.to_string(),
+            token_mint: reward_info.token_mint.to_string(),
+            token_vault: reward_info.token_vault.to_string(),
+            authority: reward_info.authority.to_string(),
+            reward_growth_global_x64: reward_info.reward_growth_global_x64.to_string(),
+        }
+    }
+}
 
-                    if let Some(start_index) = upper_surrounding {
-                        println!("\n{:-<80}", "");
-                        println!("--- (Upper Surrounding Initialized Array) ---");
-                        fetch_and_print_array_details(&rpc_client, &pool_pubkey, &program_id, start_index, &converter, &helper, price_template);
-                    } else {
-                        println!("\n{:-<80}", "");
-                        println!("--- (No initialized array found above price range) ---");
-                    }
-                },
-                RpcCommands::LiquidityCurve { pool_id, format, max_width, rpc_url, show_arrays } => {
-                    let rpc_client = RpcClient::new(rpc_url);
-                    let pool_pubkey = Pubkey::from_str(&pool_id).expect("Invalid Pool ID");
-                    let program_id = Pubkey::from_str(RAYDIUM_CLMM_PROGRAM_ID).unwrap();
+/// The bitmap extension of a snapshot: 14 chunks of 8 words per side, chunk 0 nearest the default bitmap.
+#[derive(Serialize)]
+struct ExtensionBitmapJson {
+    pubkey: String,
+    positive: Vec<Vec<String>>,
+    negative: Vec<Vec<String>>,
+}
 
-                    println!("Fetching pool info and bitmaps...");
-                    let pool_state_data = rpc_client.get_account_data(&pool_pubkey).expect("Failed to fetch pool state");
-                    let pool_state = PoolState::deserialize(&mut &pool_state_data[8..]).expect("Failed to parse pool state");
-                    
-                    let (ext_pda, _) = Pubkey::find_program_address(&[TICK_ARRAY_BITMAP_SEED, pool_pubkey.as_ref()], &program_id);
-                    let ext_data = rpc_client.get_account_data(&ext_pda).expect("Failed to fetch bitmap extension");
-                    let extension = TickArrayBitmapExtension::deserialize(&mut &ext_data[8..]).expect("Failed to parse bitmap extension");
+/// One tick array of a snapshot, with only its initialized ticks (the program zeroes the others).
+#[derive(Serialize)]
+struct TickArraySnapshotJson {
+    start_index: i32,
+    pda: String,
+    initialized_tick_count: u8,
+    recent_epoch: String,
+    ticks: Vec<TickSnapshotJson>,
+}
 
-                    // Get all initialized array start indices from bitmaps
-                    let mut all_initialized_arrays = read_default_bitmap(&pool_state.tick_array_bitmap, pool_state.tick_spacing);
-                    all_initialized_arrays.append(&mut read_extension_bitmap(&extension, pool_state.tick_spacing));
+impl TickArraySnapshotJson {
+    fn new(pda: &Pubkey, tick_array: &TickArrayState) -> Self {
+        TickArraySnapshotJson {
+            start_index: tick_array.start_tick_index,
+            pda: pda.to_string(),
+            initialized_tick_count: tick_array.initialized_tick_count,
+            recent_epoch: tick_array.recent_epoch.to_string(),
+            ticks: tick_array.ticks.iter()
+                .filter(|tick| tick.liquidity_gross != 0)
+                .map(|tick| TickSnapshotJson {
+                    tick: tick.tick,
+                    liquidity_net: tick.liquidity_net.to_string(),
+                    liquidity_gross: tick.liquidity_gross.to_string(),
+                    fee_growth_outside_0_x64: tick.fee_growth_outside_0_x64.to_string(),
+                    fee_growth_outside_1_x64: tick.fee_growth_outside_1_x64.to_string(),
+                    reward_growths_outside_x64: tick.reward_growths_outside_x64.map(|growth| growth.to_string()),
+                })
+                .collect(),
+        }
+    }
+}
 
-                    println!(
-                        "Found {} initialized tick arrays. Fetching each account... (this will be slow)",
-                        all_initialized_arrays.len()
-                    );
+/// One initialized tick of `TickArraySnapshotJson`.
+#[derive(Serialize)]
+struct TickSnapshotJson {
+    tick: i32,
+    liquidity_net: String,
+    liquidity_gross: String,
+    fee_growth_outside_0_x64: String,
+    fee_growth_outside_1_x64: String,
+    reward_growths_outside_x64: [String; 3],
+}
 
-                    // Fetch each tick array individually and extract ticks
-                    let mut all_ticks = Vec::new();
-                    for start_index in all_initialized_arrays {
-                        let (pda, _) = Pubkey::find_program_address(&[TICK_ARRAY_SEED, pool_pubkey.as_ref(), &start_index.to_be_bytes()], &program_id);
-                        if let Ok(account_data) = rpc_client.get_account_data(&pda) {
-                            if let Ok(tick_array) = TickArrayState::deserialize(&mut &account_data[8..]) {
-                                for tick_state in tick_array.ticks.iter() {
-                                    if tick_state.liquidity_gross != 0 {
-                                        all_ticks.push((tick_state.tick, tick_state.liquidity_net));
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    
-                    println!("Done fetching and parsing.");
-                    
-                    let converter = TickConverter {
-                        decimals_0: pool_state.mint_decimals_0,
-                        decimals_1: pool_state.mint_decimals_1,
-                    };
+/// An array of `PoolSnapshotJson` that is marked in a bitmap but could not be read.
+#[derive(Serialize)]
+struct UnreadableArrayJson {
+    start_index: i32,
+    pda: String,
+    error: String,
+}
 
-                    let price_format_template = match format {
-                        HumanPriceFormat::T0PerT1 => PriceInput::Token0PerToken1Human { price: 0.0 },
-                        HumanPriceFormat::T1PerT0 => PriceInput::Token1PerToken0Human { price: 0.0 },
-                    };
+/// One slot of `rpc inspect-array --output json`.
+#[derive(Serialize)]
+struct ArraySlotJson {
+    slot: usize,
+    tick: i32,
+    price: f64,
+    liquidity_net: String,
+    liquidity_gross: String,
+}
 
-                    print_exact_liquidity_ranges(
-                        &mut all_ticks,
-                        &converter,
-                        price_format_template,
-                        max_width,
-                        pool_state.tick_current,
-                        pool_state.tick_spacing,
-                        &pool_pubkey,
-                        &program_id,
-                        show_arrays,
-                    );
+impl ArraySlotJson {
+    fn new(slot: &ArraySlot) -> Self {
+        ArraySlotJson {
+            slot: slot.slot,
+            tick: slot.tick,
+            price: slot.price,
+            liquidity_net: slot.liquidity_net.to_string(),
+            liquidity_gross: slot.liquidity_gross.to_string(),
+        }
+    }
+}
 
-                },
-                RpcCommands::InspectArray { pool_id, start_index, pda, rpc_url } => {
-                    let rpc_client = RpcClient::new(rpc_url);
-                    let pool_pubkey = Pubkey::from_str(&pool_id).expect("Invalid Pool ID");
-                    let program_id = Pubkey::from_str(RAYDIUM_CLMM_PROGRAM_ID).unwrap();
+/// Bitmap words as decimal strings.
+fn u64_strings(words: &[u64]) -> Vec<String> {
+    words.iter().map(|word| word.to_string()).collect()
+}
 
-                    // Determine the PDA from the provided input (either start_index or pda)
-                    let tick_array_pda = if let Some(start_idx) = start_index {
-                        println!("Deriving PDA from start index {}...", start_idx);
-                        Pubkey::find_program_address(&[TICK_ARRAY_SEED, pool_pubkey.as_ref(), &start_idx.to_be_bytes()], &program_id).0
-                    } else if let Some(pda_str) = pda {
-                        println!("Using provided PDA {}...", &pda_str);
-                        Pubkey::from_str(&pda_str).expect("Invalid PDA format")
-                    } else {
-                        // This case is prevented by clap's `group` attribute, but we handle it anyway
-                        eprintln!("Error: You must provide either --start-index or --pda.");
-                        return;
-                    };
+/// Builds the ordered `swap_v2` account list: the 13 named accounts of the instruction, then the
+/// bitmap extension and the tick arrays as `remaining_accounts`.
+/// Input and output follow the direction: buy-t1 sells token 0, buy-t0 sells token 1.
+fn swap_v2_accounts(result: &SwapArraysJson, pool_state: &PoolState, direction: SwapDirection) -> Vec<SwapAccountJson> {
+    let (input_vault, output_vault, input_mint, output_mint) = match direction {
+        SwapDirection::BuyT1 => (pool_state.token_vault_0, pool_state.token_vault_1, pool_state.token_mint_0, pool_state.token_mint_1),
+        SwapDirection::BuyT0 => (pool_state.token_vault_1, pool_state.token_vault_0, pool_state.token_mint_1, pool_state.token_mint_0),
+    };
+    let account = |name: &str, pubkey: Option<String>, signer: bool, writable: bool| SwapAccountJson {
+        name: name.to_string(),
+        pubkey,
+        signer,
+        writable,
+    };
+
+    let mut accounts = vec![
+        account("payer", None, true, false),
+        account("amm_config", Some(pool_state.amm_config.to_string()), false, false),
+        account("pool_state", Some(result.pool_id.clone()), false, true),
+        account("input_token_account", None, false, true),
+        account("output_token_account", None, false, true),
+        account("input_vault", Some(input_vault.to_string()), false, true),
+        account("output_vault", Some(output_vault.to_string()), false, true),
+        account("observation_state", Some(result.observation_key.clone()), false, true),
+        account("token_program", Some(SPL_TOKEN_PROGRAM_ID.to_string()), false, false),
+        account("token_program_2022", Some(SPL_TOKEN_2022_PROGRAM_ID.to_string()), false, false),
+        account("memo_program", Some(SPL_MEMO_PROGRAM_ID.to_string()), false, false),
+        account("input_vault_mint", Some(input_mint.to_string()), false, false),
+        account("output_vault_mint", Some(output_mint.to_string()), false, false),
+        account("tick_array_bitmap_extension", Some(result.bitmap_extension.clone()), false, false),
+    ];
+    // Arrays that `--verify` found missing would fail the swap, so they are left out
+    accounts.extend(result.arrays.iter().filter(|array| array.exists != Some(false)).map(|array| {
+        account(&format!("tick_array {} {}", array.label, array.start_index), Some(array.pda.clone()), false, true)
+    }));
+    accounts
+}
 
-                    println!("Fetching account data for PDA: {}", tick_array_pda);
-                    let account_data = rpc_client.get_account_data(&tick_array_pda).expect("Failed to fetch tick array");
-                    let tick_array = TickArrayState::deserialize(&mut &account_data[8..]).expect("Failed to parse tick array");
-                    
-                    // We still need tick_spacing from the main pool state for correct visualization
-                    let pool_state_data = rpc_client.get_account_data(&pool_pubkey).expect("Failed to fetch pool state");
-                    let pool_state = PoolState::deserialize(&mut &pool_state_data[8..]).expect("Failed to parse pool state");
-                    
-                    println!("Done.");
+/// Batch-fetches the selected swap arrays and records `exists` and `initialized_tick_count` on each.
+/// Arrays that are missing (or not valid tick arrays of this program) get a loud warning on stderr
+/// with the nearest existing array from a getProgramAccounts sweep. Returns the number missing.
+async fn verify_swap_arrays(
+    rpc_client: &RpcFetcher,
+    result: &mut SwapArraysJson,
+    pool_pubkey: &Pubkey,
+    program_id: &Pubkey,
+) -> usize {
+    // 1. One batched fetch for all selected arrays
+    let pdas: Vec<Pubkey> = result.arrays.iter()
+        .map(|array| derive_tick_array_pda(pool_pubkey, array.start_index, program_id))
+        .collect();
+    let (_, accounts) = rpc_client.get_multiple_accounts_at(&pdas, None).await
+        .unwrap_or_else(|e| fail_fetch("the swap arrays", &e));
+
+    // 2. Annotate each array
+    let mut missing = Vec::new();
+    for ((array, pda), account) in result.arrays.iter_mut().zip(&pdas).zip(&accounts) {
+        let tick_array = account.as_ref().map(|account| TickArrayRef::new(pda, &account.owner, &account.data, program_id));
+        match tick_array {
+            Some(Ok(tick_array)) => {
+                array.exists = Some(true);
+                array.initialized_tick_count = Some(tick_array.initialized_tick_count());
+            }
+            Some(Err(e)) => {
+                eprintln!("Warning: {} is not a valid tick array: {}", pda, e);
+                array.exists = Some(false);
+                missing.push(array.start_index);
+            }
+            None => {
+                array.exists = Some(false);
+                missing.push(array.start_index);
+            }
+        }
+    }
+    if missing.is_empty() {
+        return 0;
+    }
 
-                    // Call the visualization function, now passing the PDA to be printed
-                    print_tick_array_visualization(&tick_array, pool_state.tick_spacing, &tick_array_pda);
-                },
-                RpcCommands::FullAnalysis { pool_id, format, rpc_url } => {
-                    let rpc_client = RpcClient::new(rpc_url);
-                    let pool_pubkey = Pubkey::from_str(&pool_id).expect("Invalid Pool ID");
-                    let program_id = Pubkey::from_str(RAYDIUM_CLMM_PROGRAM_ID).unwrap();
+    // 3. Suggest the nearest existing array for each missing one
+    eprintln!(
+        "WARNING: {} selected tick array(s) do not exist on-chain. A swap that reaches them will fail; \
+        the bitmap decode may be wrong for this range.",
+        missing.len()
+    );
+    let existing: Vec<i32> = match rpc_client.get_program_accounts(program_id, pool_tick_array_filters(pool_pubkey)).await {
+        Ok(swept) => swept.iter()
+            .filter_map(|(pubkey, account)| TickArrayRef::new(pubkey, &account.owner, &account.data, program_id).ok())
+            .map(|tick_array| tick_array.start_tick_index())
+            .collect(),
+        Err(e) => {
+            eprintln!("  (No alternatives: getProgramAccounts failed: {})", e);
+            Vec::new()
+        }
+    };
+    for start_index in &missing {
+        let nearest = existing.iter()
+            .filter(|s| !result.arrays.iter().any(|array| array.start_index == **s))
+            .min_by_key(|s| (*s - start_index).abs());
+        match nearest {
+            Some(&alternative) => eprintln!(
+                "  - {} is missing; nearest existing array: {} ({})",
+                start_index, alternative, derive_tick_array_pda(pool_pubkey, alternative, program_id)
+            ),
+            None => eprintln!("  - {} is missing; no other existing array found", start_index),
+        }
+    }
+    missing.len()
+}
 
-                    // 1. Fetch both Pool State and Extension Bitmap
-                    println!("Fetching on-chain data...");
-                    let pool_state_data = rpc_client.get_account_data(&pool_pubkey).expect("Failed to fetch pool state");
-                    let pool_state = PoolState::deserialize(&mut &pool_state_data[8..]).expect("Failed to parse pool state");
+/// Prints the `--full-accounts` list as a table. Caller-supplied accounts show as `<name>`.
+fn print_swap_v2_accounts(accounts: &[SwapAccountJson]) {
+    println!("\n--- swap_v2 ACCOUNTS (instruction order) ---");
+    println!("{:<3} | {:<32} | {:<6} | {:<8} | Pubkey", "#", "Name", "Signer", "Writable");
+    println!("{:-<110}", "");
+    for (i, account) in accounts.iter().enumerate() {
+        let pubkey = account.pubkey.clone().unwrap_or_else(|| format!("<{}>", account.name));
+        println!(
+            "{:<3} | {:<32} | {:<6} | {:<8} | {}",
+            i, account.name, if account.signer { "yes" } else { "" }, if account.writable { "yes" } else { "" }, pubkey
+        );
+    }
+    println!("Accounts 0-12 are the named swap_v2 accounts; the rest are remaining_accounts.");
+}
 
-                    let (ext_pda, _) = Pubkey::find_program_address(&[TICK_ARRAY_BITMAP_SEED, pool_pubkey.as_ref()], &program_id);
-                    let ext_data = rpc_client.get_account_data(&ext_pda).expect("Failed to fetch bitmap extension");
-                    let extension = TickArrayBitmapExtension::deserialize(&mut &ext_data[8..]).expect("Failed to parse bitmap extension");
-                    println!("Done.");
+/// The arrays a swap from `tick_start` over its favorable and impact ticks may traverse, assuming
+/// every array is initialized: `(favorable, core, surrounding)`, the lists in swap order. The range
+/// is clamped to `[MIN_TICK, MAX_TICK]`, and the surrounding array is the next one past it in the
+/// impact direction, `None` when the range already reaches the last valid array.
+fn blind_swap_arrays(
+    helper: &TickArrayHelper,
+    direction: SwapDirection,
+    tick_start: i32,
+    (tick_favorable, tick_impact): (i32, i32),
+) -> (Vec<i32>, Vec<i32>, Option<(i32, &'static str)>) {
+    let (min_tick, max_tick) = (tick_favorable.min(tick_impact).max(MIN_TICK), tick_favorable.max(tick_impact).min(MAX_TICK));
+
+    // 1. Calculate Potential Arrays BLINDLY
+    let start_array_min = helper.get_array_start_index(Tick(min_tick));
+    let start_array_max = helper.get_array_start_index(Tick(max_tick));
+
+    let mut potential_arrays = Vec::new();
+    let mut current_array_start = start_array_min;
+    while current_array_start <= start_array_max {
+        potential_arrays.push(current_array_start.0);
+        current_array_start = current_array_start.shifted(1, helper.tick_spacing);
+    }
+    
+    // 2. Define Core and Favorable Tick Ranges (Same as GetSwapArrays)
+    let (core_min_tick, core_max_tick) = (tick_start.min(tick_impact), tick_start.max(tick_impact));
+    let (favorable_min_tick, favorable_max_tick) = (tick_start.min(tick_favorable), tick_start.max(tick_favorable));
+
+    // 3. Split Potential Arrays into Core and Favorable (BLIND version)
+    let mut core_arrays: Vec<i32> = potential_arrays.iter()
+        .filter(|&&start_index| {
+            let (_tick_start, tick_end) = helper.get_array_tick_range(ArrayStartIndex(start_index));
+            (start_index <= core_max_tick) && (tick_end.0 >= core_min_tick)
+        })
+        .cloned()
+        .collect();
+
+    let mut favorable_arrays: Vec<i32> = potential_arrays.iter()
+        .filter(|&&start_index| {
+            let (_tick_start, tick_end) = helper.get_array_tick_range(ArrayStartIndex(start_index));
+            let in_favorable_range = (start_index <= favorable_max_tick) && (tick_end.0 >= favorable_min_tick);
+            let in_core_range = (start_index <= core_max_tick) && (tick_end.0 >= core_min_tick);
+            in_favorable_range && !in_core_range
+        })
+        .cloned()
+        .collect();
+
+    // 4. Calculate the ONE surrounding array in the direction of IMPACT (BLIND version).
+    //    None when the range already reaches the last array before MIN_TICK/MAX_TICK.
+    let (first_valid_array, last_valid_array) = helper.valid_array_start_range();
+    let surrounding_array: Option<(i32, &'static str)> = match direction {
+        SwapDirection::BuyT1 => { // Impact is DOWN (tick decreases)
+            let surrounding_start_index = start_array_min.shifted(-1, helper.tick_spacing);
+            (surrounding_start_index >= first_valid_array).then_some((surrounding_start_index.0, "SURROUNDING_DN"))
+        },
+        SwapDirection::BuyT0 => { // Impact is UP (tick increases)
+            let surrounding_start_index = start_array_max.shifted(1, helper.tick_spacing);
+            (surrounding_start_index <= last_valid_array).then_some((surrounding_start_index.0, "SURROUNDING_UP"))
+        },
+    };
 
-                    // 2. Setup helpers
-                    let helper = TickArrayHelper { tick_spacing: pool_state.tick_spacing };
-                    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
-                    
-                    // 3. Combine and sort all initialized arrays
-                    let mut initialized_default = read_default_bitmap(&pool_state.tick_array_bitmap, pool_state.tick_spacing);
-                    let mut initialized_extension = read_extension_bitmap(&extension, pool_state.tick_spacing);
-                    initialized_default.append(&mut initialized_extension);
-                    initialized_default.sort();
-                    let all_initialized_arrays = initialized_default;
+    // 5. Order arrays along the swap path: descending for buy-t1 (tick decreases),
+    //    ascending for buy-t0 (already sorted ascending from the while loop)
+    if let SwapDirection::BuyT1 = direction {
+        favorable_arrays.sort_by(|a, b| b.cmp(a));
+        core_arrays.sort_by(|a, b| b.cmp(a));
+    }
+    (favorable_arrays, core_arrays, surrounding_array)
+}
 
-                    // 4. Find the current array
-                    // let current_array_start_index = helper.get_array_start_index(pool_state.tick_current);
+/// Selects the initialized tick arrays a swap over `[min_tick, max_tick]` traverses, from the
+/// pool's bitmaps: every array overlapping the range, then the next initialized array beyond it
+/// in the impact direction. Returns them in swap order, labeled, and whether that surrounding
+/// array was found.
+fn select_swap_arrays(
+    pool_state: &PoolState,
+    extension: &TickArrayBitmapExtension,
+    direction: SwapDirection,
+    (min_tick, max_tick): (i32, i32),
+) -> (Vec<(&'static str, i32)>, bool) {
+    let helper = TickArrayHelper { tick_spacing: pool_state.tick_spacing };
+
+    // 1. Get ALL initialized arrays and SORT them
+    let mut all_initialized_arrays = read_default_bitmap(&pool_state.tick_array_bitmap, pool_state.tick_spacing);
+    all_initialized_arrays.append(&mut read_extension_bitmap(extension, pool_state.tick_spacing));
+    all_initialized_arrays.sort();
+
+    // 2. Filter and Find Arrays
+    let mut arrays_in_range: Vec<i32> = all_initialized_arrays.iter()
+        .filter(|&&start_index| {
+            let (_tick_start, tick_end) = helper.get_array_tick_range(ArrayStartIndex(start_index));
+            let array_start = start_index;
+            let array_end = tick_end; 
+            (array_start <= max_tick) && (array_end.0 >= min_tick)
+        })
+        .cloned()
+        .collect();
+
+    // 3. Find the ONE surrounding array in the direction of IMPACT: the next initialized array
+    //    past the one holding the range's far end
+    let (beyond_range, label) = match direction {
+        SwapDirection::BuyT1 => (helper.get_array_start_index(Tick(min_tick)).shifted(-1, helper.tick_spacing), "SURROUNDING_DN"), // Impact is DOWN (tick decreases)
+        SwapDirection::BuyT0 => (helper.get_array_start_index(Tick(max_tick)).shifted(1, helper.tick_spacing), "SURROUNDING_UP"), // Impact is UP (tick increases)
+    };
+    let surrounding_array = walk_initialized_arrays(&all_initialized_arrays, beyond_range.0, direction.tick_direction())
+        .first()
+        .map(|&start_index| (start_index, label));
+
+    // 4. Order arrays along the swap path: descending for buy-t1 (tick decreases),
+    //    ascending for buy-t0 (.sort() was already called)
+    if let SwapDirection::BuyT1 = direction {
+        arrays_in_range.sort_by(|a, b| b.cmp(a));
+    }
 
-                    // 5. Determine user's desired price format
-                    let (price_template, format_label) = match format {
-                        HumanPriceFormat::T0PerT1 => (PriceInput::Token0PerToken1Human{price: 0.0}, "T0/T1 (Token0/Token1)"),
-                        HumanPriceFormat::T1PerT0 => (PriceInput::Token1PerToken0Human{price: 0.0}, "T1/T0 (Token1/Token0)"),
-                    };
+    // 5. Final list in swap order
+    let mut ordered: Vec<(&'static str, i32)> = arrays_in_range.iter().map(|&start_index| ("IN-RANGE", start_index)).collect();
+    ordered.extend(surrounding_array.map(|(start_index, label)| (label, start_index)));
+    (ordered, surrounding_array.is_some())
+}
 
-                    println!("\n--- Full Liquidity Analysis for {} ---", pool_id);
-                    println!("Current Tick: {}", pool_state.tick_current);
+/// Start indices of the tick arrays a swap from `tick_current` traverses, in swap order: every
+/// array from the current one to the last valid array, or only the `initialized` ones (sorted)
+/// when given.
+fn swap_path_arrays(helper: &TickArrayHelper, direction: SwapDirection, tick_current: i32, initialized: Option<&[i32]>) -> Vec<i32> {
+    let current_start = helper.get_array_start_index(Tick(tick_current));
+    let (first_valid, last_valid) = helper.valid_array_start_range();
+    match (direction, initialized) {
+        (SwapDirection::BuyT1, None) => (0..).map(|i| current_start.shifted(-i, helper.tick_spacing)).take_while(|&s| s >= first_valid).map(|s| s.0).collect(),
+        (SwapDirection::BuyT0, None) => (0..).map(|i| current_start.shifted(i, helper.tick_spacing)).take_while(|&s| s <= last_valid).map(|s| s.0).collect(),
+        (_, Some(initialized)) => walk_initialized_arrays(initialized, current_start.0, direction.tick_direction()),
+    }
+}
 
-                    println!("\n{:<15} | {}", "Array Start/Tick", "Price / Price Range");
-                    println!("{:-<75}", "");
+/// The farthest tick a swap can reach inside the array at `start_index`: its lower boundary for
+/// buy-t1, its upper boundary (the next array's start) for buy-t0, clamped to `[MIN_TICK, MAX_TICK]`.
+fn swap_path_edge(helper: &TickArrayHelper, direction: SwapDirection, start_index: i32) -> i32 {
+    match direction {
+        SwapDirection::BuyT1 => start_index.max(MIN_TICK),
+        SwapDirection::BuyT0 => (start_index + helper.tick_indices_per_array()).min(MAX_TICK),
+    }
+}
 
-                    let mut current_tick_printed = false;
+/// Prints the tick a swap path reaches, as a tick offset, raw price move and human prices.
+fn print_coverage_edge(converter: &TickConverter, tick_current: i32, edge: i32, prices: PriceDisplay) {
+    let price_move_pct = (Q_RATIO.powi(edge - tick_current) - 1.0) * 100.0;
+    println!("Reaches Tick:   {} ({:+} ticks)", edge, edge - tick_current);
+    println!("Price Move:     {:+.4}%", price_move_pct);
+    println!(
+        "Edge Price:     {} T0/T1, {} T1/T0",
+        prices.format(converter.tick_to_price(Tick(edge), PriceInput::Token0PerToken1Human { price: 0.0 })),
+        prices.format(converter.tick_to_price(Tick(edge), PriceInput::Token1PerToken0Human { price: 0.0 }))
+    );
+}
 
-                    for &start_index in &all_initialized_arrays {
-                        // Check if the current tick's position is BEFORE the next array to be printed.
-                        if !current_tick_printed && start_index > pool_state.tick_current {
-                            let current_price = converter.tick_to_price(pool_state.tick_current, price_template);
-                            println!("{:-<75}", "");
-                            println!(
-                                "{:<15} | Price: {:.6}               <-- YOU ARE HERE",
-                                format!("Tick {}", pool_state.tick_current),
-                                current_price
-                            );
-                            println!("{:-<75}", "");
-                            current_tick_printed = true;
-                        }
+/// Returns the associated token account of `wallet` for `mint`, owned by `token_program`.
+fn associated_token_address(wallet: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    let ata_program = Pubkey::from_str(ASSOCIATED_TOKEN_PROGRAM_ID).expect("Invalid associated token program ID");
+    Pubkey::find_program_address(&[wallet.as_ref(), token_program.as_ref(), mint.as_ref()], &ata_program).0
+}
 
-                        // Print the array's info
-                        let (tick_start, tick_end) = helper.get_array_tick_range(start_index);
-                        let price_start = converter.tick_to_price(tick_start, price_template);
-                        let price_end = converter.tick_to_price(tick_end, price_template);
-                        
-                        println!(
-                            "{:<15} | [{:.6}, {:.6}]",
-                            start_index,
-                            price_start,
-                            price_end,
-                        );
-                    }
+/// An instruction in the JSON shape of a web3.js `TransactionInstruction`, with `data` in base64.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct InstructionJson {
+    program_id: String,
+    keys: Vec<AccountMetaJson>,
+    data: String,
+    data_base58: String,
+}
 
-                    // This handles the case where the current tick is after the last initialized array in the list.
-                    if !current_tick_printed {
-                        let current_price = converter.tick_to_price(pool_state.tick_current, price_template);
-                        println!("{:-<75}", "");
-                        println!(
-                            "{:<15} | Price: {:.6}               <-- YOU ARE HERE",
-                            format!("Tick {}", pool_state.tick_current),
-                            current_price
-                        );
-                        println!("{:-<75}", "");
-                    }
-                    println!("\nPrice format is: {}", format_label);
-                },
-            }
-        }
-    }
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountMetaJson {
+    pubkey: String,
+    is_signer: bool,
+    is_writable: bool,
 }
 
-// --- New Bitmap Reader Functions ---
+/// Builds the machine-readable result for the swap arrays (already in traversal order).
+fn swap_arrays_result(
+    pool_pubkey: &Pubkey,
+    pool_state: &PoolState,
+    program_id: &Pubkey,
+    direction: SwapDirection,
+    tick_start: i32,
+    (min_tick, max_tick): (i32, i32),
+    ordered: &[(&str, i32)],
+) -> SwapArraysJson {
+    let (ext_pda, _) = Pubkey::find_program_address(&[TICK_ARRAY_BITMAP_SEED, pool_pubkey.as_ref()], program_id);
+    SwapArraysJson {
+        pool_id: pool_pubkey.to_string(),
+        bitmap_extension: ext_pda.to_string(),
+        observation_key: pool_state.observation_key.to_string(),
+        direction: direction.to_possible_value().expect("No skipped variants").get_name().to_string(),
+        start_tick: tick_start,
+        tick_range: [min_tick, max_tick],
+        arrays: ordered.iter()
+            .map(|&(label, start_index)| SwapArrayJson {
+                label: label.to_string(),
+                start_index,
+                pda: derive_tick_array_pda(pool_pubkey, start_index, program_id).to_string(),
+                writable: true,
+                exists: None,
+                initialized_tick_count: None,
+            })
+            .collect(),
+        accounts: None,
+        sqrt_price_limit_x64: None,
+    }
+}
 
-/// Reads the default 1024-bit bitmap from the PoolState.
-fn read_default_bitmap(bitmap: &[u64; 16], tick_spacing: u16) -> Vec<i32> {
-    let mut initialized = Vec::new();
-    let ticks_per_array = TICK_ARRAY_SIZE * tick_spacing as i32;
+/// Prints the swap arrays on stdout as JSON, or as a TypeScript snippet with one
+/// `new PublicKey(...)` per account, tick arrays in swap order.
+fn print_swap_arrays_machine(result: &SwapArraysJson, output: OutputFormat) {
+    if output == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(result).expect("Failed to serialize JSON"));
+        return;
+    }
+    println!(
+        "// Pool {} ({}), start tick {}, tick range [{}, {}]",
+        result.pool_id, result.direction, result.start_tick, result.tick_range[0], result.tick_range[1]
+    );
+    println!("const poolId = new PublicKey(\"{}\");", result.pool_id);
+    println!("const observationKey = new PublicKey(\"{}\");", result.observation_key);
+    println!("const bitmapExtension = new PublicKey(\"{}\");", result.bitmap_extension);
+    if let Some(limit) = &result.sqrt_price_limit_x64 {
+        println!("const sqrtPriceLimitX64 = new BN(\"{}\");", limit);
+    }
+    println!("// Tick arrays in swap order");
+    println!("const tickArrays = [");
+    for array in &result.arrays {
+        if array.exists == Some(false) {
+            println!("  // MISSING on-chain: new PublicKey(\"{}\"), // {} {}", array.pda, array.label, array.start_index);
+        } else {
+            println!("  new PublicKey(\"{}\"), // {} {}", array.pda, array.label, array.start_index);
+        }
+    }
+    println!("];");
+    println!("const tickArrayAccounts = tickArrays.map((pubkey) => ({{ pubkey, isSigner: false, isWritable: true }}));");
+
+    if let Some(accounts) = &result.accounts {
+        // Caller-supplied accounts are left as variables named after the account, in camelCase
+        let placeholders: Vec<String> = accounts.iter().filter(|a| a.pubkey.is_none()).map(|a| snake_to_camel(&a.name)).collect();
+        println!("// swap_v2 accounts in instruction order; define {} first", placeholders.join(", "));
+        println!("const swapV2Accounts = [");
+        for account in accounts {
+            let pubkey = match &account.pubkey {
+                Some(pubkey) => format!("new PublicKey(\"{}\")", pubkey),
+                None => snake_to_camel(&account.name),
+            };
+            println!(
+                "  {{ pubkey: {}, isSigner: {}, isWritable: {} }}, // {}",
+                pubkey, account.signer, account.writable, account.name
+            );
+        }
+        println!("];");
+    }
+}
 
-    for (word_idx, &word) in bitmap.iter().enumerate() {
-        if word == 0 { continue; }
-        for bit_idx in 0..64 {
-            if (word & (1u64 << bit_idx)) != 0 {
-                let global_bit_pos = (word_idx * 64 + bit_idx) as i32;
-                // Default bitmap is centered. 512 is the center offset.
-                let array_offset = global_bit_pos - 512;
-                let start_index = array_offset * ticks_per_array;
-                initialized.push(start_index);
-            }
+/// `input_token_account` -> `inputTokenAccount`.
+fn snake_to_camel(name: &str) -> String {
+    let mut parts = name.split('_');
+    let mut camel = parts.next().unwrap_or_default().to_string();
+    for part in parts {
+        let mut chars = part.chars();
+        if let Some(first) = chars.next() {
+            camel.push(first.to_ascii_uppercase());
+            camel.push_str(chars.as_str());
         }
     }
-    initialized
+    camel
 }
 
 /// Prints the array start index and PDA for the swap-arrays command.
@@ -1614,52 +7397,193 @@ fn print_swap_array_info(
     println!("                  PDA: {}", pda);
 }
 
-/// Fetches, parses, and prints a detailed breakdown of a single Tick Array.
-fn fetch_and_print_array_details(
-    rpc_client: &RpcClient,
+/// A tick array account fetched for display, keyed by its start index.
+struct FetchedArray {
+    start_index: i32,
+    pda: Pubkey,
+    account: std::result::Result<Account, String>,
+}
+
+/// Fetches the given tick arrays concurrently. The result keeps the order of `start_indices`,
+/// so printing does not depend on which request completed first.
+async fn fetch_array_details(
+    rpc_client: &dyn AccountFetcher,
     pool_pubkey: &Pubkey,
     program_id: &Pubkey,
-    start_index: i32,
+    start_indices: &[i32],
+) -> Vec<FetchedArray> {
+    let pdas: Vec<Pubkey> = start_indices.iter()
+        .map(|&start_index| derive_tick_array_pda(pool_pubkey, start_index, program_id))
+        .collect();
+    let accounts = rpc_client.get_accounts_concurrent(&pdas).await;
+    start_indices.iter().zip(pdas).zip(accounts)
+        .map(|((&start_index, pda), account)| {
+            if let Ok(account) = &account
+                && let Ok(tick_array) = decode_clmm_account::<TickArrayState>(&pda, &account.owner, &account.data, program_id)
+            {
+                check_pool_id(&pda, &tick_array, pool_pubkey).unwrap_or_else(|e| fail(ExitCode::InvalidInput, e));
+            }
+            FetchedArray { start_index, pda, account: account.map_err(|e| e.to_string()) }
+        })
+        .collect()
+}
+
+/// Returns the `Ticks` cell of `full-analysis --with-ticks`: the array's initialized tick count,
+/// `missing` for an absent account, or `invalid` when it does not decode as a tick array.
+fn tick_count_cell(pda: &Pubkey, account: Option<&Account>, program_id: &Pubkey) -> String {
+    match account {
+        None => "missing".to_string(),
+        Some(account) => match decode_clmm_account::<TickArrayState>(pda, &account.owner, &account.data, program_id) {
+            Ok(tick_array) => tick_array.initialized_tick_count.to_string(),
+            Err(_) => "invalid".to_string(),
+        },
+    }
+}
+
+/// The per-array columns of `rpc full-analysis`, from `--with-ticks` and `--detailed`.
+#[derive(Clone, Copy, PartialEq)]
+enum ArrayColumns {
+    None,
+    /// The initialized tick count.
+    Ticks,
+    /// The tick count, the summed `liquidity_gross` and the net liquidity change across the array.
+    Detailed,
+}
+
+impl ArrayColumns {
+    fn header(self) -> String {
+        match self {
+            ArrayColumns::None => String::new(),
+            ArrayColumns::Ticks => format!("{:>9} | ", "Ticks"),
+            ArrayColumns::Detailed => format!("{:>9} | {:>10} | {:>11} | ", "Ticks", "Liq Gross", "Net Change"),
+        }
+    }
+
+    fn blank(self) -> String {
+        self.row(["", "", ""])
+    }
+
+    fn row(self, cells: [&str; 3]) -> String {
+        match self {
+            ArrayColumns::None => String::new(),
+            ArrayColumns::Ticks => format!("{:>9} | ", cells[0]),
+            ArrayColumns::Detailed => format!("{:>9} | {:>10} | {:>11} | ", cells[0], cells[1], cells[2]),
+        }
+    }
+
+    /// The cells of one fetched array. A missing account reads `MISSING` in detailed mode: a set
+    /// bitmap bit without an array means the bitmap was decoded wrongly.
+    fn cells(self, pda: &Pubkey, account: Option<&Account>, program_id: &Pubkey) -> String {
+        if self != ArrayColumns::Detailed {
+            return self.row([&tick_count_cell(pda, account, program_id), "", ""]);
+        }
+        let Some(account) = account else {
+            return self.row(["MISSING", "", ""]);
+        };
+        match decode_clmm_account::<TickArrayState>(pda, &account.owner, &account.data, program_id) {
+            Ok(tick_array) => {
+                let initialized = tick_array.ticks.iter().filter(|tick| tick.liquidity_gross != 0);
+                let gross: u128 = initialized.clone().map(|tick| tick.liquidity_gross).fold(0, u128::saturating_add);
+                let net: i128 = initialized.map(|tick| tick.liquidity_net).fold(0, i128::saturating_add);
+                let sign = match net.signum() { -1 => "-", 1 => "+", _ => "" };
+                self.row([
+                    &tick_array.initialized_tick_count.to_string(),
+                    &format_liquidity(gross),
+                    &format!("{}{}", sign, format_liquidity(net.unsigned_abs())),
+                ])
+            }
+            Err(_) => self.row(["invalid", "", ""]),
+        }
+    }
+
+    fn fetch_failed(self) -> String {
+        self.row(["fetch failed", "", ""])
+    }
+}
+
+/// Checks a decoded tick array against the pool's tick spacing:
+/// - `initialized_tick_count` must equal the number of slots with non-zero `liquidity_gross`;
+/// - every initialized slot must hold `tick == start_tick_index + slot * tick_spacing`.
+///
+/// A tick mismatch means the array comes from a pool with a different tick spacing or the layout
+/// is misaligned. Returns one message per problem, empty when the array is consistent.
+fn validate_tick_array(tick_array: &TickArrayState, tick_spacing: u16) -> Vec<String> {
+    let mut issues = Vec::new();
+    let ticks_per_array = TICK_ARRAY_SIZE * tick_spacing as i32;
+    if tick_array.start_tick_index % ticks_per_array != 0 {
+        issues.push(format!(
+            "start_tick_index {} is not a multiple of {} (60 * tick spacing {})",
+            tick_array.start_tick_index, ticks_per_array, tick_spacing
+        ));
+    }
+
+    let live_slots = tick_array.ticks.iter().filter(|tick_state| tick_state.liquidity_gross != 0).count();
+    if live_slots != tick_array.initialized_tick_count as usize {
+        issues.push(format!(
+            "initialized_tick_count is {} but {} slots have non-zero liquidity_gross",
+            tick_array.initialized_tick_count, live_slots
+        ));
+    }
+
+    for (slot, tick_state) in tick_array.ticks.iter().enumerate() {
+        let expected_tick = tick_array.start_tick_index + slot as i32 * tick_spacing as i32;
+        if tick_state.liquidity_gross != 0 && tick_state.tick != expected_tick {
+            issues.push(format!("slot {} holds tick {}, expected {}", slot, tick_state.tick, expected_tick));
+        }
+    }
+    issues
+}
+
+/// Prints the result of `validate_tick_array`, making any problem stand out from the tick listing.
+fn print_tick_array_validation(issues: &[String], palette: Palette) {
+    if issues.is_empty() {
+        println!("  Validation: OK (tick count and tick indices match the layout)");
+        return;
+    }
+    println!("{}", palette.warning(&format!("  !!! VALIDATION FAILED: {} problem(s) !!!", issues.len())));
+    for issue in issues {
+        println!("{}", palette.warning(&format!("    - {}", issue)));
+    }
+}
+
+/// Parses and prints a detailed breakdown of a single fetched Tick Array.
+fn print_array_details(
+    array: &FetchedArray,
+    program_id: &Pubkey,
     converter: &TickConverter,
     helper: &TickArrayHelper,
     price_template: PriceInput, // To print price ranges in the user's format
+    palette: Palette,
+    prices: PriceDisplay,
 ) {
-    // 1. Derive PDA
-    let (pda, _bump) = Pubkey::find_program_address(
-        &[
-            TICK_ARRAY_SEED,
-            pool_pubkey.as_ref(),
-            &start_index.to_be_bytes(),
-        ],
-        program_id,
-    );
-
+    let FetchedArray { start_index, pda, account } = array;
     println!("\n--- Array Start Index: {} ---", start_index);
     println!("  PDA Address: {}", pda);
 
-    // 2. Print Price Range
-    let (tick_start, tick_end) = helper.get_array_tick_range(start_index);
+    // 1. Print Price Range
+    let (tick_start, tick_end) = helper.get_array_tick_range(ArrayStartIndex(*start_index));
     let price_start = converter.tick_to_price(tick_start, price_template);
-    let price_end = converter.tick_to_price(tick_end, price_template);
+    let price_end = converter.tick_to_price(helper.last_valid_tick(ArrayStartIndex(*start_index)), price_template);
     // Handle price inversion for readability
     let (p_start, p_end) = if price_start < price_end { (price_start, price_end) } else { (price_end, price_start) };
-    println!("  Price Range: [{:.8}, {:.8}]", p_start, p_end);
+    println!("  Price Range: {}", prices.range(p_start, p_end));
     println!("  Tick Range:  [{}, {}]", tick_start, tick_end);
 
 
-    // 3. Fetch and Parse
-    match rpc_client.get_account_data(&pda) {
-        Ok(account_data) => {
-            match TickArrayState::deserialize(&mut &account_data[8..]) {
+    // 2. Parse
+    match account {
+        Ok(account) => {
+            match decode_clmm_account::<TickArrayState>(pda, &account.owner, &account.data, program_id) {
                 Ok(tick_array) => {
                     println!("  Initialized Ticks: {}/{}", tick_array.initialized_tick_count, TICK_ARRAY_SIZE);
-                    
+                    print_tick_array_validation(&validate_tick_array(&tick_array, helper.tick_spacing), palette);
+
                     if tick_array.initialized_tick_count == 0 {
                         println!("  (Array is initialized but contains no active ticks)");
                         return;
                     }
 
-                    // 4. Print detailed tick info
+                    // 3. Print detailed tick info
                     println!("  --- Initialized Tick Details ---");
                     for (slot_index, tick_state) in tick_array.ticks.iter().enumerate() {
                         if tick_state.liquidity_gross != 0 {
@@ -1672,7 +7596,7 @@ fn fetch_and_print_array_details(
                     }
                 },
                 Err(e) => {
-                    println!("  ERROR: Failed to parse TickArrayState for PDA {}: {}", pda, e);
+                    println!("  ERROR: {}", e);
                 }
             }
         },
@@ -1682,46 +7606,191 @@ fn fetch_and_print_array_details(
     }
 }
 
+/// Prints a raw account header and a hex dump of its data in 32-byte rows, with each row
+/// annotated by the `TickArrayState` fields that begin in it (offsets from `TickArrayLayout`).
+fn print_raw_tick_array(account: &Account, pubkey: &Pubkey, program_id: &Pubkey) {
+    const ROW_LEN: usize = 32;
+    let layout = TickArrayLayout::from_structs();
+    let data = &account.data;
+
+    println!("\n--- Raw Account {} ---", pubkey);
+    println!("  Owner:         {}{}", account.owner, if account.owner == *program_id { "" } else { " (NOT the CLMM program)" });
+    println!("  Lamports:      {}", account.lamports);
+    println!("  Data Length:   {} bytes (TickArrayState layout: {} bytes)", data.len(), layout.len);
+    match data.get(..8) {
+        Some(discriminator) => println!(
+            "  Discriminator: {:02x?} ({})",
+            discriminator,
+            account_type_name(data).unwrap_or("unknown account type")
+        ),
+        None => println!("  Discriminator: (data shorter than 8 bytes)"),
+    }
+    if let Err(e) = check_clmm_account::<TickArrayState>(pubkey, &account.owner, data, program_id) {
+        println!("  Validation:    {}", e);
+    }
+
+    let mut annotations = vec![
+        (0, "discriminator".to_string()),
+        (layout.pool_id, "pool_id".to_string()),
+        (layout.start_tick_index, "start_tick_index".to_string()),
+    ];
+    annotations.extend((0..TICK_ARRAY_SIZE as usize).map(|slot| (layout.tick(slot), format!("ticks[{}]", slot))));
+    annotations.push((layout.initialized_tick_count, "initialized_tick_count".to_string()));
+    annotations.push((layout.recent_epoch, "recent_epoch".to_string()));
+    annotations.push((layout.padding, "padding".to_string()));
+
+    println!("\n{:<8} {:<width$} Fields", "Offset", "Data", width = ROW_LEN * 3 - 1);
+    for (row, chunk) in data.chunks(ROW_LEN).enumerate() {
+        let row_start = row * ROW_LEN;
+        let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let fields: Vec<String> = annotations.iter()
+            .filter(|(offset, _)| (row_start..row_start + ROW_LEN).contains(offset))
+            .map(|(offset, name)| format!("{} @{:#06x}", name, offset))
+            .collect();
+        let line = format!("{:06x}   {:<width$} {}", row_start, hex.join(" "), fields.join(", "), width = ROW_LEN * 3 - 1);
+        println!("{}", line.trim_end());
+    }
+}
+
+/// Which bitmap covers a tick array.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BitmapLocation {
+    /// `PoolState::tick_array_bitmap`: array offsets -512..512.
+    Default,
+    /// `positive_tick_array_bitmap[chunk]`: offsets from 512 upward, 512 per chunk.
+    Positive { chunk: usize },
+    /// `negative_tick_array_bitmap[chunk]`: offsets from -513 downward, 512 per chunk.
+    Negative { chunk: usize },
+}
+
+/// `default`, `ext+N` or `ext-N`, as in the `Source` column of `rpc full-analysis`.
+impl std::fmt::Display for BitmapLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            BitmapLocation::Default => "default".to_string(),
+            BitmapLocation::Positive { chunk } => format!("ext+{}", chunk),
+            BitmapLocation::Negative { chunk } => format!("ext-{}", chunk),
+        };
+        f.pad(&label)
+    }
+}
+
+/// The word and bit that mark a tick array as initialized.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct BitmapPosition {
+    location: BitmapLocation,
+    word: usize,
+    bit: usize,
+}
+
+/// Number of arrays tracked by each 512-bit bitmap (the default bitmap covers one per side).
+const ARRAYS_PER_BITMAP: i32 = 512;
+/// Bit of the default bitmap that marks the array starting at tick 0 (word 8, bit 0). The default
+/// bitmap is centered: bits below it are the 512 negative array offsets, bits from it the 512 non-negative ones.
+const DEFAULT_BITMAP_CENTER_BIT: i32 = ARRAYS_PER_BITMAP;
+
+/// Number of chunks in each direction of the extension bitmap.
+const EXTENSION_CHUNKS: usize = 14;
+
+impl BitmapPosition {
+    /// The bit at `word`, `bit` of `PoolState::tick_array_bitmap`.
+    const fn in_default_bitmap(word: usize, bit: usize) -> Self {
+        Self { location: BitmapLocation::Default, word, bit }
+    }
+
+    /// Finds the bitmap bit for an array start index. Returns `None` if `start_index` is not a
+    /// multiple of `60 * tick_spacing` or lies beyond the extension's coverage.
+    fn from_start_index(start_index: i32, tick_spacing: u16) -> Option<Self> {
+        let ticks_per_array = TICK_ARRAY_SIZE * tick_spacing as i32;
+        if start_index % ticks_per_array != 0 {
+            return None;
+        }
+        let array_offset = start_index / ticks_per_array;
+        let (location, bit_pos) = if (-ARRAYS_PER_BITMAP..ARRAYS_PER_BITMAP).contains(&array_offset) {
+            (BitmapLocation::Default, array_offset + DEFAULT_BITMAP_CENTER_BIT)
+        } else if array_offset >= ARRAYS_PER_BITMAP {
+            let relative = array_offset - ARRAYS_PER_BITMAP;
+            (BitmapLocation::Positive { chunk: (relative / ARRAYS_PER_BITMAP) as usize }, relative % ARRAYS_PER_BITMAP)
+        } else {
+            // Negative chunks are filled from their top bit down
+            let relative = -(ARRAYS_PER_BITMAP + 1) - array_offset;
+            (BitmapLocation::Negative { chunk: (relative / ARRAYS_PER_BITMAP) as usize }, ARRAYS_PER_BITMAP - 1 - relative % ARRAYS_PER_BITMAP)
+        };
+        if let BitmapLocation::Positive { chunk } | BitmapLocation::Negative { chunk } = location
+            && chunk >= EXTENSION_CHUNKS
+        {
+            return None;
+        }
+        Some(Self { location, word: bit_pos as usize / 64, bit: bit_pos as usize % 64 })
+    }
+
+    /// The array start index this bit stands for. This is the arithmetic `read_default_bitmap`
+    /// and `read_extension_bitmap` use to decode set bits.
+    const fn start_index(&self, tick_spacing: u16) -> i32 {
+        let ticks_per_array = TICK_ARRAY_SIZE * tick_spacing as i32;
+        let bit_pos = (self.word * 64 + self.bit) as i32;
+        let array_offset = match self.location {
+            // Default bitmap is centered on array offset 0
+            BitmapLocation::Default => bit_pos - DEFAULT_BITMAP_CENTER_BIT,
+            // The default bitmap has 512 positive slots. Extension 0 starts after that, at array offset 512.
+            BitmapLocation::Positive { chunk } => ARRAYS_PER_BITMAP + chunk as i32 * ARRAYS_PER_BITMAP + bit_pos,
+            // The bitmap is reversed for negative indices
+            BitmapLocation::Negative { chunk } => {
+                let offset_in_bitmap = ARRAYS_PER_BITMAP - 1 - bit_pos;
+                -(ARRAYS_PER_BITMAP + 1) - chunk as i32 * ARRAYS_PER_BITMAP - offset_in_bitmap
+            }
+        };
+        array_offset * ticks_per_array
+    }
+}
+
+/// The last extension chunk on each side whose outermost word has a bit set. Chunks cover arrays
+/// beyond the valid tick range at tick spacing 1, so a bit there means the pool sits at the edge of
+/// what 14 chunks can represent, or the account layout changed. Negative chunks are filled from
+/// their top bit down, so their outermost word is the first one.
+fn extension_edge_chunks(extension: &TickArrayBitmapExtension) -> Vec<BitmapLocation> {
+    let chunk = EXTENSION_CHUNKS - 1;
+    let mut edges = Vec::new();
+    if extension.positive_tick_array_bitmap[chunk][7] != 0 {
+        edges.push(BitmapLocation::Positive { chunk });
+    }
+    if extension.negative_tick_array_bitmap[chunk][0] != 0 {
+        edges.push(BitmapLocation::Negative { chunk });
+    }
+    edges
+}
+
 /// Reads the extension bitmap.
 fn read_extension_bitmap(extension: &TickArrayBitmapExtension, tick_spacing: u16) -> Vec<i32> {
+    read_extension_bitmap_positions(extension).iter().map(|position| position.start_index(tick_spacing)).collect()
+}
+
+/// The set bits of the extension bitmap, tagged with their chunk.
+/// Warns on stderr when the outermost word of either side is in use (see `extension_edge_chunks`).
+fn read_extension_bitmap_positions(extension: &TickArrayBitmapExtension) -> Vec<BitmapPosition> {
+    for location in extension_edge_chunks(extension) {
+        eprintln!(
+            "Warning: the bitmap extension marks arrays in the outermost word of its last chunk ({}); \
+             the pool is at the edge of the representable range, and arrays may exist beyond the decoded range.",
+            location
+        );
+    }
     let mut initialized = Vec::new();
-    let ticks_per_array = TICK_ARRAY_SIZE * tick_spacing as i32;
-    let arrays_per_bitmap = 512;
+    let chunks = extension.positive_tick_array_bitmap.iter().enumerate()
+        .map(|(chunk, bitmap_chunk)| (BitmapLocation::Positive { chunk }, bitmap_chunk))
+        .chain(extension.negative_tick_array_bitmap.iter().enumerate()
+            .map(|(chunk, bitmap_chunk)| (BitmapLocation::Negative { chunk }, bitmap_chunk)));
 
-    // Positive Bitmaps
-    for (bitmap_idx, bitmap_chunk) in extension.positive_tick_array_bitmap.iter().enumerate() {
+    for (location, bitmap_chunk) in chunks {
         for (word_idx, &word) in bitmap_chunk.iter().enumerate() {
             if word == 0 { continue; }
             for bit_idx in 0..64 {
                 if (word & (1u64 << bit_idx)) != 0 {
-                    let bit_pos_in_chunk = (word_idx * 64 + bit_idx) as i32;
-                    // The default bitmap has 512 positive slots.
-                    // Extension 0 starts after that, at array offset 512.
-                    let array_offset = 512 + (bitmap_idx as i32 * arrays_per_bitmap) + bit_pos_in_chunk;
-                    let start_index = array_offset * ticks_per_array;
-                    initialized.push(start_index);
+                    initialized.push(BitmapPosition { location, word: word_idx, bit: bit_idx });
                 }
             }
         }
     }
-    
-    // Negative Bitmaps
-    for (bitmap_idx, bitmap_chunk) in extension.negative_tick_array_bitmap.iter().enumerate() {  
-        for (word_idx, &word) in bitmap_chunk.iter().enumerate() {  
-            if word == 0 { continue; }  
-            for bit_idx in 0..64 {  
-                if (word & (1u64 << bit_idx)) != 0 {  
-                    let bit_pos_in_chunk = (word_idx * 64 + bit_idx) as i32;  
-                    // For negative arrays, the bit position maps differently  
-                    // The bitmap is reversed for negative indices  
-                    let offset_in_bitmap = 511 - bit_pos_in_chunk;  
-                    let array_offset = -513 - (bitmap_idx as i32 * arrays_per_bitmap) - offset_in_bitmap;  
-                    let start_index = array_offset * ticks_per_array;  
-                    initialized.push(start_index);  
-                }  
-            }  
-        }  
-    }
 
     initialized
 }
@@ -0,0 +1,103 @@
+//! A fixture-backed `AccountFetcher`, so RPC command bodies can run in tests without a network.
+//! Fixtures live in `tests/fixtures` as `{ "slot", "epoch", "accounts": [{ "pubkey", "owner", "data" }] }`
+//! with the account data in base64, the same shape `getAccountInfo` returns.
+
+use std::collections::HashMap;
+
+use base64::Engine;
+use futures::future::{self, FutureExt, LocalBoxFuture};
+use serde::Deserialize;
+use solana_client::client_error::Result as ClientResult;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::rpc_util::{account_not_found_error, AccountFetcher};
+
+#[derive(Deserialize)]
+struct Fixture {
+    slot: u64,
+    epoch: u64,
+    accounts: Vec<FixtureAccount>,
+}
+
+#[derive(Deserialize)]
+struct FixtureAccount {
+    pubkey: String,
+    owner: String,
+    data: String,
+}
+
+fn parse_fixture(json: &str) -> Fixture {
+    serde_json::from_str(json).expect("fixture is valid JSON")
+}
+
+fn decode_account(account: FixtureAccount) -> (Pubkey, Account) {
+    let pubkey = account.pubkey.parse().expect("fixture pubkey is base58");
+    let account = Account {
+        owner: account.owner.parse().expect("fixture owner is base58"),
+        data: base64::engine::general_purpose::STANDARD.decode(account.data).expect("fixture data is base64"),
+        ..Account::default()
+    };
+    (pubkey, account)
+}
+
+/// Serves every read from a fixed set of accounts, all at the fixture's slot.
+pub struct MockFetcher {
+    accounts: HashMap<Pubkey, Account>,
+    slot: u64,
+    epoch: u64,
+}
+
+impl MockFetcher {
+    /// Parses a fixture file's contents. Panics on a malformed fixture.
+    pub fn from_fixture(json: &str) -> Self {
+        let fixture = parse_fixture(json);
+        let accounts = fixture.accounts.into_iter().map(decode_account).collect();
+        MockFetcher { accounts, slot: fixture.slot, epoch: fixture.epoch }
+    }
+
+    /// Adds the accounts of another fixture, e.g. a second pool. Slot and epoch stay this fixture's.
+    pub fn with_fixture(mut self, json: &str) -> Self {
+        self.accounts.extend(parse_fixture(json).accounts.into_iter().map(decode_account));
+        self
+    }
+
+    /// Drops an account, for tests of what a command does when it is missing.
+    pub fn without(mut self, pubkey: &Pubkey) -> Self {
+        self.accounts.remove(pubkey);
+        self
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn read(&self, pubkey: &Pubkey) -> ClientResult<Account> {
+        self.accounts.get(pubkey).cloned().ok_or_else(|| account_not_found_error(pubkey))
+    }
+}
+
+#[allow(clippy::result_large_err)]
+impl AccountFetcher for MockFetcher {
+    fn get_account<'a>(&'a self, pubkey: &'a Pubkey) -> LocalBoxFuture<'a, ClientResult<Account>> {
+        future::ready(self.read(pubkey)).boxed_local()
+    }
+
+    fn get_accounts_concurrent<'a>(&'a self, pubkeys: &'a [Pubkey]) -> LocalBoxFuture<'a, Vec<ClientResult<Account>>> {
+        future::ready(pubkeys.iter().map(|pubkey| self.read(pubkey)).collect()).boxed_local()
+    }
+
+    fn get_multiple_accounts_at<'a>(
+        &'a self,
+        pubkeys: &'a [Pubkey],
+        _min_context_slot: Option<u64>,
+    ) -> LocalBoxFuture<'a, ClientResult<(u64, Vec<Option<Account>>)>> {
+        let accounts = pubkeys.iter().map(|pubkey| self.accounts.get(pubkey).cloned()).collect();
+        future::ready(Ok((self.slot, accounts))).boxed_local()
+    }
+
+    fn get_slot(&self) -> LocalBoxFuture<'_, ClientResult<u64>> {
+        future::ready(Ok(self.slot)).boxed_local()
+    }
+
+    fn get_epoch(&self) -> LocalBoxFuture<'_, ClientResult<u64>> {
+        future::ready(Ok(self.epoch)).boxed_local()
+    }
+}
@@ -77,7 +77,7 @@ pub struct TickArrayState {
     pub padding: [u8; 107],
 }
 
-#[derive(Debug, AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+#[derive(Debug, AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
 pub struct TickState {
     pub tick: i32,
     pub liquidity_net: i128,
@@ -87,3 +87,593 @@ pub struct TickState {
     pub reward_growths_outside_x64: [u128; 3],
     pub padding: [u32; 13],
 }
+
+#[derive(Debug, AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct PersonalPositionState {
+    pub bump: [u8; 1],
+    pub nft_mint: Pubkey,
+    pub pool_id: Pubkey,
+    pub tick_lower_index: i32,
+    pub tick_upper_index: i32,
+    pub liquidity: u128,
+    pub fee_growth_inside_0_last_x64: u128,
+    pub fee_growth_inside_1_last_x64: u128,
+    pub token_fees_owed_0: u64,
+    pub token_fees_owed_1: u64,
+    pub reward_infos: [PositionRewardInfo; 3],
+    pub recent_epoch: u64,
+    pub padding: [u64; 7],
+}
+
+#[derive(Debug, AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct PositionRewardInfo {
+    pub growth_inside_last_x64: u128,
+    pub reward_amount_owed: u64,
+}
+
+/// Account size of a `PoolState`, including the discriminator.
+pub const POOL_STATE_LEN: usize = 8 + 1 + 32 * 7 + 1 + 1 + 2 + 16 * 2 + 4 + 2 + 2 + 16 * 2 + 8 * 2 + 16 * 4 + 1 + 7
+    + REWARD_INFO_LEN * 3 + 8 * 16 + 8 * 8 + 8 * 24 + 8 * 32;
+
+/// Size of one `RewardInfo`.
+const REWARD_INFO_LEN: usize = 1 + 8 * 3 + 16 + 8 * 2 + 32 * 3 + 16;
+
+/// Byte offsets of `token_mint_0` and `token_mint_1` in a `PoolState` account, for `getProgramAccounts` filters.
+pub const POOL_STATE_TOKEN_MINT_0_OFFSET: usize = 8 + 1 + 32 + 32;
+pub const POOL_STATE_TOKEN_MINT_1_OFFSET: usize = POOL_STATE_TOKEN_MINT_0_OFFSET + 32;
+
+/// Account size of a `PersonalPositionState`, including the discriminator.
+pub const PERSONAL_POSITION_STATE_LEN: usize = 8 + 1 + 32 + 32 + 4 + 4 + 16 * 3 + 8 * 2 + (16 + 8) * 3 + 8 + 8 * 7;
+
+/// Byte offset of `pool_id` in a `PersonalPositionState` account, for `getProgramAccounts` filters.
+pub const PERSONAL_POSITION_POOL_ID_OFFSET: usize = 8 + 1 + 32;
+
+/// Anchor account discriminators: the first 8 bytes of `sha256("account:<Name>")`.
+pub const POOL_STATE_DISCRIMINATOR: [u8; 8] = [247, 237, 227, 245, 215, 195, 222, 70];
+pub const TICK_ARRAY_STATE_DISCRIMINATOR: [u8; 8] = [192, 155, 85, 205, 49, 249, 129, 42];
+pub const TICK_ARRAY_BITMAP_EXTENSION_DISCRIMINATOR: [u8; 8] = [60, 150, 36, 219, 97, 128, 139, 153];
+pub const OBSERVATION_STATE_DISCRIMINATOR: [u8; 8] = [122, 174, 197, 53, 129, 9, 165, 132];
+pub const AMM_CONFIG_DISCRIMINATOR: [u8; 8] = [218, 244, 33, 104, 203, 203, 43, 111];
+pub const PERSONAL_POSITION_STATE_DISCRIMINATOR: [u8; 8] = [70, 111, 150, 126, 230, 15, 25, 117];
+pub const PROTOCOL_POSITION_STATE_DISCRIMINATOR: [u8; 8] = [100, 226, 145, 99, 146, 218, 160, 106];
+pub const OPERATION_STATE_DISCRIMINATOR: [u8; 8] = [19, 236, 58, 237, 81, 222, 183, 252];
+
+/// Anchor instruction discriminator of `swap_v2`: the first 8 bytes of `sha256("global:swap_v2")`.
+pub const SWAP_V2_DISCRIMINATOR: [u8; 8] = [43, 4, 237, 11, 26, 201, 30, 98];
+
+/// Arguments of the `swap_v2` instruction, Borsh-encoded after `SWAP_V2_DISCRIMINATOR`.
+#[derive(Debug, AnchorSerialize, Clone, Copy)]
+pub struct SwapV2Args {
+    pub amount: u64,
+    pub other_amount_threshold: u64,
+    pub sqrt_price_limit_x64: u128,
+    pub is_base_input: bool,
+}
+
+/// Every CLMM account type that can be recognized by its discriminator.
+pub const KNOWN_ACCOUNT_TYPES: [(&str, [u8; 8]); 8] = [
+    ("PoolState", POOL_STATE_DISCRIMINATOR),
+    ("TickArrayState", TICK_ARRAY_STATE_DISCRIMINATOR),
+    ("TickArrayBitmapExtension", TICK_ARRAY_BITMAP_EXTENSION_DISCRIMINATOR),
+    ("ObservationState", OBSERVATION_STATE_DISCRIMINATOR),
+    ("AmmConfig", AMM_CONFIG_DISCRIMINATOR),
+    ("PersonalPositionState", PERSONAL_POSITION_STATE_DISCRIMINATOR),
+    ("ProtocolPositionState", PROTOCOL_POSITION_STATE_DISCRIMINATOR),
+    ("OperationState", OPERATION_STATE_DISCRIMINATOR),
+];
+
+/// Returns the name of the CLMM account type whose discriminator starts `data`, if any.
+pub fn account_type_name(data: &[u8]) -> Option<&'static str> {
+    let discriminator = data.get(..8)?;
+    KNOWN_ACCOUNT_TYPES
+        .iter()
+        .find(|(_, known)| known.as_slice() == discriminator)
+        .map(|(name, _)| *name)
+}
+
+/// An account layout decoded by this tool, identified by its Anchor discriminator.
+pub trait ClmmAccount: AnchorDeserialize {
+    const NAME: &'static str;
+    const DISCRIMINATOR: [u8; 8];
+    /// Exact account size including the discriminator, for fixed-size accounts where
+    /// truncated data would otherwise still decode.
+    const EXACT_LEN: Option<usize> = None;
+}
+
+impl ClmmAccount for PoolState {
+    const NAME: &'static str = "PoolState";
+    const DISCRIMINATOR: [u8; 8] = POOL_STATE_DISCRIMINATOR;
+}
+
+/// Serialized size of a `TickState`.
+pub const TICK_STATE_LEN: usize = 4 + 16 + 16 + 16 + 16 + 16 * 3 + 4 * 13;
+
+/// Account size of a `TickArrayState`, including the discriminator.
+pub const TICK_ARRAY_STATE_LEN: usize = 8 + 32 + 4 + TICK_STATE_LEN * 60 + 1 + 8 + 107;
+
+impl ClmmAccount for TickArrayState {
+    const NAME: &'static str = "TickArrayState";
+    const DISCRIMINATOR: [u8; 8] = TICK_ARRAY_STATE_DISCRIMINATOR;
+    const EXACT_LEN: Option<usize> = Some(TICK_ARRAY_STATE_LEN);
+}
+
+impl ClmmAccount for TickArrayBitmapExtension {
+    const NAME: &'static str = "TickArrayBitmapExtension";
+    const DISCRIMINATOR: [u8; 8] = TICK_ARRAY_BITMAP_EXTENSION_DISCRIMINATOR;
+}
+
+/// A CLMM account that records the pool it belongs to.
+pub trait PoolScoped: ClmmAccount {
+    fn pool_id(&self) -> &Pubkey;
+}
+
+impl PoolScoped for TickArrayState {
+    fn pool_id(&self) -> &Pubkey {
+        &self.pool_id
+    }
+}
+
+impl PoolScoped for TickArrayBitmapExtension {
+    fn pool_id(&self) -> &Pubkey {
+        &self.pool_id
+    }
+}
+
+impl ClmmAccount for PersonalPositionState {
+    const NAME: &'static str = "PersonalPositionState";
+    const DISCRIMINATOR: [u8; 8] = PERSONAL_POSITION_STATE_DISCRIMINATOR;
+    const EXACT_LEN: Option<usize> = Some(PERSONAL_POSITION_STATE_LEN);
+}
+
+impl ClmmAccount for ObservationState {
+    const NAME: &'static str = "ObservationState";
+    const DISCRIMINATOR: [u8; 8] = OBSERVATION_STATE_DISCRIMINATOR;
+}
+
+/// Validates the length and discriminator of raw account data for `T`.
+/// Errors name the account and its actual length, so closed accounts and wrong addresses
+/// are reported instead of panicking on `data[8..]`.
+pub fn check_anchor_account<T: ClmmAccount>(pubkey: &Pubkey, data: &[u8]) -> std::result::Result<(), String> {
+    let Some(discriminator) = data.get(..8) else {
+        return Err(format!(
+            "{} has {} bytes of data, too short for an Anchor account (closed account or wrong address?)",
+            pubkey, data.len()
+        ));
+    };
+    if discriminator != T::DISCRIMINATOR {
+        let actual = match account_type_name(data) {
+            Some(name) => format!("this is a {} account", name),
+            None => "not a known CLMM account type".to_string(),
+        };
+        return Err(format!("{} is not a {} account: found discriminator {:?} ({})", pubkey, T::NAME, discriminator, actual));
+    }
+    if let Some(expected) = T::EXACT_LEN
+        && data.len() != expected
+    {
+        return Err(format!("{} has {} bytes of data, expected exactly {} for a {}", pubkey, data.len(), expected, T::NAME));
+    }
+    Ok(())
+}
+
+/// Checks raw account data with `check_anchor_account` and deserializes it as `T`.
+pub fn decode_anchor_account<T: ClmmAccount>(pubkey: &Pubkey, data: &[u8]) -> std::result::Result<T, String> {
+    check_anchor_account::<T>(pubkey, data)?;
+    T::deserialize(&mut &data[8..]).map_err(|e| format!("failed to parse {} as {}: {}", pubkey, T::NAME, e))
+}
+
+/// Checks that an account is owned by the CLMM program and carries valid `T` data.
+pub fn check_clmm_account<T: ClmmAccount>(
+    pubkey: &Pubkey,
+    owner: &Pubkey,
+    data: &[u8],
+    program_id: &Pubkey,
+) -> std::result::Result<(), String> {
+    if owner != program_id {
+        return Err(format!("{} is owned by {}, not the CLMM program {}", pubkey, owner, program_id));
+    }
+    check_anchor_account::<T>(pubkey, data)
+}
+
+/// Checks that an account is owned by the CLMM program, then decodes it with `decode_anchor_account`.
+pub fn decode_clmm_account<T: ClmmAccount>(
+    pubkey: &Pubkey,
+    owner: &Pubkey,
+    data: &[u8],
+    program_id: &Pubkey,
+) -> std::result::Result<T, String> {
+    if owner != program_id {
+        return Err(format!("{} is owned by {}, not the CLMM program {}", pubkey, owner, program_id));
+    }
+    decode_anchor_account(pubkey, data)
+}
+
+/// Field offsets of a `TickArrayState` account (including the discriminator), derived by
+/// Borsh-serializing each field of the struct definitions above rather than hard-coding them.
+#[derive(Debug, Clone, Copy)]
+pub struct TickArrayLayout {
+    pub pool_id: usize,
+    pub start_tick_index: usize,
+    pub ticks: usize,
+    pub tick_len: usize,
+    pub initialized_tick_count: usize,
+    pub recent_epoch: usize,
+    pub padding: usize,
+    pub len: usize,
+}
+
+impl TickArrayLayout {
+    pub fn from_structs() -> Self {
+        fn serialized_len<T: AnchorSerialize>(value: &T) -> usize {
+            value.try_to_vec().expect("serializing a fixed-size value cannot fail").len()
+        }
+        let array = TickArrayState {
+            pool_id: Pubkey::default(),
+            start_tick_index: 0,
+            ticks: [TickState::default(); 60],
+            initialized_tick_count: 0,
+            recent_epoch: 0,
+            padding: [0; 107],
+        };
+        let pool_id = 8;
+        let start_tick_index = pool_id + serialized_len(&array.pool_id);
+        let ticks = start_tick_index + serialized_len(&array.start_tick_index);
+        let initialized_tick_count = ticks + serialized_len(&array.ticks);
+        let recent_epoch = initialized_tick_count + serialized_len(&array.initialized_tick_count);
+        let padding = recent_epoch + serialized_len(&array.recent_epoch);
+        Self {
+            pool_id,
+            start_tick_index,
+            ticks,
+            tick_len: serialized_len(&array.ticks[0]),
+            initialized_tick_count,
+            recent_epoch,
+            padding,
+            len: 8 + serialized_len(&array),
+        }
+    }
+
+    /// Offset of the `TickState` in `slot`.
+    pub fn tick(&self, slot: usize) -> usize {
+        self.ticks + slot * self.tick_len
+    }
+}
+
+/// Byte offsets into raw `TickArrayState` account data (including the discriminator).
+pub const TICK_ARRAY_POOL_ID_OFFSET: usize = 8;
+const TICK_ARRAY_START_INDEX_OFFSET: usize = TICK_ARRAY_POOL_ID_OFFSET + 32;
+const TICK_ARRAY_TICKS_OFFSET: usize = TICK_ARRAY_START_INDEX_OFFSET + 4;
+const TICK_ARRAY_INITIALIZED_COUNT_OFFSET: usize = TICK_ARRAY_TICKS_OFFSET + TICK_STATE_LEN * 60;
+
+/// Byte offsets within one serialized `TickState`.
+const TICK_STATE_LIQUIDITY_NET_OFFSET: usize = 4;
+const TICK_STATE_LIQUIDITY_GROSS_OFFSET: usize = TICK_STATE_LIQUIDITY_NET_OFFSET + 16;
+
+/// Zero-copy view over raw `TickArrayState` account data, for bulk scans that only need a
+/// few fields per tick. Reads fields in place instead of copying all 60 `TickState`s.
+/// Use `TickArrayState` when the full layout is needed.
+#[derive(Clone, Copy)]
+pub struct TickArrayRef<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> TickArrayRef<'a> {
+    /// Verifies owner, discriminator and exact length, then wraps the account data.
+    pub fn new(pubkey: &Pubkey, owner: &Pubkey, data: &'a [u8], program_id: &Pubkey) -> std::result::Result<Self, String> {
+        check_clmm_account::<TickArrayState>(pubkey, owner, data, program_id)?;
+        Ok(Self { data })
+    }
+
+    fn bytes<const N: usize>(&self, offset: usize) -> [u8; N] {
+        self.data[offset..offset + N].try_into().unwrap()
+    }
+
+    pub fn pool_id(&self) -> Pubkey {
+        Pubkey::new_from_array(self.bytes(TICK_ARRAY_POOL_ID_OFFSET))
+    }
+
+    pub fn start_tick_index(&self) -> i32 {
+        i32::from_le_bytes(self.bytes(TICK_ARRAY_START_INDEX_OFFSET))
+    }
+
+    pub fn initialized_tick_count(&self) -> u8 {
+        self.data[TICK_ARRAY_INITIALIZED_COUNT_OFFSET]
+    }
+
+    /// Iterates `(tick, liquidity_net, liquidity_gross)` for every tick with non-zero gross liquidity.
+    pub fn initialized_ticks(&self) -> impl Iterator<Item = (i32, i128, u128)> + 'a {
+        let view = *self;
+        (0..60).filter_map(move |slot| {
+            let offset = TICK_ARRAY_TICKS_OFFSET + slot * TICK_STATE_LEN;
+            let liquidity_gross = u128::from_le_bytes(view.bytes(offset + TICK_STATE_LIQUIDITY_GROSS_OFFSET));
+            (liquidity_gross != 0).then(|| (
+                i32::from_le_bytes(view.bytes(offset)),
+                i128::from_le_bytes(view.bytes(offset + TICK_STATE_LIQUIDITY_NET_OFFSET)),
+                liquidity_gross,
+            ))
+        })
+    }
+}
+
+/// `PoolState::status`: each set bit disables one operation, and a zero byte means everything is
+/// enabled. Bit 0 covers open position and increase liquidity, bit 1 decrease liquidity, bit 2 fee
+/// collection, bit 3 reward collection and bit 4 swaps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoolStatus(pub u8);
+
+impl PoolStatus {
+    fn enabled(self, bit: u8) -> bool {
+        self.0 & (1 << bit) == 0
+    }
+
+    pub fn open_position_enabled(self) -> bool {
+        self.enabled(0)
+    }
+
+    pub fn decrease_liquidity_enabled(self) -> bool {
+        self.enabled(1)
+    }
+
+    pub fn collect_fee_enabled(self) -> bool {
+        self.enabled(2)
+    }
+
+    pub fn collect_reward_enabled(self) -> bool {
+        self.enabled(3)
+    }
+
+    pub fn swap_enabled(self) -> bool {
+        self.enabled(4)
+    }
+
+    /// `(operation, enabled)` pairs in bit order.
+    pub fn operations(self) -> [(&'static str, bool); 5] {
+        [
+            ("deposits", self.open_position_enabled()),
+            ("withdrawals", self.decrease_liquidity_enabled()),
+            ("fee collection", self.collect_fee_enabled()),
+            ("reward collection", self.collect_reward_enabled()),
+            ("swaps", self.swap_enabled()),
+        ]
+    }
+}
+
+/// `deposits ENABLED, ..., swaps DISABLED (raw: 0b00010000)`.
+impl std::fmt::Display for PoolStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let operations: Vec<String> = self.operations().iter()
+            .map(|(operation, enabled)| format!("{} {}", operation, if *enabled { "ENABLED" } else { "DISABLED" }))
+            .collect();
+        write!(f, "{} (raw: {:#010b})", operations.join(", "), self.0)
+    }
+}
+
+impl PoolState {
+    pub fn pool_status(&self) -> PoolStatus {
+        PoolStatus(self.status)
+    }
+}
+
+pub const SPL_TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+pub const SPL_TOKEN_2022_PROGRAM_ID: &str = "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb";
+
+/// Byte offset of `decimals` in the SPL Token `Mint` layout
+/// (after the `COption<Pubkey>` mint authority and the `u64` supply).
+pub const MINT_DECIMALS_OFFSET: usize = 44;
+/// Byte offset of `is_initialized` in the `Mint` layout, right after `decimals`.
+const MINT_IS_INITIALIZED_OFFSET: usize = MINT_DECIMALS_OFFSET + 1;
+/// Size of the base `Mint` layout, shared by both token programs.
+pub const MINT_LEN: usize = 82;
+/// Token-2022 accounts with extensions pad the base layout to the size of a token
+/// `Account` (165 bytes), then store an `AccountType` byte: 1 for a mint.
+const TOKEN_2022_ACCOUNT_TYPE_OFFSET: usize = 165;
+const TOKEN_2022_ACCOUNT_TYPE_MINT: u8 = 1;
+
+/// Reads the `decimals` field from a raw mint account owned by SPL Token or Token-2022.
+/// Both programs share the base `Mint` layout, so `decimals` is at the same offset; a
+/// Token-2022 mint may carry extensions after it, marked by the account type byte.
+pub fn read_mint_decimals(owner: &Pubkey, data: &[u8]) -> std::result::Result<u8, String> {
+    let owner = owner.to_string();
+    if owner == SPL_TOKEN_PROGRAM_ID {
+        if data.len() != MINT_LEN {
+            return Err(format!("SPL Token mint has {} bytes, expected {}", data.len(), MINT_LEN));
+        }
+    } else if owner == SPL_TOKEN_2022_PROGRAM_ID {
+        if data.len() > MINT_LEN && data.get(TOKEN_2022_ACCOUNT_TYPE_OFFSET) != Some(&TOKEN_2022_ACCOUNT_TYPE_MINT) {
+            return Err("Token-2022 account is not a mint".to_string());
+        }
+        if data.len() < MINT_LEN {
+            return Err(format!("Token-2022 mint has {} bytes, expected at least {}", data.len(), MINT_LEN));
+        }
+    } else {
+        return Err(format!("owner {} is not a token program", owner));
+    }
+    if data[MINT_IS_INITIALIZED_OFFSET] == 0 {
+        return Err("mint is not initialized".to_string());
+    }
+    Ok(data[MINT_DECIMALS_OFFSET])
+}
+
+/// Byte offset of `owner` in the SPL Token `Account` layout (after the `Pubkey` mint).
+pub const TOKEN_ACCOUNT_OWNER_OFFSET: usize = 32;
+
+/// Reads the `owner` field from raw SPL Token (or Token-2022) token account data.
+pub fn read_token_account_owner(data: &[u8]) -> Option<Pubkey> {
+    let bytes = data.get(TOKEN_ACCOUNT_OWNER_OFFSET..TOKEN_ACCOUNT_OWNER_OFFSET + 32)?;
+    Some(Pubkey::new_from_array(bytes.try_into().ok()?))
+}
+
+/// Denominator of the fee rates in `AmmConfig` (rates are in hundredths of a basis point).
+pub const FEE_RATE_DENOMINATOR: u32 = 1_000_000;
+
+#[derive(Debug, AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct AmmConfig {
+    pub bump: u8,
+    pub index: u16,
+    pub owner: Pubkey,
+    pub protocol_fee_rate: u32,
+    pub trade_fee_rate: u32,
+    pub tick_spacing: u16,
+    pub fund_fee_rate: u32,
+    pub padding_u32: u32,
+    pub fund_owner: Pubkey,
+    pub padding: [u64; 3],
+}
+
+impl ClmmAccount for AmmConfig {
+    const NAME: &'static str = "AmmConfig";
+    const DISCRIMINATOR: [u8; 8] = AMM_CONFIG_DISCRIMINATOR;
+}
+
+/// Number of entries in the observation ring buffer.
+pub const OBSERVATION_NUM: usize = 100;
+
+#[derive(Debug, AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ObservationState {
+    pub initialized: bool,
+    pub recent_epoch: u64,
+    pub observation_index: u16,
+    pub pool_id: Pubkey,
+    pub observations: [Observation; OBSERVATION_NUM],
+    pub padding: [u64; 4],
+}
+
+#[derive(Debug, AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct Observation {
+    pub block_timestamp: u32,
+    pub tick_cumulative: i64,
+    pub padding: [u64; 4],
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn tick_array_data(len: usize) -> Vec<u8> {
+        let mut data = TICK_ARRAY_STATE_DISCRIMINATOR.to_vec();
+        data.resize(len, 0);
+        data
+    }
+
+    #[test]
+    fn discriminators_are_the_sha256_of_their_names() {
+        let discriminator = |preimage: &str| anchor_lang::solana_program::hash::hash(preimage.as_bytes()).to_bytes()[..8].to_vec();
+        for (name, known) in KNOWN_ACCOUNT_TYPES {
+            assert_eq!(discriminator(&format!("account:{}", name)), known, "{}", name);
+        }
+        assert_eq!(discriminator("global:swap_v2"), SWAP_V2_DISCRIMINATOR);
+    }
+
+    #[test]
+    fn buffers_too_short_for_a_discriminator_are_rejected() {
+        let pubkey = Pubkey::new_unique();
+        for len in [0, 7] {
+            let error = decode_anchor_account::<TickArrayState>(&pubkey, &vec![0; len]).unwrap_err();
+            assert_eq!(
+                error,
+                format!("{} has {} bytes of data, too short for an Anchor account (closed account or wrong address?)", pubkey, len)
+            );
+        }
+    }
+
+    #[test]
+    fn tick_arrays_must_have_their_exact_length() {
+        let pubkey = Pubkey::new_unique();
+        for len in [8, TICK_ARRAY_STATE_LEN - 1, TICK_ARRAY_STATE_LEN + 1] {
+            let error = decode_anchor_account::<TickArrayState>(&pubkey, &tick_array_data(len)).unwrap_err();
+            assert_eq!(error, format!("{} has {} bytes of data, expected exactly {} for a TickArrayState", pubkey, len, TICK_ARRAY_STATE_LEN));
+        }
+        assert!(decode_anchor_account::<TickArrayState>(&pubkey, &tick_array_data(TICK_ARRAY_STATE_LEN)).is_ok());
+    }
+
+    #[test]
+    fn truncated_accounts_without_a_fixed_length_fail_to_parse() {
+        let pubkey = Pubkey::new_unique();
+        let error = decode_anchor_account::<PoolState>(&pubkey, &POOL_STATE_DISCRIMINATOR).unwrap_err();
+        assert!(error.starts_with(&format!("failed to parse {} as PoolState: ", pubkey)), "{}", error);
+    }
+
+    /// Raw account data for a tick array, encoded with Borsh rather than the view's offsets.
+    fn encode_tick_array(array: &TickArrayState) -> Vec<u8> {
+        [TICK_ARRAY_STATE_DISCRIMINATOR.to_vec(), array.try_to_vec().unwrap()].concat()
+    }
+
+    #[test]
+    fn zero_copy_tick_arrays_agree_with_the_borsh_decode() {
+        let program_id = Pubkey::new_unique();
+        let mut array = TickArrayState::deserialize(&mut &[0; TICK_ARRAY_STATE_LEN - 8][..]).unwrap();
+        array.pool_id = Pubkey::new_unique();
+        array.start_tick_index = -443640;
+        let empty = encode_tick_array(&array);
+
+        // Every other slot initialized, with liquidity at the extremes of its type
+        for (slot, tick) in array.ticks.iter_mut().enumerate().filter(|(slot, _)| slot % 2 == 0) {
+            tick.tick = -443640 + slot as i32 * 60;
+            tick.liquidity_net = if slot % 4 == 0 { i128::MIN + slot as i128 } else { i128::MAX - slot as i128 };
+            tick.liquidity_gross = u128::MAX - slot as u128;
+            tick.fee_growth_outside_0_x64 = u128::MAX;
+            tick.reward_growths_outside_x64 = [1, 2, 3];
+        }
+        array.initialized_tick_count = 30;
+        array.recent_epoch = u64::MAX;
+        array.padding = [0xff; 107];
+
+        for (data, initialized) in [(empty, 0), (encode_tick_array(&array), 30)] {
+            let view = TickArrayRef::new(&Pubkey::new_unique(), &program_id, &data, &program_id).unwrap();
+            let decoded = TickArrayState::deserialize(&mut &data[8..]).unwrap();
+            assert_eq!(view.pool_id(), decoded.pool_id);
+            assert_eq!(view.start_tick_index(), decoded.start_tick_index);
+            assert_eq!(view.initialized_tick_count(), decoded.initialized_tick_count);
+            let expected: Vec<(i32, i128, u128)> = decoded.ticks.iter()
+                .filter(|tick| tick.liquidity_gross != 0)
+                .map(|tick| (tick.tick, tick.liquidity_net, tick.liquidity_gross))
+                .collect();
+            assert_eq!(view.initialized_ticks().collect::<Vec<_>>(), expected);
+            assert_eq!(expected.len(), initialized);
+        }
+    }
+
+    /// A mint account of `len` bytes with the given decimals, initialized.
+    fn mint_data(len: usize, decimals: u8) -> Vec<u8> {
+        let mut data = vec![0; len];
+        data[MINT_DECIMALS_OFFSET] = decimals;
+        data[MINT_IS_INITIALIZED_OFFSET] = 1;
+        data
+    }
+
+    #[test]
+    fn mint_decimals_are_read_from_both_token_programs() {
+        let token = Pubkey::from_str(SPL_TOKEN_PROGRAM_ID).unwrap();
+        let token_2022 = Pubkey::from_str(SPL_TOKEN_2022_PROGRAM_ID).unwrap();
+        assert_eq!(read_mint_decimals(&token, &mint_data(MINT_LEN, 6)), Ok(6));
+        assert_eq!(read_mint_decimals(&token_2022, &mint_data(MINT_LEN, 9)), Ok(9));
+
+        // A Token-2022 mint with extensions: the account type byte, then a TLV entry
+        let mut with_extensions = mint_data(TOKEN_2022_ACCOUNT_TYPE_OFFSET + 1 + 4 + 32, 18);
+        with_extensions[TOKEN_2022_ACCOUNT_TYPE_OFFSET] = TOKEN_2022_ACCOUNT_TYPE_MINT;
+        with_extensions[TOKEN_2022_ACCOUNT_TYPE_OFFSET + 1..][..4].copy_from_slice(&[3, 0, 32, 0]);
+        assert_eq!(read_mint_decimals(&token_2022, &with_extensions), Ok(18));
+
+        // A Token-2022 token account has the same padding, with account type 2
+        with_extensions[TOKEN_2022_ACCOUNT_TYPE_OFFSET] = 2;
+        assert_eq!(read_mint_decimals(&token_2022, &with_extensions), Err("Token-2022 account is not a mint".to_string()));
+    }
+
+    #[test]
+    fn accounts_that_are_not_initialized_mints_have_no_decimals() {
+        let token = Pubkey::from_str(SPL_TOKEN_PROGRAM_ID).unwrap();
+        let token_2022 = Pubkey::from_str(SPL_TOKEN_2022_PROGRAM_ID).unwrap();
+        let system_program = Pubkey::default();
+        assert_eq!(
+            read_mint_decimals(&system_program, &mint_data(MINT_LEN, 6)),
+            Err(format!("owner {} is not a token program", system_program))
+        );
+        // An SPL Token account (165 bytes) is not a mint
+        assert_eq!(read_mint_decimals(&token, &mint_data(165, 6)), Err("SPL Token mint has 165 bytes, expected 82".to_string()));
+        assert_eq!(read_mint_decimals(&token_2022, &[0; 40]), Err("Token-2022 mint has 40 bytes, expected at least 82".to_string()));
+        let mut uninitialized = mint_data(MINT_LEN, 6);
+        uninitialized[MINT_IS_INITIALIZED_OFFSET] = 0;
+        assert_eq!(read_mint_decimals(&token, &uninitialized), Err("mint is not initialized".to_string()));
+    }
+}
@@ -0,0 +1,90 @@
+//! Opt-in on-disk cache of fetched `PoolState` accounts, keyed by pool id.
+//! Offline commands given `--pool-id` only need the pool's tick spacing and mint decimals, which
+//! never change, so a cached account saves the fetch when exploring one pool across many runs.
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::onchain_states::PoolState;
+
+/// One cached account, stored as `<cache dir>/<pool id>.json`.
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    /// Unix time of the fetch, in seconds.
+    fetched_at: u64,
+    owner: String,
+    /// Account data in base64.
+    data: String,
+    /// Decoded for readability only; the values used are read back from `data`.
+    tick_spacing: u16,
+    mint_decimals_0: u8,
+    mint_decimals_1: u8,
+}
+
+pub struct PoolCache {
+    dir: PathBuf,
+    ttl: Duration,
+    /// `false` under `--no-cache`: entries are refreshed but never read.
+    read: bool,
+}
+
+impl PoolCache {
+    pub fn new(dir: PathBuf, ttl: Duration, read: bool) -> Self {
+        PoolCache { dir, ttl, read }
+    }
+
+    fn path(&self, pool: &Pubkey) -> PathBuf {
+        self.dir.join(format!("{}.json", pool))
+    }
+
+    /// Returns the cached account and its age, if an entry exists and is younger than the TTL.
+    /// Unreadable or corrupt entries count as misses.
+    pub fn load(&self, pool: &Pubkey) -> Option<(Account, Duration)> {
+        if !self.read {
+            return None;
+        }
+        let entry: CacheEntry = serde_json::from_slice(&fs::read(self.path(pool)).ok()?).ok()?;
+        let age = Duration::from_secs(unix_now().saturating_sub(entry.fetched_at));
+        if age > self.ttl {
+            return None;
+        }
+        let account = Account {
+            owner: entry.owner.parse().ok()?,
+            data: base64::engine::general_purpose::STANDARD.decode(entry.data).ok()?,
+            ..Account::default()
+        };
+        Some((account, age))
+    }
+
+    /// Writes a freshly fetched account. A cache that cannot be written only warns, since the
+    /// command itself already has what it needs.
+    pub fn store(&self, pool: &Pubkey, account: &Account, pool_state: &PoolState) {
+        let entry = CacheEntry {
+            fetched_at: unix_now(),
+            owner: account.owner.to_string(),
+            data: base64::engine::general_purpose::STANDARD.encode(&account.data),
+            tick_spacing: pool_state.tick_spacing,
+            mint_decimals_0: pool_state.mint_decimals_0,
+            mint_decimals_1: pool_state.mint_decimals_1,
+        };
+        // Write to a temporary file and rename, so a concurrent run never reads half an entry
+        let path = self.path(pool);
+        let temp = path.with_extension("json.tmp");
+        let result = fs::create_dir_all(&self.dir)
+            .and_then(|_| fs::write(&temp, serde_json::to_vec_pretty(&entry).expect("cache entry serializes")))
+            .and_then(|_| fs::rename(&temp, &path));
+        if let Err(e) = result {
+            eprintln!("Warning: could not write the pool cache {}: {}", path.display(), e);
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
@@ -0,0 +1,239 @@
+//! End-to-end runs of RPC command bodies against `MockFetcher`. The fixture is a synthetic pool
+//! (tick spacing 10, decimals 9 and 6, current tick -20000) with five positions, whose ticks fill
+//! the nine arrays listed in `FIXTURE_ARRAYS`. The second fixture is another pool (tick spacing 60)
+//! with its own bitmap extension and the tick array at start index 0.
+
+use std::str::FromStr;
+
+use anchor_lang::AnchorDeserialize;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::mock_fetcher::MockFetcher;
+use crate::rpc_util::{is_account_not_found, AccountFetcher};
+use crate::onchain_states::{
+    check_clmm_account, PoolState, TickArrayBitmapExtension, TickArrayRef, TickArrayState, POOL_STATE_DISCRIMINATOR, SPL_TOKEN_PROGRAM_ID,
+};
+use crate::{
+    check_pool_id, derive_tick_array_pda, extension_edge_chunks, fetch_clmm_account, full_analysis_command, pool_state_command, read_default_bitmap_positions,
+    read_extension_bitmap_positions, select_swap_arrays, snapshot_command, swap_tick_bounds, tick_array_command, ArrayColumns, BitmapLocation,
+    HumanPriceFormat, Palette, PriceDisplay, SwapDirection, SwapRange, TickConverter, RAYDIUM_CLMM_PROGRAM_ID, TICK_ARRAY_BITMAP_SEED,
+};
+
+const FIXTURE: &str = include_str!("../tests/fixtures/pool_accounts.json");
+const FIXTURE_POOL: &str = "US517G5965aydkZ46HS38QLi7UQiSojurfbQfKCELFx";
+const FIXTURE_ARRAYS: [i32; 9] = [-400200, -30000, -25200, -21000, -20400, -19200, -15000, 30000, 399600];
+const SECOND_FIXTURE: &str = include_str!("../tests/fixtures/second_pool_accounts.json");
+const SECOND_POOL: &str = "32ZsJ2yJjwuoBiWE5xnZjG9tKmK3CubbmEzgkQLyQzgD";
+
+const PRICES: PriceDisplay = PriceDisplay { precision: None, scientific: false };
+const PALETTE: Palette = Palette { enabled: false };
+
+fn fixture() -> (MockFetcher, Pubkey, Pubkey) {
+    let pool = Pubkey::from_str(FIXTURE_POOL).unwrap();
+    let program_id = Pubkey::from_str(RAYDIUM_CLMM_PROGRAM_ID).unwrap();
+    (MockFetcher::from_fixture(FIXTURE), pool, program_id)
+}
+
+#[tokio::test]
+async fn pool_state_decodes_the_fixture_pool() {
+    let (fetcher, pool, program_id) = fixture();
+    let pool_state = pool_state_command(&fetcher, &pool, &program_id, true, PRICES).await;
+    assert_eq!(pool_state.tick_current, -20000);
+    assert_eq!(pool_state.tick_spacing, 10);
+    assert_eq!((pool_state.mint_decimals_0, pool_state.mint_decimals_1), (9, 6));
+    // The four positions spanning tick -20000: 1e9 + 5e9 + 2e8 + 1000
+    assert_eq!(pool_state.liquidity, 6_200_001_000);
+}
+
+#[tokio::test]
+async fn tick_array_lists_the_initialized_ticks() {
+    let (fetcher, pool, program_id) = fixture();
+    let tick_array = tick_array_command(&fetcher, &pool, &program_id, -21000, HumanPriceFormat::T0PerT1, PRICES).await;
+    assert_eq!(tick_array.start_tick_index, -21000);
+    assert_eq!(tick_array.initialized_tick_count, 2);
+    let initialized: Vec<(i32, i128, u128)> = tick_array.ticks.iter()
+        .filter(|tick| tick.liquidity_gross != 0)
+        .map(|tick| (tick.tick, tick.liquidity_net, tick.liquidity_gross))
+        .collect();
+    assert_eq!(initialized, [(-21000, 5_000_000_000, 5_000_000_000), (-20500, 3_000_000_000, 3_000_000_000)]);
+}
+
+#[tokio::test]
+async fn full_analysis_reads_every_bitmap_array() {
+    let (fetcher, pool, program_id) = fixture();
+    let (arrays, inconsistent) =
+        full_analysis_command(&fetcher, &pool, &program_id, HumanPriceFormat::T1PerT0, false, ArrayColumns::Detailed, PALETTE, PRICES).await;
+    assert_eq!(arrays, FIXTURE_ARRAYS);
+    assert!(inconsistent.is_empty());
+}
+
+#[tokio::test]
+async fn full_analysis_snapshot_flags_a_missing_array() {
+    let (fetcher, pool, program_id) = fixture();
+    let fetcher = fetcher.without(&derive_tick_array_pda(&pool, -19200, &program_id));
+    let (arrays, inconsistent) =
+        full_analysis_command(&fetcher, &pool, &program_id, HumanPriceFormat::T0PerT1, true, ArrayColumns::Ticks, PALETTE, PRICES).await;
+    assert_eq!(arrays, FIXTURE_ARRAYS);
+    assert_eq!(inconsistent, [(-19200, "account not found".to_string())]);
+}
+
+#[tokio::test]
+async fn bitmap_readers_tag_each_array_with_its_source() {
+    let (fetcher, pool, program_id) = fixture();
+    let pool_state = fetch_clmm_account::<PoolState>(&fetcher, &pool, &program_id).await;
+    let (ext_pda, _) = Pubkey::find_program_address(&[TICK_ARRAY_BITMAP_SEED, pool.as_ref()], &program_id);
+    let extension = fetch_clmm_account::<TickArrayBitmapExtension>(&fetcher, &ext_pda, &program_id).await;
+
+    let mut sourced: Vec<(i32, BitmapLocation)> = read_default_bitmap_positions(&pool_state.tick_array_bitmap).iter()
+        .chain(&read_extension_bitmap_positions(&extension))
+        .map(|position| (position.start_index(pool_state.tick_spacing), position.location))
+        .collect();
+    sourced.sort_by_key(|&(start_index, _)| start_index);
+    // Array offsets beyond the default bitmap (here -667 and +666) live in the first extension chunk on each side
+    assert_eq!(sourced.first(), Some(&(-400200, BitmapLocation::Negative { chunk: 0 })));
+    assert_eq!(sourced.last(), Some(&(399600, BitmapLocation::Positive { chunk: 0 })));
+    assert!(sourced[1..sourced.len() - 1].iter().all(|&(_, location)| location == BitmapLocation::Default));
+    assert_eq!(BitmapLocation::Negative { chunk: 0 }.to_string(), "ext-0");
+}
+
+#[tokio::test]
+async fn bits_in_the_outermost_extension_words_are_flagged() {
+    let (fetcher, pool, program_id) = fixture();
+    let (ext_pda, _) = Pubkey::find_program_address(&[TICK_ARRAY_BITMAP_SEED, pool.as_ref()], &program_id);
+    let mut extension = fetch_clmm_account::<TickArrayBitmapExtension>(&fetcher, &ext_pda, &program_id).await;
+    assert!(extension_edge_chunks(&extension).is_empty());
+
+    // The farthest array on each side: the top bit of the last positive chunk, the bottom bit of the last negative one
+    extension.positive_tick_array_bitmap[13][7] |= 1 << 63;
+    extension.negative_tick_array_bitmap[13][0] |= 1;
+    assert_eq!(extension_edge_chunks(&extension), [BitmapLocation::Positive { chunk: 13 }, BitmapLocation::Negative { chunk: 13 }]);
+    let mut start_indices: Vec<i32> = read_extension_bitmap_positions(&extension).iter().map(|position| position.start_index(1)).collect();
+    start_indices.sort();
+    // 7680 arrays of 60 ticks on each side, the last of them starting at -7680 * 60 and 7679 * 60
+    assert_eq!((start_indices[0], start_indices[start_indices.len() - 1]), (-460800, 460740));
+}
+
+#[tokio::test]
+async fn swap_arrays_end_with_the_surrounding_array_on_the_impact_side() {
+    let (fetcher, pool, program_id) = fixture();
+    let pool_state = fetch_clmm_account::<PoolState>(&fetcher, &pool, &program_id).await;
+    let (ext_pda, _) = Pubkey::find_program_address(&[TICK_ARRAY_BITMAP_SEED, pool.as_ref()], &program_id);
+    let extension = fetch_clmm_account::<TickArrayBitmapExtension>(&fetcher, &ext_pda, &program_id).await;
+    let converter = TickConverter { decimals_0: pool_state.mint_decimals_0, decimals_1: pool_state.mint_decimals_1 };
+    let range = SwapRange::Ticks { favorable: 100, impact: 200 };
+
+    // From tick -20000 (array -20400), buy-t1 covers [-20200, -19900] and continues down to -21000
+    let (favorable, impact) = swap_tick_bounds(&converter, SwapDirection::BuyT1, pool_state.tick_current, range).unwrap();
+    assert_eq!((favorable, impact), (-19900, -20200));
+    let (ordered, has_surrounding) = select_swap_arrays(&pool_state, &extension, SwapDirection::BuyT1, (impact, favorable));
+    assert!(has_surrounding);
+    assert_eq!(ordered, [("IN-RANGE", -20400), ("SURROUNDING_DN", -21000)]);
+
+    // Buy-t0 covers [-20100, -19800], reaching array -19800, which is not initialized, and continues up to -19200
+    let (favorable, impact) = swap_tick_bounds(&converter, SwapDirection::BuyT0, pool_state.tick_current, range).unwrap();
+    assert_eq!((favorable, impact), (-20100, -19800));
+    let (ordered, has_surrounding) = select_swap_arrays(&pool_state, &extension, SwapDirection::BuyT0, (favorable, impact));
+    assert!(has_surrounding);
+    assert_eq!(ordered, [("IN-RANGE", -20400), ("SURROUNDING_UP", -19200)]);
+}
+
+#[tokio::test]
+async fn detailed_columns_sum_the_array_liquidity() {
+    let (fetcher, pool, program_id) = fixture();
+    let pdas = [-21000, -19200].map(|start_index| derive_tick_array_pda(&pool, start_index, &program_id));
+    let (_, accounts) = fetcher.get_multiple_accounts_at(&pdas, None).await.unwrap();
+    // Ticks -21000 (+5B) and -20500 (+3B) both open positions
+    let cells = ArrayColumns::Detailed.cells(&pdas[0], accounts[0].as_ref(), &program_id);
+    assert_eq!(cells, ArrayColumns::Detailed.row(["2", "8.00B", "+8.00B"]));
+    let cells = ArrayColumns::Detailed.cells(&pdas[1], None, &program_id);
+    assert_eq!(cells, ArrayColumns::Detailed.row(["MISSING", "", ""]));
+}
+
+#[tokio::test]
+async fn zero_copy_view_agrees_with_the_borsh_decode_on_fixture_arrays() {
+    let (fetcher, pool, program_id) = fixture();
+    let pdas = FIXTURE_ARRAYS.map(|start_index| derive_tick_array_pda(&pool, start_index, &program_id));
+    let (_, accounts) = fetcher.get_multiple_accounts_at(&pdas, None).await.unwrap();
+    for (pda, account) in pdas.iter().zip(accounts) {
+        let data = account.unwrap().data;
+        let view = TickArrayRef::new(pda, &program_id, &data, &program_id).unwrap();
+        let decoded = TickArrayState::deserialize(&mut &data[8..]).unwrap();
+        assert_eq!((view.pool_id(), view.start_tick_index()), (decoded.pool_id, decoded.start_tick_index));
+        assert_eq!(view.initialized_tick_count(), decoded.initialized_tick_count);
+        let expected: Vec<(i32, i128, u128)> = decoded.ticks.iter()
+            .filter(|tick| tick.liquidity_gross != 0)
+            .map(|tick| (tick.tick, tick.liquidity_net, tick.liquidity_gross))
+            .collect();
+        assert!(!expected.is_empty());
+        assert_eq!(view.initialized_ticks().collect::<Vec<_>>(), expected);
+    }
+}
+
+#[tokio::test]
+async fn snapshot_archives_every_array_with_wide_integers_as_strings() {
+    let (fetcher, pool, program_id) = fixture();
+    let fetcher = fetcher.without(&derive_tick_array_pda(&pool, 30000, &program_id));
+    let snapshot = snapshot_command(&fetcher, &pool, &program_id).await;
+    assert_eq!(snapshot.slot, 250_000_000);
+    assert_eq!(snapshot.initialized_arrays, FIXTURE_ARRAYS);
+    assert_eq!(snapshot.tick_arrays.len(), FIXTURE_ARRAYS.len() - 1);
+    assert_eq!(snapshot.unreadable_arrays.iter().map(|array| array.start_index).collect::<Vec<_>>(), [30000]);
+
+    let json = serde_json::to_value(&snapshot).unwrap();
+    assert_eq!(json["pool"]["liquidity"], "6200001000");
+    let array = json["tick_arrays"].as_array().unwrap().iter().find(|array| array["start_index"] == -21000).unwrap();
+    assert_eq!(array["ticks"][0]["tick"], -21000);
+    assert_eq!(array["ticks"][0]["liquidity_net"], "5000000000");
+}
+
+#[tokio::test]
+async fn accounts_of_another_pool_fail_the_pool_id_check() {
+    let (fetcher, pool, program_id) = fixture();
+    let fetcher = fetcher.with_fixture(SECOND_FIXTURE);
+    let second_pool = Pubkey::from_str(SECOND_POOL).unwrap();
+
+    let array_pda = derive_tick_array_pda(&second_pool, 0, &program_id);
+    let tick_array = fetch_clmm_account::<TickArrayState>(&fetcher, &array_pda, &program_id).await;
+    assert_eq!(check_pool_id(&array_pda, &tick_array, &second_pool), Ok(()));
+    let error = check_pool_id(&array_pda, &tick_array, &pool).unwrap_err();
+    assert_eq!(error, format!("TickArrayState {} belongs to pool {}, not {}.", array_pda, second_pool, pool));
+
+    let (ext_pda, _) = Pubkey::find_program_address(&[TICK_ARRAY_BITMAP_SEED, second_pool.as_ref()], &program_id);
+    let extension = fetch_clmm_account::<TickArrayBitmapExtension>(&fetcher, &ext_pda, &program_id).await;
+    assert_eq!(check_pool_id(&ext_pda, &extension, &second_pool), Ok(()));
+    assert!(check_pool_id(&ext_pda, &extension, &pool).is_err());
+}
+
+#[tokio::test]
+async fn account_checks_report_the_owner_or_the_account_type_found() {
+    let (fetcher, pool, program_id) = fixture();
+    let account = fetcher.get_account(&pool).await.unwrap();
+    assert_eq!(check_clmm_account::<PoolState>(&pool, &account.owner, &account.data, &program_id), Ok(()));
+
+    let token_program = Pubkey::from_str(SPL_TOKEN_PROGRAM_ID).unwrap();
+    let error = check_clmm_account::<PoolState>(&pool, &token_program, &account.data, &program_id).unwrap_err();
+    assert_eq!(error, format!("{} is owned by {}, not the CLMM program {}", pool, token_program, program_id));
+
+    // A pool passed where a tick array is expected is named by its own discriminator
+    let error = check_clmm_account::<TickArrayState>(&pool, &account.owner, &account.data, &program_id).unwrap_err();
+    assert_eq!(
+        error,
+        format!("{} is not a TickArrayState account: found discriminator {:?} (this is a PoolState account)", pool, POOL_STATE_DISCRIMINATOR)
+    );
+
+    let mut data = account.data.clone();
+    data[..8].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+    let error = check_clmm_account::<PoolState>(&pool, &account.owner, &data, &program_id).unwrap_err();
+    assert_eq!(
+        error,
+        format!("{} is not a PoolState account: found discriminator [1, 2, 3, 4, 5, 6, 7, 8] (not a known CLMM account type)", pool)
+    );
+}
+
+#[tokio::test]
+async fn missing_accounts_are_reported_as_not_found() {
+    let (fetcher, pool, _) = fixture();
+    let fetcher = fetcher.without(&pool);
+    let error = fetcher.get_account(&pool).await.unwrap_err();
+    assert!(is_account_not_found(&error));
+}
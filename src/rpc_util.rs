@@ -0,0 +1,543 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use futures::future::{FutureExt, LocalBoxFuture};
+use futures::stream::{self, StreamExt};
+use indicatif::{ProgressBar, ProgressStyle};
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_client::client_error::{ClientError, ClientErrorKind, Result as ClientResult};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::RpcFilterType;
+use solana_client::rpc_request::{RpcError, RpcRequest};
+use solana_client::rpc_response::Response;
+use solana_sdk::account::Account;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+
+/// JSON-RPC error code returned by nodes that are behind or unhealthy.
+const RPC_NODE_UNHEALTHY: i64 = -32005;
+/// JSON-RPC error code returned when the node has not reached the requested `min_context_slot`.
+const RPC_MIN_CONTEXT_SLOT_NOT_REACHED: i64 = -32016;
+/// Maximum number of accounts the RPC accepts in one `getMultipleAccounts` request.
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+/// Multi-account fetches larger than this show a progress bar.
+const PROGRESS_MIN_ACCOUNTS: usize = 5;
+/// Consecutive failures after which an endpoint is skipped while healthier ones exist.
+const UNHEALTHY_AFTER_FAILURES: u32 = 3;
+
+/// Options shared by every fetch within a command, resolved from the global CLI flags.
+#[derive(Clone, Copy, Debug)]
+pub struct FetchOptions {
+    pub commitment: CommitmentConfig,
+    /// Number of retries after the first failed attempt.
+    pub retries: u32,
+    /// Base delay before the first retry; doubled on each further attempt.
+    pub retry_delay: Duration,
+    /// Pin every fetch to the first endpoint that responds, to avoid cross-endpoint slot skew.
+    pub strict_single_slot: bool,
+    /// Client-side request rate limit shared by all fetches of a command.
+    pub max_rps: Option<f64>,
+    /// Maximum number of account fetches in flight at once.
+    pub concurrency: usize,
+    /// Show a progress bar on stderr for multi-account fetches.
+    pub show_progress: bool,
+    /// Require account fetches to be served at or after this slot (`min_context_slot`).
+    pub min_context_slot: Option<u64>,
+}
+
+/// Token-bucket rate limiter. Each RPC request (including a batched
+/// `get_multiple_accounts` call) takes one token; the bucket holds at most one
+/// second's worth of tokens.
+pub struct RateLimiter {
+    rate: f64,
+    state: Mutex<(f64, Instant)>, // (available tokens, last refill)
+    notified: AtomicBool,
+}
+
+impl RateLimiter {
+    pub fn new(max_rps: f64) -> Self {
+        Self {
+            rate: max_rps,
+            state: Mutex::new((max_rps, Instant::now())),
+            notified: AtomicBool::new(false),
+        }
+    }
+
+    /// Reserves one token and returns how long the caller must wait before sending.
+    pub fn reserve(&self) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        let (tokens, last_refill) = &mut *state;
+        let now = Instant::now();
+        *tokens = (*tokens + now.duration_since(*last_refill).as_secs_f64() * self.rate).min(self.rate);
+        *last_refill = now;
+        *tokens -= 1.0;
+
+        if *tokens >= 0.0 {
+            return Duration::ZERO;
+        }
+        if !self.notified.swap(true, Ordering::Relaxed) {
+            eprintln!("Note: rate limiting to {} rps", self.rate);
+        }
+        Duration::from_secs_f64(-*tokens / self.rate)
+    }
+
+    /// Waits until a token is available.
+    pub async fn acquire(&self) {
+        let delay = self.reserve();
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// A single RPC endpoint and its health counters.
+struct Endpoint {
+    url: String,
+    client: RpcClient,
+    consecutive_failures: AtomicU32,
+    successes: AtomicU32,
+    failures: AtomicU32,
+}
+
+/// A set of RPC endpoints used round-robin, failing over to the next endpoint on errors.
+pub struct RpcPool {
+    endpoints: Vec<Endpoint>,
+    next: AtomicUsize,
+    pinned: OnceLock<usize>,
+    strict_single_slot: bool,
+}
+
+impl RpcPool {
+    pub fn new(rpc_urls: Vec<String>, commitment: CommitmentConfig, strict_single_slot: bool) -> Self {
+        assert!(!rpc_urls.is_empty(), "At least one RPC URL is required");
+        let endpoints = rpc_urls
+            .into_iter()
+            .map(|url| Endpoint {
+                client: RpcClient::new_with_commitment(url.clone(), commitment),
+                url,
+                consecutive_failures: AtomicU32::new(0),
+                successes: AtomicU32::new(0),
+                failures: AtomicU32::new(0),
+            })
+            .collect();
+        Self {
+            endpoints,
+            next: AtomicUsize::new(0),
+            pinned: OnceLock::new(),
+            strict_single_slot,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.endpoints.len()
+    }
+
+    /// Picks the endpoint for the next request: the pinned one in strict mode, otherwise
+    /// the next healthy endpoint in round-robin order.
+    fn pick(&self) -> usize {
+        if let Some(&pinned) = self.pinned.get() {
+            return pinned;
+        }
+        let count = self.endpoints.len();
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        (0..count)
+            .map(|offset| (start + offset) % count)
+            .find(|&index| self.endpoints[index].consecutive_failures.load(Ordering::Relaxed) < UNHEALTHY_AFTER_FAILURES)
+            .unwrap_or(start % count)
+    }
+
+    fn record_success(&self, index: usize) {
+        let endpoint = &self.endpoints[index];
+        endpoint.consecutive_failures.store(0, Ordering::Relaxed);
+        endpoint.successes.fetch_add(1, Ordering::Relaxed);
+        if self.strict_single_slot {
+            let _ = self.pinned.set(index);
+        }
+    }
+
+    fn record_failure(&self, index: usize) {
+        let endpoint = &self.endpoints[index];
+        endpoint.consecutive_failures.fetch_add(1, Ordering::Relaxed);
+        endpoint.failures.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Fetch layer used by every RPC command.
+/// Records the context slot of each fetch so commands can report how fresh their data is,
+/// retries transient failures across the endpoint pool, and keeps a list of accounts that
+/// could not be fetched.
+pub struct RpcFetcher {
+    pool: RpcPool,
+    options: FetchOptions,
+    limiter: Option<RateLimiter>,
+    slots: Mutex<Vec<u64>>,
+    failures: Mutex<Vec<(String, String)>>,
+}
+
+impl RpcFetcher {
+    pub fn new(rpc_urls: Vec<String>, options: FetchOptions) -> Self {
+        Self {
+            pool: RpcPool::new(rpc_urls, options.commitment, options.strict_single_slot),
+            options,
+            limiter: options.max_rps.map(RateLimiter::new),
+            slots: Mutex::new(Vec::new()),
+            failures: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns a progress bar for fetching `total` accounts, or a hidden one when the fetch is
+    /// small or progress output is disabled.
+    fn progress_bar(&self, total: usize) -> ProgressBar {
+        if !self.options.show_progress || total <= PROGRESS_MIN_ACCOUNTS {
+            return ProgressBar::hidden();
+        }
+        let progress = ProgressBar::new(total as u64);
+        progress.set_style(
+            ProgressStyle::with_template("Fetching accounts [{bar:30}] {pos}/{len} ({per_sec}) {msg}")
+                .expect("Invalid progress bar template")
+                .progress_chars("=> "),
+        );
+        progress
+    }
+
+    /// Number of endpoints requests are spread over (one when pinned).
+    fn endpoint_count(&self) -> usize {
+        if self.options.strict_single_slot { 1 } else { self.pool.len() }
+    }
+
+    /// Fetches an account (data and owner), recording the slot it was read at.
+    /// Sends `getAccountInfo` directly: `RpcClient::get_account_with_config` turns every error,
+    /// including transport errors, into an "AccountNotFound" message, which defeats retries.
+    #[allow(clippy::result_large_err)] // Mirrors `RpcClient`'s own error type
+    pub async fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account> {
+        let config = RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            data_slice: None,
+            commitment: Some(self.options.commitment),
+            min_context_slot: self.options.min_context_slot,
+        };
+        let result = self.with_retries(async |client| {
+            let params = serde_json::json!([pubkey.to_string(), config.clone()]);
+            let response: Response<Option<UiAccount>> = client.send(RpcRequest::GetAccountInfo, params).await?;
+            self.slots.lock().unwrap().push(response.context.slot);
+            match response.value.and_then(|account| account.decode()) {
+                Some(account) => Ok(account),
+                None => Err(account_not_found_error(pubkey)),
+            }
+        }).await;
+        if let Err(e) = &result {
+            self.failures.lock().unwrap().push((pubkey.to_string(), e.to_string()));
+        }
+        result
+    }
+
+    /// Fetches accounts one request each, with up to `--concurrency` requests in flight.
+    /// Results are returned in the order of `pubkeys`, regardless of completion order.
+    pub async fn get_accounts_concurrent(&self, pubkeys: &[Pubkey]) -> Vec<ClientResult<Account>> {
+        let progress = self.progress_bar(pubkeys.len());
+        let failed = AtomicU32::new(0);
+        let results = stream::iter(pubkeys)
+            .map(async |pubkey| {
+                let result = self.get_account(pubkey).await;
+                // Counted on completion, not on yield, so the bar is accurate with requests in flight
+                if result.is_err() {
+                    let failed = failed.fetch_add(1, Ordering::Relaxed) + 1;
+                    progress.set_message(format!("{} failed", failed));
+                }
+                progress.inc(1);
+                result
+            })
+            .buffered(self.options.concurrency)
+            .collect()
+            .await;
+        progress.finish_and_clear();
+        results
+    }
+
+    /// Fetches several accounts with `get_multiple_accounts` (one call per 100 accounts).
+    /// Missing accounts are returned as `None`.
+    #[allow(clippy::result_large_err)]
+    pub async fn get_multiple_accounts_data(&self, pubkeys: &[Pubkey]) -> ClientResult<Vec<Option<Vec<u8>>>> {
+        let (_, accounts) = self.get_multiple_accounts_at(pubkeys, None).await?;
+        Ok(accounts.into_iter().map(|account| account.map(|a| a.data)).collect())
+    }
+
+    /// Fetches several accounts with `get_multiple_accounts`, requiring the node to serve data
+    /// from at least `min_context_slot` (or `--min-slot`, whichever is later). Returns the lowest
+    /// context slot across the batched calls.
+    /// Accounts within one call are always read at the same slot; separate calls are not.
+    #[allow(clippy::result_large_err)]
+    pub async fn get_multiple_accounts_at(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<u64>,
+    ) -> ClientResult<(u64, Vec<Option<Account>>)> {
+        let config = RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            data_slice: None,
+            commitment: Some(self.options.commitment),
+            min_context_slot: min_context_slot.max(self.options.min_context_slot),
+        };
+
+        let progress = self.progress_bar(pubkeys.len());
+        let mut accounts = Vec::with_capacity(pubkeys.len());
+        let mut min_slot = u64::MAX;
+        for chunk in pubkeys.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            let result = self.with_retries(async |client| {
+                let response = client.get_multiple_accounts_with_config(chunk, config.clone()).await?;
+                self.slots.lock().unwrap().push(response.context.slot);
+                Ok((response.context.slot, response.value))
+            }).await;
+            match result {
+                Ok((slot, chunk_accounts)) => {
+                    min_slot = min_slot.min(slot);
+                    accounts.extend(chunk_accounts);
+                    progress.inc(chunk.len() as u64);
+                }
+                Err(e) => {
+                    progress.finish_and_clear();
+                    let mut failures = self.failures.lock().unwrap();
+                    for pubkey in chunk {
+                        failures.push((pubkey.to_string(), e.to_string()));
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        progress.finish_and_clear();
+        Ok((min_slot, accounts))
+    }
+
+    /// Fetches every account owned by `program_id` that matches `filters`.
+    /// Many public RPC providers disable or restrict `getProgramAccounts`.
+    #[allow(clippy::result_large_err)]
+    pub async fn get_program_accounts(&self, program_id: &Pubkey, filters: Vec<RpcFilterType>) -> ClientResult<Vec<(Pubkey, Account)>> {
+        let config = RpcProgramAccountsConfig {
+            filters: Some(filters),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                data_slice: None,
+                commitment: Some(self.options.commitment),
+                min_context_slot: self.options.min_context_slot,
+            },
+            with_context: None,
+        };
+        let result = self.with_retries(async |client| client.get_program_accounts_with_config(program_id, config.clone()).await).await;
+        if let Err(e) = &result {
+            self.failures.lock().unwrap().push((program_id.to_string(), e.to_string()));
+        }
+        result
+    }
+
+    /// Looks up the token account holding the largest balance of each mint (for an NFT, the
+    /// holder's account) concurrently. The result keeps the order of `mints`.
+    pub async fn get_largest_token_accounts_concurrent(&self, mints: &[Pubkey]) -> Vec<ClientResult<Option<Pubkey>>> {
+        let progress = self.progress_bar(mints.len());
+        let results = stream::iter(mints)
+            .map(async |mint| {
+                let result = self.with_retries(async |client| {
+                    let response = client.get_token_largest_accounts_with_commitment(mint, self.options.commitment).await?;
+                    self.slots.lock().unwrap().push(response.context.slot);
+                    Ok(response.value.into_iter()
+                        .find(|balance| balance.amount.amount != "0")
+                        .and_then(|balance| balance.address.parse().ok()))
+                }).await;
+                if let Err(e) = &result {
+                    self.failures.lock().unwrap().push((mint.to_string(), e.to_string()));
+                }
+                progress.inc(1);
+                result
+            })
+            .buffered(self.options.concurrency)
+            .collect()
+            .await;
+        progress.finish_and_clear();
+        results
+    }
+
+    /// Returns the current slot at the configured commitment.
+    #[allow(clippy::result_large_err)]
+    pub async fn get_slot(&self) -> ClientResult<u64> {
+        self.with_retries(async |client| client.get_slot_with_commitment(self.options.commitment).await).await
+    }
+
+    /// Returns the current epoch at the configured commitment.
+    #[allow(clippy::result_large_err)]
+    pub async fn get_epoch(&self) -> ClientResult<u64> {
+        self.with_retries(async |client| client.get_epoch_info_with_commitment(self.options.commitment).await)
+            .await
+            .map(|info| info.epoch)
+    }
+
+    /// Runs `request` against the pool, failing over to the next endpoint on retryable errors.
+    /// The backoff delay is only applied once every endpoint has been tried.
+    #[allow(clippy::result_large_err)]
+    async fn with_retries<T>(&self, request: impl AsyncFn(&RpcClient) -> ClientResult<T>) -> ClientResult<T> {
+        let mut attempt = 0;
+        loop {
+            if let Some(limiter) = &self.limiter {
+                limiter.acquire().await;
+            }
+            let index = self.pool.pick();
+            let endpoint = &self.pool.endpoints[index];
+            match request(&endpoint.client).await {
+                Ok(value) => {
+                    self.pool.record_success(index);
+                    return Ok(value);
+                }
+                Err(e) if attempt < self.options.retries && is_retryable(&e) => {
+                    self.pool.record_failure(index);
+                    let round = attempt / self.endpoint_count() as u32;
+                    if (attempt + 1) % self.endpoint_count() as u32 == 0 {
+                        let delay = self.options.retry_delay * 2u32.saturating_pow(round);
+                        eprintln!("Warning: RPC request to {} failed ({}), retrying in {}ms...", endpoint.url, e, delay.as_millis());
+                        tokio::time::sleep(delay).await;
+                    } else {
+                        eprintln!("Warning: RPC request to {} failed ({}), failing over...", endpoint.url, e);
+                    }
+                    attempt += 1;
+                }
+                Err(e) => {
+                    if is_retryable(&e) {
+                        self.pool.record_failure(index);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    /// Prints the slot (or slot range) that the fetched data was read at,
+    /// followed by any accounts that still failed after all retries.
+    pub fn print_fetch_summary(&self) {
+        let slots = self.slots.lock().unwrap();
+        if let (Some(min_slot), Some(max_slot)) = (slots.iter().min(), slots.iter().max()) {
+            let commitment = self.options.commitment.commitment;
+            println!();
+            if min_slot == max_slot {
+                println!("Data slot: {} (commitment: {:?}, {} fetches)", min_slot, commitment, slots.len());
+            } else {
+                println!(
+                    "Data slots: min {}, max {} (commitment: {:?}, {} fetches)",
+                    min_slot, max_slot, commitment, slots.len()
+                );
+                println!("Note: Accounts were read at different slots and may not form a consistent snapshot.");
+            }
+            if let Some(min_context_slot) = self.options.min_context_slot {
+                println!("Minimum context slot: {} (data is {} slots newer)", min_context_slot, min_slot.saturating_sub(min_context_slot));
+            }
+        }
+
+        if self.pool.len() > 1 {
+            println!("Endpoints:");
+            for endpoint in &self.pool.endpoints {
+                println!(
+                    "  - {}: {} ok, {} failed",
+                    endpoint.url,
+                    endpoint.successes.load(Ordering::Relaxed),
+                    endpoint.failures.load(Ordering::Relaxed)
+                );
+            }
+        }
+
+        self.print_fetch_failures();
+    }
+
+    /// Prints accounts that still failed after all retries to stderr.
+    pub fn print_fetch_failures(&self) {
+        let failures = self.failures.lock().unwrap();
+        if !failures.is_empty() {
+            eprintln!("\n--- {} account(s) could not be fetched ---", failures.len());
+            for (account, error) in failures.iter() {
+                eprintln!("  - {}: {}", account, error);
+            }
+        }
+    }
+}
+
+/// The account reads shared by the RPC commands, so a command body can run against any source of
+/// accounts. `RpcFetcher` is the live implementation; tests use a fixture-backed one.
+/// Futures are boxed to keep the trait usable as `&dyn AccountFetcher`.
+#[allow(clippy::result_large_err)]
+pub trait AccountFetcher {
+    /// Fetches an account (data and owner). A missing account is an error that `is_account_not_found` recognizes.
+    fn get_account<'a>(&'a self, pubkey: &'a Pubkey) -> LocalBoxFuture<'a, ClientResult<Account>>;
+
+    /// Fetches accounts one request each; the result keeps the order of `pubkeys`.
+    fn get_accounts_concurrent<'a>(&'a self, pubkeys: &'a [Pubkey]) -> LocalBoxFuture<'a, Vec<ClientResult<Account>>>;
+
+    /// Fetches several accounts at one slot no older than `min_context_slot`, returning that slot.
+    /// Missing accounts are returned as `None`.
+    fn get_multiple_accounts_at<'a>(
+        &'a self,
+        pubkeys: &'a [Pubkey],
+        min_context_slot: Option<u64>,
+    ) -> LocalBoxFuture<'a, ClientResult<(u64, Vec<Option<Account>>)>>;
+
+    /// Returns the current slot.
+    fn get_slot(&self) -> LocalBoxFuture<'_, ClientResult<u64>>;
+
+    /// Returns the current epoch.
+    fn get_epoch(&self) -> LocalBoxFuture<'_, ClientResult<u64>>;
+}
+
+// Each method forwards to the inherent method of the same name, which takes precedence in the path
+impl AccountFetcher for RpcFetcher {
+    fn get_account<'a>(&'a self, pubkey: &'a Pubkey) -> LocalBoxFuture<'a, ClientResult<Account>> {
+        RpcFetcher::get_account(self, pubkey).boxed_local()
+    }
+
+    fn get_accounts_concurrent<'a>(&'a self, pubkeys: &'a [Pubkey]) -> LocalBoxFuture<'a, Vec<ClientResult<Account>>> {
+        RpcFetcher::get_accounts_concurrent(self, pubkeys).boxed_local()
+    }
+
+    fn get_multiple_accounts_at<'a>(
+        &'a self,
+        pubkeys: &'a [Pubkey],
+        min_context_slot: Option<u64>,
+    ) -> LocalBoxFuture<'a, ClientResult<(u64, Vec<Option<Account>>)>> {
+        RpcFetcher::get_multiple_accounts_at(self, pubkeys, min_context_slot).boxed_local()
+    }
+
+    fn get_slot(&self) -> LocalBoxFuture<'_, ClientResult<u64>> {
+        RpcFetcher::get_slot(self).boxed_local()
+    }
+
+    fn get_epoch(&self) -> LocalBoxFuture<'_, ClientResult<u64>> {
+        RpcFetcher::get_epoch(self).boxed_local()
+    }
+}
+
+/// Prefix of the error returned by `RpcFetcher::get_account` for a missing account.
+const ACCOUNT_NOT_FOUND: &str = "AccountNotFound";
+
+/// The error `AccountFetcher::get_account` returns for a missing account.
+pub fn account_not_found_error(pubkey: &Pubkey) -> ClientError {
+    ClientError::from(RpcError::ForUser(format!("{}: pubkey={}", ACCOUNT_NOT_FOUND, pubkey)))
+}
+
+/// Returns true when `get_account` failed because the account does not exist.
+pub fn is_account_not_found(error: &ClientError) -> bool {
+    matches!(error.kind(), ClientErrorKind::RpcError(RpcError::ForUser(message)) if message.starts_with(ACCOUNT_NOT_FOUND))
+}
+
+/// Returns true for errors worth retrying (rate limits, timeouts, transient server errors).
+/// Permanent errors such as a missing account are not retried.
+fn is_retryable(error: &ClientError) -> bool {
+    match error.kind() {
+        ClientErrorKind::Io(_) => true,
+        ClientErrorKind::Reqwest(e) => {
+            e.is_timeout()
+                || e.is_connect()
+                || e.status().is_some_and(|status| status.as_u16() == 429 || status.is_server_error())
+        }
+        ClientErrorKind::RpcError(RpcError::RpcRequestError(_)) => true,
+        // A node behind `--min-slot` may catch up, or another endpoint may already be there
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) => {
+            *code == RPC_NODE_UNHEALTHY || *code == RPC_MIN_CONTEXT_SLOT_NOT_REACHED
+        }
+        _ => false,
+    }
+}
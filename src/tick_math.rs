@@ -0,0 +1,190 @@
+//! Exact integer tick <-> sqrt price conversions, ported from the Raydium CLMM program.
+//! Prices are Q64.64 fixed point, matching `PoolState.sqrt_price_x64`.
+
+pub const MIN_TICK: i32 = -443636;
+pub const MAX_TICK: i32 = -MIN_TICK;
+
+/// `get_sqrt_price_at_tick(MIN_TICK)` and `get_sqrt_price_at_tick(MAX_TICK)`.
+pub const MIN_SQRT_PRICE_X64: u128 = 4295048016;
+pub const MAX_SQRT_PRICE_X64: u128 = 79226673521066979257578248091;
+
+/// Number of fractional bits computed for log2 in `get_tick_at_sqrt_price`.
+const BIT_PRECISION: u32 = 16;
+
+/// `2^64 / sqrt(1.0001)^(2^i)` for each bit `i` of the absolute tick, in Q64.64.
+const SQRT_RATIO_FACTORS_X64: [u128; 19] = [
+    0xfffcb933bd6fb800,
+    0xfff97272373d4000,
+    0xfff2e50f5f657000,
+    0xffe5caca7e10f000,
+    0xffcb9843d60f7000,
+    0xff973b41fa98e800,
+    0xff2ea16466c9b000,
+    0xfe5dee046a9a3800,
+    0xfcbe86c7900bb000,
+    0xf987a7253ac65800,
+    0xf3392b0822bb6000,
+    0xe7159475a2caf000,
+    0xd097f3bdfd2f2000,
+    0xa9f746462d9f8000,
+    0x70d869a156f31c00,
+    0x31be135f97ed3200,
+    0x9aa508b5b85a500,
+    0x5d6af8dedc582c,
+    0x2216e584f5fa,
+];
+
+/// Returns `sqrt(1.0001^tick)` as Q64.64, bit-exact with the program.
+/// The ratio is built for `-|tick|` by multiplying the factors of the set bits, then inverted for positive ticks.
+pub fn get_sqrt_price_at_tick(tick: i32) -> std::result::Result<u128, String> {
+    if !(MIN_TICK..=MAX_TICK).contains(&tick) {
+        return Err(format!("tick {} is outside [{}, {}]", tick, MIN_TICK, MAX_TICK));
+    }
+    let abs_tick = tick.unsigned_abs();
+
+    let mut ratio: u128 = 1 << 64;
+    for (bit, factor) in SQRT_RATIO_FACTORS_X64.iter().enumerate() {
+        if abs_tick & (1 << bit) != 0 {
+            ratio = (ratio * factor) >> 64;
+        }
+    }
+    if tick > 0 {
+        ratio = u128::MAX / ratio;
+    }
+    Ok(ratio)
+}
+
+/// Returns the greatest tick whose sqrt price is at or below `sqrt_price_x64`, bit-exact with the program.
+/// Computes log2 of the Q64.64 value with integer squaring, converts it to base sqrt(1.0001), and
+/// resolves the remaining one-tick ambiguity with `get_sqrt_price_at_tick`.
+pub fn get_tick_at_sqrt_price(sqrt_price_x64: u128) -> std::result::Result<i32, String> {
+    if !(MIN_SQRT_PRICE_X64..MAX_SQRT_PRICE_X64).contains(&sqrt_price_x64) {
+        return Err(format!(
+            "sqrt_price_x64 {} is outside [{}, {})",
+            sqrt_price_x64, MIN_SQRT_PRICE_X64, MAX_SQRT_PRICE_X64
+        ));
+    }
+
+    // 1. Integer part of log2, as Q32.32
+    let msb = 127 - sqrt_price_x64.leading_zeros();
+    let log2p_integer_x32 = (msb as i128 - 64) << 32;
+
+    // 2. Fractional part: normalize to [1, 2) in Q1.63 and square repeatedly;
+    //    each square that reaches 2 contributes the current fraction bit
+    let mut r = if msb >= 64 { sqrt_price_x64 >> (msb - 63) } else { sqrt_price_x64 << (63 - msb) };
+    let mut bit: i128 = 0x8000_0000_0000_0000;
+    let mut precision = 0;
+    let mut log2p_fraction_x64: i128 = 0;
+    while bit > 0 && precision < BIT_PRECISION {
+        r *= r;
+        let is_r_more_than_two = (r >> 127) as u32;
+        r >>= 63 + is_r_more_than_two;
+        log2p_fraction_x64 += bit * is_r_more_than_two as i128;
+        bit >>= 1;
+        precision += 1;
+    }
+    let log2p_x32 = log2p_integer_x32 + (log2p_fraction_x64 >> 32);
+
+    // 3. Change of base: multiply by 2^32 / log2(sqrt(1.0001)) to get log_sqrt(1.0001) as Q64.64
+    let log_sqrt_10001_x64 = log2p_x32 * 59543866431248i128;
+
+    // The truncated log2 is accurate to within one tick: tick - 0.01 and tick + 2^-16 / log2(sqrt(1.0001)) + 0.01
+    let tick_low = ((log_sqrt_10001_x64 - 184467440737095516i128) >> 64) as i32;
+    let tick_high = ((log_sqrt_10001_x64 + 15793534762490258745i128) >> 64) as i32;
+
+    Ok(if tick_low == tick_high {
+        tick_low
+    } else if get_sqrt_price_at_tick(tick_high)? <= sqrt_price_x64 {
+        tick_high
+    } else {
+        tick_low
+    })
+}
+
+/// Returns `a * b` as a 256-bit `(high, low)` pair.
+fn mul_wide(a: u128, b: u128) -> (u128, u128) {
+    const MASK: u128 = u64::MAX as u128;
+    let (a_hi, a_lo) = (a >> 64, a & MASK);
+    let (b_hi, b_lo) = (b >> 64, b & MASK);
+    let lo_lo = a_lo * b_lo;
+    let hi_lo = a_hi * b_lo;
+    let lo_hi = a_lo * b_hi;
+    let mid = (lo_lo >> 64) + (hi_lo & MASK) + (lo_hi & MASK);
+    let low = (mid << 64) | (lo_lo & MASK);
+    let high = a_hi * b_hi + (hi_lo >> 64) + (lo_hi >> 64) + (mid >> 64);
+    (high, low)
+}
+
+/// Multiplies a 256-bit `(high, low)` pair by `m`; the caller guarantees the product fits.
+fn mul_wide_small((high, low): (u128, u128), m: u64) -> (u128, u128) {
+    let (carry, low) = mul_wide(low, m as u128);
+    (high * m as u128 + carry, low)
+}
+
+/// Returns `floor(sqrt_price_x64 * sqrt(numerator / denominator))`, i.e. the Q64.64 sqrt price of
+/// `price * numerator / denominator`, exactly.
+/// Binary searches the largest `x` with `x^2 * denominator <= sqrt_price_x64^2 * numerator` in 256-bit
+/// arithmetic; valid for program sqrt prices and `numerator`, `denominator` below 2^54.
+pub fn scale_sqrt_price(sqrt_price_x64: u128, numerator: u64, denominator: u64) -> u128 {
+    let target = mul_wide_small(mul_wide(sqrt_price_x64, sqrt_price_x64), numerator);
+    let (mut low, mut high) = (0u128, 1u128 << 100);
+    while low < high {
+        let mid = low + (high - low).div_ceil(2);
+        if mul_wide_small(mul_wide(mid, mid), denominator) <= target {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    low
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds_and_tick_zero_map_to_their_known_sqrt_prices() {
+        assert_eq!(get_sqrt_price_at_tick(MIN_TICK), Ok(MIN_SQRT_PRICE_X64));
+        assert_eq!(get_sqrt_price_at_tick(MAX_TICK), Ok(MAX_SQRT_PRICE_X64));
+        assert_eq!(get_sqrt_price_at_tick(0), Ok(1 << 64));
+        assert_eq!(get_tick_at_sqrt_price(MIN_SQRT_PRICE_X64), Ok(MIN_TICK));
+        assert_eq!(get_tick_at_sqrt_price(1 << 64), Ok(0));
+        // MAX_SQRT_PRICE_X64 itself is excluded, as in the program: the last price below it is in MAX_TICK - 1
+        assert_eq!(get_tick_at_sqrt_price(MAX_SQRT_PRICE_X64 - 1), Ok(MAX_TICK - 1));
+    }
+
+    #[test]
+    fn ticks_round_trip_next_to_the_bounds() {
+        for tick in [MIN_TICK, MIN_TICK + 1, -1, 0, 1, MAX_TICK - 2, MAX_TICK - 1] {
+            let sqrt_price_x64 = get_sqrt_price_at_tick(tick).unwrap();
+            assert_eq!(get_tick_at_sqrt_price(sqrt_price_x64), Ok(tick), "tick {}", tick);
+            // One below a tick's sqrt price is still in the tick below
+            if tick > MIN_TICK {
+                assert_eq!(get_tick_at_sqrt_price(sqrt_price_x64 - 1), Ok(tick - 1), "tick {}", tick);
+            }
+        }
+    }
+
+    #[test]
+    fn sqrt_prices_match_the_float_formula() {
+        for tick in [-100_000, -1, 1, 12_345, 200_000] {
+            let exact = get_sqrt_price_at_tick(tick).unwrap() as f64 / 2f64.powi(64);
+            let expected = 1.0001f64.powf(tick as f64 / 2.0);
+            // The program's fixed-point factors and f64 powf each drift by ~1e-12 over large ticks
+            assert!((exact / expected - 1.0).abs() < 1e-10, "tick {}: {} vs {}", tick, exact, expected);
+        }
+    }
+
+    #[test]
+    fn out_of_range_inputs_are_errors() {
+        assert_eq!(get_sqrt_price_at_tick(MIN_TICK - 1), Err("tick -443637 is outside [-443636, 443636]".to_string()));
+        assert_eq!(get_sqrt_price_at_tick(MAX_TICK + 1), Err("tick 443637 is outside [-443636, 443636]".to_string()));
+        for sqrt_price_x64 in [0, MIN_SQRT_PRICE_X64 - 1, MAX_SQRT_PRICE_X64, u128::MAX] {
+            assert_eq!(
+                get_tick_at_sqrt_price(sqrt_price_x64),
+                Err(format!("sqrt_price_x64 {} is outside [{}, {})", sqrt_price_x64, MIN_SQRT_PRICE_X64, MAX_SQRT_PRICE_X64))
+            );
+        }
+    }
+}
@@ -0,0 +1,525 @@
+//! Unit tests of the helpers in `main.rs`, on hand-built inputs: bitmaps, tick arrays and prices
+//! that no fixture covers, such as the edges of the tick range.
+
+use solana_sdk::pubkey::Pubkey;
+
+use crate::onchain_states::{Observation, ObservationState, TickArrayBitmapExtension, TickArrayState, TickState, OBSERVATION_NUM};
+use crate::tick_math::{MAX_TICK, MIN_TICK};
+use crate::units::{ArrayStartIndex, Tick};
+use crate::{
+    array_price_ranges, blind_swap_arrays, check_pool_id, compute_twap_tick, cross_liquidity_net, cumulative_liquidity_at,
+    format_token_amount, human_to_raw_amount, liquidity_bar_width, liquidity_fraction, liquidity_segments, read_default_bitmap,
+    read_default_bitmap_positions, read_extension_bitmap, swap_tick_bounds, validate_tick_array, walk_initialized_arrays,
+    BitmapLocation, BitmapPosition, PriceDisplay, PriceInput, RoundingMode, SwapDirection, SwapRange, TickArrayHelper,
+    TickConverter, TickDirection, Q_RATIO, TICK_SNAP_EPSILON,
+};
+
+/// Builds a default bitmap with the given `(word, bit)` pairs set and decodes it at `tick_spacing`.
+fn default_bitmap_start_indices(bits: &[(usize, usize)], tick_spacing: u16) -> Vec<i32> {
+    let mut bitmap = [0u64; 16];
+    for &(word, bit) in bits {
+        bitmap[word] |= 1 << bit;
+    }
+    assert!(read_default_bitmap_positions(&bitmap).iter().all(|position| position.location == BitmapLocation::Default));
+    read_default_bitmap(&bitmap, tick_spacing)
+}
+
+#[test]
+fn default_bitmap_is_centered_on_the_array_at_tick_zero() {
+    assert_eq!(default_bitmap_start_indices(&[(8, 0)], 1), [0]);
+    assert_eq!(default_bitmap_start_indices(&[(8, 0)], 10), [0]);
+    assert_eq!(default_bitmap_start_indices(&[(7, 63)], 10), [-600]);
+}
+
+#[test]
+fn default_bitmap_edges_are_the_outermost_array_offsets() {
+    // Bit 0 of word 0 is offset -512, bit 63 of word 15 is offset 511
+    assert_eq!(default_bitmap_start_indices(&[(0, 0)], 10), [-512 * 600]);
+    assert_eq!(default_bitmap_start_indices(&[(15, 63)], 10), [511 * 600]);
+    assert_eq!(default_bitmap_start_indices(&[(0, 0), (7, 63), (8, 0), (15, 63)], 60), [-512 * 3600, -3600, 0, 511 * 3600]);
+}
+
+/// The decimals of typical mints: none, USDC, SOL and most bridged ERC-20 tokens.
+const DECIMALS: [u8; 4] = [0, 6, 9, 18];
+
+fn decimal_pairs() -> impl Iterator<Item = (u8, u8)> {
+    DECIMALS.into_iter().flat_map(|decimals_0| DECIMALS.map(|decimals_1| (decimals_0, decimals_1)))
+}
+
+#[test]
+fn human_prices_round_trip_to_their_tick_at_any_decimals() {
+    for (decimals_0, decimals_1) in decimal_pairs() {
+        let converter = TickConverter { decimals_0, decimals_1 };
+        for tick in [-200_000, -1, 0, 1, 200_000].map(Tick) {
+            let t1_per_t0 = converter.tick_to_price(tick, PriceInput::Token1PerToken0Human { price: 0.0 });
+            let t0_per_t1 = converter.tick_to_price(tick, PriceInput::Token0PerToken1Human { price: 0.0 });
+            // The human price is the exact sqrt price squared, scaled by 10^(decimals_0 - decimals_1)
+            let expected = tick.sqrt_price().unwrap().to_raw_price() * 10f64.powi(decimals_0 as i32 - decimals_1 as i32);
+            assert!((t1_per_t0 / expected - 1.0).abs() < 1e-9, "tick {}: {} vs {}", tick, t1_per_t0, expected);
+            for rounding in [RoundingMode::Floor, RoundingMode::Ceil, RoundingMode::Nearest] {
+                let context = format!("tick {} decimals ({}, {}) {:?}", tick, decimals_0, decimals_1, rounding);
+                assert_eq!(converter.price_to_tick_rounded(PriceInput::Token1PerToken0Human { price: t1_per_t0 }, rounding), tick, "{}", context);
+                assert_eq!(converter.price_to_tick_rounded(PriceInput::Token0PerToken1Human { price: t0_per_t1 }, rounding), tick, "{}", context);
+            }
+        }
+    }
+}
+
+#[test]
+fn human_prices_between_ticks_round_by_mode_at_any_decimals() {
+    for (decimals_0, decimals_1) in decimal_pairs() {
+        let converter = TickConverter { decimals_0, decimals_1 };
+        let adjustment = 10f64.powi(decimals_0 as i32 - decimals_1 as i32);
+        // A quarter of a tick above -1001: floor and nearest give -1001, ceil -1000
+        let price = Q_RATIO.powf(-1000.75) * adjustment;
+        let input = PriceInput::Token1PerToken0Human { price };
+        assert_eq!(converter.price_to_tick_rounded(input, RoundingMode::Floor), Tick(-1001));
+        assert_eq!(converter.price_to_tick_rounded(input, RoundingMode::Nearest), Tick(-1001));
+        assert_eq!(converter.price_to_tick_rounded(input, RoundingMode::Ceil), Tick(-1000));
+    }
+}
+
+#[test]
+fn token_amounts_round_trip_at_any_decimals() {
+    for (decimals, human, raw, formatted) in [
+        (0, 42.0, 42, "42"),
+        (6, 1.5, 1_500_000, "1.500000"),
+        (9, 0.000000001, 1, "0.000000001"),
+        (18, 2.0, 2_000_000_000_000_000_000, "2.000000000000000000"),
+    ] {
+        assert_eq!(human_to_raw_amount(human, decimals, "--amount"), raw);
+        assert_eq!(format_token_amount(raw, decimals), formatted);
+    }
+    // Amounts finer than one unit round to the nearest unit
+    assert_eq!(human_to_raw_amount(2.5, 0, "--amount"), 3);
+    assert_eq!(human_to_raw_amount(2.4, 0, "--amount"), 2);
+    assert_eq!(human_to_raw_amount(1.0000004, 6, "--amount"), 1_000_000);
+    assert_eq!(human_to_raw_amount(1.0000006, 6, "--amount"), 1_000_001);
+}
+
+const CONVERTER: TickConverter = TickConverter { decimals_0: 9, decimals_1: 6 };
+
+#[test]
+fn swap_bounds_put_the_favorable_side_against_the_swap_direction() {
+    let ticks = SwapRange::Ticks { favorable: 100, impact: 200 };
+    assert_eq!(swap_tick_bounds(&CONVERTER, SwapDirection::BuyT1, -20000, ticks), Ok((-19900, -20200)));
+    assert_eq!(swap_tick_bounds(&CONVERTER, SwapDirection::BuyT0, -20000, ticks), Ok((-20100, -19800)));
+    // +1% is 99.5 ticks and -2% is -202.0; -1% is -100.5 and +2% is 198.0, all rounded down
+    let percent = SwapRange::Percent { favorable: 1.0, impact: 2.0 };
+    assert_eq!(swap_tick_bounds(&CONVERTER, SwapDirection::BuyT1, -20000, percent), Ok((-19901, -20203)));
+    assert_eq!(swap_tick_bounds(&CONVERTER, SwapDirection::BuyT0, -20000, percent), Ok((-20101, -19802)));
+}
+
+#[test]
+fn swap_bounds_are_clamped_to_the_tick_range() {
+    let ticks = SwapRange::Ticks { favorable: u32::MAX, impact: u32::MAX };
+    assert_eq!(swap_tick_bounds(&CONVERTER, SwapDirection::BuyT1, 0, ticks), Ok((MAX_TICK, MIN_TICK)));
+    assert_eq!(swap_tick_bounds(&CONVERTER, SwapDirection::BuyT0, 0, ticks), Ok((MIN_TICK, MAX_TICK)));
+    // A 99.9% fall is 69081.1 ticks down
+    let percent = SwapRange::Percent { favorable: 1_000.0, impact: 99.9 };
+    assert_eq!(swap_tick_bounds(&CONVERTER, SwapDirection::BuyT1, MAX_TICK - 10, percent), Ok((MAX_TICK, MAX_TICK - 10 - 69082)));
+    assert_eq!(swap_tick_bounds(&CONVERTER, SwapDirection::BuyT1, MIN_TICK + 10, percent).map(|(_, impact)| impact), Ok(MIN_TICK));
+}
+
+#[test]
+fn swap_bounds_reject_a_fall_of_100_percent() {
+    let percent = SwapRange::Percent { favorable: 150.0, impact: 100.0 };
+    assert_eq!(
+        swap_tick_bounds(&CONVERTER, SwapDirection::BuyT1, 0, percent),
+        Err("--impact-pct must be below 100 for BuyT1: the price cannot fall by 100%".to_string())
+    );
+    assert_eq!(
+        swap_tick_bounds(&CONVERTER, SwapDirection::BuyT0, 0, percent),
+        Err("--favorable-pct must be below 100 for BuyT0: the price cannot fall by 150%".to_string())
+    );
+    // Rising prices have no such limit: +150% is 9163.4 ticks
+    let percent = SwapRange::Percent { favorable: 150.0, impact: 50.0 };
+    assert_eq!(swap_tick_bounds(&CONVERTER, SwapDirection::BuyT1, 0, percent), Ok((9163, -6932)));
+}
+
+#[test]
+fn blind_swap_arrays_stop_at_the_last_valid_array() {
+    for tick_spacing in [1, 60, 120, 1000] {
+        let helper = TickArrayHelper { tick_spacing };
+        let (first_valid, last_valid) = helper.valid_array_start_range();
+        let valid = |start_index: &i32| (first_valid.0..=last_valid.0).contains(start_index);
+        // Impact ranges reaching past each end of the tick range, as given on the command line
+        for (direction, tick_start, bounds) in [
+            (SwapDirection::BuyT1, MIN_TICK + 100, (MIN_TICK + 200, MIN_TICK - 50_000)),
+            (SwapDirection::BuyT0, MAX_TICK - 100, (MAX_TICK - 200, MAX_TICK + 50_000)),
+        ] {
+            let context = format!("{:?} at tick spacing {}", direction, tick_spacing);
+            let (favorable, core, surrounding) = blind_swap_arrays(&helper, direction, tick_start, bounds);
+            assert_eq!(surrounding, None, "{}", context);
+            assert!(favorable.iter().chain(&core).all(valid), "{}: {:?} {:?}", context, favorable, core);
+            let last = match direction {
+                SwapDirection::BuyT1 => first_valid.0,
+                SwapDirection::BuyT0 => last_valid.0,
+            };
+            assert_eq!(core.last(), Some(&last), "{}", context);
+        }
+    }
+}
+
+#[test]
+fn blind_swap_arrays_list_the_surrounding_array_past_the_range() {
+    let helper = TickArrayHelper { tick_spacing: 10 };
+    let bounds = (-19900, -20200);
+    assert_eq!(
+        blind_swap_arrays(&helper, SwapDirection::BuyT1, -20000, bounds),
+        (vec![], vec![-20400], Some((-21000, "SURROUNDING_DN")))
+    );
+    let bounds = (-20100, -19500);
+    assert_eq!(
+        blind_swap_arrays(&helper, SwapDirection::BuyT0, -20000, bounds),
+        (vec![], vec![-20400, -19800], Some((-19200, "SURROUNDING_UP")))
+    );
+    // The favorable side lists only the arrays the core range does not reach
+    let bounds = (-18500, -20200);
+    assert_eq!(
+        blind_swap_arrays(&helper, SwapDirection::BuyT1, -20000, bounds),
+        (vec![-18600, -19200, -19800], vec![-20400], Some((-21000, "SURROUNDING_DN")))
+    );
+}
+
+/// Builds a tick array whose `initialized_slots` hold their on-grid tick at `tick_spacing`.
+fn tick_array(pool_id: Pubkey, start_tick_index: i32, tick_spacing: u16, initialized_slots: &[usize]) -> TickArrayState {
+    let mut ticks = [TickState::default(); 60];
+    for &slot in initialized_slots {
+        ticks[slot].tick = start_tick_index + slot as i32 * tick_spacing as i32;
+        ticks[slot].liquidity_gross = 1_000;
+        ticks[slot].liquidity_net = 1_000;
+    }
+    TickArrayState {
+        pool_id,
+        start_tick_index,
+        ticks,
+        initialized_tick_count: initialized_slots.len() as u8,
+        recent_epoch: 0,
+        padding: [0; 107],
+    }
+}
+
+#[test]
+fn consistent_tick_arrays_pass_validation() {
+    let pool = Pubkey::new_unique();
+    assert!(validate_tick_array(&tick_array(pool, -21000, 10, &[0, 50, 59]), 10).is_empty());
+    assert!(validate_tick_array(&tick_array(pool, -443640, 1, &[4]), 1).is_empty());
+    assert!(validate_tick_array(&tick_array(pool, 0, 60, &[]), 60).is_empty());
+}
+
+#[test]
+fn tick_array_validation_reports_each_problem() {
+    let pool = Pubkey::new_unique();
+    let mut miscounted = tick_array(pool, -21000, 10, &[0, 50]);
+    miscounted.initialized_tick_count = 3;
+    assert_eq!(validate_tick_array(&miscounted, 10), ["initialized_tick_count is 3 but 2 slots have non-zero liquidity_gross"]);
+
+    let misaligned = tick_array(pool, -20990, 10, &[0]);
+    assert_eq!(validate_tick_array(&misaligned, 10), ["start_tick_index -20990 is not a multiple of 600 (60 * tick spacing 10)"]);
+
+    // An array of a tick spacing 60 pool read at spacing 10: the start fits both, the ticks do not
+    let other_spacing = tick_array(pool, -21600, 60, &[0, 1, 2]);
+    assert_eq!(validate_tick_array(&other_spacing, 10), ["slot 1 holds tick -21540, expected -21590", "slot 2 holds tick -21480, expected -21580"]);
+}
+
+#[test]
+fn tick_arrays_of_another_pool_fail_the_pool_id_check() {
+    // The pool id is checked when the array is fetched; the layout of another pool's array can be valid
+    let (pool, other_pool, pda) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let array = tick_array(other_pool, -21000, 10, &[0]);
+    assert!(validate_tick_array(&array, 10).is_empty());
+    assert_eq!(check_pool_id(&pda, &array, &other_pool), Ok(()));
+    assert_eq!(check_pool_id(&pda, &array, &pool), Err(format!("TickArrayState {} belongs to pool {}, not {}.", pda, other_pool, pool)));
+}
+
+#[test]
+fn liquidity_sums_saturate_instead_of_overflowing() {
+    // Net liquidity summing past i128::MAX only comes from corrupt ticks; the sum pins at the limit
+    let ticks = [(-10, i128::MAX - 5), (0, 4), (10, 2), (20, 1_000)];
+    assert_eq!(cumulative_liquidity_at(&ticks, -10), i128::MAX - 5);
+    assert_eq!(cumulative_liquidity_at(&ticks, 0), i128::MAX - 1);
+    assert_eq!(cumulative_liquidity_at(&ticks, 10), i128::MAX);
+    assert_eq!(cumulative_liquidity_at(&ticks, 20), i128::MAX);
+    assert_eq!(cumulative_liquidity_at(&[(-10, i128::MIN), (0, -1)], 0), i128::MIN);
+
+    assert_eq!(cross_liquidity_net(i128::MAX, 1, 0, TickDirection::Up), i128::MAX);
+    assert_eq!(cross_liquidity_net(0, i128::MIN, 0, TickDirection::Down), i128::MAX);
+    assert_eq!(cross_liquidity_net(i128::MIN, 1, 0, TickDirection::Down), i128::MIN);
+    // Sums that fit are exact in both directions
+    assert_eq!(cross_liquidity_net(100, -30, 0, TickDirection::Up), 70);
+    assert_eq!(cross_liquidity_net(100, -30, 0, TickDirection::Down), 130);
+}
+
+const ROUNDING_MODES: [RoundingMode; 4] = [RoundingMode::Floor, RoundingMode::Ceil, RoundingMode::Nearest, RoundingMode::NearestAligned];
+
+/// The four price input formats, as constructors.
+const PRICE_FORMATS: [fn(f64) -> PriceInput; 4] = [
+    |price| PriceInput::Token1PerToken0Raw { price },
+    |price| PriceInput::Token0PerToken1Raw { price },
+    |price| PriceInput::Token1PerToken0Human { price },
+    |price| PriceInput::Token0PerToken1Human { price },
+];
+
+#[test]
+fn exact_tick_prices_convert_back_to_their_tick_in_every_format_and_mode() {
+    for tick in [MIN_TICK, -200_001, -1, 0, 1, 200_001, MAX_TICK].map(Tick) {
+        for format in PRICE_FORMATS {
+            let input = format(CONVERTER.tick_to_price(tick, format(0.0)));
+            for rounding in ROUNDING_MODES {
+                assert_eq!(CONVERTER.price_to_tick_spaced(input, rounding, 1), tick, "{:?} {:?}", input, rounding);
+            }
+        }
+    }
+}
+
+#[test]
+fn prices_within_the_snap_distance_of_a_tick_land_on_it() {
+    let tick = -20_000;
+    let raw_price = CONVERTER.tick_to_raw_price(Tick(tick));
+    // One ulp either side is about 1e-12 ticks away, well inside the snap distance
+    for price in [raw_price.next_down(), raw_price, raw_price.next_up()] {
+        for rounding in ROUNDING_MODES {
+            assert_eq!(CONVERTER.raw_price_to_tick_rounded(price, rounding), Tick(tick), "{} {:?}", price, rounding);
+        }
+    }
+    // Twice the snap distance away, floor and ceil see the price as between two ticks
+    let below = Q_RATIO.powf(tick as f64 - 2.0 * TICK_SNAP_EPSILON);
+    let above = Q_RATIO.powf(tick as f64 + 2.0 * TICK_SNAP_EPSILON);
+    assert_eq!(CONVERTER.raw_price_to_tick_rounded(below, RoundingMode::Floor), Tick(tick - 1));
+    assert_eq!(CONVERTER.raw_price_to_tick_rounded(below, RoundingMode::Ceil), Tick(tick));
+    assert_eq!(CONVERTER.raw_price_to_tick_rounded(above, RoundingMode::Floor), Tick(tick));
+    assert_eq!(CONVERTER.raw_price_to_tick_rounded(above, RoundingMode::Ceil), Tick(tick + 1));
+}
+
+#[test]
+fn nearest_aligned_rounds_halfway_ticks_away_from_zero() {
+    let aligned = |tick: i32, tick_spacing: u16| {
+        CONVERTER.raw_price_to_tick_spaced(Q_RATIO.powi(tick), RoundingMode::NearestAligned, tick_spacing)
+    };
+    assert_eq!(aligned(15, 10), Tick(20));
+    assert_eq!(aligned(-15, 10), Tick(-20));
+    assert_eq!(aligned(-25, 10), Tick(-30));
+    assert_eq!(aligned(-30, 60), Tick(-60));
+    // Just inside the halfway point rounds toward it
+    assert_eq!(aligned(-14, 10), Tick(-10));
+    assert_eq!(aligned(-16, 10), Tick(-20));
+}
+
+#[test]
+fn nearest_aligned_stays_within_the_usable_ticks() {
+    for (tick_spacing, usable) in [(1, 443636), (10, 443630), (60, 443580), (200, 443600)] {
+        for (price, expected) in [(1e300, usable), (1e-300, -usable), (Q_RATIO.powi(MAX_TICK), usable), (Q_RATIO.powi(MIN_TICK), -usable)] {
+            assert_eq!(
+                CONVERTER.raw_price_to_tick_spaced(price, RoundingMode::NearestAligned, tick_spacing),
+                Tick(expected),
+                "price {} at tick spacing {}",
+                price,
+                tick_spacing
+            );
+        }
+    }
+}
+
+#[test]
+fn last_valid_tick_is_the_last_usable_slot() {
+    let helper = TickArrayHelper { tick_spacing: 1 };
+    // At spacing 1 the last slot is the end of the array's tick span
+    assert_eq!(helper.last_valid_tick(ArrayStartIndex(0)), Tick(59));
+    assert_eq!(helper.get_array_tick_range(ArrayStartIndex(0)), (Tick(0), Tick(59)));
+    assert_eq!(helper.last_valid_tick(ArrayStartIndex(-60)), Tick(-1));
+    // The array holding MAX_TICK has slots past it
+    assert_eq!(helper.get_array_tick_range(ArrayStartIndex(443580)), (Tick(443580), Tick(443639)));
+    assert_eq!(helper.last_valid_tick(ArrayStartIndex(443580)), Tick(MAX_TICK));
+    assert_eq!(helper.get_array_valid_tick_range(ArrayStartIndex(-443640)), (Tick(MIN_TICK), Tick(-443581)));
+
+    let helper = TickArrayHelper { tick_spacing: 60 };
+    assert_eq!(helper.last_valid_tick(ArrayStartIndex(0)), Tick(3540));
+    assert_eq!(helper.get_array_tick_range(ArrayStartIndex(0)), (Tick(0), Tick(3599)));
+    assert_eq!(helper.last_valid_tick(ArrayStartIndex(-3600)), Tick(-60));
+    assert_eq!(helper.get_array_tick_range(ArrayStartIndex(442800)), (Tick(442800), Tick(446399)));
+    assert_eq!(helper.last_valid_tick(ArrayStartIndex(442800)), Tick(443580));
+    assert_eq!(helper.get_array_valid_tick_range(ArrayStartIndex(-446400)), (Tick(-443580), Tick(-442860)));
+}
+
+#[test]
+fn decimal_adjustment_stays_finite_at_the_widest_decimal_gap() {
+    for (decimals_0, decimals_1, adjustment) in [(0, 18, 1e-18), (18, 0, 1e18), (18, 18, 1.0)] {
+        let converter = TickConverter { decimals_0, decimals_1 };
+        assert_eq!(converter.decimal_adjustment(), adjustment);
+        // The human formats (the last two) stay finite and non-zero at the tick limits in both orientations
+        for tick in [Tick(MIN_TICK), Tick(MAX_TICK)] {
+            for format in &PRICE_FORMATS[2..] {
+                let price = converter.tick_to_price(tick, format(0.0));
+                assert!(price.is_finite() && price > 0.0, "tick {} decimals ({}, {}): {}", tick, decimals_0, decimals_1, price);
+                assert_eq!(converter.price_to_tick_rounded(format(price), RoundingMode::Nearest), tick);
+            }
+        }
+    }
+}
+
+#[test]
+fn the_extension_starts_right_past_the_default_bitmap() {
+    for tick_spacing in [1, 10, 60] {
+        let seam = 512 * 60 * tick_spacing as i32;
+        let position = |start_index: i32| BitmapPosition::from_start_index(start_index, tick_spacing).unwrap();
+        assert_eq!(position(seam - 60 * tick_spacing as i32), BitmapPosition { location: BitmapLocation::Default, word: 15, bit: 63 });
+        assert_eq!(position(seam), BitmapPosition { location: BitmapLocation::Positive { chunk: 0 }, word: 0, bit: 0 });
+        assert_eq!(position(-seam), BitmapPosition { location: BitmapLocation::Default, word: 0, bit: 0 });
+        // Array offset -513 is the first negative extension array, the top bit of chunk 0
+        assert_eq!(position(-seam - 60 * tick_spacing as i32), BitmapPosition { location: BitmapLocation::Negative { chunk: 0 }, word: 7, bit: 63 });
+    }
+}
+
+#[test]
+fn array_walks_cross_the_extension_seam_in_order() {
+    let tick_spacing = 10;
+    let ticks_per_array = 600;
+    // Array offsets -513 and 512 in the extension, -512 and 511 at the ends of the default bitmap
+    let mut default_bitmap = [0u64; 16];
+    default_bitmap[0] |= 1;
+    default_bitmap[15] |= 1 << 63;
+    let mut extension = TickArrayBitmapExtension {
+        pool_id: Pubkey::default(),
+        positive_tick_array_bitmap: [[0; 8]; 14],
+        negative_tick_array_bitmap: [[0; 8]; 14],
+    };
+    extension.positive_tick_array_bitmap[0][0] |= 1;
+    extension.negative_tick_array_bitmap[0][7] |= 1 << 63;
+
+    let mut initialized = read_default_bitmap(&default_bitmap, tick_spacing);
+    initialized.append(&mut read_extension_bitmap(&extension, tick_spacing));
+    initialized.sort();
+    let offsets = [-513, -512, 511, 512].map(|offset| offset * ticks_per_array);
+    assert_eq!(initialized, offsets);
+
+    assert_eq!(walk_initialized_arrays(&initialized, offsets[0], TickDirection::Up), offsets);
+    assert_eq!(walk_initialized_arrays(&initialized, offsets[3], TickDirection::Down), [offsets[3], offsets[2], offsets[1], offsets[0]]);
+    // From an uninitialized array between the sides, each walk starts at the nearest array
+    assert_eq!(walk_initialized_arrays(&initialized, 0, TickDirection::Up), offsets[2..]);
+    assert_eq!(walk_initialized_arrays(&initialized, 0, TickDirection::Down), [offsets[1], offsets[0]]);
+    // Past the last array there is nothing left to walk
+    assert!(walk_initialized_arrays(&initialized, offsets[3] + ticks_per_array, TickDirection::Up).is_empty());
+    assert!(walk_initialized_arrays(&initialized, offsets[0] - ticks_per_array, TickDirection::Down).is_empty());
+}
+
+#[test]
+fn liquidity_bars_scale_to_the_peak_and_keep_thin_ranges_visible() {
+    // Active liquidity 1e6, then 1, then 6, then negative (corrupt ticks) past tick 100
+    let ticks = [(-200, 1_000_000), (-100, -999_999), (0, 5), (100, -1_000_006), (200, 0)];
+    let segments = liquidity_segments(&ticks);
+    assert_eq!(segments, [(-200, -100, 1_000_000), (-100, 0, 1), (0, 100, 6)]);
+    let max_liquidity = 1_000_000;
+
+    let widths = |log_scale: bool| -> Vec<usize> {
+        segments.iter().map(|&(_, _, liquidity)| liquidity_bar_width(liquidity, max_liquidity, 40, log_scale)).collect()
+    };
+    // Linear: 1 and 6 round to no blocks but still get one
+    assert_eq!(widths(false), [40, 1, 1]);
+    // Log: ln(2) and ln(7) against ln(1e6 + 1)
+    assert_eq!(widths(true), [40, 2, 6]);
+
+    for log_scale in [false, true] {
+        assert_eq!(liquidity_bar_width(0, max_liquidity, 40, log_scale), 0);
+        assert_eq!(liquidity_bar_width(-1_000_000, max_liquidity, 40, log_scale), 0);
+        assert_eq!(liquidity_bar_width(1, 0, 40, log_scale), 0);
+        assert_eq!(liquidity_fraction(-5, max_liquidity, log_scale), 0.0);
+        assert_eq!(liquidity_bar_width(i128::MAX, i128::MAX, 40, log_scale), 40);
+        for max_width in [0, 1, 7, 200] {
+            for liquidity in [1, 999, 500_000, max_liquidity] {
+                let width = liquidity_bar_width(liquidity, max_liquidity, max_width, log_scale);
+                assert!(width <= max_width && (width > 0) == (max_width > 0), "{} at width {}: {}", liquidity, max_width, width);
+            }
+        }
+    }
+}
+
+/// An observation ring buffer holding `(block_timestamp, tick_cumulative)` entries, oldest first,
+/// with the last one at `observation_index`.
+fn observation_state(observation_index: u16, entries: &[(u32, i64)]) -> ObservationState {
+    let empty = Observation { block_timestamp: 0, tick_cumulative: 0, padding: [0; 4] };
+    let mut observations = [empty; OBSERVATION_NUM];
+    for (age, &(block_timestamp, tick_cumulative)) in entries.iter().rev().enumerate() {
+        let index = (observation_index as usize + OBSERVATION_NUM - age) % OBSERVATION_NUM;
+        observations[index] = Observation { block_timestamp, tick_cumulative, padding: [0; 4] };
+    }
+    ObservationState { initialized: true, recent_epoch: 0, observation_index, pool_id: Pubkey::default(), observations, padding: [0; 4] }
+}
+
+#[test]
+fn twap_interpolates_the_window_start_between_observations() {
+    // Tick 100 over 1000..1010, -50 over 1010..1030, 7 over 1030..1060; wraps from slot 98 to slot 1
+    let state = observation_state(1, &[(1000, 0), (1010, 1000), (1030, 0), (1060, 210)]);
+
+    // Window starting on an observation
+    assert_eq!(compute_twap_tick(&state, 30), Some((7, 30)));
+    // Starts inside (1010, 1030]: 10s at -50 and 30s at 7 average -7.25, floored
+    assert_eq!(compute_twap_tick(&state, 40), Some((-8, 40)));
+    // Starts inside (1000, 1010]: 5s at 100, 20s at -50 and 30s at 7 sum to -290
+    assert_eq!(compute_twap_tick(&state, 55), Some((-6, 55)));
+    // Longer than the history: the oldest observation starts the window
+    assert_eq!(compute_twap_tick(&state, 60), Some((3, 60)));
+    assert_eq!(compute_twap_tick(&state, 1000), Some((3, 60)));
+
+    assert_eq!(compute_twap_tick(&state, 0), None);
+    assert_eq!(compute_twap_tick(&observation_state(1, &[(1060, 210)]), 30), None);
+    assert_eq!(compute_twap_tick(&observation_state(1, &[]), 30), None);
+}
+
+#[test]
+fn edge_array_price_ranges_stop_at_the_usable_ticks() {
+    let t1_per_t0 = |tick| CONVERTER.tick_to_price(Tick(tick), PriceInput::Token1PerToken0Human { price: 0.0 });
+    let t0_per_t1 = |tick| CONVERTER.tick_to_price(Tick(tick), PriceInput::Token0PerToken1Human { price: 0.0 });
+    for (tick_spacing, first_start, last_start, max_usable) in [(1, -443640, 443580, 443636), (60, -446400, 442800, 443580)] {
+        let helper = TickArrayHelper { tick_spacing };
+        let (min_start, max_start) = helper.valid_array_start_range();
+        assert_eq!((min_start.0, max_start.0), (first_start, last_start));
+
+        // The first array starts below MIN_TICK: both formats end at -max_usable on that side
+        let last_in_first = first_start + 59 * tick_spacing as i32;
+        assert_eq!(array_price_ranges(&helper, &CONVERTER, first_start), [
+            (t1_per_t0(-max_usable), t1_per_t0(last_in_first)),
+            (t0_per_t1(last_in_first), t0_per_t1(-max_usable)),
+        ]);
+        // The last array runs past MAX_TICK
+        assert_eq!(array_price_ranges(&helper, &CONVERTER, last_start), [
+            (t1_per_t0(last_start), t1_per_t0(max_usable)),
+            (t0_per_t1(max_usable), t0_per_t1(last_start)),
+        ]);
+        // Arrays inside the range are not clamped
+        assert_eq!(array_price_ranges(&helper, &CONVERTER, 0)[0], (t1_per_t0(0), t1_per_t0(59 * tick_spacing as i32)));
+    }
+}
+
+#[test]
+fn prices_keep_six_significant_digits_by_default() {
+    let default = PriceDisplay { precision: None, scientific: false };
+    assert_eq!(default.format(1234567.891), "1,234,570");
+    assert_eq!(default.format(999999.7), "1,000,000");
+    assert_eq!(default.format(1234.5), "1,234.50");
+    assert_eq!(default.format(-1234.5), "-1,234.50");
+    assert_eq!(default.format(-1234567.891), "-1,234,570");
+    assert_eq!(default.format(1.0), "1.00000");
+    assert_eq!(default.format(0.000000004213), "0.00000000421300");
+    assert_eq!(default.format(0.0), "0");
+
+    // Outside PRICE_FIXED_RANGE the default switches to scientific notation
+    assert_eq!(default.format(123456789012345.0), "123,457,000,000,000");
+    assert_eq!(default.format(1e15), "1.00000e15");
+    assert_eq!(default.format(1e-12), "0.00000000000100000");
+    assert_eq!(default.format(1e-13), "1.00000e-13");
+    assert_eq!(default.format(-1e-13), "-1.00000e-13");
+
+    // --precision fixes the decimal places, without the switch
+    let fixed = PriceDisplay { precision: Some(2), scientific: false };
+    assert_eq!(fixed.format(1234567.891), "1,234,567.89");
+    assert_eq!(fixed.format(-1234.5), "-1,234.50");
+    assert_eq!(fixed.format(0.000000004213), "0.00");
+    assert_eq!(fixed.format(1e15), "1,000,000,000,000,000.00");
+    assert_eq!(fixed.format_plain(1234567.891), "1234567.89");
+
+    // --scientific, with --precision as the mantissa digits
+    assert_eq!(PriceDisplay { precision: None, scientific: true }.format(1234.5), "1.23450e3");
+    assert_eq!(PriceDisplay { precision: Some(2), scientific: true }.format(0.000000004213), "4.21e-9");
+    assert_eq!(fixed.range(0.5, 2.0), "[0.50, 2.00]");
+}
@@ -0,0 +1,155 @@
+//! Newtypes for the integers that describe positions on the price curve.
+//! A tick index, the start index of the tick array holding it and a Q64.64 sqrt price are all
+//! plain integers on-chain; wrapping them keeps one from being passed where another is expected.
+
+use std::fmt;
+
+use crate::tick_math::{get_sqrt_price_at_tick, get_tick_at_sqrt_price};
+use crate::TICK_ARRAY_SIZE;
+
+/// A tick index: the price level `1.0001^tick` (raw token_1 / token_0).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Tick(pub i32);
+
+/// The start tick index of a tick array, a multiple of `60 * tick_spacing`. Also the array's PDA seed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ArrayStartIndex(pub i32);
+
+/// A sqrt price in Q64.64 fixed point, as stored in `PoolState.sqrt_price_x64`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SqrtPriceX64(pub u128);
+
+impl Tick {
+    /// Start index of the array holding this tick. Rounds toward negative infinity, so negative
+    /// ticks land in the array below zero rather than the one starting at zero.
+    pub fn array_start(self, tick_spacing: u16) -> ArrayStartIndex {
+        let ticks_per_array = TICK_ARRAY_SIZE * tick_spacing as i32;
+        ArrayStartIndex(self.0.div_euclid(ticks_per_array) * ticks_per_array)
+    }
+
+    /// The closest multiple of `tick_spacing` at or below this tick.
+    pub fn align_down(self, tick_spacing: u16) -> Tick {
+        Tick(self.0.div_euclid(tick_spacing as i32) * tick_spacing as i32)
+    }
+
+    /// The tick `ticks` indices away (negative moves down).
+    pub fn offset(self, ticks: i32) -> Tick {
+        Tick(self.0 + ticks)
+    }
+
+    /// The exact sqrt price at this tick; fails outside `[MIN_TICK, MAX_TICK]`.
+    pub fn sqrt_price(self) -> Result<SqrtPriceX64, String> {
+        get_sqrt_price_at_tick(self.0).map(SqrtPriceX64)
+    }
+}
+
+impl ArrayStartIndex {
+    /// The first tick of the array, which is its start index.
+    pub fn first_tick(self) -> Tick {
+        Tick(self.0)
+    }
+
+    /// The last tick index covered by the array: `start + 60 * tick_spacing - 1`.
+    pub fn end_tick(self, tick_spacing: u16) -> Tick {
+        Tick(self.0 + TICK_ARRAY_SIZE * tick_spacing as i32 - 1)
+    }
+
+    /// The last on-grid tick stored in the array: `start + 59 * tick_spacing`.
+    pub fn last_tick(self, tick_spacing: u16) -> Tick {
+        Tick(self.0 + (TICK_ARRAY_SIZE - 1) * tick_spacing as i32)
+    }
+
+    /// The start index `arrays` arrays away (negative moves down).
+    pub fn shifted(self, arrays: i32, tick_spacing: u16) -> ArrayStartIndex {
+        ArrayStartIndex(self.0 + arrays * TICK_ARRAY_SIZE * tick_spacing as i32)
+    }
+
+    /// The tick stored in `slot` (0-59): `start + slot * tick_spacing`.
+    pub fn tick_at_slot(self, slot: i32, tick_spacing: u16) -> Tick {
+        Tick(self.0 + slot * tick_spacing as i32)
+    }
+
+    /// The storage slot (0-59) of an aligned tick within this array.
+    pub fn slot_of(self, tick: Tick, tick_spacing: u16) -> i32 {
+        (tick.0 - self.0) / tick_spacing as i32
+    }
+}
+
+impl SqrtPriceX64 {
+    /// The greatest tick whose sqrt price is at or below this one, bit-exact with the program.
+    pub fn tick(self) -> Result<Tick, String> {
+        get_tick_at_sqrt_price(self.0).map(Tick)
+    }
+
+    /// The raw price (token_1 / token_0) as `f64`.
+    pub fn to_raw_price(self) -> f64 {
+        q64_to_f64(self.0).powi(2)
+    }
+}
+
+/// Decodes a Q64.64 fixed-point value (sqrt prices, fee and reward growths, emission rates) as `f64`.
+pub fn q64_to_f64(value: u128) -> f64 {
+    value as f64 / 2f64.powi(64)
+}
+
+// Display delegates to the inner integer, so width and alignment flags apply as before.
+impl fmt::Display for Tick {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl fmt::Display for ArrayStartIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl fmt::Display for SqrtPriceX64 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tick_math::{MAX_TICK, MIN_TICK};
+
+    #[test]
+    fn array_starts_round_toward_negative_infinity() {
+        assert_eq!(Tick(0).array_start(1), ArrayStartIndex(0));
+        assert_eq!(Tick(59).array_start(1), ArrayStartIndex(0));
+        assert_eq!(Tick(-1).array_start(1), ArrayStartIndex(-60));
+        assert_eq!(Tick(-60).array_start(1), ArrayStartIndex(-60));
+        assert_eq!(Tick(-61).array_start(10), ArrayStartIndex(-600));
+        assert_eq!(Tick(MIN_TICK).array_start(60), ArrayStartIndex(-446400));
+        assert_eq!(Tick(MAX_TICK).array_start(60), ArrayStartIndex(442800));
+        assert_eq!(Tick(-7).align_down(10), Tick(-10));
+        assert_eq!(Tick(7).align_down(10), Tick(0));
+    }
+
+    #[test]
+    fn slots_and_ticks_round_trip_within_an_array() {
+        for tick_spacing in [1, 10, 60] {
+            let start = Tick(-1).array_start(tick_spacing);
+            for slot in [0, 1, 59] {
+                let tick = start.tick_at_slot(slot, tick_spacing);
+                assert_eq!(tick.array_start(tick_spacing), start);
+                assert_eq!(start.slot_of(tick, tick_spacing), slot);
+            }
+            assert_eq!(start.last_tick(tick_spacing), start.tick_at_slot(59, tick_spacing));
+            assert_eq!(start.end_tick(tick_spacing).offset(1), start.shifted(1, tick_spacing).first_tick());
+        }
+    }
+
+    #[test]
+    fn ticks_and_sqrt_prices_round_trip() {
+        for tick in [MIN_TICK, -1, 0, 1, MAX_TICK - 1] {
+            assert_eq!(Tick(tick).sqrt_price().and_then(SqrtPriceX64::tick), Ok(Tick(tick)));
+        }
+        assert!(Tick(MAX_TICK + 1).sqrt_price().is_err());
+        assert_eq!(SqrtPriceX64(1 << 64).to_raw_price(), 1.0);
+        assert_eq!(SqrtPriceX64(2 << 64).to_raw_price(), 4.0);
+    }
+}